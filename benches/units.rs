@@ -0,0 +1,66 @@
+//! Measures how long formatting a million file sizes takes, as when listing a large tree.
+//!
+//! Run with `cargo bench --bench units`.
+
+use filey::units::UnitOfInfo;
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const SIZES: u64 = 1_000_000;
+
+/// Sizes spread over every unit up to EiB, so that no branch is favored.
+fn sizes() -> impl Iterator<Item = u64> {
+    (0..SIZES).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (i % 64))
+}
+
+/// The unit picked by counting the digits of the size, which the integer math replaced.
+fn decimal_by_digits(size: u64) -> UnitOfInfo {
+    match size.to_string().len() {
+        0..=3 => UnitOfInfo::Byte,
+        4..=6 => UnitOfInfo::KB,
+        7..=9 => UnitOfInfo::MB,
+        10..=12 => UnitOfInfo::GB,
+        13..=15 => UnitOfInfo::TB,
+        16..=18 => UnitOfInfo::PB,
+        _ => UnitOfInfo::EB,
+    }
+}
+
+fn bench<F: FnMut(u64)>(name: &str, mut f: F) {
+    // Warm up, then keep the fastest of a few runs.
+    sizes().for_each(&mut f);
+    let best = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            sizes().for_each(&mut f);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+    println!(
+        "{:<24} {:>10.2?} ({:.1} ns per size)",
+        name,
+        best,
+        best.as_nanos() as f64 / SIZES as f64
+    );
+}
+
+fn main() {
+    bench("binary_for", |size| {
+        black_box(UnitOfInfo::binary_for(black_box(size)));
+    });
+    bench("decimal_for", |size| {
+        black_box(UnitOfInfo::decimal_for(black_box(size)));
+    });
+    bench("decimal by digit count", |size| {
+        black_box(decimal_by_digits(black_box(size)));
+    });
+    bench("format", |size| {
+        black_box(UnitOfInfo::format(black_box(size)));
+    });
+    bench("format_decimal", |size| {
+        black_box(UnitOfInfo::format_decimal(black_box(size)));
+    });
+}
@@ -0,0 +1,39 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Makes path absolute against the current directory and removes `.` and `..` components
+/// without touching the filesystem, so symbolic links are not resolved.
+#[cfg(not(target_os = "wasi"))]
+pub(crate) fn absolutize(path: &Path) -> io::Result<PathBuf> {
+    use path_absolutize::Absolutize;
+
+    path.absolutize().map(|path| path.to_path_buf())
+}
+
+/// Makes path absolute against the current directory and removes `.` and `..` components
+/// without touching the filesystem, so symbolic links are not resolved.
+/// path-absolutize doesn't support WASI, where paths always use `/`.
+#[cfg(target_os = "wasi")]
+pub(crate) fn absolutize(path: &Path) -> io::Result<PathBuf> {
+    use std::path::Component;
+
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+
+    let mut absolutized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolutized.pop();
+            }
+            component => absolutized.push(component),
+        }
+    }
+    Ok(absolutized)
+}
@@ -0,0 +1,112 @@
+use crate::{op::OpContext, Op, Result};
+use std::{
+    fs::{read_dir, symlink_metadata},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Returns every entry under path, including path itself, which the current user can't modify
+/// or remove. A directory which can't be listed is reported without descending into it.
+/// Symbolic links are only checked for removal, since changing one means replacing it.
+pub(crate) fn unwritable(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut unwritable = Vec::new();
+    check_tree(path, &mut unwritable)?;
+    Ok(unwritable)
+}
+
+fn check_tree(path: &Path, unwritable: &mut Vec<PathBuf>) -> Result<()> {
+    let metadata = symlink_metadata(path).op(Op::Metadata, path)?;
+    let writable = metadata.is_symlink() || can_write(path);
+    let reported = !writable || !can_remove(path);
+    if reported {
+        unwritable.push(path.to_path_buf());
+    }
+    if !metadata.is_dir() {
+        return Ok(());
+    }
+
+    let mut entries =
+        match read_dir(path).and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                if writable {
+                    unwritable.push(path.to_path_buf());
+                }
+                return Ok(());
+            }
+            Err(e) => return Err(Op::ReadDir.error(path, e)),
+        };
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        check_tree(&entry.path(), unwritable)?;
+    }
+    Ok(())
+}
+
+/// Returns true if the current user may read path.
+#[cfg(target_family = "unix")]
+pub(crate) fn can_read(path: &Path) -> bool {
+    check(path, libc::R_OK)
+}
+
+/// Returns true if the current user may write to path.
+#[cfg(target_family = "unix")]
+pub(crate) fn can_write(path: &Path) -> bool {
+    check(path, libc::W_OK)
+}
+
+/// Returns true if the current user may remove path, which takes write and search permission
+/// on the directory containing it. In a sticky directory like /tmp, the user must also own
+/// either the entry or the directory.
+#[cfg(target_family = "unix")]
+pub(crate) fn can_remove(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let parent = match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => return false,
+    };
+    if !check(parent, libc::W_OK | libc::X_OK) {
+        return false;
+    }
+    let (Ok(directory), Ok(entry)) = (parent.metadata(), path.symlink_metadata()) else {
+        return false;
+    };
+    let uid = unsafe { libc::geteuid() };
+    // 0o1000 is the sticky bit.
+    directory.mode() & 0o1000 == 0 || uid == 0 || uid == entry.uid() || uid == directory.uid()
+}
+
+/// Asks the kernel with the effective user and group, like the operation itself would.
+#[cfg(target_family = "unix")]
+fn check(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(c_path) = crate::sys::to_cstring(path) else {
+        return false;
+    };
+    unsafe { libc::faccessat(libc::AT_FDCWD, c_path.as_ptr(), mode, libc::AT_EACCESS) == 0 }
+}
+
+/// Returns true if the current user may read path.
+#[cfg(not(target_family = "unix"))]
+pub(crate) fn can_read(path: &Path) -> bool {
+    if path.is_dir() {
+        std::fs::read_dir(path).is_ok()
+    } else {
+        std::fs::File::open(path).is_ok()
+    }
+}
+
+/// Returns true if path isn't read-only.
+#[cfg(not(target_family = "unix"))]
+pub(crate) fn can_write(path: &Path) -> bool {
+    path.symlink_metadata()
+        .map(|metadata| !metadata.permissions().readonly())
+        .unwrap_or(false)
+}
+
+/// Returns true if path isn't read-only, which would make removing it fail on Windows.
+#[cfg(not(target_family = "unix"))]
+pub(crate) fn can_remove(path: &Path) -> bool {
+    can_write(path)
+}
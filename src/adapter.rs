@@ -0,0 +1,123 @@
+use crate::{op::OpContext, BufferSize, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// The buffer size of [`BufReader`] and [`BufWriter`].
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Where [`Filey::into_writer`](crate::Filey::into_writer) starts writing.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WriteMode {
+    /// Creates the file or empties an existing one.
+    #[default]
+    Truncate,
+    /// Creates the file or writes after the end of an existing one. Every write goes to the
+    /// end, even after seeking.
+    Append,
+}
+
+/// A buffered reader returned by [`Filey::into_reader`](crate::Filey::into_reader).
+#[derive(Debug)]
+pub struct FileyReader {
+    path: PathBuf,
+    reader: BufReader<File>,
+}
+
+impl FileyReader {
+    pub(crate) fn new(path: PathBuf, file: File, buffer_size: BufferSize) -> Self {
+        let capacity = buffer_size.resolve(&file, DEFAULT_CAPACITY);
+        Self {
+            path,
+            reader: BufReader::with_capacity(capacity, file),
+        }
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Read for FileyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for FileyReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.reader.consume(amount)
+    }
+}
+
+impl Seek for FileyReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
+
+/// A buffered writer returned by [`Filey::into_writer`](crate::Filey::into_writer).
+///
+/// Buffered data is written when it is dropped, which ignores errors; call
+/// [`FileyWriter::sync`] or [`Write::flush`] to see them.
+#[derive(Debug)]
+pub struct FileyWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl FileyWriter {
+    pub(crate) fn open(path: PathBuf, mode: WriteMode, buffer_size: BufferSize) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        match mode {
+            WriteMode::Truncate => options.write(true).create(true).truncate(true),
+            WriteMode::Append => options.append(true).create(true),
+        };
+        let file = options.open(&path).op(Op::Open, &path)?;
+        let capacity = buffer_size.resolve(&file, DEFAULT_CAPACITY);
+        Ok(Self {
+            path,
+            writer: BufWriter::with_capacity(capacity, file),
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes buffered data and waits until the file is stored on the device.
+    ///
+    /// # Errors
+    /// * The disk is full or the device fails.
+    pub fn sync(&mut self) -> Result<()> {
+        self.writer.flush().op(Op::Write, &self.path)?;
+        self.writer.get_ref().sync_all().op(Op::Write, &self.path)
+    }
+}
+
+impl Write for FileyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Seek for FileyWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.writer.seek(pos)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{op::OpContext, Error::GetFileNameError, Op, Result};
+use std::{
+    fs::{metadata, remove_file, rename, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a unique temporary path next to path, so that renaming it over path stays on the
+/// same filesystem.
+pub(crate) fn temp_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| GetFileNameError {
+        path: path.to_string_lossy().to_string(),
+    })?;
+    let temp_name = format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Writes a temporary file with f and renames it over path, so readers see either the old or
+/// the new contents but never a partially written file.
+/// The permissions of an existing file are kept.
+/// Returns the handle of the temporary file, which is now at path.
+pub(crate) fn write_with<P, F>(path: P, f: F) -> Result<File>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let path = path.as_ref();
+    let temp = temp_path(path)?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .op(Op::Create, &temp)?;
+
+    let result = (|| {
+        f(&mut file)?;
+        if let Ok(metadata) = metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        rename(&temp, path)
+    })();
+
+    match result {
+        Ok(()) => Ok(file),
+        Err(e) => {
+            let _ = remove_file(&temp);
+            Err(Op::Write.error(path, e))
+        }
+    }
+}
+
+/// Creates a new file at a temporary path next to path with create and renames it over path,
+/// so that path never goes missing while it is replaced.
+pub(crate) fn replace_with<F>(path: &Path, create: F) -> Result<()>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    let temp = temp_path(path)?;
+    create(&temp)?;
+    if let Err(e) = rename(&temp, path) {
+        let _ = remove_file(&temp);
+        return Err(Op::Rename.error2(&temp, path, e));
+    }
+    Ok(())
+}
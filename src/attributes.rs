@@ -0,0 +1,96 @@
+use crate::{op::OpContext, win, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{io, path::Path};
+use windows_sys::Win32::Storage::FileSystem::{
+    GetFileAttributesW, SetFileAttributesW, FILE_ATTRIBUTE_ARCHIVE, FILE_ATTRIBUTE_HIDDEN,
+    FILE_ATTRIBUTE_NORMAL, FILE_ATTRIBUTE_READONLY, FILE_ATTRIBUTE_SYSTEM, INVALID_FILE_ATTRIBUTES,
+};
+
+/// The attribute bits of a file on Windows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WindowsAttributes {
+    bits: u32,
+}
+
+impl WindowsAttributes {
+    /// Creates attributes from the raw FILE_ATTRIBUTE_* bits.
+    pub fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Queries the attributes of a file. Symbolic links are not followed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let wide = win::to_wide(path)?;
+        let bits = unsafe { GetFileAttributesW(wide.as_ptr()) };
+        if bits == INVALID_FILE_ATTRIBUTES {
+            return Err(Op::Metadata.error(path, io::Error::last_os_error()));
+        }
+        Ok(Self::from_bits(bits))
+    }
+
+    /// Writes the attributes to a file.
+    /// Bits which can't be set with SetFileAttributesW, such as the directory bit, are ignored.
+    pub fn apply<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let wide = win::to_wide(path)?;
+        let bits = if self.bits == 0 {
+            FILE_ATTRIBUTE_NORMAL
+        } else {
+            self.bits
+        };
+        win::check(unsafe { SetFileAttributesW(wide.as_ptr(), bits) }).op(Op::SetAttributes, path)
+    }
+
+    /// Returns the raw FILE_ATTRIBUTE_* bits.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.has(FILE_ATTRIBUTE_READONLY)
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.has(FILE_ATTRIBUTE_HIDDEN)
+    }
+
+    pub fn is_system(&self) -> bool {
+        self.has(FILE_ATTRIBUTE_SYSTEM)
+    }
+
+    pub fn is_archive(&self) -> bool {
+        self.has(FILE_ATTRIBUTE_ARCHIVE)
+    }
+
+    pub fn set_readonly(&mut self, value: bool) -> &mut Self {
+        self.set(FILE_ATTRIBUTE_READONLY, value)
+    }
+
+    pub fn set_hidden(&mut self, value: bool) -> &mut Self {
+        self.set(FILE_ATTRIBUTE_HIDDEN, value)
+    }
+
+    pub fn set_system(&mut self, value: bool) -> &mut Self {
+        self.set(FILE_ATTRIBUTE_SYSTEM, value)
+    }
+
+    pub fn set_archive(&mut self, value: bool) -> &mut Self {
+        self.set(FILE_ATTRIBUTE_ARCHIVE, value)
+    }
+
+    fn has(&self, bit: u32) -> bool {
+        self.bits & bit != 0
+    }
+
+    fn set(&mut self, bit: u32, value: bool) -> &mut Self {
+        if value {
+            self.bits |= bit;
+        } else {
+            self.bits &= !bit;
+        }
+        self
+    }
+}
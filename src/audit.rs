@@ -0,0 +1,254 @@
+//! An audit trail of every operation through [`Filey`](crate::Filey) which changes the
+//! filesystem, built on [`hooks`](crate::hooks), and checks of a tree for risky permissions.
+//!
+//! Each operation appends a JSON line such as
+//! ```text
+//! {"timestamp":"2024-05-02T09:30:00.125Z","op":"Copy","paths":["report.pdf","/mnt/share"],"size":48213,"result":"ok","pid":4242}
+//! ```
+//! `size` is the length of the first of the paths which is a regular file after the
+//! operation, or null. `result` is `ok`, `refused` or `error`, and the last two come with an
+//! `error` message.
+//!
+//! # Examples
+//! ```
+//! # use filey::{audit::AuditLog, logfile::Appender, units::MIB, Filey};
+//! # use std::error::Error;
+//! #
+//! # fn audited() -> Result<(), Box<Error>> {
+//! let _audit = AuditLog::open("/var/log/app/audit.jsonl")?;
+//! Filey::new("uploads/tmp").remove()?;
+//!
+//! // Or rotate the log.
+//! let _audit = AuditLog::to_writer(Appender::new("audit.jsonl", 100 * MIB, 10)?);
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # audited().unwrap();
+//! # }
+//! ```
+
+use crate::{
+    hooks::{self, HookId, Operation},
+    op::OpContext,
+    Error, Op, Result,
+};
+use std::{
+    fmt::Write as _,
+    fs::{symlink_metadata, OpenOptions},
+    io::Write,
+    path::Path,
+    process,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+#[cfg(target_family = "unix")]
+use std::{fs::read_dir, os::unix::fs::PermissionsExt, path::PathBuf};
+
+/// Records operations while it is alive.
+/// Dropping it stops recording.
+#[derive(Debug)]
+pub struct AuditLog {
+    hook: HookId,
+}
+
+impl AuditLog {
+    /// Appends the trail to path, creating it if it doesn't exist.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .op(Op::Open, path)?;
+        Ok(Self::to_writer(file))
+    }
+
+    /// Writes the trail to writer, one line per write.
+    /// Errors writing to it are ignored so that they don't fail the operations being recorded.
+    pub fn to_writer<W: Write + Send + 'static>(writer: W) -> Self {
+        let writer = Mutex::new(writer);
+        let hook = hooks::on_after(move |operation, result| {
+            let line = record(operation, result, SystemTime::now());
+            if let Ok(mut writer) = writer.lock() {
+                let _ = writer
+                    .write_all(line.as_bytes())
+                    .and_then(|()| writer.flush());
+            }
+        });
+        Self { hook }
+    }
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        hooks::remove(self.hook);
+    }
+}
+
+/// An entry found by [`world_writable`].
+#[cfg(target_family = "unix")]
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct Finding {
+    pub path: PathBuf,
+    /// The permission bits including setuid, setgid and sticky, e.g. 0o4755.
+    pub mode: u32,
+    /// Anyone may write to it, or create and remove entries in it if it is a directory.
+    pub world_writable: bool,
+    /// It runs with the privileges of its owner or group (setuid or setgid).
+    pub setuid: bool,
+}
+
+/// (Unix only) Walks root and everything under it, reporting entries which are world-writable
+/// or setuid/setgid, sorted by path.
+/// Symbolic links are neither reported nor followed.
+///
+/// # Errors
+/// * root doesn't exist.
+/// * The user lacks permissions to read a directory.
+///
+/// # Examples
+/// ```
+/// # use filey::audit;
+/// # use std::error::Error;
+/// #
+/// # fn harden() -> Result<(), Box<Error>> {
+/// for finding in audit::world_writable("/srv/app")? {
+///     println!("{:o} {}", finding.mode, finding.path.display());
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # harden().unwrap();
+/// # }
+/// ```
+#[cfg(target_family = "unix")]
+pub fn world_writable<P: AsRef<Path>>(root: P) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    check_tree(root.as_ref(), &mut findings)?;
+    findings.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(findings)
+}
+
+#[cfg(target_family = "unix")]
+fn check_tree(path: &Path, findings: &mut Vec<Finding>) -> Result<()> {
+    let metadata = symlink_metadata(path).op(Op::Metadata, path)?;
+    if metadata.is_symlink() {
+        return Ok(());
+    }
+    let mode = metadata.permissions().mode() & 0o7777;
+    let world_writable = mode & 0o002 != 0;
+    let setuid = mode & 0o6000 != 0;
+    if world_writable || setuid {
+        findings.push(Finding {
+            path: path.to_path_buf(),
+            mode,
+            world_writable,
+            setuid,
+        });
+    }
+    if metadata.is_dir() {
+        for entry in read_dir(path).op(Op::ReadDir, path)? {
+            check_tree(&entry.op(Op::ReadDir, path)?.path(), findings)?;
+        }
+    }
+    Ok(())
+}
+
+fn record(
+    operation: &Operation,
+    result: std::result::Result<(), &Error>,
+    now: SystemTime,
+) -> String {
+    let paths = operation
+        .paths()
+        .map(|path| json_string(&path.to_string_lossy()))
+        .collect::<Vec<_>>();
+    let size = operation
+        .paths()
+        .filter_map(|path| symlink_metadata(path).ok())
+        .find(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len().to_string())
+        .unwrap_or_else(|| "null".to_string());
+
+    let mut line = format!(
+        "{{\"timestamp\":\"{}\",\"op\":\"{:?}\",\"paths\":[{}],\"size\":{},",
+        timestamp(now),
+        operation.op,
+        paths.join(","),
+        size
+    );
+    match result {
+        Ok(()) => line.push_str("\"result\":\"ok\","),
+        Err(e) => {
+            let kind = match e {
+                Error::Refused { .. } => "refused",
+                _ => "error",
+            };
+            let _ = write!(
+                line,
+                "\"result\":\"{}\",\"error\":{},",
+                kind,
+                json_string(&e.to_string())
+            );
+        }
+    }
+    let _ = writeln!(line, "\"pid\":{}}}", process::id());
+    line
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats time as RFC 3339 in UTC with milliseconds.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+
+    // Converts days since 1970-01-01 to a date in the proleptic Gregorian calendar.
+    let days = days as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
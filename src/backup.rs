@@ -0,0 +1,166 @@
+//! Incremental backups.
+//!
+//! # Examples
+//! ```
+//! # use filey::backup;
+//! # use std::error::Error;
+//! #
+//! # fn nightly() -> Result<(), Box<Error>> {
+//! let report = backup::link_dest(
+//!     "/home/alice",
+//!     "/backup/2024-05-02",
+//!     Some("/backup/2024-05-01"),
+//! )?;
+//! println!("{} copied, {} unchanged", report.copied.len(), report.linked.len());
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # nightly().unwrap();
+//! # }
+//! ```
+
+use crate::{copy, op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir, hard_link, read_dir, set_permissions, symlink_metadata, File, Metadata},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// What [`link_dest`] did with each regular file.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BackupReport {
+    /// Files which were unchanged since the previous backup and hard linked to it.
+    pub linked: Vec<PathBuf>,
+    /// Files which were new or changed and copied.
+    pub copied: Vec<PathBuf>,
+}
+
+/// Backs up the directory src to dest, which must not exist, like `rsync --link-dest`.
+///
+/// A regular file with the same size and modification time as the file at the same path in
+/// previous is hard linked to it instead of copied, so every backup is a complete tree but only
+/// changed files take up space. Copied files keep their modification times so that the next
+/// backup can link to them. Symbolic links are copied as links.
+/// Without previous every file is copied.
+///
+/// The paths in the report are relative to src.
+///
+/// # Errors
+/// * src doesn't exist.
+/// * dest already exists.
+/// * The user lacks permissions.
+pub fn link_dest<P, Q, R>(src: P, dest: Q, previous: Option<R>) -> Result<BackupReport>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    R: AsRef<Path>,
+{
+    let mut report = BackupReport::default();
+    back_up(
+        src.as_ref(),
+        dest.as_ref(),
+        previous.as_ref().map(|p| p.as_ref()),
+        Path::new(""),
+        &mut report,
+    )?;
+    Ok(report)
+}
+
+fn back_up(
+    src: &Path,
+    dest: &Path,
+    previous: Option<&Path>,
+    relative: &Path,
+    report: &mut BackupReport,
+) -> Result<()> {
+    let metadata = symlink_metadata(src).op(Op::Metadata, src)?;
+    create_dir(dest).op(Op::CreateDir, dest)?;
+
+    let mut entries = read_dir(src)
+        .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+        .op(Op::ReadDir, src)?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        let source = entry.path();
+        let destination = dest.join(&name);
+        let previous = previous.map(|previous| previous.join(&name));
+        let relative = relative.join(&name);
+        let metadata = symlink_metadata(&source).op(Op::Metadata, &source)?;
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            back_up(
+                &source,
+                &destination,
+                previous.as_deref(),
+                &relative,
+                report,
+            )?;
+        } else if file_type.is_symlink() {
+            copy::copy_symlink(&source, &destination)?;
+        } else if link_unchanged(&metadata, previous.as_deref(), &destination)? {
+            report.linked.push(relative);
+        } else {
+            copy_file(&source, &destination, &metadata)?;
+            report.copied.push(relative);
+        }
+    }
+
+    // Set last so a read-only directory can still be filled.
+    set_permissions(dest, metadata.permissions()).op(Op::SetPermissions, dest)
+}
+
+/// Hard links destination to previous if the file is unchanged.
+/// Returns false if it has to be copied instead, which includes a previous file that can't have
+/// another hard link.
+fn link_unchanged(
+    metadata: &Metadata,
+    previous: Option<&Path>,
+    destination: &Path,
+) -> Result<bool> {
+    let Some(previous) = previous else {
+        return Ok(false);
+    };
+    let Ok(old) = symlink_metadata(previous) else {
+        return Ok(false);
+    };
+    let unchanged = match (old.modified(), metadata.modified()) {
+        (Ok(old_modified), Ok(modified)) => {
+            old.is_file() && old.len() == metadata.len() && old_modified == modified
+        }
+        _ => false,
+    };
+    if !unchanged {
+        return Ok(false);
+    }
+
+    match hard_link(previous, destination) {
+        Ok(()) => Ok(true),
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::CrossesDevices | ErrorKind::TooManyLinks
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(Op::HardLink.error2(destination, previous, e)),
+    }
+}
+
+fn copy_file(source: &Path, destination: &Path, metadata: &Metadata) -> Result<()> {
+    let mut reader = File::open(source).op(Op::Open, source)?;
+    let mut writer = File::create_new(destination).op(Op::Create, destination)?;
+    io::copy(&mut reader, &mut writer).op2(Op::Copy, source, destination)?;
+    if let Ok(modified) = metadata.modified() {
+        writer
+            .set_modified(modified)
+            .op(Op::SetAttributes, destination)?;
+    }
+    // Set last because a read-only file can't be written to.
+    set_permissions(destination, metadata.permissions()).op(Op::SetPermissions, destination)
+}
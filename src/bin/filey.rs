@@ -0,0 +1,259 @@
+//! A command line front end to the filey library.
+
+use filey::{
+    checksum::{self, Algorithm},
+    snapshot::{self, SnapshotOptions},
+    units::UnitOfInfo,
+    BatchOptions, CopyOptions, FileTypes, Filey, SymlinkBehavior,
+};
+use std::{collections::BTreeMap, env, error::Error, fs, path::Path, process::ExitCode};
+
+const USAGE: &str = "\
+Usage: filey <command> [arguments]
+
+Commands:
+  tree [DIR]                      Print the directory tree
+  du [PATH]...                    Print the size of files and directories
+  checksum [-a ALGORITHM] [-j N] PATH...
+                                  Print the digest of files and directory trees
+                                  (ALGORITHM is sha256 or fnv1a64)
+  sync [-l SYMLINKS] SRC DEST     Make DEST an exact copy of the directory SRC
+                                  (SYMLINKS is preserve, follow or skip)
+  dedupe DIR                      Print groups of files with identical contents
+  trash PATH...                   Move files and directories to the trash
+";
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprint!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let result = match command.as_str() {
+        "tree" => tree(args),
+        "du" => du(args),
+        "checksum" => checksum(args),
+        "sync" => sync(args),
+        "dedupe" => dedupe(args),
+        "trash" => trash(args),
+        "help" | "-h" | "--help" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("Unknown command '{}'\n\n{}", command, USAGE).into()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("filey: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn tree(args: &[String]) -> CliResult {
+    let root = args.first().map(String::as_str).unwrap_or(".");
+    println!("{}", root);
+    print_tree(Path::new(root), "")
+}
+
+fn print_tree(path: &Path, prefix: &str) -> CliResult {
+    let mut entries = Filey::new(path).list_entries()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for (i, entry) in entries.iter().enumerate() {
+        let last = i + 1 == entries.len();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let branch = if last { "└── " } else { "├── " };
+        match entry.file_type() {
+            FileTypes::Symlink => {
+                let target = fs::read_link(entry.path())?;
+                println!("{}{}{} -> {}", prefix, branch, name, target.display());
+            }
+            FileTypes::Directory => {
+                println!("{}{}{}/", prefix, branch, name);
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                print_tree(&entry.path(), &prefix)?;
+            }
+            _ => println!("{}{}{}", prefix, branch, name),
+        }
+    }
+    Ok(())
+}
+
+fn du(args: &[String]) -> CliResult {
+    let paths = if args.is_empty() {
+        vec![".".to_string()]
+    } else {
+        args.to_vec()
+    };
+    for path in paths {
+        println!("{}\t{}", UnitOfInfo::format(usage(Path::new(&path))?), path);
+    }
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn usage(path: &Path) -> Result<u64, Box<dyn Error>> {
+    Ok(Filey::new(path).disk_usage()?.on_disk())
+}
+
+#[cfg(not(target_family = "unix"))]
+fn usage(path: &Path) -> Result<u64, Box<dyn Error>> {
+    Ok(Filey::new(path).plan_remove()?.bytes)
+}
+
+fn checksum(args: &[String]) -> CliResult {
+    let mut algorithm = Algorithm::Sha256;
+    let mut jobs = 0;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-a" | "--algorithm" => {
+                algorithm = match args.next().map(String::as_str) {
+                    Some("sha256") => Algorithm::Sha256,
+                    Some("fnv1a64") => Algorithm::Fnv1a64,
+                    other => return Err(format!("Unknown algorithm {:?}", other).into()),
+                }
+            }
+            "-j" | "--jobs" => {
+                jobs = args.next().ok_or("-j needs the number of jobs")?.parse()?;
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return Err("checksum needs at least one path".into());
+    }
+
+    for path in paths {
+        let digest = if Path::new(path).is_dir() {
+            checksum::hash_tree(path, algorithm, jobs)?.root
+        } else {
+            checksum::hash_file(path, algorithm)?
+        };
+        println!("{}  {}", digest, path);
+    }
+    Ok(())
+}
+
+fn sync(args: &[String]) -> CliResult {
+    let mut symlinks = SymlinkBehavior::Preserve;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" | "--symlinks" => {
+                symlinks = match args.next().map(String::as_str) {
+                    Some("preserve") => SymlinkBehavior::Preserve,
+                    Some("follow") => SymlinkBehavior::Follow,
+                    Some("skip") => SymlinkBehavior::Skip,
+                    other => return Err(format!("Unknown symlink behavior {:?}", other).into()),
+                }
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let [src, dest] = paths[..] else {
+        return Err("sync needs SRC and DEST".into());
+    };
+    let (src, dest) = (Path::new(src), Path::new(dest));
+    let batch = BatchOptions {
+        symlinks,
+        ..Default::default()
+    };
+    if !dest.exists() {
+        Filey::new(src).copy_dir_filtered_with(dest, |_| true, &batch)?;
+        return Ok(());
+    }
+
+    // Copies get new modification times, so compare by contents.
+    let options = SnapshotOptions {
+        hash: Some(Algorithm::Sha256),
+        batch,
+        ..Default::default()
+    };
+    let source = snapshot::take_with(src, &options)?;
+    let changes = snapshot::diff(&snapshot::take_with(dest, &options)?, &source);
+    for path in changes.removed.iter().chain(&changes.modified).rev() {
+        let target = Filey::new(dest.join(path));
+        if target.path().symlink_metadata().is_ok() {
+            target.remove()?;
+            println!("removed {}", path.display());
+        }
+    }
+    let copy = CopyOptions {
+        create_parents: true,
+        ..Default::default()
+    };
+    for path in changes.added.iter().chain(&changes.modified) {
+        let from = src.join(path);
+        let target = dest.join(path);
+        if target.symlink_metadata().is_ok() {
+            // Copied along with its parent directory.
+            continue;
+        }
+        // Followed links are recorded as what they point to.
+        match source.entries[path].file_type {
+            FileTypes::Directory => {
+                Filey::new(&from).copy_dir_filtered_with(&target, |_| true, &options.batch)?
+            }
+            FileTypes::Symlink => copy_symlink(&from, &target)?,
+            _ => Filey::new(&from).copy_with(&target, &copy)?,
+        }
+        println!("copied {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn copy_symlink(source: &Path, target: &Path) -> CliResult {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)?;
+    Ok(())
+}
+
+#[cfg(not(target_family = "unix"))]
+fn copy_symlink(source: &Path, target: &Path) -> CliResult {
+    fs::copy(source, target)?;
+    Ok(())
+}
+
+fn dedupe(args: &[String]) -> CliResult {
+    let root = Path::new(args.first().ok_or("dedupe needs a directory")?);
+    let manifest = snapshot::take(root)?;
+
+    let mut by_size: BTreeMap<u64, Vec<&Path>> = BTreeMap::new();
+    for (path, entry) in &manifest.entries {
+        if entry.file_type == FileTypes::File && entry.size > 0 {
+            by_size.entry(entry.size).or_default().push(path);
+        }
+    }
+
+    for paths in by_size.values().filter(|paths| paths.len() > 1) {
+        let mut by_digest = BTreeMap::new();
+        for path in paths {
+            let digest = checksum::hash_file(root.join(path), Algorithm::Sha256)?;
+            by_digest.entry(digest).or_insert_with(Vec::new).push(path);
+        }
+        for duplicates in by_digest.values().filter(|paths| paths.len() > 1) {
+            for path in duplicates {
+                println!("{}", root.join(path).display());
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn trash(args: &[String]) -> CliResult {
+    if args.is_empty() {
+        return Err("trash needs at least one path".into());
+    }
+    for path in args {
+        Filey::new(path).trash()?;
+    }
+    Ok(())
+}
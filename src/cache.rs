@@ -0,0 +1,209 @@
+//! On-disk caches.
+//!
+//! # Examples
+//! ```
+//! # use filey::cache::CacheDir;
+//! # use std::{error::Error, time::Duration};
+//! #
+//! # fn thumbnails() -> Result<(), Box<Error>> {
+//! let cache = CacheDir::new("/var/cache/app/thumbnails")?;
+//! let thumbnail = match cache.get("photos/beach.jpg@256")? {
+//!     Some(thumbnail) => thumbnail,
+//!     None => {
+//!         let thumbnail = vec![0; 1024]; // render it
+//!         cache.put("photos/beach.jpg@256", &thumbnail)?;
+//!         thumbnail
+//!     }
+//! };
+//! cache.evict_older_than(Duration::from_secs(7 * 24 * 60 * 60))?;
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # thumbnails().unwrap();
+//! # }
+//! ```
+
+use crate::{
+    atomic,
+    checksum::{self, Algorithm},
+    op::OpContext,
+    FileTypes, Op, Result, Walk, WalkOptions,
+};
+use std::{
+    fs::{create_dir_all, metadata, read, remove_file, symlink_metadata, Metadata},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+/// The metadata of a path, queried at most once per kind until it is cleared.
+/// Only successful queries are cached, so a missing file is looked up again every time.
+#[derive(Debug, Default)]
+pub(crate) struct MetadataCache {
+    follow: Mutex<Option<Metadata>>,
+    no_follow: Mutex<Option<Metadata>>,
+}
+
+impl Clone for MetadataCache {
+    fn clone(&self) -> Self {
+        Self {
+            follow: Mutex::new(lock(&self.follow).clone()),
+            no_follow: Mutex::new(lock(&self.no_follow).clone()),
+        }
+    }
+}
+
+impl MetadataCache {
+    /// Returns the metadata of path like stat(2).
+    pub(crate) fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        // stat and lstat only differ for symbolic links.
+        if let Some(no_follow) = lock(&self.no_follow).as_ref() {
+            if !no_follow.is_symlink() {
+                return Ok(no_follow.clone());
+            }
+        }
+        cached(&self.follow, || metadata(path))
+    }
+
+    /// Returns the metadata of path like lstat(2).
+    pub(crate) fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        cached(&self.no_follow, || symlink_metadata(path))
+    }
+
+    /// Discards the cached metadata, e.g. after the file changed.
+    pub(crate) fn clear(&self) {
+        *lock(&self.follow) = None;
+        *lock(&self.no_follow) = None;
+    }
+}
+
+// The cells are only ever assigned whole, so a poisoned one is still consistent.
+fn lock(cell: &Mutex<Option<Metadata>>) -> MutexGuard<'_, Option<Metadata>> {
+    cell.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn cached<F: FnOnce() -> io::Result<Metadata>>(
+    cell: &Mutex<Option<Metadata>>,
+    query: F,
+) -> io::Result<Metadata> {
+    if let Some(metadata) = lock(cell).as_ref() {
+        return Ok(metadata.clone());
+    }
+    let metadata = query()?;
+    *lock(cell) = Some(metadata.clone());
+    Ok(metadata)
+}
+
+/// A directory storing values under string keys, for caching data such as thumbnails or
+/// downloads between runs.
+///
+/// Each value is a file named after the SHA-256 digest of its key, in a subdirectory named
+/// after the first two hex digits of the digest, so that no directory grows too large.
+/// Values are written atomically, so a reader never sees a partially written value even if
+/// several processes share the directory.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct CacheDir {
+    root: PathBuf,
+}
+
+impl CacheDir {
+    /// Opens the cache at root, creating the directory and its parents if they don't exist.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref();
+        create_dir_all(root).op(Op::CreateDir, root)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// Returns the directory of the cache.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path of the file holding the value of key, whether it exists or not.
+    pub fn path(&self, key: &str) -> PathBuf {
+        let digest = checksum::hash_bytes(key.as_bytes(), Algorithm::Sha256).to_string();
+        let (shard, name) = digest.split_at(2);
+        self.root.join(shard).join(name)
+    }
+
+    /// Stores value under key, replacing a previous value.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn put<V: AsRef<[u8]>>(&self, key: &str, value: V) -> Result<()> {
+        let path = self.path(key);
+        if let Some(shard) = path.parent() {
+            create_dir_all(shard).op(Op::CreateDir, shard)?;
+        }
+        atomic::write_with(&path, |file| file.write_all(value.as_ref()))?;
+        Ok(())
+    }
+
+    /// Returns the value stored under key, or None if there is none.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.path(key);
+        match read(&path) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Op::Read.error(&path, e)),
+        }
+    }
+
+    /// Removes the value stored under key. Returns false if there was none.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn remove(&self, key: &str) -> Result<bool> {
+        let path = self.path(key);
+        match remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(Op::Remove.error(&path, e)),
+        }
+    }
+
+    /// Removes the values stored longer ago than age, judging by the modification times of
+    /// their files, and returns how many were removed. Temporary files left behind by
+    /// interrupted writes are removed the same way.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn evict_older_than(&self, age: Duration) -> Result<usize> {
+        let Some(cutoff) = SystemTime::now().checked_sub(age) else {
+            return Ok(0);
+        };
+        let options = WalkOptions {
+            max_depth: Some(2),
+            file_types: vec![FileTypes::File],
+            ..Default::default()
+        };
+        let mut evicted = 0;
+        for entry in Walk::new(&self.root, &options)? {
+            let path = entry?.path();
+            let modified = match symlink_metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(Op::Metadata.error(&path, e)),
+            };
+            if modified >= cutoff {
+                continue;
+            }
+            match remove_file(&path) {
+                Ok(()) => evicted += 1,
+                // Evicted by another process.
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(Op::Remove.error(&path, e)),
+            }
+        }
+        Ok(evicted)
+    }
+}
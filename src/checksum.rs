@@ -0,0 +1,523 @@
+//! File and directory tree checksums.
+//!
+//! # Examples
+//! ```
+//! # use filey::checksum::{self, Algorithm};
+//! # use std::error::Error;
+//! #
+//! # fn verify() -> Result<(), Box<Error>> {
+//! let exported = checksum::hash_tree("/mnt/export/dataset", Algorithm::Sha256, 8)?;
+//! let original = checksum::hash_tree("dataset", Algorithm::Sha256, 8)?;
+//! assert_eq!(exported.root, original.root);
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # verify().unwrap();
+//! # }
+//! ```
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use crate::{
+    hooks::{self, Operation},
+    xattr,
+};
+use crate::{op::OpContext, pool, BatchOptions, BufferSize, IoStats, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fmt,
+    fs::{read_dir, read_link, symlink_metadata, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::{fs::metadata, time::UNIX_EPOCH};
+
+/// The extended attribute [`hash_file_cached`] stores digests in.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const CACHE_ATTRIBUTE: &str = "user.filey.checksum";
+
+/// A hash algorithm.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Algorithm {
+    /// SHA-256, for verifying data against corruption and tampering.
+    Sha256,
+    /// 64-bit FNV-1a, which is much faster but only detects accidental changes.
+    Fnv1a64,
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sha256 => write!(f, "sha256"),
+            Self::Fnv1a64 => write!(f, "fnv1a64"),
+        }
+    }
+}
+
+/// Options for [`hash_file_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HashOptions {
+    /// The size of the buffer the file is read through, 64 KiB by default.
+    pub buffer_size: BufferSize,
+}
+
+/// The output of a hash algorithm, displayed and serialized in lowercase hexadecimal.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Hash)]
+pub struct Digest(Vec<u8>);
+
+impl Digest {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Parses a digest from hexadecimal.
+    /// Returns None if hex isn't an even number of hexadecimal digits.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<Vec<_>>>()
+            .map(Self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Self::from_hex(&hex)
+            .ok_or_else(|| de::Error::custom(format!("'{}' isn't a hexadecimal digest", hex)))
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The digests of every file in a directory tree.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TreeDigest {
+    /// The digest of each regular file and symbolic link, keyed by its path relative to the
+    /// root. A symbolic link is hashed by the path it points to.
+    pub files: BTreeMap<PathBuf, Digest>,
+    /// The digest of the whole tree.
+    /// Each directory is hashed from the names, types and digests of its children, so two
+    /// trees have the same root digest only if they have the same structure and contents.
+    pub root: Digest,
+}
+
+/// Hashes the contents of the file at path.
+///
+/// # Errors
+/// * The file doesn't exist.
+/// * The user lacks permissions.
+pub fn hash_file<P: AsRef<Path>>(path: P, algorithm: Algorithm) -> Result<Digest> {
+    hash_file_with_stats(path, algorithm).map(|(digest, _)| digest)
+}
+
+/// Hashes the file at path like [`hash_file`], with options such as the buffer size.
+///
+/// # Errors
+/// * The file doesn't exist.
+/// * The user lacks permissions.
+///
+/// # Examples
+/// ```
+/// # use filey::{checksum::{self, Algorithm, HashOptions}, BufferSize};
+/// # use std::error::Error;
+/// #
+/// # fn verify() -> Result<(), Box<Error>> {
+/// let options = HashOptions {
+///     buffer_size: BufferSize::Auto,
+/// };
+/// let digest = checksum::hash_file_with("/mnt/nfs/image.iso", Algorithm::Sha256, &options)?;
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # verify().unwrap();
+/// # }
+/// ```
+pub fn hash_file_with<P: AsRef<Path>>(
+    path: P,
+    algorithm: Algorithm,
+    options: &HashOptions,
+) -> Result<Digest> {
+    hash(path.as_ref(), algorithm, options.buffer_size).map(|(digest, _)| digest)
+}
+
+/// Hashes the file at path like [`hash_file`], also returning how many bytes were read and
+/// how long it took.
+///
+/// # Errors
+/// * The file doesn't exist.
+/// * The user lacks permissions.
+pub fn hash_file_with_stats<P: AsRef<Path>>(
+    path: P,
+    algorithm: Algorithm,
+) -> Result<(Digest, IoStats)> {
+    hash(path.as_ref(), algorithm, BufferSize::Default)
+}
+
+fn hash(path: &Path, algorithm: Algorithm, buffer_size: BufferSize) -> Result<(Digest, IoStats)> {
+    let started = Instant::now();
+    let mut file = File::open(path).op(Op::Open, path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buf = vec![0; buffer_size.resolve(&file, 64 * 1024)];
+    let mut bytes_read = 0;
+    loop {
+        let n = file.read(&mut buf).op(Op::Read, path)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        bytes_read += n as u64;
+    }
+    let stats = IoStats {
+        bytes_read,
+        files: 1,
+        elapsed: started.elapsed(),
+        ..Default::default()
+    };
+    Ok((hasher.finish(), stats))
+}
+
+/// Hashes data in memory.
+pub(crate) fn hash_bytes(data: &[u8], algorithm: Algorithm) -> Digest {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Hashes the file at path like [`hash_file`], reusing the digest stored in an extended
+/// attribute by a previous call while the size and modification time of the file are the same.
+/// Caching is best effort: without support for extended attributes the file is hashed every
+/// time.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn hash_file_cached(path: &Path, algorithm: Algorithm) -> Result<Digest> {
+    // Taken before hashing, so a file changed meanwhile doesn't match the stored fingerprint.
+    let metadata = metadata(path).op(Op::Metadata, path)?;
+    let fingerprint = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| {
+            format!(
+                "{} {} {}.{:09}",
+                algorithm,
+                metadata.len(),
+                modified.as_secs(),
+                modified.subsec_nanos()
+            )
+        });
+    let Some(fingerprint) = fingerprint else {
+        return hash_file(path, algorithm);
+    };
+
+    if let Ok(Some(cached)) = xattr::get(path, CACHE_ATTRIBUTE) {
+        let digest = String::from_utf8_lossy(&cached)
+            .strip_prefix(&fingerprint)
+            .and_then(|rest| rest.strip_prefix(' '))
+            .and_then(Digest::from_hex);
+        if let Some(digest) = digest {
+            return Ok(digest);
+        }
+    }
+
+    let digest = hash_file(path, algorithm)?;
+    let cached = format!("{} {}", fingerprint, digest);
+    // A hook refusing the write only keeps the digest from being cached.
+    let _ = hooks::run(Operation::new(Op::SetXattr, path), || {
+        xattr::set(path, CACHE_ATTRIBUTE, cached.as_bytes())
+    });
+    Ok(digest)
+}
+
+/// Hashes the file at path like [`hash_file`]; extended attributes aren't supported here.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn hash_file_cached(path: &Path, algorithm: Algorithm) -> Result<Digest> {
+    hash_file(path, algorithm)
+}
+
+/// Hashes every file under root on up to jobs threads (0 = the number of CPUs).
+/// Symbolic links are not followed.
+///
+/// # Errors
+/// * root doesn't exist or isn't a directory.
+/// * The user lacks permissions.
+pub fn hash_tree<P: AsRef<Path>>(root: P, algorithm: Algorithm, jobs: usize) -> Result<TreeDigest> {
+    hash_tree_with_stats(root, algorithm, jobs).map(|(digest, _)| digest)
+}
+
+/// Hashes every file under root like [`hash_tree`], also returning how many files and bytes
+/// were read and how long it took.
+///
+/// # Errors
+/// * root doesn't exist or isn't a directory.
+/// * The user lacks permissions.
+pub fn hash_tree_with_stats<P: AsRef<Path>>(
+    root: P,
+    algorithm: Algorithm,
+    jobs: usize,
+) -> Result<(TreeDigest, IoStats)> {
+    let started = Instant::now();
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    let tree = scan(root, Path::new(""), &mut files)?;
+
+    let options = BatchOptions {
+        max_in_flight: jobs,
+        ..Default::default()
+    };
+    let mut stats = IoStats::default();
+    let digests = pool::map(files, &options, |relative| {
+        hash_file_with_stats(root.join(&relative), algorithm)
+            .map(|(digest, file_stats)| (relative, digest, file_stats))
+    })
+    .into_iter()
+    .map(|hashed| {
+        hashed.map(|(relative, digest, file_stats)| {
+            stats.bytes_read += file_stats.bytes_read;
+            stats.files += 1;
+            (relative, digest)
+        })
+    })
+    .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let mut symlinks = BTreeMap::new();
+    let root_digest = fold(&tree, Path::new(""), algorithm, &digests, &mut symlinks);
+    let mut files = digests;
+    files.append(&mut symlinks);
+    stats.elapsed = started.elapsed();
+    let digest = TreeDigest {
+        files,
+        root: root_digest,
+    };
+    Ok((digest, stats))
+}
+
+enum Node {
+    File,
+    Symlink(PathBuf),
+    Directory(Vec<(OsString, Node)>),
+}
+
+/// Walks the tree, collecting the relative paths of the regular files to hash.
+fn scan(path: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<Node> {
+    let mut entries = read_dir(path)
+        .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+        .op(Op::ReadDir, path)?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut children = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let source = entry.path();
+        let relative = relative.join(entry.file_name());
+        let file_type = symlink_metadata(&source)
+            .op(Op::Metadata, &source)?
+            .file_type();
+        let node = if file_type.is_dir() {
+            scan(&source, &relative, files)?
+        } else if file_type.is_symlink() {
+            Node::Symlink(read_link(&source).op(Op::ReadLink, &source)?)
+        } else {
+            files.push(relative);
+            Node::File
+        };
+        children.push((entry.file_name(), node));
+    }
+    Ok(Node::Directory(children))
+}
+
+/// Computes the digest of node from the digests of the files below it.
+fn fold(
+    node: &Node,
+    relative: &Path,
+    algorithm: Algorithm,
+    digests: &BTreeMap<PathBuf, Digest>,
+    symlinks: &mut BTreeMap<PathBuf, Digest>,
+) -> Digest {
+    match node {
+        Node::File => digests[relative].clone(),
+        Node::Symlink(target) => {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(target.to_string_lossy().as_bytes());
+            let digest = hasher.finish();
+            symlinks.insert(relative.to_path_buf(), digest.clone());
+            digest
+        }
+        Node::Directory(children) => {
+            let mut hasher = Hasher::new(algorithm);
+            for (name, child) in children {
+                let digest = fold(child, &relative.join(name), algorithm, digests, symlinks);
+                let kind = match child {
+                    Node::File => b'f',
+                    Node::Symlink(_) => b'l',
+                    Node::Directory(_) => b'd',
+                };
+                hasher.update(&[kind]);
+                hasher.update(name.to_string_lossy().as_bytes());
+                hasher.update(&[0]);
+                hasher.update(digest.as_bytes());
+            }
+            hasher.finish()
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Fnv1a64(u64),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Sha256 => Self::Sha256(Sha256::new()),
+            Algorithm::Fnv1a64 => Self::Fnv1a64(0xcbf29ce484222325),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(sha256) => sha256.update(data),
+            Self::Fnv1a64(state) => {
+                for byte in data {
+                    *state ^= *byte as u64;
+                    *state = state.wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Digest {
+        match self {
+            Self::Sha256(sha256) => Digest(sha256.finish().to_vec()),
+            Self::Fnv1a64(state) => Digest(state.to_be_bytes().to_vec()),
+        }
+    }
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 as specified in FIPS 180-4.
+struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    filled: usize,
+    length: u64,
+}
+
+impl Sha256 {
+    fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            block: [0; 64],
+            filled: 0,
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let n = (64 - self.filled).min(data.len());
+            self.block[self.filled..self.filled + n].copy_from_slice(&data[..n]);
+            self.filled += n;
+            data = &data[n..];
+            if self.filled == 64 {
+                self.compress();
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 32] {
+        let bits = self.length.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.filled != 56 {
+            self.update(&[0]);
+        }
+        self.block[56..].copy_from_slice(&bits.to_be_bytes());
+        self.compress();
+
+        let mut digest = [0; 32];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (i, chunk) in self.block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{absolute, op::OpContext, space, Op, Result};
+use std::{
+    fs::canonicalize,
+    path::{Component, Path, PathBuf},
+};
+
+/// Compares two paths component by component, ignoring case like the default filesystems of
+/// Windows and macOS.
+/// Components which aren't valid Unicode are compared exactly.
+pub(crate) fn eq_ignore_case(a: &Path, b: &Path) -> bool {
+    let mut a = a.components();
+    let mut b = b.components();
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(a), Some(b)) if component_eq_ignore_case(a, b) => continue,
+            _ => return false,
+        }
+    }
+}
+
+/// Returns path with the components which are valid Unicode in lowercase, so that paths
+/// equal by [`eq_ignore_case`] are equal.
+pub(crate) fn fold_case(path: &Path) -> PathBuf {
+    path.components()
+        .map(|component| match component.as_os_str().to_str() {
+            Some(name) => name
+                .chars()
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+                .into(),
+            None => component.as_os_str().to_os_string(),
+        })
+        .collect()
+}
+
+fn component_eq_ignore_case(a: Component, b: Component) -> bool {
+    match (a.as_os_str().to_str(), b.as_os_str().to_str()) {
+        (Some(a), Some(b)) => a
+            .chars()
+            .flat_map(char::to_lowercase)
+            .eq(b.chars().flat_map(char::to_lowercase)),
+        _ => a == b,
+    }
+}
+
+/// Compares two paths component by component after normalizing them to NFC.
+/// Components which aren't valid Unicode are compared exactly.
+#[cfg(feature = "unicode")]
+pub(crate) fn eq_normalized(a: &Path, b: &Path) -> bool {
+    use unicode_normalization::UnicodeNormalization;
+
+    let mut a = a.components();
+    let mut b = b.components();
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(a), Some(b)) => {
+                let equal = match (a.as_os_str().to_str(), b.as_os_str().to_str()) {
+                    (Some(a), Some(b)) => a.nfc().eq(b.nfc()),
+                    _ => a == b,
+                };
+                if !equal {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Returns true if a and b refer to the same location after resolving them.
+pub(crate) fn canonical_eq(a: &Path, b: &Path) -> Result<bool> {
+    let (a, b) = (resolve(a)?, resolve(b)?);
+    if cfg!(any(target_family = "windows", target_os = "macos")) {
+        Ok(eq_ignore_case(&a, &b))
+    } else {
+        Ok(a == b)
+    }
+}
+
+/// Canonicalizes the nearest existing ancestor of path and appends the rest of it.
+pub(crate) fn resolve(path: &Path) -> Result<PathBuf> {
+    let absolute = absolute::absolutize(path).op(Op::Canonicalize, path)?;
+    let existing = space::nearest_existing(&absolute);
+    let rest = absolute.strip_prefix(existing).unwrap_or(Path::new(""));
+    let canonical = canonicalize(existing).op(Op::Canonicalize, existing)?;
+    Ok(canonical.join(rest))
+}
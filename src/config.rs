@@ -0,0 +1,208 @@
+#[cfg(feature = "serde")]
+use crate::{
+    atomic,
+    op::OpContext,
+    Error::{FileyError, UnknownFormat},
+    Op, Result,
+};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, path::Path};
+#[cfg(feature = "serde")]
+use std::{
+    fs::read_to_string,
+    io::{ErrorKind, Write},
+};
+
+/// A serialization format of configuration files.
+///
+/// Each format needs the cargo feature of the same name (`json`, `toml`, `yaml` or `ron`), which
+/// also enables the `serde` feature.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+    Ron,
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Toml => write!(f, "toml"),
+            Self::Yaml => write!(f, "yaml"),
+            Self::Ron => write!(f, "ron"),
+        }
+    }
+}
+
+impl ConfigFormat {
+    /// Detects the format from the extension of path.
+    /// Returns None if the extension is unknown.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "ron" => Some(Self::Ron),
+            _ => None,
+        }
+    }
+
+    /// Detects the format by trying to parse contents with each enabled format.
+    /// YAML is tried last because almost any text is valid YAML.
+    /// Returns None if no enabled format can parse contents or contents is blank.
+    pub fn sniff(contents: &str) -> Option<Self> {
+        if contents.trim().is_empty() {
+            return None;
+        }
+        [Self::Json, Self::Toml, Self::Ron, Self::Yaml]
+            .into_iter()
+            .find(|format| format.parses(contents))
+    }
+
+    /// Detects the format from the extension of path, then from its contents.
+    pub fn detect<P: AsRef<Path>>(path: P, contents: &str) -> Option<Self> {
+        Self::from_extension(path).or_else(|| Self::sniff(contents))
+    }
+
+    fn parses(&self, contents: &str) -> bool {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_str::<serde_json::Value>(contents).is_ok(),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::from_str::<toml::Table>(contents).is_ok(),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::from_str::<serde_yaml::Mapping>(contents).is_ok(),
+            #[cfg(feature = "ron")]
+            Self::Ron => ron::from_str::<ron::Value>(contents).is_ok(),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = contents;
+                false
+            }
+        }
+    }
+
+    /// (Needs the `serde` feature) Deserializes contents in this format.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: DeserializeOwned>(&self, contents: &str) -> Result<T> {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_str(contents)
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::from_str(contents)
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::from_str(contents)
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[cfg(feature = "ron")]
+            Self::Ron => ron::from_str(contents)
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = contents;
+                Err(self.disabled())
+            }
+        }
+    }
+
+    /// (Needs the `serde` feature) Serializes value in this format.
+    #[cfg(feature = "serde")]
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String> {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::to_string_pretty(value)
+                .map(|s| s + "\n")
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::to_string_pretty(value)
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::to_string(value)
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[cfg(feature = "ron")]
+            Self::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                .map(|s| s + "\n")
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = value;
+                Err(self.disabled())
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[allow(dead_code)]
+    fn disabled(&self) -> crate::Error {
+        FileyError(anyhow::anyhow!(
+            "Enable the '{}' feature of filey to use {} files",
+            self,
+            self
+        ))
+    }
+}
+
+/// Reads path and deserializes it in the format detected by [`ConfigFormat::detect`].
+#[cfg(feature = "serde")]
+pub(crate) fn read<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T> {
+    let path = path.as_ref();
+    let contents = read_to_string(path).op(Op::Read, path)?;
+    let format = ConfigFormat::detect(path, &contents).ok_or_else(|| UnknownFormat {
+        path: path.to_string_lossy().to_string(),
+    })?;
+    format.deserialize(&contents)
+}
+
+/// Reads path and deserializes it in format.
+#[cfg(feature = "serde")]
+#[allow(dead_code)]
+pub(crate) fn read_as<T: DeserializeOwned, P: AsRef<Path>>(
+    path: P,
+    format: ConfigFormat,
+) -> Result<T> {
+    let path = path.as_ref();
+    let contents = read_to_string(path).op(Op::Read, path)?;
+    format.deserialize(&contents)
+}
+
+/// Serializes value in the format of the existing file (or of the extension of path) and writes
+/// it atomically.
+#[cfg(feature = "serde")]
+pub(crate) fn write<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> Result<()> {
+    let path = path.as_ref();
+    let existing = match read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Op::Read.error(path, e)),
+    };
+    let format = ConfigFormat::detect(path, &existing).ok_or_else(|| UnknownFormat {
+        path: path.to_string_lossy().to_string(),
+    })?;
+    write_as(path, value, format)
+}
+
+/// Serializes value in format and writes it atomically.
+#[cfg(feature = "serde")]
+pub(crate) fn write_as<T: Serialize, P: AsRef<Path>>(
+    path: P,
+    value: &T,
+    format: ConfigFormat,
+) -> Result<()> {
+    let contents = format.serialize(value)?;
+    atomic::write_with(path, |file| file.write_all(contents.as_bytes()))?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use crate::{
+    compare,
+    op::OpContext,
+    pool, space, BatchOptions, BufferSize,
+    Error::{AlreadyExists, FileyError},
+    Filter, IoStats, OnConflict, Op, Result, SymlinkBehavior,
+};
+use std::{
+    fs::{
+        canonicalize, copy, create_dir, metadata as metadata_of, read_dir, remove_dir_all,
+        remove_file, set_permissions, symlink_metadata, File, Metadata, Permissions,
+    },
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// Copies the file from to to with its permissions and returns the number of bytes copied.
+/// [`BufferSize::Default`] leaves it to [`std::fs::copy`].
+pub(crate) fn copy_file(from: &Path, to: &Path, buffer_size: BufferSize) -> Result<u64> {
+    if buffer_size == BufferSize::Default {
+        return copy(from, to).op2(Op::Copy, from, to);
+    }
+    let mut source = File::open(from).op(Op::Open, from)?;
+    let permissions = source.metadata().op(Op::Metadata, from)?.permissions();
+    let mut destination = File::create(to).op(Op::Create, to)?;
+    let mut buf = vec![0; buffer_size.resolve(&source, 0)];
+    let mut copied = 0;
+    loop {
+        match source.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                destination.write_all(&buf[..n]).op(Op::Write, to)?;
+                copied += n as u64;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Op::Read.error(from, e)),
+        }
+    }
+    destination
+        .set_permissions(permissions)
+        .op(Op::SetPermissions, to)?;
+    Ok(copied)
+}
+
+/// Copies the directory from to to recursively, skipping every entry (and, for directories,
+/// everything below it) for which filter returns false.
+/// filter receives the path of the entry relative to from.
+///
+/// The whole tree is walked before anything is created. Then the directories and symbolic
+/// links are created and the files are copied on up to options.max_in_flight threads.
+/// Symbolic links are treated according to options.symlinks, and files are copied through a
+/// buffer of options.buffer_size.
+pub(crate) fn copy_dir_filtered<F: FnMut(&Path) -> bool>(
+    from: &Path,
+    to: &Path,
+    mut filter: F,
+    options: &BatchOptions,
+) -> Result<IoStats> {
+    let started = Instant::now();
+    let mut plan = Plan::default();
+    let mut walker = Walker {
+        filter: &mut filter,
+        symlinks: options.symlinks,
+        ancestors: Vec::new(),
+        plan: &mut plan,
+    };
+    walker.walk(from, to, Path::new(""))?;
+
+    if options.check_space {
+        space::ensure(to, plan.bytes())?;
+    }
+
+    for (directory, _) in &plan.directories {
+        create_dir(directory).op(Op::CreateDir, directory)?;
+    }
+    for (source, destination) in &plan.symlinks {
+        copy_symlink(source, destination)?;
+    }
+    let copied = pool::map(plan.files, options, |(source, destination, _)| {
+        copy_file(&source, &destination, options.buffer_size)
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+
+    // Set last so a read-only directory can still be filled. Children come first.
+    for (directory, permissions) in plan.directories.into_iter().rev() {
+        set_permissions(&directory, permissions).op(Op::SetPermissions, &directory)?;
+    }
+    let bytes = copied.iter().sum();
+    Ok(IoStats {
+        bytes_read: bytes,
+        bytes_written: bytes,
+        files: copied.len() as u64,
+        elapsed: started.elapsed(),
+    })
+}
+
+/// What copying a tree will do.
+#[derive(Default)]
+struct Plan {
+    /// The directories to create, parents first, with the permissions to give them.
+    directories: Vec<(PathBuf, Permissions)>,
+    symlinks: Vec<(PathBuf, PathBuf)>,
+    /// The files to copy with their lengths.
+    files: Vec<(PathBuf, PathBuf, u64)>,
+}
+
+impl Plan {
+    /// Returns the number of bytes the files will take up.
+    fn bytes(&self) -> u64 {
+        self.files.iter().map(|(_, _, len)| len).sum()
+    }
+}
+
+/// Walks a tree to copy into a [`Plan`].
+struct Walker<'a, F> {
+    filter: &'a mut F,
+    symlinks: SymlinkBehavior,
+    /// The resolved paths of the directories being walked, to recognize links leading back
+    /// to one of them when following links.
+    ancestors: Vec<PathBuf>,
+    plan: &'a mut Plan,
+}
+
+impl<F: FnMut(&Path) -> bool> Walker<'_, F> {
+    fn walk(&mut self, from: &Path, to: &Path, relative: &Path) -> Result<()> {
+        let metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+        self.plan
+            .directories
+            .push((to.to_path_buf(), metadata.permissions()));
+        if self.symlinks == SymlinkBehavior::Follow {
+            let canonical = canonicalize(from).op(Op::Canonicalize, from)?;
+            if self.ancestors.contains(&canonical) {
+                return Err(FileyError(anyhow::anyhow!(
+                    "'{}' leads back to a directory containing it",
+                    from.display()
+                )));
+            }
+            self.ancestors.push(canonical);
+        }
+
+        let mut entries = read_dir(from)
+            .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+            .op(Op::ReadDir, from)?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let relative = relative.join(entry.file_name());
+            if !(self.filter)(&relative) {
+                continue;
+            }
+            let source = entry.path();
+            let destination = to.join(entry.file_name());
+            let mut metadata = symlink_metadata(&source).op(Op::Metadata, &source)?;
+            if metadata.is_symlink() {
+                match self.symlinks {
+                    SymlinkBehavior::Preserve => {
+                        self.plan.symlinks.push((source, destination));
+                        continue;
+                    }
+                    SymlinkBehavior::Skip => continue,
+                    SymlinkBehavior::Follow => {
+                        metadata = metadata_of(&source).op(Op::Metadata, &source)?;
+                    }
+                }
+            }
+            if metadata.is_dir() {
+                self.walk(&source, &destination, &relative)?;
+            } else {
+                self.plan.files.push((source, destination, metadata.len()));
+            }
+        }
+        if self.symlinks == SymlinkBehavior::Follow {
+            self.ancestors.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Copies from to to recursively, merging directories into the ones which already exist at
+/// to. A file or symbolic link which already exists where something is copied to is handled
+/// according to on_conflict. Symbolic links are copied as links, and files and the
+/// directories created get the permissions of their sources.
+/// The entries below from which filter rejects are left out.
+pub(crate) fn copy_merging(
+    from: &Path,
+    to: &Path,
+    on_conflict: OnConflict,
+    filter: &Filter,
+) -> Result<()> {
+    // Copying a directory into itself would keep finding the entries it just copied.
+    if symlink_metadata(from).is_ok_and(|metadata| metadata.is_dir())
+        && compare::resolve(to)?.starts_with(compare::resolve(from)?)
+    {
+        return Err(FileyError(anyhow::anyhow!(
+            "Can't copy '{}' into itself at '{}'",
+            from.display(),
+            to.display()
+        )));
+    }
+    copy_entry(from, to, Path::new(""), on_conflict, filter)
+}
+
+/// Copies the entry from, at relative below the root of the copy, to to.
+fn copy_entry(
+    from: &Path,
+    to: &Path,
+    relative: &Path,
+    on_conflict: OnConflict,
+    filter: &Filter,
+) -> Result<()> {
+    let metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+    if !relative.as_os_str().is_empty() && !filter.matches(relative, &metadata) {
+        return Ok(());
+    }
+    let copy_children = || -> Result<()> {
+        for entry in read_dir(from).op(Op::ReadDir, from)? {
+            let entry = entry.op(Op::ReadDir, from)?;
+            copy_entry(
+                &entry.path(),
+                &to.join(entry.file_name()),
+                &relative.join(entry.file_name()),
+                on_conflict,
+                filter,
+            )?;
+        }
+        Ok(())
+    };
+    let existing = symlink_metadata(to).ok();
+    if metadata.is_dir() && existing.as_ref().is_some_and(Metadata::is_dir) {
+        return copy_children();
+    }
+
+    if let Some(existing) = existing {
+        match on_conflict {
+            OnConflict::Skip => return Ok(()),
+            OnConflict::Overwrite => {
+                if existing.is_dir() {
+                    remove_dir_all(to)
+                } else {
+                    remove_file(to)
+                }
+                .op(Op::Remove, to)?;
+            }
+            OnConflict::Fail => {
+                return Err(AlreadyExists {
+                    path: to.to_string_lossy().to_string(),
+                })
+            }
+        }
+    }
+
+    if metadata.is_symlink() {
+        copy_symlink(from, to)
+    } else if metadata.is_dir() {
+        create_dir(to).op(Op::CreateDir, to)?;
+        copy_children()?;
+        // Set last so a read-only directory can still be filled.
+        set_permissions(to, metadata.permissions()).op(Op::SetPermissions, to)
+    } else {
+        copy(from, to).op2(Op::Copy, from, to)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_family = "unix")]
+pub(crate) fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    let target = std::fs::read_link(from).op(Op::ReadLink, from)?;
+    std::os::unix::fs::symlink(&target, to).op2(Op::Symlink, to, &target)
+}
+
+#[cfg(target_family = "windows")]
+pub(crate) fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+
+    let target = std::fs::read_link(from).op(Op::ReadLink, from)?;
+    let result = if from.is_dir() {
+        symlink_dir(&target, to)
+    } else {
+        symlink_file(&target, to)
+    };
+    result.op2(Op::Symlink, to, &target)
+}
+
+#[cfg(not(any(target_family = "unix", target_family = "windows")))]
+pub(crate) fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    copy(from, to).op2(Op::Copy, from, to)?;
+    Ok(())
+}
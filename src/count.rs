@@ -0,0 +1,77 @@
+use crate::{op::OpContext, Op, Result};
+use std::{
+    fs::{read_dir, File, FileType},
+    io::{self, Read},
+    path::Path,
+};
+
+/// Reads path in chunks and calls f with each of them.
+fn for_each_chunk<P: AsRef<Path>, F: FnMut(&[u8])>(path: P, mut f: F) -> Result<()> {
+    let path = path.as_ref();
+    let mut file = File::open(path).op(Op::Open, path)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Op::Read.error(path, e)),
+        }
+    }
+}
+
+/// Counts the newlines like `wc -l`.
+pub(crate) fn lines<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut lines = 0;
+    // A plain filter-count over a byte slice is auto-vectorized.
+    for_each_chunk(path, |chunk| {
+        lines += chunk.iter().filter(|&&b| b == b'\n').count() as u64
+    })?;
+    Ok(lines)
+}
+
+/// Counts the sequences of non-whitespace bytes like `wc -w` in the C locale.
+pub(crate) fn words<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut words = 0;
+    // Whether the previous byte, possibly in the previous chunk, was whitespace.
+    let mut in_space = true;
+    for_each_chunk(path, |chunk| {
+        for &b in chunk {
+            let is_space = matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c');
+            if in_space && !is_space {
+                words += 1;
+            }
+            in_space = is_space;
+        }
+    })?;
+    Ok(words)
+}
+
+/// Counts the bytes by reading them, so it also works for pipes and files in /proc.
+pub(crate) fn bytes<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut bytes = 0;
+    for_each_chunk(path, |chunk| bytes += chunk.len() as u64)?;
+    Ok(bytes)
+}
+
+/// Counts the entries of the directory path whose type matches, descending into subdirectories
+/// if recursive. Symbolic links are counted as links and not followed.
+pub(crate) fn entries<P, F>(path: P, recursive: bool, matches: F) -> Result<u64>
+where
+    P: AsRef<Path>,
+    F: Fn(&FileType) -> bool + Copy,
+{
+    let path = path.as_ref();
+    let mut count = 0;
+    for entry in read_dir(path).op(Op::ReadDir, path)? {
+        let entry = entry.op(Op::ReadDir, path)?;
+        let file_type = entry.file_type().op(Op::Metadata, entry.path())?;
+        if matches(&file_type) {
+            count += 1;
+        }
+        if recursive && file_type.is_dir() {
+            count += entries(entry.path(), recursive, matches)?;
+        }
+    }
+    Ok(count)
+}
@@ -0,0 +1,50 @@
+use crate::{atomic, Error::FileyError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs::File, path::Path};
+
+/// A streaming iterator over the records of a CSV file.
+/// The first row is treated as the header.
+pub struct CsvRecords<T> {
+    records: ::csv::DeserializeRecordsIntoIter<File, T>,
+}
+
+impl<T: DeserializeOwned> CsvRecords<T> {
+    pub(crate) fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = ::csv::Reader::from_path(path)
+            .map_err(|e| e.into())
+            .map_err(FileyError)?;
+        Ok(Self {
+            records: reader.into_deserialize(),
+        })
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for CsvRecords<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.records
+                .next()?
+                .map_err(|e| e.into())
+                .map_err(FileyError),
+        )
+    }
+}
+
+/// Serializes rows with a header row and writes them atomically.
+pub(crate) fn write<P, I, T>(path: P, rows: I) -> Result<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = T>,
+    T: Serialize,
+{
+    atomic::write_with(path, |file| {
+        let mut writer = ::csv::Writer::from_writer(file);
+        for row in rows {
+            writer.serialize(row)?;
+        }
+        writer.flush()
+    })?;
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::{
+    atomic, copy,
+    list::Entry,
+    op::OpContext,
+    BufferSize,
+    Error::{FileyError, Timeout},
+    Op, Result,
+};
+use std::{
+    fs::{read_dir, remove_file, rename, DirEntry},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Runs f on a worker thread and waits up to timeout for it to finish.
+///
+/// A thread blocked in a system call on a hung network filesystem can't be interrupted, so on
+/// timeout the worker is left behind and the flag passed to f is set; f should check it
+/// between steps and undo what it did if it finishes late.
+fn run<T, F>(path: &Path, timeout: Duration, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&AtomicBool) -> Result<T> + Send + 'static,
+{
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    let worker_cancelled = Arc::clone(&cancelled);
+    thread::Builder::new()
+        .name("filey-deadline".to_string())
+        .spawn(move || {
+            let _ = sender.send(f(&worker_cancelled));
+        })
+        .map_err(|e| e.into())
+        .map_err(FileyError)?;
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Err(Timeout {
+                path: path.to_string_lossy().to_string(),
+                timeout,
+            })
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(FileyError(anyhow::anyhow!(
+            "The worker for '{}' panicked",
+            path.display()
+        ))),
+    }
+}
+
+/// Copies from to to, giving up after timeout.
+/// The copy is made under a temporary name and renamed over to only if it completes before
+/// the deadline, so to is left untouched once the caller has been told it failed. Returns the
+/// number of bytes copied.
+pub(crate) fn copy(
+    from: &Path,
+    to: &Path,
+    timeout: Duration,
+    buffer_size: BufferSize,
+) -> Result<u64> {
+    let worker_from = from.to_path_buf();
+    let (worker_to, temp) = (to.to_path_buf(), atomic::temp_path(to)?);
+    run(from, timeout, move |cancelled| {
+        let copied = copy::copy_file(&worker_from, &temp, buffer_size);
+        if copied.is_err() || cancelled.load(Ordering::Relaxed) {
+            let _ = remove_file(&temp);
+            return copied;
+        }
+        if let Err(e) = rename(&temp, &worker_to) {
+            let _ = remove_file(&temp);
+            return Err(Op::Rename.error2(&temp, &worker_to, e));
+        }
+        copied
+    })
+}
+
+/// Lists the entries of the directory path, giving up after timeout.
+pub(crate) fn entries(path: &Path, timeout: Duration) -> Result<Vec<Entry>> {
+    let worker_path = path.to_path_buf();
+    run(path, timeout, move |cancelled| {
+        let mut entries = Vec::new();
+        for entry in read_dir(&worker_path).op(Op::ReadDir, &worker_path)? {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            entries.push(Entry::new(entry.op(Op::ReadDir, &worker_path)?)?);
+        }
+        Ok(entries)
+    })
+}
+
+/// Reads the entries of the directory path, giving up after timeout.
+pub(crate) fn dir_entries(path: &Path, timeout: Duration) -> Result<Vec<DirEntry>> {
+    let worker_path = path.to_path_buf();
+    run(path, timeout, move |cancelled| {
+        let mut entries = Vec::new();
+        for entry in read_dir(&worker_path).op(Op::ReadDir, &worker_path)? {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            entries.push(entry.op(Op::ReadDir, &worker_path)?);
+        }
+        Ok(entries)
+    })
+}
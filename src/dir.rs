@@ -0,0 +1,218 @@
+use crate::{op::OpContext, sys, Error::FileyError, FileyHandle, Op, Result};
+use std::{
+    fs::File,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::{Component, Path, PathBuf},
+};
+
+/// (Unix only) An opened directory whose children are created, opened, renamed and removed
+/// relative to it like openat(2).
+///
+/// Every operation takes the name of an entry directly inside the directory and never follows
+/// a symbolic link at that name, so moving the directory or swapping one of its ancestors for
+/// a symbolic link can't redirect it elsewhere. To go deeper, open subdirectories with
+/// [`Dir::open_dir`].
+///
+/// # Examples
+/// ```
+/// # use filey::Dir;
+/// # use std::{error::Error, io::Write};
+/// #
+/// # fn scratch() -> Result<(), Box<Error>> {
+/// let dir = Dir::open("/tmp/app-scratch")?;
+/// let mut part = dir.create("upload.part", 0o600)?;
+/// part.write_all(b"...")?;
+/// dir.rename("upload.part", "upload")?;
+/// dir.remove("upload")?;
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # scratch().unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Dir {
+    path: PathBuf,
+    fd: OwnedFd,
+}
+
+impl Dir {
+    /// Opens the directory at path.
+    ///
+    /// # Errors
+    /// * path doesn't exist or isn't a directory.
+    /// * The user lacks permissions.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let fd = File::open(path).op(Op::Open, path)?;
+        if !fd.metadata().op(Op::Metadata, path)?.is_dir() {
+            return Err(FileyError(anyhow::anyhow!(
+                "'{}' is not a directory",
+                path.display()
+            )));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            fd: fd.into(),
+        })
+    }
+
+    /// Returns the path the directory was opened from.
+    /// It may no longer lead to the directory if it has been moved since.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Opens the file name in read-only mode.
+    ///
+    /// # Errors
+    /// * name isn't a single component such as `file.txt`.
+    /// * name doesn't exist or is a symbolic link.
+    pub fn open_file<P: AsRef<Path>>(&self, name: P) -> Result<FileyHandle> {
+        let name = child(name.as_ref())?;
+        let fd = sys::openat(
+            self.as_raw_fd(),
+            &self.path,
+            name,
+            libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW,
+            0,
+        )?;
+        Ok(FileyHandle::new(self.path.join(name), File::from(fd)))
+    }
+
+    /// Opens the subdirectory name.
+    ///
+    /// # Errors
+    /// * name isn't a single component such as `cache`.
+    /// * name doesn't exist, isn't a directory or is a symbolic link.
+    pub fn open_dir<P: AsRef<Path>>(&self, name: P) -> Result<Self> {
+        let name = child(name.as_ref())?;
+        let fd = sys::openat(
+            self.as_raw_fd(),
+            &self.path,
+            name,
+            libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW | libc::O_DIRECTORY,
+            0,
+        )?;
+        Ok(Self {
+            path: self.path.join(name),
+            fd,
+        })
+    }
+
+    /// Creates the file name with the given permission bits, to which the process's umask is
+    /// applied, and opens it in write-only mode.
+    ///
+    /// # Errors
+    /// * name isn't a single component such as `file.txt`.
+    /// * name already exists, even as a symbolic link.
+    /// * The user lacks permissions.
+    pub fn create<P: AsRef<Path>>(&self, name: P, mode: u32) -> Result<FileyHandle> {
+        let name = child(name.as_ref())?;
+        let fd = sys::openat(
+            self.as_raw_fd(),
+            &self.path,
+            name,
+            libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC,
+            mode,
+        )?;
+        Ok(FileyHandle::new(self.path.join(name), File::from(fd)))
+    }
+
+    /// Creates the subdirectory name with the given permission bits, to which the process's
+    /// umask is applied, and opens it.
+    ///
+    /// # Errors
+    /// * name isn't a single component such as `cache`.
+    /// * name already exists.
+    /// * The user lacks permissions.
+    pub fn create_dir<P: AsRef<Path>>(&self, name: P, mode: u32) -> Result<Self> {
+        let name = child(name.as_ref())?;
+        sys::mkdirat(self.as_raw_fd(), &self.path, name, mode)?;
+        self.open_dir(name)
+    }
+
+    /// Renames the entry from to to, replacing an existing file at to.
+    ///
+    /// # Errors
+    /// * from or to isn't a single component such as `file.txt`.
+    /// * from doesn't exist.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        self.rename_into(from, self, to)
+    }
+
+    /// Moves the entry from to to inside the directory target, which has to be on the same
+    /// filesystem.
+    ///
+    /// # Errors
+    /// * from or to isn't a single component such as `file.txt`.
+    /// * from doesn't exist.
+    /// * target is on another filesystem.
+    pub fn rename_into<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        target: &Dir,
+        to: Q,
+    ) -> Result<()> {
+        let (from, to) = (child(from.as_ref())?, child(to.as_ref())?);
+        sys::renameat(
+            self.as_raw_fd(),
+            &self.path,
+            from,
+            target.as_raw_fd(),
+            &target.path,
+            to,
+        )
+    }
+
+    /// Removes the entry name, which mustn't be a directory. A symbolic link is removed itself.
+    ///
+    /// # Errors
+    /// * name isn't a single component such as `file.txt`.
+    /// * name doesn't exist or is a directory.
+    pub fn remove<P: AsRef<Path>>(&self, name: P) -> Result<()> {
+        sys::unlinkat(self.as_raw_fd(), &self.path, child(name.as_ref())?, 0)
+    }
+
+    /// Removes the empty subdirectory name.
+    ///
+    /// # Errors
+    /// * name isn't a single component such as `cache`.
+    /// * name doesn't exist, isn't a directory or isn't empty.
+    pub fn remove_dir<P: AsRef<Path>>(&self, name: P) -> Result<()> {
+        let name = child(name.as_ref())?;
+        sys::unlinkat(self.as_raw_fd(), &self.path, name, libc::AT_REMOVEDIR)
+    }
+}
+
+impl AsFd for Dir {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for Dir {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl From<Dir> for OwnedFd {
+    fn from(dir: Dir) -> Self {
+        dir.fd
+    }
+}
+
+/// Accepts only the name of an entry directly inside a directory, so that no symbolic link
+/// on the way is followed. components() drops a trailing slash, which would make openat(2)
+/// follow a symbolic link despite O_NOFOLLOW, so the name has to be the component itself.
+fn child(name: &Path) -> Result<&Path> {
+    let mut components = name.components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(normal)), None) if name.as_os_str() == normal => Ok(name),
+        _ => Err(FileyError(anyhow::anyhow!(
+            "'{}' is not the name of an entry in a directory",
+            name.display()
+        ))),
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{atomic, op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+    str,
+};
+
+/// A byte order mark.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Bom {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl fmt::Display for Bom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Utf8 => write!(f, "UTF-8"),
+            Self::Utf16Le => write!(f, "UTF-16LE"),
+            Self::Utf16Be => write!(f, "UTF-16BE"),
+            Self::Utf32Le => write!(f, "UTF-32LE"),
+            Self::Utf32Be => write!(f, "UTF-32BE"),
+        }
+    }
+}
+
+impl Bom {
+    /// Detects the byte order mark at the start of bytes.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        // UTF-32LE must be checked before UTF-16LE, which is its prefix.
+        match bytes {
+            [0xFF, 0xFE, 0x00, 0x00, ..] => Some(Self::Utf32Le),
+            [0x00, 0x00, 0xFE, 0xFF, ..] => Some(Self::Utf32Be),
+            [0xEF, 0xBB, 0xBF, ..] => Some(Self::Utf8),
+            [0xFF, 0xFE, ..] => Some(Self::Utf16Le),
+            [0xFE, 0xFF, ..] => Some(Self::Utf16Be),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of the byte order mark.
+    pub fn bytes(&self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Self::Utf16Le => &[0xFF, 0xFE],
+            Self::Utf16Be => &[0xFE, 0xFF],
+            Self::Utf32Le => &[0xFF, 0xFE, 0x00, 0x00],
+            Self::Utf32Be => &[0x00, 0x00, 0xFE, 0xFF],
+        }
+    }
+}
+
+fn read_head(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(4);
+    file.take(4).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+pub(crate) fn bom<P: AsRef<Path>>(path: P) -> Result<Option<Bom>> {
+    let path = path.as_ref();
+    let mut file = File::open(path).op(Op::Open, path)?;
+    let head = read_head(&mut file).op(Op::Read, path)?;
+    Ok(Bom::detect(&head))
+}
+
+/// Rewrites path without its byte order mark and returns the removed one.
+pub(crate) fn strip_bom<P: AsRef<Path>>(path: P) -> Result<Option<Bom>> {
+    let path = path.as_ref();
+    let mut file = File::open(path).op(Op::Open, path)?;
+    let head = read_head(&mut file).op(Op::Read, path)?;
+    let Some(bom) = Bom::detect(&head) else {
+        return Ok(None);
+    };
+
+    file.seek(SeekFrom::Start(bom.bytes().len() as u64))
+        .op(Op::Read, path)?;
+    atomic::write_with(path, |temp| io::copy(&mut file, temp).map(|_| ()))?;
+    Ok(Some(bom))
+}
+
+/// The result of checking whether a file is valid UTF-8.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Utf8Report {
+    len: u64,
+    invalid: Vec<Range<u64>>,
+}
+
+impl Utf8Report {
+    /// Returns true if the file is valid UTF-8.
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_empty()
+    }
+
+    /// Returns the byte offset of the first invalid byte.
+    pub fn first_invalid(&self) -> Option<u64> {
+        self.invalid.first().map(|range| range.start)
+    }
+
+    /// Returns the byte ranges that are not valid UTF-8. Adjacent ranges are merged.
+    pub fn invalid_ranges(&self) -> &[Range<u64>] {
+        &self.invalid
+    }
+
+    /// Returns the number of bytes checked.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn push_invalid(&mut self, range: Range<u64>) {
+        match self.invalid.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => self.invalid.push(range),
+        }
+    }
+}
+
+/// Streams path and collects the byte ranges that are not valid UTF-8.
+pub(crate) fn validate_utf8<P: AsRef<Path>>(path: P) -> Result<Utf8Report> {
+    let path = path.as_ref();
+    let file = File::open(path).op(Op::Open, path)?;
+    validate_utf8_reader(file).op(Op::Read, path)
+}
+
+fn validate_utf8_reader<R: Read>(mut reader: R) -> io::Result<Utf8Report> {
+    let mut report = Utf8Report::default();
+    let mut chunk = [0; 8192];
+    // An incomplete sequence at the end of the previous chunk.
+    let mut buf = Vec::with_capacity(chunk.len() + 3);
+
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let base = report.len - buf.len() as u64;
+        report.len += n as u64;
+        buf.extend_from_slice(&chunk[..n]);
+
+        let mut i = 0;
+        while i < buf.len() {
+            match str::from_utf8(&buf[i..]) {
+                Ok(_) => i = buf.len(),
+                Err(e) => {
+                    i += e.valid_up_to();
+                    match e.error_len() {
+                        Some(len) => {
+                            let start = base + i as u64;
+                            report.push_invalid(start..start + len as u64);
+                            i += len;
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+        buf.drain(..i);
+    }
+
+    // A sequence cut off by the end of the file.
+    if !buf.is_empty() {
+        report.push_invalid(report.len - buf.len() as u64..report.len);
+    }
+    Ok(report)
+}
@@ -0,0 +1,276 @@
+use crate::{op::OpContext, Op, Result, Shebang};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// A CPU architecture an executable is built for.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    PowerPc,
+    PowerPc64,
+    Mips,
+    RiscV,
+    /// The raw machine or CPU type number of the format.
+    Other(u32),
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::X86 => write!(f, "x86"),
+            Self::X86_64 => write!(f, "x86-64"),
+            Self::Arm => write!(f, "ARM"),
+            Self::Aarch64 => write!(f, "AArch64"),
+            Self::PowerPc => write!(f, "PowerPC"),
+            Self::PowerPc64 => write!(f, "PowerPC64"),
+            Self::Mips => write!(f, "MIPS"),
+            Self::RiscV => write!(f, "RISC-V"),
+            Self::Other(n) => write!(f, "unknown architecture {:#x}", n),
+        }
+    }
+}
+
+/// The word size and the architecture of a binary executable.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BinaryInfo {
+    bits64: bool,
+    arch: Arch,
+}
+
+impl fmt::Display for BinaryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bits = if self.bits64 { 64 } else { 32 };
+        write!(f, "{}-bit {}", bits, self.arch)
+    }
+}
+
+impl BinaryInfo {
+    pub fn new(bits64: bool, arch: Arch) -> Self {
+        Self { bits64, arch }
+    }
+
+    /// Returns true if the binary is 64-bit.
+    pub fn is_64bit(&self) -> bool {
+        self.bits64
+    }
+
+    pub fn arch(&self) -> Arch {
+        self.arch
+    }
+}
+
+/// The kind of an executable, detected from its magic bytes.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ExecutableKind {
+    /// Executable and Linkable Format, used by Linux and most Unix systems.
+    Elf(BinaryInfo),
+    /// Portable Executable, used by Windows.
+    Pe(BinaryInfo),
+    /// Mach-O, used by macOS and iOS.
+    MachO(BinaryInfo),
+    /// A universal (fat) Mach-O binary bundling one binary per architecture.
+    UniversalMachO(Vec<BinaryInfo>),
+    /// A script starting with a shebang.
+    Script(Shebang),
+    NotExecutable,
+}
+
+impl fmt::Display for ExecutableKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Elf(info) => write!(f, "ELF {}", info),
+            Self::Pe(info) => write!(f, "PE {}", info),
+            Self::MachO(info) => write!(f, "Mach-O {}", info),
+            Self::UniversalMachO(infos) => {
+                write!(f, "Mach-O universal binary")?;
+                for (i, info) in infos.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { "," }, info)?;
+                }
+                Ok(())
+            }
+            Self::Script(shebang) => write!(f, "script ({})", shebang),
+            Self::NotExecutable => write!(f, "not executable"),
+        }
+    }
+}
+
+// Enough to hold every header parsed here, including typical PE and fat Mach-O headers.
+const HEAD_LEN: u64 = 4096;
+
+impl ExecutableKind {
+    /// Detects the kind of the executable at path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path).op(Op::Open, path)?;
+        Self::from_file(&mut file).op(Op::Read, path)
+    }
+
+    fn from_file(file: &mut File) -> io::Result<Self> {
+        let mut head = Vec::new();
+        file.take(HEAD_LEN).read_to_end(&mut head)?;
+
+        if head.starts_with(b"MZ") {
+            // The PE header can in theory lie beyond the first few KiB.
+            let offset = match u32_at(&head, 0x3c, false) {
+                Some(offset) => offset as u64,
+                None => return Ok(Self::NotExecutable),
+            };
+            let mut header = [0; 26];
+            file.seek(SeekFrom::Start(offset))?;
+            return match file.read_exact(&mut header) {
+                Ok(()) => Ok(parse_pe(&header).unwrap_or(Self::NotExecutable)),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(Self::NotExecutable),
+                Err(e) => Err(e),
+            };
+        }
+
+        Ok(Self::from_head(&head))
+    }
+
+    /// Detects the kind of an executable from its first bytes.
+    /// The header of a PE file is usually not at the start, so only its DOS stub is recognized
+    /// here if the PE header isn't within bytes.
+    pub fn from_head(bytes: &[u8]) -> Self {
+        let kind = match bytes {
+            [0x7f, b'E', b'L', b'F', ..] => parse_elf(bytes),
+            [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..] => parse_macho(bytes, true),
+            [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..] => parse_macho(bytes, false),
+            [0xca, 0xfe, 0xba, 0xbe, ..] => parse_fat(bytes),
+            [b'M', b'Z', ..] => u32_at(bytes, 0x3c, false)
+                .and_then(|offset| bytes.get(offset as usize..))
+                .and_then(parse_pe),
+            [b'#', b'!', ..] => std::str::from_utf8(first_line(bytes))
+                .ok()
+                .and_then(Shebang::parse)
+                .map(Self::Script),
+            _ => None,
+        };
+        kind.unwrap_or(Self::NotExecutable)
+    }
+
+    /// Returns the word size and the architecture of a (non-universal) binary.
+    pub fn binary_info(&self) -> Option<BinaryInfo> {
+        match self {
+            Self::Elf(info) | Self::Pe(info) | Self::MachO(info) => Some(*info),
+            _ => None,
+        }
+    }
+
+    /// Returns true unless this is [`ExecutableKind::NotExecutable`].
+    pub fn is_executable(&self) -> bool {
+        *self != Self::NotExecutable
+    }
+}
+
+fn first_line(bytes: &[u8]) -> &[u8] {
+    match bytes.iter().position(|&b| b == b'\n') {
+        Some(end) => &bytes[..end],
+        None => bytes,
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn u32_at(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn parse_elf(bytes: &[u8]) -> Option<ExecutableKind> {
+    let bits64 = match bytes.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    let big_endian = match bytes.get(5)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    let arch = match u16_at(bytes, 18, big_endian)? {
+        3 => Arch::X86,
+        62 => Arch::X86_64,
+        40 => Arch::Arm,
+        183 => Arch::Aarch64,
+        20 => Arch::PowerPc,
+        21 => Arch::PowerPc64,
+        8 => Arch::Mips,
+        243 => Arch::RiscV,
+        n => Arch::Other(n as u32),
+    };
+    Some(ExecutableKind::Elf(BinaryInfo::new(bits64, arch)))
+}
+
+/// Parses a PE header starting at its `PE\0\0` signature.
+fn parse_pe(header: &[u8]) -> Option<ExecutableKind> {
+    if !header.starts_with(b"PE\0\0") {
+        return None;
+    }
+    let arch = match u16_at(header, 4, false)? {
+        0x14c => Arch::X86,
+        0x8664 => Arch::X86_64,
+        0x1c0 | 0x1c4 => Arch::Arm,
+        0xaa64 => Arch::Aarch64,
+        0x5032 | 0x5064 => Arch::RiscV,
+        n => Arch::Other(n as u32),
+    };
+    // The magic of the optional header: PE32 or PE32+.
+    let bits64 = u16_at(header, 24, false)? == 0x20b;
+    Some(ExecutableKind::Pe(BinaryInfo::new(bits64, arch)))
+}
+
+const CPU_ARCH_ABI64: u32 = 0x0100_0000;
+
+fn macho_info(cputype: u32) -> BinaryInfo {
+    let arch = match cputype {
+        7 => Arch::X86,
+        0x0100_0007 => Arch::X86_64,
+        12 => Arch::Arm,
+        0x0100_000c => Arch::Aarch64,
+        18 => Arch::PowerPc,
+        0x0100_0012 => Arch::PowerPc64,
+        n => Arch::Other(n),
+    };
+    BinaryInfo::new(cputype & CPU_ARCH_ABI64 != 0, arch)
+}
+
+fn parse_macho(bytes: &[u8], big_endian: bool) -> Option<ExecutableKind> {
+    let cputype = u32_at(bytes, 4, big_endian)?;
+    Some(ExecutableKind::MachO(macho_info(cputype)))
+}
+
+fn parse_fat(bytes: &[u8]) -> Option<ExecutableKind> {
+    // Java class files share the magic; their version number is always 45 or greater while a
+    // universal binary never bundles that many architectures.
+    let count = u32_at(bytes, 4, true)?;
+    if count == 0 || count >= 45 {
+        return None;
+    }
+    let infos = (0..count as usize)
+        .map(|i| u32_at(bytes, 8 + i * 20, true).map(macho_info))
+        .collect::<Option<Vec<_>>>()?;
+    Some(ExecutableKind::UniversalMachO(infos))
+}
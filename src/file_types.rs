@@ -1,11 +1,18 @@
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{fmt, path::Path};
+use std::{
+    fmt,
+    fs::{FileType, Metadata},
+    path::Path,
+};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize, Hash)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FileTypes {
     File,
     Directory,
     Symlink,
+    Socket,
 }
 
 impl fmt::Display for FileTypes {
@@ -14,12 +21,14 @@ impl fmt::Display for FileTypes {
             Self::File => write!(f, "file"),
             Self::Directory => write!(f, "directory"),
             Self::Symlink => write!(f, "symlink"),
+            Self::Socket => write!(f, "socket"),
         }
     }
 }
 
 impl FileTypes {
-    /// Detects the type of a file.
+    /// Detects the type of the directory entry itself, without following symbolic links.
+    /// A symbolic link is reported as [`FileTypes::Symlink`] even if its target doesn't exist.
     /// If the file doesn't exist, returns None.
     ///
     /// # Examples
@@ -43,14 +52,84 @@ impl FileTypes {
 
         if path.is_symlink() {
             Some(Self::Symlink)
-        } else if path.exists() {
-            if path.is_dir() {
-                Some(Self::Directory)
-            } else {
-                Some(Self::File)
-            }
         } else {
+            Self::which_follow(path)
+        }
+    }
+
+    /// Detects the type of a file after resolving symbolic links, so this never returns
+    /// [`FileTypes::Symlink`].
+    /// If the file or the target of a symbolic link doesn't exist, returns None.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::FileTypes;
+    /// #
+    /// # fn kind() -> Option<()> {
+    /// // current -> releases/v2
+    /// assert_eq!(FileTypes::which("current")?, FileTypes::Symlink);
+    /// assert_eq!(FileTypes::which_follow("current")?, FileTypes::Directory);
+    /// # Some(())
+    /// # }
+    /// # fn main() {
+    /// # kind().unwrap();
+    /// # }
+    /// ```
+    pub fn which_follow<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+
+        if !path.exists() {
             None
+        } else if is_socket(path) {
+            Some(Self::Socket)
+        } else if path.is_dir() {
+            Some(Self::Directory)
+        } else {
+            Some(Self::File)
         }
     }
+
+    /// Detects the type from metadata, which reports a symbolic link only if it was queried
+    /// without following links.
+    pub(crate) fn from_metadata(metadata: &Metadata) -> Self {
+        Self::from_file_type(&metadata.file_type())
+    }
+
+    pub(crate) fn from_file_type(file_type: &FileType) -> Self {
+        if file_type.is_symlink() {
+            Self::Symlink
+        } else if is_socket_type(file_type) {
+            Self::Socket
+        } else if file_type.is_dir() {
+            Self::Directory
+        } else {
+            Self::File
+        }
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn is_socket_type(file_type: &FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    file_type.is_socket()
+}
+
+#[cfg(not(target_family = "unix"))]
+fn is_socket_type(_file_type: &FileType) -> bool {
+    false
+}
+
+#[cfg(target_family = "unix")]
+fn is_socket(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    path.metadata()
+        .map(|m| m.file_type().is_socket())
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_family = "unix"))]
+fn is_socket(_path: &Path) -> bool {
+    false
 }
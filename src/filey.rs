@@ -1,28 +1,130 @@
+#[cfg(target_os = "macos")]
+use crate::xattr;
+#[cfg(any(not(target_os = "wasi"), feature = "bincode"))]
+use crate::Error::FileyError;
+#[cfg(feature = "unicode")]
+use crate::NormalizationForm;
 use crate::{
+    absolute, access, atomic,
+    cache::MetadataCache,
+    checksum::{self, Algorithm, Digest},
+    compare, copy, count, deadline, encoding,
     file_types::FileTypes,
-    Error::{FileyError, GetFileNameError, AlreadyExists},
-    Permissions, Result,
+    grep, hexdump,
+    hooks::{self, Operation},
+    links, list, merge,
+    op::OpContext,
+    options, patch, plan, relocate, remove, replace, resume, shebang, sniff, space, times, trash,
+    watch, BatchOptions, Bom, BufferSize, Change, CopyOptions, Entry, Error,
+    Error::{AlreadyExists, GetFileNameError},
+    ExecutableKind, FileyReader, FileyWriter, Filter, Glob, GlobOptions, GrepMatch,
+    HardLinkOptions, HexRow, IoStats, ListOptions, MoveOptions, MoveReport, OnConflict, Op,
+    OpenFileOptions, OperationPlan, PatchOptions, Pattern, RemoveOptions, ReplaceOptions, Result,
+    ResumeReport, Shebang, Utf8Report, Walk, WalkOptions, Watcher, WriteMode,
 };
-use path_absolutize::Absolutize;
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use crate::{
+    config,
+    snapshot::{self, ManifestOptions},
+    ConfigFormat,
+};
+#[cfg(feature = "csv")]
+use crate::{csv, CsvRecords};
+#[cfg(feature = "ini")]
+use crate::{ini, Ini};
+#[cfg(target_family = "unix")]
+use crate::{
+    mount, owner, sys, CreateOptions, DiskUsage, Error::NotASocket, FileyHandle, Owner,
+    OwnerOptions, Permissions, Stat, SymlinkOptions,
+};
+#[cfg(target_family = "windows")]
+use crate::{streams, win, AlternateStream, WindowsAttributes};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(not(target_family = "windows"))]
+use std::ffi::{OsStr, OsString};
+#[cfg(any(not(target_family = "windows"), feature = "unicode"))]
+use std::fs::rename;
 use std::{
+    cmp::Ordering,
     convert::AsRef,
-    env::var,
     fmt,
-    fs::{copy, create_dir_all, hard_link, metadata, remove_dir_all, remove_file, rename, File},
-    io::{Read, Write},
-    os::unix::fs::symlink,
+    fs::{create_dir_all, metadata, symlink_metadata, File, FileTimes, Metadata, OpenOptions},
+    hash::{Hash, Hasher},
+    io::{BufWriter, Read, Write},
+    ops::RangeBounds,
     path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+#[cfg(target_family = "unix")]
+use std::{
+    io::ErrorKind,
+    os::{
+        fd::OwnedFd,
+        unix::{
+            fs::{OpenOptionsExt, PermissionsExt},
+            net::UnixStream,
+        },
+    },
 };
 
-#[derive(Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug, Serialize, Deserialize)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Filey {
     path: PathBuf,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: Option<MetadataCache>,
+    /// Opened by the first read through [`Read`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    reader: Option<File>,
+    /// Opened by the first write through [`Write`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    writer: Option<BufWriter<File>>,
+}
+
+// A clone starts without open handles, which can't be shared.
+impl Clone for Filey {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            cache: self.cache.clone(),
+            reader: None,
+            writer: None,
+        }
+    }
+}
+
+// Only the path identifies a Filey; the cache and handles are implementation details.
+impl PartialEq for Filey {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Filey {}
+
+impl PartialOrd for Filey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Filey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.path.cmp(&other.path)
+    }
+}
+
+impl Hash for Filey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state)
+    }
 }
 
 impl fmt::Display for Filey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.path.to_string_lossy())
+        // Path::display writes lossily without building a String.
+        write!(f, "{}", self.path.display())
     }
 }
 
@@ -33,22 +135,49 @@ impl AsRef<Path> for Filey {
     }
 }
 
+/// Reads the file from the start. The file is opened by the first read and kept open, so
+/// consecutive reads continue where the previous one stopped.
 impl Read for Filey {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let mut f = File::open(self)?;
-        f.read(buf)
+        let reader = match self.reader.take() {
+            Some(reader) => reader,
+            None => File::open(&self.path)?,
+        };
+        self.reader.insert(reader).read(buf)
     }
 }
 
+/// Writes the file from the start. The file is created (or truncated) by the first write and
+/// kept open, so consecutive writes such as a `write!` in a loop add to it.
+///
+/// Writes are buffered and flushed when the Filey is dropped, which ignores errors; call
+/// [`Write::flush`] to see them. [`Filey::append`] and [`Filey::open_append`] append to the
+/// file instead.
 impl Write for Filey {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let mut f = File::create(self)?;
-        f.write(buf)
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            // Run through the hooks like Filey::into_writer, so that policies apply to
+            // `write!` too. Only the creation is hooked, not every buffered write.
+            None => self
+                .run(Operation::new(Op::Write, &self.path), || {
+                    File::create(&self.path).op(Op::Create, &self.path)
+                })
+                .map(BufWriter::new)
+                .map_err(|e| match e {
+                    Error::Io { source, .. } => source,
+                    // Refused by a hook.
+                    e => std::io::Error::new(std::io::ErrorKind::PermissionDenied, e),
+                })?,
+        };
+        self.writer.insert(writer).write(buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        let mut f = File::create(self)?;
-        f.flush()
+        match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
     }
 }
 
@@ -57,21 +186,88 @@ impl Filey {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         Filey {
             path: path.as_ref().to_path_buf(),
+            cache: None,
+            reader: None,
+            writer: None,
         }
     }
 
-    /// Returns path to the file.
-    pub fn path(&self) -> &PathBuf {
-        &self.path
+    /// Caches the metadata of the file, so chained calls such as [`Filey::exists`],
+    /// [`Filey::file_type`] and [`Filey::size`] query the filesystem only once.
+    /// Useful on network filesystems where every stat(2) is a round trip.
+    ///
+    /// Modifying the file through this Filey discards the cache, but changes made by anything
+    /// else aren't noticed, so call [`Filey::invalidate`] after them.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, FileTypes};
+    /// # use std::error::Error;
+    /// #
+    /// # fn inspect() -> Result<(), Box<Error>> {
+    /// let mut file = Filey::new("/mnt/nfs/dataset.csv");
+    /// file.enable_cache();
+    /// if file.exists() && file.file_type() == Some(FileTypes::File) {
+    ///     println!("{}", file.size()?); // one stat in total
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # inspect().unwrap();
+    /// # }
+    /// ```
+    pub fn enable_cache(&mut self) -> &mut Self {
+        if self.cache.is_none() {
+            self.cache = Some(MetadataCache::default());
+        }
+        self
     }
 
-    /// Returns the type of the file.
-    /// If the path doesn't exist, return None.
-    pub fn file_type(&self) -> Option<FileTypes> {
-        FileTypes::which(&self.path)
+    /// Discards the cached metadata. Does nothing if caching isn't enabled.
+    pub fn invalidate(&mut self) -> &mut Self {
+        self.clear_cache();
+        self
     }
 
-    /// Returns size of the file.
+    fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Runs a modification of the file through the hooks and discards the cached metadata,
+    /// which it makes stale.
+    fn run<T, F: FnOnce() -> Result<T>>(&self, operation: Operation, f: F) -> Result<T> {
+        let result = hooks::run(operation, f);
+        self.clear_cache();
+        result
+    }
+
+    fn query_metadata(&self) -> std::io::Result<Metadata> {
+        match &self.cache {
+            Some(cache) => cache.metadata(&self.path),
+            None => metadata(&self.path),
+        }
+    }
+
+    fn query_symlink_metadata(&self) -> std::io::Result<Metadata> {
+        match &self.cache {
+            Some(cache) => cache.symlink_metadata(&self.path),
+            None => symlink_metadata(&self.path),
+        }
+    }
+
+    /// (Unix only) Takes ownership of a file descriptor and wraps it in a [`FileyHandle`].
+    /// The path is resolved from the descriptor (via /proc/self/fd on Linux).
+    ///
+    /// # Errors
+    /// * The path of the descriptor can't be resolved on this platform.
+    #[cfg(target_family = "unix")]
+    pub fn from_fd(fd: OwnedFd) -> Result<FileyHandle> {
+        FileyHandle::from_fd(fd)
+    }
+
+    /// (Unix only) Opens the file in read-only mode and returns a handle to it.
     ///
     /// # Errors
     /// * The user lacks permissions.
@@ -80,354 +276,3072 @@ impl Filey {
     /// # Examples
     /// ```
     /// # use filey::Filey;
+    /// # use std::os::fd::AsRawFd;
     /// # use std::error::Error;
     /// #
-    /// # fn get_size() -> Result<(), Box<Error>> {
-    /// let size = Filey::new("install.sh").size()?;
-    /// println!("{}", size); // 1079
+    /// # fn get_fd() -> Result<(), Box<Error>> {
+    /// let handle = Filey::new("data.db").open()?;
+    /// println!("{}", handle.as_raw_fd()); // 3
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_size().unwrap();
+    /// # get_fd().unwrap();
     /// # }
     /// ```
-    pub fn size(&self) -> Result<u64> {
-        let metadata = metadata(&self.path)
-            .map_err(|e| e.into())
-            .map_err(FileyError)?;
-        let size = metadata.len();
-        Ok(size)
-    }
-
-    pub fn permissions(&self) -> Result<Permissions> {
-        Permissions::from_path(self)
+    #[cfg(target_family = "unix")]
+    pub fn open(&self) -> Result<FileyHandle> {
+        let file = File::open(self).op(Op::Open, self)?;
+        Ok(FileyHandle::new(self, file))
     }
 
-    /// Returns the file name or the directory name.
-    /// Returns None if the path terminates in ...
+    /// Opens the file with the given options like [`std::fs::OpenOptions`], e.g. for reading
+    /// and writing without truncating it, and returns the handle.
+    /// Opening for writing or appending runs the [`hooks`](crate::hooks) as a write, since the
+    /// handle can change the file.
+    ///
+    /// # Errors
+    /// * [`crate::Error::Refused`] if a hook refuses a write.
+    /// * The options are invalid, e.g. none of read, write and append is set.
+    /// * The file doesn't exist and options.create isn't set, or it exists and
+    ///   options.create_new is set.
+    /// * The user lacks permissions.
     ///
     /// # Examples
     /// ```
-    /// # use filey::Filey;
+    /// # use filey::{Filey, OpenFileOptions};
+    /// # use std::io::{Seek, SeekFrom, Write};
+    /// # use std::error::Error;
     /// #
-    /// # fn get_file_name() -> Option<String> {
-    /// let file = Filey::new("src/lib.rs");
-    /// assert_eq!(file.file_name()?.as_str(), "lib.rs");
-    ///
-    /// let directory = Filey::new("src/lib.rs");
-    /// assert_eq!(directory.file_name()?.as_str(), "src");
-    /// # Some(directory.to_string())
+    /// # fn patch_header() -> Result<(), Box<Error>> {
+    /// let options = OpenFileOptions {
+    ///     read: true,
+    ///     write: true,
+    ///     ..Default::default()
+    /// };
+    /// let mut file = Filey::new("image.bin").open_with(&options)?;
+    /// file.seek(SeekFrom::Start(4))?;
+    /// file.write_all(&[0x01, 0x00])?;
+    /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_file_name().unwrap();
+    /// # patch_header().unwrap();
     /// # }
     /// ```
-    pub fn file_name(&self) -> Option<String> {
-        Some(self.path.file_name()?.to_string_lossy().to_string())
+    pub fn open_with(&self, options: &OpenFileOptions) -> Result<File> {
+        let open = || options.to_std().open(&self.path).op(Op::Open, &self.path);
+        if options.modifies() {
+            self.run(Operation::new(Op::Write, &self.path), open)
+        } else {
+            open()
+        }
     }
 
-    /// Returns the stem portion of the file name.
-    /// Returns None if there is no file name.
+    /// Reads the whole file into a string.
+    ///
+    /// This takes precedence over [`Read::read_to_string`] in method calls; call that as
+    /// `Read::read_to_string(&mut file, &mut buf)` to continue reading through the Filey.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    /// * The file isn't valid UTF-8.
     ///
     /// # Examples
     /// ```
     /// # use filey::Filey;
     /// # use std::error::Error;
     /// #
-    /// # fn get_file_stem() -> Option<String> {
-    /// let file = Filey::new("src/lib.rs");
-    /// assert_eq!(file.file_stem()?.as_str(), "lib");
-    /// # Some(file.to_string())
+    /// # fn greet() -> Result<(), Box<Error>> {
+    /// let name = Filey::new("name.txt").read_to_string()?;
+    /// println!("Hello, {}!", name.trim());
+    /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_file_stem().unwrap();
+    /// # greet().unwrap();
     /// # }
     /// ```
-    pub fn file_stem(&self) -> Option<String> {
-        Some(self.path.file_stem()?.to_string_lossy().to_string())
+    pub fn read_to_string(&self) -> Result<String> {
+        std::fs::read_to_string(&self.path).op(Op::Read, &self.path)
     }
 
-    /// Returns the parent directory.
-    /// Returns None if the path terminates in a root or prefix, or if it's the empty string.
+    /// Reads the whole file into a vector of bytes.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn read_to_vec(&self) -> Result<Vec<u8>> {
+        std::fs::read(&self.path).op(Op::Read, &self.path)
+    }
+
+    /// Consumes the Filey and returns a buffered reader of the file which implements [`Read`],
+    /// [`std::io::BufRead`] and [`std::io::Seek`], for generic code taking a reader.
+    /// Reading continues where reading through the Filey stopped, if it was read from.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
     ///
     /// # Examples
     /// ```
     /// # use filey::Filey;
+    /// # use std::io::BufRead;
+    /// # use std::error::Error;
     /// #
-    /// # fn get_parent_dir() -> Option<PathBuf> {
-    /// let file = Filey::new("src/lib.rs");
-    /// assert_eq!(file.parent_dir()?.as_str(), "src");
-    /// # Some(file.path())
+    /// # fn count() -> Result<(), Box<Error>> {
+    /// let reader = Filey::new("access.log").into_reader()?;
+    /// let errors = reader
+    ///     .lines()
+    ///     .filter(|line| line.as_ref().is_ok_and(|line| line.contains(" 500 ")))
+    ///     .count();
+    /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_parent_dir().unwrap();
+    /// # count().unwrap();
     /// # }
     /// ```
-    pub fn parent_dir(&self) -> Option<String> {
-        Some(self.path.parent()?.to_string_lossy().to_string())
+    pub fn into_reader(self) -> Result<FileyReader> {
+        self.into_reader_with(BufferSize::Default)
     }
 
-    /// Returns the absolutized path of the file or the directory.
+    /// Same as [`Filey::into_reader`], reading through a buffer of the given size instead of
+    /// 8 KiB.
     ///
     /// # Errors
-    /// * The environment variable HOME isn't set.
-    /// * The environment variable's name contains the equal sign character (=) or the NUL
-    /// character.
-    /// * The environment variable's value is not valid Unicode.
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn into_reader_with(self, buffer_size: BufferSize) -> Result<FileyReader> {
+        let file = match self.reader {
+            Some(file) => file,
+            None => File::open(&self.path).op(Op::Open, &self.path)?,
+        };
+        Ok(FileyReader::new(self.path, file, buffer_size))
+    }
+
+    /// Consumes the Filey and returns a buffered writer of the file which implements [`Write`]
+    /// and [`std::io::Seek`], for generic code taking a writer.
+    /// Data written through the Filey is flushed first.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * Flushing data written through the Filey fails.
     ///
     /// # Examples
     /// ```
-    /// # use filey::Filey;
+    /// # use filey::{Filey, WriteMode};
+    /// # use std::io::Write;
     /// # use std::error::Error;
     /// #
-    /// # fn get_absoluzed() -> Result<(), Box<Error>> {
-    /// let mut file = Filey::new("src/lib.rs");
-    /// assert_eq!(file.absolutized()?
-    ///     .to_string()
-    ///     .as_str(),
-    ///     "/home/Tom/src/lib.rs");
+    /// # fn record() -> Result<(), Box<Error>> {
+    /// let mut history = Filey::new("history.txt").into_writer(WriteMode::Append)?;
+    /// writeln!(history, "ls -la")?;
+    /// history.sync()?;
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_absoluzed().unwrap();
+    /// # record().unwrap();
     /// # }
     /// ```
-    pub fn absolutize(&mut self) -> Result<&mut Self> {
-        let absolutized = self
-            .path
-            .absolutize()
-            .map_err(|e| e.into())
-            .map_err(FileyError)?;
-        self.path = absolutized.to_path_buf();
-        Ok(self)
+    pub fn into_writer(self, mode: WriteMode) -> Result<FileyWriter> {
+        self.into_writer_with(mode, BufferSize::Default)
     }
 
-    /// Return the canonicalized(absolutized and symbolic links solved) path.
+    /// Same as [`Filey::into_writer`], writing through a buffer of the given size instead of
+    /// 8 KiB.
     ///
     /// # Errors
-    /// * The path doesn't exist.
-    /// * A non-final component in path is not a directory.
+    /// * The user lacks permissions.
+    /// * Flushing data written through the Filey fails.
     ///
     /// # Examples
     /// ```
-    /// # use filey::Filey;
+    /// # use filey::{BufferSize, Filey, WriteMode};
+    /// # use std::io::Write;
     /// # use std::error::Error;
     /// #
-    /// # fn get_canonicalized() -> Result<(), Box<Error>> {
-    /// // nvim/init.lua -> /home/Lisa/dotfiles/nvim/init.lua
-    /// let mut file = Filey::new("nvim/init.lua");
-    /// assert_eq!(file.canonicalize()?
-    ///     .to_string()
-    ///     .as_str(),
-    ///     "/home/Lisa/dotfiles/nvim/init.lua");
+    /// # fn export() -> Result<(), Box<Error>> {
+    /// let mut export = Filey::new("/mnt/nfs/export.csv")
+    ///     .into_writer_with(WriteMode::Truncate, BufferSize::Auto)?;
+    /// writeln!(export, "id,name")?;
+    /// export.sync()?;
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_canonicalized().unwrap();
+    /// # export().unwrap();
     /// # }
     /// ```
-    pub fn canonicalize(&mut self) -> Result<&mut Self> {
-        let canonicalized = self
-            .path
-            .canonicalize()
-            .map_err(|e| e.into())
-            .map_err(FileyError)?;
-        self.path = canonicalized;
-        Ok(self)
+    pub fn into_writer_with(self, mode: WriteMode, buffer_size: BufferSize) -> Result<FileyWriter> {
+        hooks::run(Operation::new(Op::Write, &self.path), || {
+            if let Some(mut writer) = self.writer {
+                writer.flush().op(Op::Write, &self.path)?;
+            }
+            FileyWriter::open(self.path.clone(), mode, buffer_size)
+        })
     }
 
-    /// Replaces an initial tilde of the path by the environment variable HOME.
+    /// Appends contents to the end of the file, creating it if it doesn't exist.
+    /// The file is opened in append mode, so appends from several processes don't overwrite
+    /// each other, but large contents may be written in several parts which interleave with
+    /// theirs.
     ///
     /// # Errors
-    /// * The environment variable HOME isn't set.
-    /// * The environment variable's name contains the equal sign character (=) or the NUL
-    /// character.
-    /// * The environment variable's value is not valid Unicode.
+    /// * The user lacks permissions.
+    /// * The parent directory doesn't exist.
     ///
     /// # Examples
     /// ```
     /// # use filey::Filey;
     /// # use std::error::Error;
     /// #
-    /// # fn get_expanded() -> Result<(), Box<Error>> {
-    /// let mut directory = Filey::new("~/audio");
-    /// assert_eq!(directory.expand_user()?
-    ///     .to_string()
-    ///     .as_str(),
-    ///     "/home/Mike/audio");
+    /// # fn audit() -> Result<(), Box<Error>> {
+    /// Filey::new("audit.log").append("user alice logged in\n")?;
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_expanded().unwrap();
+    /// # audit().unwrap();
     /// # }
     /// ```
-    pub fn expand_user(&mut self) -> Result<&mut Self> {
-        let s = &self.to_string();
-        if s.starts_with('~') {
-            let expanded = s.replacen('~', &home_dir()?, 1);
-            self.path = Path::new(&expanded).to_path_buf();
-        }
-        Ok(self)
+    pub fn append<C: AsRef<[u8]>>(&self, contents: C) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&self.path)
+                .and_then(|mut file| file.write_all(contents.as_ref()))
+                .op(Op::Write, &self.path)
+        })
     }
 
-    /// Replaces path_to_home by tilde.
+    /// Returns a buffered writer appending to the file, which is created if it doesn't exist.
+    /// Unlike [`Filey::into_writer`] it leaves the Filey usable, but data written through the
+    /// Filey itself and not yet flushed isn't flushed first.
     ///
     /// # Errors
-    /// * The environment variable HOME isn't set.
-    /// * The environment variable's name contains the equal sign character (=) or the NUL
-    /// character.
-    /// * The environment variable's value is not valid Unicode.
+    /// * The user lacks permissions.
+    /// * The parent directory doesn't exist.
     ///
     /// # Examples
     /// ```
     /// # use filey::Filey;
+    /// # use std::io::Write;
     /// # use std::error::Error;
     /// #
-    /// # fn get_closed() -> Result<(), Box<Error>> {
-    /// let mut file = Filey::new("/home/Meg/cats.png");
-    /// assert_eq!(file.close_user()?.as_str(), "~/cats.png")
+    /// # fn progress() -> Result<(), Box<Error>> {
+    /// let log = Filey::new("build.log");
+    /// let mut writer = log.open_append()?;
+    /// for step in ["configure", "compile", "link"] {
+    ///     writeln!(writer, "{} done", step)?;
+    /// }
+    /// writer.flush()?;
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # get_closed().unwrap();
+    /// # progress().unwrap();
     /// # }
     /// ```
-    pub fn contract_user(&mut self) -> Result<&mut Self> {
-        let home_dir = &home_dir()?;
-        let s = self.to_string();
-        if s.starts_with(home_dir) {
-            let contracted = s.replacen(home_dir, "~", 1);
-            self.path = Path::new(&contracted).to_path_buf();
-        }
-        Ok(self)
+    pub fn open_append(&self) -> Result<FileyWriter> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            FileyWriter::open(self.path.clone(), WriteMode::Append, BufferSize::Default)
+        })
     }
 
-    /// Move a file or a directory to the given path.
+    /// Returns path to the file.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Returns the type of the file without following symbolic links.
+    /// If the path doesn't exist, return None.
+    pub fn file_type(&self) -> Option<FileTypes> {
+        let metadata = self.query_symlink_metadata().ok()?;
+        Some(FileTypes::from_metadata(&metadata))
+    }
+
+    /// Returns the type of the file after resolving symbolic links.
+    /// If the path or the target of a symbolic link doesn't exist, return None.
+    pub fn file_type_follow(&self) -> Option<FileTypes> {
+        let metadata = self.query_metadata().ok()?;
+        Some(FileTypes::from_metadata(&metadata))
+    }
+
+    /// Returns the files and directories in the directory.
+    /// The order is the one the filesystem returns them in; see [`Filey::list_with`] for a
+    /// stable order.
     ///
     /// # Errors
     /// * The user lacks permissions.
-    /// * from(Filey) and to(path: P) are on separate filesystems.
+    /// * The path is not a directory.
+    pub fn list(&self) -> Result<Vec<Filey>> {
+        Ok(self.list_entries()?.iter().map(Entry::filey).collect())
+    }
+
+    /// Same as [`Filey::list`], with options such as sorting the entries by name.
     ///
-    /// # Panics
-    /// * Both from and to don't exist.
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
     ///
     /// # Examples
     /// ```
-    /// # use std::path::Path;
-    /// # use filey::Filey;
+    /// # use filey::{Filey, ListOptions};
     /// # use std::error::Error;
     /// #
-    /// # fn moves() -> Result<(), Box<Error>> {
-    /// let mut file = Filey::new("cats.png");
-    /// file.move_to("photos/animals/")?;
-    /// assert_eq!(Path::new("photos/animals/cats.png").exists(), true);
+    /// # fn migrations() -> Result<(), Box<Error>> {
+    /// let options = ListOptions { sorted: true };
+    /// for migration in Filey::new("migrations").list_with(&options)? {
+    ///     println!("{}", migration); // 0001_init.sql, 0002_users.sql, ...
+    /// }
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # moves().unwrap();
+    /// # migrations().unwrap();
     /// # }
     /// ```
-    pub fn move_to<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
-        let path = path.as_ref();
-
-        if path.is_dir() {
-            let file_name = self.file_name().ok_or_else(|| GetFileNameError {
-                path: self.to_string(),
-            })?;
-            let to = path.to_path_buf().join(file_name);
+    pub fn list_with(&self, options: &ListOptions) -> Result<Vec<Filey>> {
+        Ok(self
+            .list_entries_with(options)?
+            .iter()
+            .map(Entry::filey)
+            .collect())
+    }
 
-            rename(&self, &to)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-            self.path = to;
-            Ok(self)
-        } else {
-            rename(&self, path)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-            self.path = path.to_path_buf();
-            Ok(self)
-        }
+    /// Same as [`Filey::list`], but gives up after timeout, e.g. on a hung network filesystem.
+    ///
+    /// # Errors
+    /// * [`crate::Error::Timeout`] if listing takes longer than timeout.
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    pub fn list_with_timeout(&self, timeout: Duration) -> Result<Vec<Filey>> {
+        Ok(self
+            .list_entries_with_timeout(timeout)?
+            .iter()
+            .map(Entry::filey)
+            .collect())
     }
 
-    /// Detects the type of a file and remove the file.
+    /// Same as [`Filey::list_entries`], but gives up after timeout.
+    /// The listing runs on a worker thread, which is left to finish in the background if the
+    /// filesystem doesn't respond.
     ///
     /// # Errors
-    /// * The file doesn't exist.
+    /// * [`crate::Error::Timeout`] if listing takes longer than timeout.
     /// * The user lacks permissions.
-    ///  
+    /// * The path is not a directory.
+    ///
     /// # Examples
     /// ```
     /// # use filey::Filey;
-    /// # use std::error::Error;
+    /// # use std::{error::Error, time::Duration};
     /// #
-    /// # fn rm() -> Result<(), Box<Error>> {
-    /// let file = Filey::new("coredump");
-    /// file.remove()?;
-    /// assert_eq!(file.exists(), false);
+    /// # fn ls() -> Result<(), Box<Error>> {
+    /// let entries = Filey::new("/mnt/nfs/shared").list_entries_with_timeout(Duration::from_secs(10))?;
     /// # Ok(())
     /// # }
     /// # fn main() {
-    /// # rm().unwrap();
+    /// # ls().unwrap();
     /// # }
     /// ```
-    pub fn remove(&self) -> Result<()> {
-        if self.path.is_dir() {
-            remove_dir_all(self)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?
-        } else {
-            remove_file(self)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-        }
-        Ok(())
+    pub fn list_entries_with_timeout(&self, timeout: Duration) -> Result<Vec<Entry>> {
+        deadline::entries(&self.path, timeout)
     }
 
-    pub fn create_file(&self) -> Result<Self> {
-        if self.exists() {
-            return Err(AlreadyExists {
-                path: self.to_string(),
-            })
+    /// Returns the entries of the directory with their types.
+    /// The types come from the directory listing, so telling files from directories costs no
+    /// extra syscall per entry, and [`Entry::metadata`] is only queried when needed.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, FileTypes};
+    /// # use std::error::Error;
+    /// #
+    /// # fn directories() -> Result<(), Box<Error>> {
+    /// for entry in Filey::new("src").list_entries()? {
+    ///     if entry.file_type() == FileTypes::Directory {
+    ///         println!("{}", entry.path().display()); // src/bin
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # directories().unwrap();
+    /// # }
+    /// ```
+    pub fn list_entries(&self) -> Result<Vec<Entry>> {
+        list::entries(self)
+    }
+
+    /// Same as [`Filey::list_entries`], with options such as sorting the entries by name.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    pub fn list_entries_with(&self, options: &ListOptions) -> Result<Vec<Entry>> {
+        list::entries_with(self, options)
+    }
+
+    /// Returns an iterator over every entry below the directory, descending into
+    /// subdirectories. Symbolic links are yielded but not followed.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn tree() -> Result<(), Box<Error>> {
+    /// for entry in Filey::new("src").walk()? {
+    ///     let entry = entry?;
+    ///     println!("{}{:?}", "  ".repeat(entry.depth() - 1), entry.file_name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # tree().unwrap();
+    /// # }
+    /// ```
+    pub fn walk(&self) -> Result<Walk> {
+        self.walk_with(&WalkOptions::default())
+    }
+
+    /// Same as [`Filey::walk`], with options such as the maximum depth, following symbolic
+    /// links and the types of entries to yield.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    /// * [`crate::Error::Timeout`] if options.timeout is set and reading the directory takes
+    ///   longer.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{FileTypes, Filey, WalkOptions};
+    /// # use std::error::Error;
+    /// #
+    /// # fn sources() -> Result<(), Box<Error>> {
+    /// let options = WalkOptions {
+    ///     max_depth: Some(3),
+    ///     file_types: vec![FileTypes::File],
+    ///     ..Default::default()
+    /// };
+    /// let files = Filey::new("src")
+    ///     .walk_with(&options)?
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # sources().unwrap();
+    /// # }
+    /// ```
+    pub fn walk_with(&self, options: &WalkOptions) -> Result<Walk> {
+        Walk::new(&self.path, options)
+    }
+
+    /// Returns the entries below the directory whose paths relative to it match pattern, such
+    /// as `*.rs` for the Rust files directly inside it or `**/*.toml` for the TOML files at
+    /// any depth. See [`Glob`] for the syntax.
+    /// The entries are sorted by path, and symbolic links aren't followed.
+    ///
+    /// Unlike with [`Glob::is_match`], a pattern without a `/` only matches entries directly
+    /// inside the directory, like in a shell.
+    ///
+    /// # Errors
+    /// * pattern isn't a valid glob.
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn manifests() -> Result<(), Box<Error>> {
+    /// for manifest in Filey::new("workspace").glob("**/Cargo.toml")? {
+    ///     println!("{}", manifest);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # manifests().unwrap();
+    /// # }
+    /// ```
+    pub fn glob(&self, pattern: &str) -> Result<Vec<Filey>> {
+        self.glob_with(pattern, &GlobOptions::default())
+    }
+
+    /// Same as [`Filey::glob`], with options such as ignoring case.
+    ///
+    /// # Errors
+    /// * pattern isn't a valid glob.
+    /// * The user lacks permissions.
+    /// * The path is not a directory.
+    pub fn glob_with(&self, pattern: &str, options: &GlobOptions) -> Result<Vec<Filey>> {
+        let glob = Glob::with_options(pattern, options)?;
+        let options = WalkOptions {
+            max_depth: glob.max_depth(),
+            sorted: true,
+            ..Default::default()
+        };
+        let mut matches = Vec::new();
+        for entry in self.walk_with(&options)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(&self.path).unwrap_or(&path);
+            if glob.is_match(relative) {
+                matches.push(Filey::new(&path));
+            }
         }
+        Ok(matches)
+    }
+
+    /// Watches the file on a background thread and calls callback with what happened each
+    /// time it changes, usually with its new contents. Changes are noticed by polling the
+    /// size and modification time, and callback is only called once the file has stayed the
+    /// same for debounce, so an editor saving in several steps triggers it once.
+    /// The file doesn't have to exist yet.
+    ///
+    /// Watching stops when the returned [`Watcher`] is dropped.
+    ///
+    /// # Errors
+    /// * The watcher thread can't be spawned.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Change, Filey};
+    /// # use std::{error::Error, time::Duration};
+    /// #
+    /// # fn reload() -> Result<(), Box<Error>> {
+    /// let _watcher = Filey::new("config.toml").on_change(Duration::from_millis(200), |change| {
+    ///     if let Change::Modified(contents) = change {
+    ///         println!("reloading {} bytes of configuration", contents.len());
+    ///     }
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # reload().unwrap();
+    /// # }
+    /// ```
+    pub fn on_change<F>(&self, debounce: Duration, callback: F) -> Result<Watcher>
+    where
+        F: FnMut(Change) + Send + 'static,
+    {
+        watch::on_change(&self.path, debounce, callback)
+    }
+
+    /// Returns size of the file.
+    /// For a directory this is the size of the directory itself, which depends on the
+    /// filesystem; use [`Filey::count_entries`] for the number of entries in it.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_size() -> Result<(), Box<Error>> {
+    /// let size = Filey::new("install.sh").size()?;
+    /// println!("{}", size); // 1079
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_size().unwrap();
+    /// # }
+    /// ```
+    pub fn size(&self) -> Result<u64> {
+        let metadata = self.query_metadata().op(Op::Metadata, self)?;
+        let size = metadata.len();
+        Ok(size)
+    }
+
+    /// Returns size of the file without following symbolic links.
+    /// If the file is a symbolic link, returns the size of the link itself (the length of its
+    /// target path on most platforms).
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    pub fn size_no_follow(&self) -> Result<u64> {
+        Ok(self.metadata_no_follow()?.len())
+    }
+
+    /// Returns the metadata of the file, following symbolic links.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_metadata() -> Result<(), Box<Error>> {
+    /// let metadata = Filey::new("install.sh").metadata()?;
+    /// println!("{}", metadata.size()); // 1079
+    /// println!("{:?}", metadata.modified()); // Some(SystemTime { .. })
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_metadata().unwrap();
+    /// # }
+    /// ```
+    pub fn metadata(&self) -> Result<crate::Metadata> {
+        let metadata = self.query_metadata().op(Op::Metadata, self)?;
+        Ok(crate::Metadata::from(&metadata))
+    }
+
+    /// Returns the metadata of the file like lstat(2).
+    /// If the file is a symbolic link, returns the metadata of the link itself.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_metadata() -> Result<(), Box<Error>> {
+    /// // current -> releases/v2
+    /// let metadata = Filey::new("current").metadata_no_follow()?;
+    /// assert!(metadata.is_symlink());
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_metadata().unwrap();
+    /// # }
+    /// ```
+    pub fn metadata_no_follow(&self) -> Result<Metadata> {
+        self.query_symlink_metadata().op(Op::Metadata, self)
+    }
+
+    /// Returns the time the file was last modified, following symbolic links.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * The platform doesn't record the time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn is_stale() -> Result<(), Box<Error>> {
+    /// let source = Filey::new("style.scss");
+    /// let output = Filey::new("style.css");
+    /// if !output.exists() || source.modified()? > output.modified()? {
+    ///     println!("rebuilding style.css");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # is_stale().unwrap();
+    /// # }
+    /// ```
+    pub fn modified(&self) -> Result<SystemTime> {
+        let metadata = self.query_metadata().op(Op::Metadata, self)?;
+        metadata.modified().op(Op::Metadata, self)
+    }
+
+    /// Returns the time the file was created, following symbolic links.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * The platform or the filesystem doesn't record the time.
+    pub fn created(&self) -> Result<SystemTime> {
+        let metadata = self.query_metadata().op(Op::Metadata, self)?;
+        metadata.created().op(Op::Metadata, self)
+    }
+
+    /// Returns the time the file was last accessed, following symbolic links.
+    /// Many systems update it lazily or not at all (e.g. with the `noatime` mount option).
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * The platform doesn't record the time.
+    pub fn accessed(&self) -> Result<SystemTime> {
+        let metadata = self.query_metadata().op(Op::Metadata, self)?;
+        metadata.accessed().op(Op::Metadata, self)
+    }
+
+    /// Sets the time the file was last modified, following symbolic links.
+    /// Works on directories too.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// # use std::time::{Duration, SystemTime};
+    /// #
+    /// # fn expire() -> Result<(), Box<Error>> {
+    /// // Makes the cache entry look a day old.
+    /// let a_day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    /// Filey::new("cache/index.json").set_modified(a_day_ago)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # expire().unwrap();
+    /// # }
+    /// ```
+    pub fn set_modified(&self, time: SystemTime) -> Result<()> {
+        self.run(Operation::new(Op::SetAttributes, &self.path), || {
+            times::set_times(&self.path, FileTimes::new().set_modified(time))
+        })
+    }
+
+    /// Sets the access and modification times of the file to now like touch(1), creating an
+    /// empty file if it doesn't exist. Symbolic links are followed, and directories can be
+    /// touched too.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The parent directory doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn mark_done() -> Result<(), Box<Error>> {
+    /// Filey::new("build/.stamp").touch()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # mark_done().unwrap();
+    /// # }
+    /// ```
+    pub fn touch(&self) -> Result<()> {
+        let op = if self.path.exists() {
+            Op::SetAttributes
+        } else {
+            Op::Create
+        };
+        self.run(Operation::new(op, &self.path), || {
+            if op == Op::Create {
+                // Another process may have created it meanwhile, so it mustn't be truncated.
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(&self.path)
+                    .op(Op::Create, self)?;
+            }
+            let now = SystemTime::now();
+            times::set_times(
+                &self.path,
+                FileTimes::new().set_accessed(now).set_modified(now),
+            )
+        })
+    }
+
+    /// (Unix only) Returns the logical length and the allocated bytes on disk.
+    /// Directories are measured recursively, like du(1).
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_disk_usage() -> Result<(), Box<Error>> {
+    /// let usage = Filey::new("disk.img").disk_usage()?;
+    /// println!("{}", usage.apparent()); // 1073741824
+    /// println!("{}", usage.on_disk()); // 4096
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_disk_usage().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn disk_usage(&self) -> Result<DiskUsage> {
+        DiskUsage::from_path(self)
+    }
+
+    /// Returns the number of bytes available to the current user on the filesystem containing
+    /// the file.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{units::UnitOfInfo, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn free() -> Result<(), Box<Error>> {
+    /// let available = Filey::new("/var/backups").available_space()?;
+    /// println!("{}", UnitOfInfo::format(available)); // 12.4 GiB
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # free().unwrap();
+    /// # }
+    /// ```
+    pub fn available_space(&self) -> Result<u64> {
+        space::available(self)
+    }
+
+    #[cfg(target_family = "unix")]
+    pub fn permissions(&self) -> Result<Permissions> {
+        Permissions::from_path(self)
+    }
+
+    /// (Windows only) Returns the hidden, system, archive and read-only attributes of the file.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    #[cfg(target_family = "windows")]
+    pub fn windows_attributes(&self) -> Result<WindowsAttributes> {
+        WindowsAttributes::from_path(self)
+    }
+
+    /// (Windows only) Sets the attributes of the file.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn mark_system() -> Result<(), Box<Error>> {
+    /// let file = Filey::new("desktop.ini");
+    /// let mut attributes = file.windows_attributes()?;
+    /// attributes.set_hidden(true).set_system(true);
+    /// file.set_windows_attributes(&attributes)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # mark_system().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "windows")]
+    pub fn set_windows_attributes(&self, attributes: &WindowsAttributes) -> Result<()> {
+        self.run(Operation::new(Op::SetAttributes, &self.path), || {
+            attributes.apply(self)
+        })
+    }
+
+    /// (macOS only) Returns true if the file has the `com.apple.quarantine` extended attribute,
+    /// which makes Gatekeeper check it before it's opened for the first time.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    #[cfg(target_os = "macos")]
+    pub fn is_quarantined(&self) -> Result<bool> {
+        Ok(xattr::get(self, QUARANTINE)?.is_some())
+    }
+
+    /// (macOS only) Removes the `com.apple.quarantine` extended attribute, like
+    /// `xattr -d com.apple.quarantine`. Returns false if the file wasn't quarantined.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn install() -> Result<(), Box<Error>> {
+    /// let binary = Filey::new("/usr/local/bin/tool");
+    /// if binary.remove_quarantine()? {
+    ///     println!("removed the quarantine flag from {}", binary);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # install().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_os = "macos")]
+    pub fn remove_quarantine(&self) -> Result<bool> {
+        self.run(Operation::new(Op::RemoveXattr, &self.path), || {
+            xattr::remove(self, QUARANTINE)
+        })
+    }
+
+    /// (Windows only) Lists the NTFS alternate data streams of the file.
+    /// The unnamed main stream is not included.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * The filesystem doesn't support streams.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn list_streams() -> Result<(), Box<Error>> {
+    /// for stream in Filey::new("setup.exe").streams()? {
+    ///     println!("{} ({} bytes)", stream.name(), stream.size()); // Zone.Identifier (26 bytes)
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # list_streams().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "windows")]
+    pub fn streams(&self) -> Result<Vec<AlternateStream>> {
+        streams::streams(self)
+    }
+
+    /// (Windows only) Opens the alternate data stream name of the file in read-only mode.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file or the stream doesn't exist.
+    #[cfg(target_family = "windows")]
+    pub fn open_stream(&self, name: &str) -> Result<File> {
+        let path = streams::stream_path(self, name);
+        File::open(&path).op(Op::Open, &path)
+    }
+
+    /// (Windows only) Removes the alternate data stream name from the file, e.g. the
+    /// `Zone.Identifier` mark of the web.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file or the stream doesn't exist.
+    #[cfg(target_family = "windows")]
+    pub fn remove_stream(&self, name: &str) -> Result<()> {
+        self.run(Operation::new(Op::Remove, &self.path), || {
+            let path = streams::stream_path(self, name);
+            std::fs::remove_file(&path).op(Op::Remove, &path)
+        })
+    }
+
+    /// Returns true if the file is hidden.
+    /// On Unix a file is hidden if its name starts with a dot, and on Windows if it has the
+    /// hidden attribute.
+    ///
+    /// # Errors
+    /// * The user lacks permissions. (Windows only)
+    /// * The file doesn't exist. (Windows only)
+    pub fn is_hidden(&self) -> Result<bool> {
+        #[cfg(target_family = "windows")]
+        {
+            Ok(self.windows_attributes()?.is_hidden())
+        }
+        #[cfg(not(target_family = "windows"))]
+        {
+            Ok(self.file_name().is_some_and(|name| name.starts_with('.')))
+        }
+    }
+
+    /// Hides or unhides the file.
+    /// On Windows this sets or clears the hidden attribute. On Unix the file is renamed to add
+    /// or remove the leading dot, and the path of self is updated.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * The renamed file already exists. (Unix only)
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn hide() -> Result<(), Box<Error>> {
+    /// let mut cache = Filey::new("cache");
+    /// cache.set_hidden(true)?;
+    /// assert!(cache.is_hidden()?);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # hide().unwrap();
+    /// # }
+    /// ```
+    pub fn set_hidden(&mut self, hidden: bool) -> Result<&mut Self> {
+        #[cfg(target_family = "windows")]
+        {
+            let mut attributes = self.windows_attributes()?;
+            attributes.set_hidden(hidden);
+            self.set_windows_attributes(&attributes)?;
+        }
+        #[cfg(not(target_family = "windows"))]
+        {
+            let file_name = self.path.file_name().ok_or_else(|| GetFileNameError {
+                path: self.to_string(),
+            })?;
+            // Works on the bytes so that names which aren't valid Unicode are kept, and adds or
+            // removes exactly one dot.
+            let bytes = file_name.as_encoded_bytes();
+            let renamed = match (hidden, bytes.strip_prefix(b".")) {
+                (true, None) => {
+                    let mut renamed = OsString::from(".");
+                    renamed.push(file_name);
+                    renamed
+                }
+                // Splitting right after an ASCII character keeps the encoding valid.
+                (false, Some(rest)) => unsafe { OsStr::from_encoded_bytes_unchecked(rest) }.into(),
+                _ => return Ok(self),
+            };
+            if renamed.is_empty() {
+                return Err(GetFileNameError {
+                    path: self.to_string(),
+                });
+            }
+            let to = self.path.with_file_name(renamed);
+            if to.exists() || to.is_symlink() {
+                return Err(AlreadyExists {
+                    path: to.to_string_lossy().to_string(),
+                });
+            }
+            self.run(Operation::with_target(Op::Rename, &self.path, &to), || {
+                rename(&self.path, &to).op2(Op::Rename, &self.path, &to)
+            })?;
+            self.path = to;
+        }
+        Ok(self)
+    }
+
+    /// (Unix only) Returns the owner and the group of the file.
+    /// The Display of [`Owner`] looks like `user:group`.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_owner() -> Result<(), Box<Error>> {
+    /// let owner = Filey::new("install.sh").owner()?;
+    /// println!("{}", owner); // alice:users
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_owner().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn owner(&self) -> Result<Owner> {
+        Owner::from_path(self)
+    }
+
+    /// (Unix only) Changes the owner and the group of the file, and of everything under it if
+    /// it is a directory, like `chown -R`. None keeps the current id.
+    /// Symbolic links are never followed; the owner of the link itself is changed.
+    ///
+    /// # Errors
+    /// * The user lacks permissions, usually because they aren't root.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn prepare_rootfs() -> Result<(), Box<Error>> {
+    /// Filey::new("rootfs/home/app").set_owner_recursive(Some(1000), Some(1000))?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # prepare_rootfs().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn set_owner_recursive(&self, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.set_owner_recursive_with(uid, gid, &OwnerOptions::default())
+    }
+
+    /// (Unix only) Changes the owner and the group recursively like
+    /// [`Filey::set_owner_recursive`], optionally skipping symbolic links and other filesystems.
+    ///
+    /// # Errors
+    /// * The user lacks permissions, usually because they aren't root.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, OwnerOptions};
+    /// # use std::error::Error;
+    /// #
+    /// # fn prepare_chroot() -> Result<(), Box<Error>> {
+    /// let options = OwnerOptions {
+    ///     one_file_system: true, // leaves a mounted /proc alone
+    ///     ..Default::default()
+    /// };
+    /// Filey::new("/srv/chroot").set_owner_recursive_with(Some(0), Some(0), &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # prepare_chroot().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn set_owner_recursive_with(
+        &self,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        options: &OwnerOptions,
+    ) -> Result<()> {
+        self.run(Operation::new(Op::SetOwner, &self.path), || {
+            owner::set_recursive(&self.path, uid, gid, options)
+        })
+    }
+
+    /// (Unix only) Returns the information of the file like stat(2).
+    /// Symbolic links are followed.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_stat() -> Result<(), Box<Error>> {
+    /// let stat = Filey::new("install.sh").stat()?;
+    /// println!("{:o}", stat.mode()); // 100755
+    /// println!("{}", stat.mtime().nsec()); // 261731522
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_stat().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn stat(&self) -> Result<Stat> {
+        Stat::from_path(self)
+    }
+
+    /// (Unix only) Returns the information of the file like lstat(2).
+    /// If the file is a symbolic link, returns the information of the link itself.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    #[cfg(target_family = "unix")]
+    pub fn lstat(&self) -> Result<Stat> {
+        Stat::from_path_no_follow(self)
+    }
+
+    /// (Unix only) Returns the name of the user who owns the file.
+    /// Returns None if the uid has no entry in the passwd database.
+    #[cfg(target_family = "unix")]
+    pub fn owner_name(&self) -> Result<Option<String>> {
+        Ok(self.owner()?.user().map(|s| s.to_string()))
+    }
+
+    /// (Unix only) Returns the name of the group which owns the file.
+    /// Returns None if the gid has no entry in the group database.
+    #[cfg(target_family = "unix")]
+    pub fn group_name(&self) -> Result<Option<String>> {
+        Ok(self.owner()?.group().map(|s| s.to_string()))
+    }
+
+    /// Returns the file name or the directory name.
+    /// Returns None if the path terminates in ...
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// #
+    /// # fn get_file_name() -> Option<String> {
+    /// let file = Filey::new("src/lib.rs");
+    /// assert_eq!(file.file_name()?.as_str(), "lib.rs");
+    ///
+    /// let directory = Filey::new("src/lib.rs");
+    /// assert_eq!(directory.file_name()?.as_str(), "src");
+    /// # Some(directory.to_string())
+    /// # }
+    /// # fn main() {
+    /// # get_file_name().unwrap();
+    /// # }
+    /// ```
+    pub fn file_name(&self) -> Option<String> {
+        Some(self.path.file_name()?.to_string_lossy().to_string())
+    }
+
+    /// Returns the stem portion of the file name.
+    /// Returns None if there is no file name.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_file_stem() -> Option<String> {
+    /// let file = Filey::new("src/lib.rs");
+    /// assert_eq!(file.file_stem()?.as_str(), "lib");
+    /// # Some(file.to_string())
+    /// # }
+    /// # fn main() {
+    /// # get_file_stem().unwrap();
+    /// # }
+    /// ```
+    pub fn file_stem(&self) -> Option<String> {
+        Some(self.path.file_stem()?.to_string_lossy().to_string())
+    }
+
+    /// Returns the file name without allocating.
+    /// Returns None if there is no file name or it's not valid Unicode.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// #
+    /// let file = Filey::new("src/lib.rs");
+    /// assert_eq!(file.file_name_str(), Some("lib.rs"));
+    /// ```
+    pub fn file_name_str(&self) -> Option<&str> {
+        self.path.file_name()?.to_str()
+    }
+
+    /// Returns the stem portion of the file name without allocating.
+    /// Returns None if there is no file name or it's not valid Unicode.
+    pub fn file_stem_str(&self) -> Option<&str> {
+        self.path.file_stem()?.to_str()
+    }
+
+    /// Returns the path as a string slice without allocating.
+    /// Returns None if the path is not valid Unicode.
+    pub fn to_str(&self) -> Option<&str> {
+        self.path.to_str()
+    }
+
+    /// Returns the parent directory.
+    /// Returns None if the path terminates in a root or prefix, or if it's the empty string.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// #
+    /// # fn get_parent_dir() -> Option<PathBuf> {
+    /// let file = Filey::new("src/lib.rs");
+    /// assert_eq!(file.parent_dir()?.as_str(), "src");
+    /// # Some(file.path())
+    /// # }
+    /// # fn main() {
+    /// # get_parent_dir().unwrap();
+    /// # }
+    /// ```
+    pub fn parent_dir(&self) -> Option<String> {
+        Some(self.path.parent()?.to_string_lossy().to_string())
+    }
+
+    /// Returns the absolutized path of the file or the directory.
+    ///
+    /// # Errors
+    /// * The environment variable HOME isn't set.
+    /// * The environment variable's name contains the equal sign character (=) or the NUL
+    ///   character.
+    /// * The environment variable's value is not valid Unicode.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_absoluzed() -> Result<(), Box<Error>> {
+    /// let mut file = Filey::new("src/lib.rs");
+    /// assert_eq!(file.absolutized()?
+    ///     .to_string()
+    ///     .as_str(),
+    ///     "/home/Tom/src/lib.rs");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_absoluzed().unwrap();
+    /// # }
+    /// ```
+    pub fn absolutize(&mut self) -> Result<&mut Self> {
+        let absolutized = absolute::absolutize(&self.path).op(Op::Canonicalize, &self.path)?;
+        self.path = absolutized.to_path_buf();
+        Ok(self)
+    }
+
+    /// Return the canonicalized(absolutized and symbolic links solved) path.
+    ///
+    /// # Errors
+    /// * The path doesn't exist.
+    /// * A non-final component in path is not a directory.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_canonicalized() -> Result<(), Box<Error>> {
+    /// // nvim/init.lua -> /home/Lisa/dotfiles/nvim/init.lua
+    /// let mut file = Filey::new("nvim/init.lua");
+    /// assert_eq!(file.canonicalize()?
+    ///     .to_string()
+    ///     .as_str(),
+    ///     "/home/Lisa/dotfiles/nvim/init.lua");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_canonicalized().unwrap();
+    /// # }
+    /// ```
+    pub fn canonicalize(&mut self) -> Result<&mut Self> {
+        let canonicalized = self.path.canonicalize().op(Op::Canonicalize, &self.path)?;
+        self.path = canonicalized;
+        Ok(self)
+    }
+
+    /// Returns the path with every ancestor canonicalized but the final component left as is.
+    /// If the file is a symbolic link, the result still points to the link itself, so it can
+    /// be removed or re-pointed. The final component doesn't need to exist.
+    ///
+    /// # Errors
+    /// * The parent directory doesn't exist.
+    /// * A non-final component in path is not a directory.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_canonicalized() -> Result<(), Box<Error>> {
+    /// // dotfiles -> /home/Lisa/src/dotfiles
+    /// // dotfiles/current -> v2
+    /// let link = Filey::new("dotfiles/current").canonicalized_keep_symlink()?;
+    /// assert_eq!(link.to_string().as_str(), "/home/Lisa/src/dotfiles/current");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_canonicalized().unwrap();
+    /// # }
+    /// ```
+    pub fn canonicalized_keep_symlink(&self) -> Result<Self> {
+        let file_name = match self.path.file_name() {
+            Some(file_name) => file_name,
+            // The path ends in a root or "..", which has to be resolved.
+            None => {
+                let canonicalized = self.path.canonicalize().op(Op::Canonicalize, &self.path)?;
+                return Ok(Self::new(canonicalized));
+            }
+        };
+        let parent = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let canonicalized = parent.canonicalize().op(Op::Canonicalize, parent)?;
+        Ok(Self::new(canonicalized.join(file_name)))
+    }
+
+    /// (Windows only) Returns the extended-length form of the path, `\\?\C:\...` or
+    /// `\\?\UNC\server\share\...`, which isn't limited to MAX_PATH characters.
+    /// Relative paths are resolved against the current directory first.
+    ///
+    /// Filey applies this form automatically when a path exceeds MAX_PATH, so this is only
+    /// needed to pass long paths to other libraries or processes.
+    ///
+    /// # Errors
+    /// * The path can't be resolved.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn extend() -> Result<(), Box<Error>> {
+    /// let share = Filey::new(r"\\fileserver\builds\node_modules").to_extended_length()?;
+    /// assert_eq!(share.to_str(), Some(r"\\?\UNC\fileserver\builds\node_modules"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # extend().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "windows")]
+    pub fn to_extended_length(&self) -> Result<PathBuf> {
+        win::to_extended_length(self)
+    }
+
+    /// (Not on WASI) Replaces an initial tilde of the path by the environment variable HOME.
+    ///
+    /// # Errors
+    /// * The environment variable HOME isn't set.
+    /// * The environment variable's name contains the equal sign character (=) or the NUL
+    ///   character.
+    /// * The environment variable's value is not valid Unicode.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_expanded() -> Result<(), Box<Error>> {
+    /// let mut directory = Filey::new("~/audio");
+    /// assert_eq!(directory.expand_user()?
+    ///     .to_string()
+    ///     .as_str(),
+    ///     "/home/Mike/audio");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_expanded().unwrap();
+    /// # }
+    /// ```
+    #[cfg(not(target_os = "wasi"))]
+    pub fn expand_user(&mut self) -> Result<&mut Self> {
+        let s = &self.to_string();
+        if s.starts_with('~') {
+            let expanded = s.replacen('~', &home_dir()?, 1);
+            self.path = Path::new(&expanded).to_path_buf();
+        }
+        Ok(self)
+    }
+
+    /// (Not on WASI) Replaces path_to_home by tilde.
+    ///
+    /// # Errors
+    /// * The environment variable HOME isn't set.
+    /// * The environment variable's name contains the equal sign character (=) or the NUL
+    ///   character.
+    /// * The environment variable's value is not valid Unicode.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_closed() -> Result<(), Box<Error>> {
+    /// let mut file = Filey::new("/home/Meg/cats.png");
+    /// assert_eq!(file.close_user()?.as_str(), "~/cats.png")
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_closed().unwrap();
+    /// # }
+    /// ```
+    #[cfg(not(target_os = "wasi"))]
+    pub fn contract_user(&mut self) -> Result<&mut Self> {
+        let home_dir = &home_dir()?;
+        let s = self.to_string();
+        if s.starts_with(home_dir) {
+            let contracted = s.replacen(home_dir, "~", 1);
+            self.path = Path::new(&contracted).to_path_buf();
+        }
+        Ok(self)
+    }
+
+    /// Move a file or a directory to the given path.
+    /// If it is on another filesystem, the file or directory is cloned there where the
+    /// filesystem supports it (reflink), or copied otherwise, and then removed.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * Copying to another filesystem fails. What was already copied is left there.
+    ///
+    /// # Panics
+    /// * Both from and to don't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::Path;
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn moves() -> Result<(), Box<Error>> {
+    /// let mut file = Filey::new("cats.png");
+    /// file.move_to("photos/animals/")?;
+    /// assert_eq!(Path::new("photos/animals/cats.png").exists(), true);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # moves().unwrap();
+    /// # }
+    /// ```
+    pub fn move_to<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
+        self.move_to_with(path, &MoveOptions::default())
+    }
+
+    /// Same as [`Filey::move_to`], with options such as creating missing parent directories.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, MoveOptions};
+    /// # use std::error::Error;
+    /// #
+    /// # fn archive() -> Result<(), Box<Error>> {
+    /// let options = MoveOptions {
+    ///     create_parents: true,
+    ///     ..Default::default()
+    /// };
+    /// Filey::new("a.txt").move_to_with("archive/2024/06/a.txt", &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # archive().unwrap();
+    /// # }
+    /// ```
+    pub fn move_to_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &MoveOptions,
+    ) -> Result<&mut Self> {
+        self.move_to_with_report(path, options)?;
+        Ok(self)
+    }
+
+    /// Same as [`Filey::move_to_with`], returning whether the file or directory was renamed,
+    /// cloned or copied.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The source and the destination are on separate filesystems and
+    ///   [`MoveOptions::rename_only`] is set.
+    /// * Copying to another filesystem fails. What was already copied is left there.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, MoveOptions, MoveStrategy};
+    /// # use std::error::Error;
+    /// #
+    /// # fn offload() -> Result<(), Box<Error>> {
+    /// let report = Filey::new("render.mkv")
+    ///     .move_to_with_report("/mnt/archive/", &MoveOptions::default())?;
+    /// if report.strategy == MoveStrategy::Copy {
+    ///     println!("copied to {}", report.path.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # offload().unwrap();
+    /// # }
+    /// ```
+    pub fn move_to_with_report<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &MoveOptions,
+    ) -> Result<MoveReport> {
+        let path = path.as_ref();
+        let from = self.path.clone();
+        // The closure moves self, so the cache is cleared here rather than by Filey::run.
+        self.clear_cache();
+        hooks::run(Operation::with_target(Op::Rename, &from, path), move || {
+            if options.create_parents && !path.is_dir() {
+                options::create_parents(path)?;
+            }
+
+            let to = if path.is_dir() {
+                let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+                    path: self.to_string(),
+                })?;
+                path.join(file_name)
+            } else {
+                path.to_path_buf()
+            };
+
+            let strategy = relocate::move_path(&self.path, &to, options.rename_only)?;
+            self.path = to.clone();
+            Ok(MoveReport { path: to, strategy })
+        })
+    }
+
+    /// Moves a file or a directory to path, merging it into path if both are directories.
+    /// Each child is moved separately and subdirectories are merged recursively; a file which
+    /// already exists at the destination is handled according to on_conflict.
+    /// The source directory is removed once everything has been moved out of it.
+    ///
+    /// Unlike [`Filey::move_to`], path is the new path itself, not the directory to move into.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The source and the destination are on separate filesystems.
+    /// * A file already exists at the destination and on_conflict is [`OnConflict::Fail`].
+    ///   Children moved before the conflict was found stay at the destination.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, OnConflict};
+    /// # use std::error::Error;
+    /// #
+    /// # fn import() -> Result<(), Box<Error>> {
+    /// let mut download = Filey::new("Downloads/photos");
+    /// download.move_into_merging("Pictures/photos", OnConflict::Skip)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # import().unwrap();
+    /// # }
+    /// ```
+    pub fn move_into_merging<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        on_conflict: OnConflict,
+    ) -> Result<&mut Self> {
+        let path = path.as_ref();
+        self.run(Operation::with_target(Op::Rename, &self.path, path), || {
+            merge::move_merging(&self.path, path, on_conflict)
+        })?;
+        self.path = path.to_path_buf();
+        Ok(self)
+    }
+
+    /// Detects the type of a file and remove the file.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///  
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn rm() -> Result<(), Box<Error>> {
+    /// let file = Filey::new("coredump");
+    /// file.remove()?;
+    /// assert_eq!(file.exists(), false);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # rm().unwrap();
+    /// # }
+    /// ```
+    pub fn remove(&self) -> Result<()> {
+        self.run(Operation::new(Op::Remove, &self.path), || {
+            remove::remove(&self.path)
+        })
+    }
+
+    /// Moves the file or directory into the trash of the current user instead of removing it,
+    /// so that it can be restored from the file manager. Returns the path it has in the trash,
+    /// or None on Windows, where the Recycle Bin doesn't expose one.
+    ///
+    /// On Linux and the other Unix systems but macOS the freedesktop.org trash in
+    /// `$XDG_DATA_HOME/Trash` is used, on macOS `~/.Trash`. A name already in the trash gets a
+    /// numbered suffix. Hooks see it as [`Op::Remove`].
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    /// * The platform has no trash, e.g. WASI.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn discard() -> Result<(), Box<Error>> {
+    /// let draft = Filey::new("draft.md");
+    /// if let Some(trashed) = draft.trash()? {
+    ///     println!("moved to {}", trashed.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # discard().unwrap();
+    /// # }
+    /// ```
+    pub fn trash(&self) -> Result<Option<PathBuf>> {
+        self.run(Operation::new(Op::Remove, &self.path), || {
+            trash::trash(&self.path)
+        })
+    }
+
+    /// Walks the tree and returns every entry the current user can't modify or remove, so that
+    /// an operation which would partially fail can be detected before it starts.
+    /// A directory which can't be listed is returned without descending into it.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn uninstall() -> Result<(), Box<Error>> {
+    /// let blocked = Filey::new("/opt/app").check_writable_tree()?;
+    /// for path in &blocked {
+    ///     eprintln!("can't remove {}", path.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # uninstall().unwrap();
+    /// # }
+    /// ```
+    pub fn check_writable_tree(&self) -> Result<Vec<PathBuf>> {
+        access::unwritable(&self.path)
+    }
+
+    /// Computes what [`Filey::remove`] would remove without removing anything: every file and
+    /// directory, the bytes they take up, and the entries the current user can't remove.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn uninstall() -> Result<(), Box<Error>> {
+    /// let app = Filey::new("/opt/app");
+    /// let plan = app.plan_remove()?;
+    /// println!("{} files, {} bytes", plan.files.len(), plan.bytes);
+    /// if plan.is_clean() {
+    ///     app.remove()?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # uninstall().unwrap();
+    /// # }
+    /// ```
+    pub fn plan_remove(&self) -> Result<OperationPlan> {
+        plan::remove(&self.path)
+    }
+
+    /// Same as [`Filey::remove`], with options such as retrying while the file is in use.
+    pub fn remove_with(&self, options: &RemoveOptions) -> Result<()> {
+        self.run(Operation::new(Op::Remove, &self.path), || {
+            remove::remove_with(&self.path, options)
+        })
+    }
+
+    /// Removes the file, retrying with backoff for up to timeout while another process has it
+    /// open. On Windows, antivirus scanners and the search indexer briefly open new files,
+    /// which makes removal fail with a sharing violation.
+    ///
+    /// # Errors
+    /// * The file is still in use when timeout has passed; the last error is returned.
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::{error::Error, time::Duration};
+    /// #
+    /// # fn clean() -> Result<(), Box<Error>> {
+    /// Filey::new("target/tmp").remove_eventually(Duration::from_secs(5))?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # clean().unwrap();
+    /// # }
+    /// ```
+    pub fn remove_eventually(&self, timeout: Duration) -> Result<()> {
+        self.remove_with(&RemoveOptions {
+            retry_for: Some(timeout),
+        })
+    }
+
+    /// (Unix only) Removes a unix domain socket if nothing is listening on it.
+    /// Returns true if the socket was removed, false if it is still in use or doesn't exist.
+    ///
+    /// # Errors
+    /// * The path is not a socket.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::os::unix::net::UnixListener;
+    /// # use std::error::Error;
+    /// #
+    /// # fn bind() -> Result<(), Box<Error>> {
+    /// let socket = Filey::new("/run/user/1000/app.sock");
+    /// socket.remove_stale_socket()?;
+    /// let listener = UnixListener::bind(&socket)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # bind().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn remove_stale_socket(&self) -> Result<bool> {
+        match self.file_type() {
+            None => return Ok(false),
+            Some(FileTypes::Socket) => {}
+            Some(_) => {
+                return Err(NotASocket {
+                    path: self.to_string(),
+                })
+            }
+        }
+
+        match UnixStream::connect(self) {
+            Ok(_) => Ok(false),
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                self.remove()?;
+                Ok(true)
+            }
+            Err(e) => Err(Op::Connect.error(self, e)),
+        }
+    }
+
+    pub fn create_file(&self) -> Result<Self> {
+        self.run(Operation::new(Op::Create, &self.path), || {
+            if self.exists() {
+                return Err(AlreadyExists {
+                    path: self.to_string(),
+                });
+            }
+
+            File::create(self).op(Op::Create, self)?;
+
+            Ok(self.clone())
+        })
+    }
+
+    pub fn create_dir(&self) -> Result<Self> {
+        self.run(Operation::new(Op::CreateDir, &self.path), || {
+            if self.exists() {
+                return Err(AlreadyExists {
+                    path: self.to_string(),
+                });
+            }
+
+            create_dir_all(self).op(Op::CreateDir, self)?;
+
+            Ok(self.clone())
+        })
+    }
+
+    /// (Unix only) Creates a regular file with the given permission bits.
+    /// The process's umask is applied to mode unless `exact_mode` is set, as with
+    /// [`Filey::create_file`], which uses 0o666.
+    ///
+    /// # Errors
+    /// * The path already exists.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{CreateOptions, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn create_script() -> Result<(), Box<Error>> {
+    /// // Group-writable even with a umask of 022.
+    /// let options = CreateOptions { exact_mode: true };
+    /// Filey::new("deploy.sh").create_with_mode(0o775, &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # create_script().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn create_with_mode(&self, mode: u32, options: &CreateOptions) -> Result<Self> {
+        self.run(Operation::new(Op::Create, &self.path), || {
+            if self.exists() {
+                return Err(AlreadyExists {
+                    path: self.to_string(),
+                });
+            }
+
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(mode)
+                .open(self)
+                .op(Op::Create, self)?;
+            if options.exact_mode {
+                file.set_permissions(std::fs::Permissions::from_mode(mode))
+                    .op(Op::SetPermissions, self)?;
+            }
+
+            Ok(self.clone())
+        })
+    }
+
+    /// (Unix only) Creates a FIFO special file (named pipe) with the given permission bits.
+    /// The process's umask is applied to mode.
+    ///
+    /// # Errors
+    /// * The path already exists.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn create_pipe() -> Result<(), Box<Error>> {
+    /// let fifo = Filey::new("/tmp/app.fifo").create_fifo(0o600)?;
+    /// assert!(fifo.exists());
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # create_pipe().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn create_fifo(&self, mode: u32) -> Result<Self> {
+        self.run(Operation::new(Op::Create, &self.path), || {
+            if self.exists() {
+                return Err(AlreadyExists {
+                    path: self.to_string(),
+                });
+            }
+
+            sys::mkfifo(self, mode)?;
+
+            Ok(self.clone())
+        })
+    }
+
+    /// (Unix only) Creates a filesystem node like mknod(2).
+    /// mode holds both the file type (e.g. `libc::S_IFCHR`) and the permission bits,
+    /// dev is the device number of a character or block special file.
+    /// Creating device special files usually requires privileges.
+    ///
+    /// # Errors
+    /// * The path already exists.
+    /// * The user lacks permissions.
+    #[cfg(target_family = "unix")]
+    pub fn mknod(&self, mode: u32, dev: u64) -> Result<Self> {
+        self.run(Operation::new(Op::Create, &self.path), || {
+            if self.exists() {
+                return Err(AlreadyExists {
+                    path: self.to_string(),
+                });
+            }
+
+            sys::mknod(self, mode, dev)?;
+
+            Ok(self.clone())
+        })
+    }
+
+    /// Replaces the contents of the file atomically.
+    /// The contents are written to a temporary file in the same directory, which is then renamed
+    /// over the file, so readers never see a partially written file.
+    /// The permissions of an existing file are kept.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The parent directory doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn save() -> Result<(), Box<Error>> {
+    /// Filey::new("settings.toml").write_atomic("theme = \"dark\"\n")?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # save().unwrap();
+    /// # }
+    /// ```
+    pub fn write_atomic<C: AsRef<[u8]>>(&self, contents: C) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            atomic::write_with(self, |file| file.write_all(contents.as_ref()))?;
+            Ok(())
+        })
+    }
+
+    /// (Needs the `serde` feature) Deserializes a configuration file.
+    /// The format is selected by the extension (.json, .toml, .yaml, .yml or .ron), or by trying
+    /// each enabled format if the extension is unknown.
+    /// Each format needs the cargo feature of the same name.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The format couldn't be detected.
+    /// * The contents don't match T.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use serde::Deserialize;
+    /// # use std::error::Error;
+    /// #
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     theme: String,
+    /// }
+    ///
+    /// # fn load() -> Result<(), Box<Error>> {
+    /// let config: Config = Filey::new("~/.config/app/config.yaml")
+    ///     .expand_user()?
+    ///     .read_config()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # load().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn read_config<T: DeserializeOwned>(&self) -> Result<T> {
+        config::read(self)
+    }
+
+    /// (Needs the `serde` feature) Serializes value into the file atomically.
+    /// An existing file keeps its format, a new file gets the format of its extension.
+    ///
+    /// # Errors
+    /// * The format couldn't be detected.
+    /// * The user lacks permissions.
+    #[cfg(feature = "serde")]
+    pub fn write_config<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            config::write(self, value)
+        })
+    }
+
+    /// (Needs the `serde` feature) Serializes value into the file atomically in the given format.
+    #[cfg(feature = "serde")]
+    pub fn write_config_as<T: Serialize>(&self, value: &T, format: ConfigFormat) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            config::write_as(self, value, format)
+        })
+    }
+
+    /// (Needs the `serde` feature) Describes the directory tree (types, sizes, permissions, targets
+    /// of symbolic links and optionally digests) and serializes it in options.format.
+    /// The result deserializes into a [`snapshot::Manifest`].
+    ///
+    /// # Errors
+    /// * The directory doesn't exist.
+    /// * The user lacks permissions.
+    /// * The feature of options.format isn't enabled.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{checksum::Algorithm, snapshot::ManifestOptions, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn package() -> Result<(), Box<Error>> {
+    /// let mut options = ManifestOptions::default();
+    /// options.snapshot.hash = Some(Algorithm::Sha256);
+    /// options.snapshot.omit_modified = true;
+    /// let manifest = Filey::new("dist").manifest(&options)?;
+    /// Filey::new("dist.manifest.json").write_atomic(manifest)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # package().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn manifest(&self, options: &ManifestOptions) -> Result<String> {
+        let manifest = snapshot::take_with(self, &options.snapshot)?;
+        options.format.serialize(&manifest)
+    }
+
+    /// Deserializes a JSON file.
+    #[cfg(feature = "json")]
+    pub fn read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        config::read_as(self, ConfigFormat::Json)
+    }
+
+    /// Serializes value into the file atomically as pretty-printed JSON.
+    #[cfg(feature = "json")]
+    pub fn write_json<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            config::write_as(self, value, ConfigFormat::Json)
+        })
+    }
+
+    /// Deserializes a TOML file.
+    #[cfg(feature = "toml")]
+    pub fn read_toml<T: DeserializeOwned>(&self) -> Result<T> {
+        config::read_as(self, ConfigFormat::Toml)
+    }
+
+    /// Serializes value into the file atomically as TOML.
+    #[cfg(feature = "toml")]
+    pub fn write_toml<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            config::write_as(self, value, ConfigFormat::Toml)
+        })
+    }
+
+    /// Deserializes a YAML file.
+    #[cfg(feature = "yaml")]
+    pub fn read_yaml<T: DeserializeOwned>(&self) -> Result<T> {
+        config::read_as(self, ConfigFormat::Yaml)
+    }
+
+    /// Serializes value into the file atomically as YAML.
+    #[cfg(feature = "yaml")]
+    pub fn write_yaml<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            config::write_as(self, value, ConfigFormat::Yaml)
+        })
+    }
+
+    /// Deserializes a RON (Rusty Object Notation) file.
+    #[cfg(feature = "ron")]
+    pub fn read_ron<T: DeserializeOwned>(&self) -> Result<T> {
+        config::read_as(self, ConfigFormat::Ron)
+    }
+
+    /// Serializes value into the file atomically as pretty-printed RON.
+    #[cfg(feature = "ron")]
+    pub fn write_ron<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            config::write_as(self, value, ConfigFormat::Ron)
+        })
+    }
+
+    /// (Needs the `bincode` feature) Deserializes a file written by [`Filey::write_bincode`].
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The contents don't match T.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::collections::HashMap;
+    /// # use std::error::Error;
+    /// #
+    /// # fn load_cache() -> Result<(), Box<Error>> {
+    /// let index: HashMap<String, u64> = Filey::new("cache/index.bin").read_bincode()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # load_cache().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "bincode")]
+    pub fn read_bincode<T: DeserializeOwned>(&self) -> Result<T> {
+        let file = File::open(self).op(Op::Open, self)?;
+        bincode::deserialize_from(std::io::BufReader::new(file))
+            .map_err(|e| e.into())
+            .map_err(FileyError)
+    }
+
+    /// (Needs the `bincode` feature) Serializes value into the file atomically with bincode.
+    #[cfg(feature = "bincode")]
+    pub fn write_bincode<T: Serialize>(&self, value: &T) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            atomic::write_with(self, |file| {
+                let mut writer = std::io::BufWriter::new(file);
+                bincode::serialize_into(&mut writer, value)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                writer.flush()
+            })?;
+            Ok(())
+        })
+    }
+
+    /// (Needs the `base64` feature) Reads the file and encodes it in standard base64 with
+    /// padding.
+    /// The file is encoded while it is read instead of being loaded first.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn embed() -> Result<(), Box<Error>> {
+    /// let icon = Filey::new("icon.png").read_base64()?;
+    /// let payload = format!(r#"{{"icon": "{}"}}"#, icon);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # embed().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "base64")]
+    pub fn read_base64(&self) -> Result<String> {
+        use base64::{engine::general_purpose::STANDARD, write::EncoderStringWriter};
+
+        let mut file = File::open(self).op(Op::Open, self)?;
+        let mut encoder = EncoderStringWriter::new(&STANDARD);
+        std::io::copy(&mut file, &mut encoder).op(Op::Read, self)?;
+        Ok(encoder.into_inner())
+    }
+
+    /// (Needs the `base64` feature) Decodes standard base64 and writes the bytes into the file
+    /// atomically.
+    /// Whitespace such as the line breaks of MIME-wrapped input is ignored.
+    ///
+    /// # Errors
+    /// * encoded is not valid base64.
+    /// * The user lacks permissions.
+    #[cfg(feature = "base64")]
+    pub fn write_base64(&self, encoded: &str) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            use base64::{engine::general_purpose::STANDARD, read::DecoderReader};
+
+            let encoded = if encoded.contains(|c: char| c.is_ascii_whitespace()) {
+                std::borrow::Cow::Owned(encoded.split_ascii_whitespace().collect::<String>())
+            } else {
+                std::borrow::Cow::Borrowed(encoded)
+            };
+            let mut decoder = DecoderReader::new(encoded.as_bytes(), &STANDARD);
+            atomic::write_with(self, |file| std::io::copy(&mut decoder, file).map(|_| ()))?;
+            Ok(())
+        })
+    }
+
+    /// (Needs the `ini` feature) Reads an INI file as a map of sections to keys and values.
+    /// Keys before the first section header are stored in the section "".
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * A line is neither a section header, a comment nor `key = value`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn get_email() -> Result<(), Box<Error>> {
+    /// let gitconfig = Filey::new("~/.gitconfig").expand_user()?.read_ini()?;
+    /// println!("{}", gitconfig["user"]["email"]); // tom@example.com
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # get_email().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "ini")]
+    pub fn read_ini(&self) -> Result<Ini> {
+        ini::read(self)
+    }
+
+    /// (Needs the `ini` feature) Writes an INI file atomically.
+    /// Comments of an existing file are not kept.
+    #[cfg(feature = "ini")]
+    pub fn write_ini(&self, ini: &Ini) -> Result<()> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            ini::write(self, ini)
+        })
+    }
+
+    /// (Needs the `csv` feature) Returns a streaming iterator deserializing each record of a CSV
+    /// file into T.
+    /// The first row is treated as the header and matched against the field names of T.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use serde::Deserialize;
+    /// # use std::error::Error;
+    /// #
+    /// #[derive(Deserialize)]
+    /// struct Sale {
+    ///     item: String,
+    ///     price: u64,
+    /// }
+    ///
+    /// # fn total() -> Result<(), Box<Error>> {
+    /// let mut total = 0;
+    /// for sale in Filey::new("sales.csv").csv_records::<Sale>()? {
+    ///     total += sale?.price;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # total().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "csv")]
+    pub fn csv_records<T: DeserializeOwned>(&self) -> Result<CsvRecords<T>> {
+        CsvRecords::from_path(self)
+    }
+
+    /// (Needs the `csv` feature) Serializes rows into a CSV file atomically.
+    /// A header row is written from the field names of T.
+    #[cfg(feature = "csv")]
+    pub fn write_csv<I, T>(&self, rows: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: Serialize,
+    {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            csv::write(self, rows)
+        })
+    }
+
+    /// Searches the file for pattern and returns every match with its line number and byte
+    /// offset.
+    /// pattern is either a literal string or (with the `regex` feature) a [`regex::Regex`].
+    /// The file is read in chunks, so large files don't have to fit in memory.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn todo() -> Result<(), Box<Error>> {
+    /// for m in Filey::new("src/main.rs").grep("TODO")? {
+    ///     println!("{}: {}", m.line_number(), m.line()); // 42: // TODO: handle errors
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # todo().unwrap();
+    /// # }
+    /// ```
+    pub fn grep<P: Into<Pattern>>(&self, pattern: P) -> Result<Vec<GrepMatch>> {
+        grep::grep(self, &pattern.into())
+    }
+
+    /// Returns the number of newlines in the file like `wc -l`.
+    /// The file is read in chunks, so it doesn't need to fit in memory.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn count() -> Result<(), Box<Error>> {
+    /// let log = Filey::new("access.log");
+    /// println!("{}", log.line_count()?); // 120391
+    /// println!("{}", log.word_count()?); // 1324301
+    /// println!("{}", log.byte_count()?); // 9823017
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # count().unwrap();
+    /// # }
+    /// ```
+    pub fn line_count(&self) -> Result<u64> {
+        count::lines(self)
+    }
+
+    /// Returns the number of whitespace-separated words in the file like `wc -w`.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn word_count(&self) -> Result<u64> {
+        count::words(self)
+    }
+
+    /// Returns the number of bytes in the file like `wc -c`.
+    /// Unlike [`Filey::size`], the file is read, so this works for pipes and files whose size
+    /// isn't known in advance, such as those in /proc.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn byte_count(&self) -> Result<u64> {
+        count::bytes(self)
+    }
+
+    /// Returns the number of entries in the directory, including hidden ones, and in all its
+    /// subdirectories if recursive. The directory itself isn't counted.
+    /// Symbolic links are counted as entries and not followed.
+    ///
+    /// # Errors
+    /// * The directory doesn't exist.
+    /// * The path isn't a directory.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn check_inbox() -> Result<(), Box<Error>> {
+    /// if Filey::new("inbox").count_entries(false)? > 0 {
+    ///     println!("You have mail");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # check_inbox().unwrap();
+    /// # }
+    /// ```
+    pub fn count_entries(&self, recursive: bool) -> Result<u64> {
+        count::entries(self, recursive, |_| true)
+    }
+
+    /// Returns the number of regular files like [`Filey::count_entries`].
+    /// Symbolic links to files aren't counted.
+    pub fn count_files(&self, recursive: bool) -> Result<u64> {
+        count::entries(self, recursive, |file_type| file_type.is_file())
+    }
+
+    /// Returns the number of directories like [`Filey::count_entries`].
+    /// Symbolic links to directories aren't counted.
+    pub fn count_dirs(&self, recursive: bool) -> Result<u64> {
+        count::entries(self, recursive, |file_type| file_type.is_dir())
+    }
+
+    /// Hashes the contents of the file, storing the digest with the size and modification time
+    /// of the file in the extended attribute `user.filey.checksum`. Later calls return the
+    /// stored digest without reading the file while the size and modification time match, so
+    /// verifying a large unchanged library again is almost instant.
+    ///
+    /// Caching needs Linux or macOS, a filesystem with extended attributes and write permission;
+    /// otherwise the file is hashed every time. Storing the digest runs the
+    /// [hooks](crate::hooks) as [`Op::SetXattr`](crate::Op::SetXattr), and a refusal only skips
+    /// caching. Like `make`, a change which keeps both the size and the modification time isn't
+    /// noticed.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions to read it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{checksum::Algorithm, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn verify() -> Result<(), Box<Error>> {
+    /// for entry in Filey::new("photos").list_entries()? {
+    ///     println!("{}  {}", entry.filey().cached_hash(Algorithm::Sha256)?, entry.path().display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # verify().unwrap();
+    /// # }
+    /// ```
+    pub fn cached_hash(&self, algorithm: Algorithm) -> Result<Digest> {
+        checksum::hash_file_cached(&self.path, algorithm)
+    }
+
+    /// Returns true if the file starts with a byte order mark.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    pub fn has_bom(&self) -> Result<bool> {
+        Ok(self.bom()?.is_some())
+    }
+
+    /// Detects the byte order mark at the start of the file.
+    /// Returns None if there is none.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    pub fn bom(&self) -> Result<Option<Bom>> {
+        encoding::bom(self)
+    }
+
+    /// Removes the byte order mark at the start of the file and returns which one it was.
+    /// The file is rewritten atomically, and only if it has a byte order mark.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn clean() -> Result<(), Box<Error>> {
+    /// let csv = Filey::new("export.csv");
+    /// if let Some(bom) = csv.strip_bom()? {
+    ///     println!("removed {} BOM", bom); // removed UTF-8 BOM
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # clean().unwrap();
+    /// # }
+    /// ```
+    pub fn strip_bom(&self) -> Result<Option<Bom>> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            encoding::strip_bom(self)
+        })
+    }
+
+    /// Checks whether the file is valid UTF-8 without loading it into memory.
+    /// The report contains the byte ranges of every invalid sequence.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn check() -> Result<(), Box<Error>> {
+    /// let report = Filey::new("import.txt").validate_utf8()?;
+    /// if let Some(offset) = report.first_invalid() {
+    ///     eprintln!("invalid UTF-8 at byte {}", offset);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # check().unwrap();
+    /// # }
+    /// ```
+    pub fn validate_utf8(&self) -> Result<Utf8Report> {
+        encoding::validate_utf8(self)
+    }
+
+    /// Reads the interpreter and its arguments from the `#!` line of the file.
+    /// Returns None if the file doesn't start with a shebang.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn interpreter() -> Result<(), Box<Error>> {
+    /// // #!/usr/bin/env python3 -u
+    /// let shebang = Filey::new("build.py").shebang()?.unwrap();
+    /// assert_eq!(shebang.interpreter().to_str(), Some("/usr/bin/env"));
+    /// assert_eq!(shebang.args(), &vec!["python3".to_string(), "-u".to_string()]);
+    /// assert_eq!(shebang.program(), Some("python3"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # interpreter().unwrap();
+    /// # }
+    /// ```
+    pub fn shebang(&self) -> Result<Option<Shebang>> {
+        shebang::shebang(self)
+    }
+
+    /// Returns true if the file starts with a shebang.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn is_script(&self) -> Result<bool> {
+        Ok(self.shebang()?.is_some())
+    }
+
+    /// Detects whether the file is an ELF, PE or Mach-O binary or a script from its magic bytes.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Arch, ExecutableKind, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn check() -> Result<(), Box<Error>> {
+    /// match Filey::new("target/release/app").executable_kind()? {
+    ///     ExecutableKind::Elf(info) if info.is_64bit() && info.arch() == Arch::X86_64 => (),
+    ///     kind => panic!("unexpected artifact: {}", kind),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # check().unwrap();
+    /// # }
+    /// ```
+    pub fn executable_kind(&self) -> Result<ExecutableKind> {
+        ExecutableKind::from_path(self)
+    }
+
+    /// Detects the format of the file from its magic bytes with the built-in signatures.
+    /// Returns a label such as "gzip" or "png", or None if no signature matches.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn detect_format(&self) -> Result<Option<String>> {
+        sniff::detect_format(self)
+    }
+
+    /// Detects the format of the file with the signatures of registry.
+    /// See [`sniff`] for an example.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    pub fn detect_format_with(&self, registry: &sniff::Registry) -> Result<Option<String>> {
+        registry.detect_path(self)
+    }
+
+    /// Returns an xxd-style hex dump of the bytes within range.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn dump() -> Result<(), Box<Error>> {
+    /// print!("{}", Filey::new("hello.txt").hexdump(..)?);
+    /// // 00000000: 4865 6c6c 6f0a                           Hello.
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # dump().unwrap();
+    /// # }
+    /// ```
+    pub fn hexdump<R: RangeBounds<u64>>(&self, range: R) -> Result<String> {
+        let rows = self.hex_rows(range)?;
+        Ok(rows.iter().map(|row| format!("{}\n", row)).collect())
+    }
+
+    /// Returns the bytes within range split into rows of 16 bytes with their offsets.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    pub fn hex_rows<R: RangeBounds<u64>>(&self, range: R) -> Result<Vec<HexRow>> {
+        hexdump::rows(self, range)
+    }
+
+    /// Replaces every match of pattern in the file with replacement, line by line, and returns
+    /// the number of replacements.
+    /// pattern is either a literal string or (with the `regex` feature) a [`regex::Regex`].
+    /// The file is rewritten atomically, and only if something matched.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The file is not valid UTF-8.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use regex::Regex;
+    /// # use std::error::Error;
+    /// #
+    /// # fn patch() -> Result<(), Box<Error>> {
+    /// let config = Filey::new("/etc/ssh/sshd_config");
+    /// config.replace_in_file("PermitRootLogin yes", "PermitRootLogin no")?;
+    /// let port = Regex::new(r"^#?Port \d+$")?;
+    /// assert_eq!(config.replace_in_file(port, "Port 2222")?, 1);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # patch().unwrap();
+    /// # }
+    /// ```
+    pub fn replace_in_file<P: Into<Pattern>>(
+        &self,
+        pattern: P,
+        replacement: &str,
+    ) -> Result<usize> {
+        self.replace_in_file_with(pattern, replacement, &ReplaceOptions::default())
+    }
+
+    /// Same as [`Filey::replace_in_file`], with options such as making a backup.
+    pub fn replace_in_file_with<P: Into<Pattern>>(
+        &self,
+        pattern: P,
+        replacement: &str,
+        options: &ReplaceOptions,
+    ) -> Result<usize> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            replace::replace_in_file(self, &pattern.into(), replacement, options)
+        })
+    }
+
+    /// Applies a unified diff (as produced by `diff -u` or `git diff`) to the file and returns
+    /// the number of hunks applied.
+    /// Hunks which moved are found by searching around their original position, and up to 2
+    /// context lines may mismatch. The file is rewritten atomically, and only if every hunk
+    /// applies. CRLF line endings are kept.
+    ///
+    /// # Errors
+    /// * [`crate::Error::PatchFailed`] if a hunk doesn't apply.
+    /// * A hunk has more or fewer lines than its header says.
+    /// * The diff changes more than one file.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn fix() -> Result<(), Box<Error>> {
+    /// let diff = std::fs::read_to_string("fix-typo.patch")?;
+    /// Filey::new("README.md").apply_patch(&diff)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # fix().unwrap();
+    /// # }
+    /// ```
+    pub fn apply_patch(&self, diff: &str) -> Result<usize> {
+        self.apply_patch_with(diff, &PatchOptions::default())
+    }
+
+    /// Same as [`Filey::apply_patch`], with options for the fuzz factor and dry runs.
+    pub fn apply_patch_with(&self, diff: &str, options: &PatchOptions) -> Result<usize> {
+        self.run(Operation::new(Op::Write, &self.path), || {
+            patch::apply_patch(self, diff, options)
+        })
+    }
+
+    /// Copy the contents of file to another.
+    pub fn copy<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.copy_with(path, &CopyOptions::default())
+    }
+
+    /// Same as [`Filey::copy`], with options such as creating missing parent directories.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * options.check_space is set and the destination filesystem doesn't have room for the
+    ///   file.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{CopyOptions, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn build() -> Result<(), Box<Error>> {
+    /// let options = CopyOptions {
+    ///     create_parents: true,
+    ///     ..Default::default()
+    /// };
+    /// Filey::new("assets/logo.svg").copy_with("dist/assets/logo.svg", &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # build().unwrap();
+    /// # }
+    /// ```
+    pub fn copy_with<P: AsRef<Path>>(&self, path: P, options: &CopyOptions) -> Result<()> {
+        self.copy_with_stats(path, options)?;
+        Ok(())
+    }
+
+    /// Same as [`Filey::copy_with`], returning how many bytes were copied and how long it took.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * options.check_space is set and the destination filesystem doesn't have room for the
+    ///   file.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{CopyOptions, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn upload() -> Result<(), Box<Error>> {
+    /// let dataset = Filey::new("dataset.tar");
+    /// let stats = dataset.copy_with_stats("/mnt/nas/", &CopyOptions::default())?;
+    /// println!("copied {}", stats); // copied 3.2 GiB in 41.0 s (80.0 MiB/s)
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # upload().unwrap();
+    /// # }
+    /// ```
+    pub fn copy_with_stats<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &CopyOptions,
+    ) -> Result<IoStats> {
+        let started = Instant::now();
+        let path = path.as_ref();
+        self.run(Operation::with_target(Op::Copy, &self.path, path), || {
+            if options.create_parents && !path.is_dir() {
+                options::create_parents(path)?;
+            }
 
-        File::create(self)
-            .map_err(|e| e.into())
-            .map_err(FileyError)?;
+            let to = if path.is_dir() {
+                let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+                    path: self.to_string(),
+                })?;
+                path.join(file_name)
+            } else {
+                path.to_path_buf()
+            };
 
-        Ok(self.clone())
-    }
+            if options.check_space {
+                space::ensure(&to, self.size()?)?;
+            }
 
-    pub fn create_dir(&self) -> Result<Self> {
-        if self.exists() {
-            return Err(AlreadyExists {
-                path: self.to_string(),
+            let bytes = match options.timeout {
+                Some(timeout) => deadline::copy(&self.path, &to, timeout, options.buffer_size)?,
+                None => copy::copy_file(&self.path, &to, options.buffer_size)?,
+            };
+            Ok(IoStats {
+                bytes_read: bytes,
+                bytes_written: bytes,
+                files: 1,
+                elapsed: started.elapsed(),
             })
-        }
+        })
+    }
 
-        create_dir_all(self)
-            .map_err(|e| e.into())
-            .map_err(FileyError)?;
+    /// Copies a large file to path so that an interrupted copy can be resumed.
+    /// The digest of every chunk copied is recorded in state_file. Calling this again with the
+    /// same state_file keeps the chunks at path which still match their digests and copies
+    /// only the rest. state_file is removed once the copy is complete.
+    /// Chunks of zeros are skipped, so holes in a sparse file stay holes.
+    ///
+    /// The progress is thrown away if the file has been modified since it was recorded.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    /// * The disk is full or the device fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn upload() -> Result<(), Box<Error>> {
+    /// let image = Filey::new("disk.img");
+    /// // Run again after an interruption to pick up where it left off.
+    /// let report = image.copy_resumable("/mnt/backup/", "disk.img.progress")?;
+    /// println!("kept {} bytes, copied {}", report.verified, report.copied);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # upload().unwrap();
+    /// # }
+    /// ```
+    pub fn copy_resumable<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        path: P,
+        state_file: Q,
+    ) -> Result<ResumeReport> {
+        let path = path.as_ref();
+        self.run(Operation::with_target(Op::Copy, &self.path, path), || {
+            let to = if path.is_dir() {
+                let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+                    path: self.to_string(),
+                })?;
+                path.join(file_name)
+            } else {
+                path.to_path_buf()
+            };
+            resume::copy_resumable(&self.path, &to, state_file.as_ref())
+        })
+    }
 
-        Ok(self.clone())
+    /// Computes what [`Filey::copy`] (for a file) or [`Filey::copy_dir_filtered`] (for a
+    /// directory, without a filter) would create at path, without copying anything.
+    /// Destination paths which already exist are reported as conflicts, and unreadable sources
+    /// and an unwritable destination directory as permission problems.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * A directory of the tree can't be read for another reason than permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn confirm() -> Result<(), Box<Error>> {
+    /// let plan = Filey::new("photos").plan_copy("/mnt/usb")?;
+    /// for conflict in &plan.conflicts {
+    ///     println!("would overwrite {}", conflict.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # confirm().unwrap();
+    /// # }
+    /// ```
+    pub fn plan_copy<P: AsRef<Path>>(&self, path: P) -> Result<OperationPlan> {
+        plan::copy(&self.path, path.as_ref())
     }
 
-    /// Copy the contents of file to another.
-    pub fn copy<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    /// Copies a file or a directory to path recursively, merging it into path if both are
+    /// directories. Symbolic links are copied as links, and files and the directories created
+    /// keep their permissions. A file which already exists at the destination is handled
+    /// according to on_conflict; directories which already exist keep their permissions.
+    ///
+    /// Unlike [`Filey::copy_dir_filtered`], path is the path of the copy itself, not the
+    /// directory to copy into.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    /// * A file already exists at the destination and on_conflict is [`OnConflict::Fail`].
+    ///   Everything copied before the conflict was found stays at the destination.
+    /// * The file is a directory and the destination is inside it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, OnConflict};
+    /// # use std::error::Error;
+    /// #
+    /// # fn install() -> Result<(), Box<Error>> {
+    /// // Adds the default configuration without touching files the user has changed.
+    /// Filey::new("/usr/share/app/config").copy_recursive("/etc/app", OnConflict::Skip)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # install().unwrap();
+    /// # }
+    /// ```
+    pub fn copy_recursive<P: AsRef<Path>>(&self, path: P, on_conflict: OnConflict) -> Result<()> {
+        self.copy_recursive_filtered(path, on_conflict, &Filter::default())
+    }
+
+    /// Same as [`Filey::copy_recursive`], leaving out the entries which filter rejects.
+    pub fn copy_recursive_filtered<P: AsRef<Path>>(
+        &self,
+        path: P,
+        on_conflict: OnConflict,
+        filter: &Filter,
+    ) -> Result<()> {
         let path = path.as_ref();
+        self.run(Operation::with_target(Op::Copy, &self.path, path), || {
+            copy::copy_merging(&self.path, path, on_conflict, filter)
+        })
+    }
 
-        if path.is_dir() {
-            let file_name = self.file_name().ok_or_else(|| GetFileNameError {
-                path: self.to_string(),
-            })?;
-            let to = path.to_path_buf().join(file_name);
+    /// Copies the directory recursively, skipping the entries for which filter returns false.
+    /// filter receives the path of each entry relative to the directory, and a skipped
+    /// directory isn't descended into. Symbolic links are copied as links.
+    /// If path is an existing directory, the directory is copied into it.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The directory doesn't exist.
+    /// * The destination already exists.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::path::Path;
+    /// # use std::error::Error;
+    /// #
+    /// # fn backup() -> Result<(), Box<Error>> {
+    /// Filey::new("project").copy_dir_filtered("/backup/project", |entry| {
+    ///     entry != Path::new("target") && entry.file_name() != Some(".git".as_ref())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # backup().unwrap();
+    /// # }
+    /// ```
+    pub fn copy_dir_filtered<P, F>(&self, path: P, filter: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> bool,
+    {
+        self.copy_dir_filtered_with(path, filter, &BatchOptions::sequential())
+    }
 
-            copy(self, to).map_err(|e| e.into()).map_err(FileyError)?;
-            Ok(())
-        } else {
-            copy(self, path).map_err(|e| e.into()).map_err(FileyError)?;
-            Ok(())
-        }
+    /// Copies the directory recursively like [`Filey::copy_dir_filtered`], copying up to
+    /// options.max_in_flight files at once.
+    /// Keep the limit low on spinning disks and network shares, where many concurrent copies
+    /// are slower than a few. Symbolic links are treated according to options.symlinks.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The directory doesn't exist.
+    /// * The destination already exists.
+    /// * options.check_space is set and the destination filesystem doesn't have room for the
+    ///   files.
+    /// * options.symlinks is [`SymlinkBehavior::Follow`](crate::SymlinkBehavior::Follow) and a
+    ///   link is broken or leads back to a directory containing it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{BatchOptions, Filey};
+    /// # use std::error::Error;
+    /// #
+    /// # fn backup() -> Result<(), Box<Error>> {
+    /// let options = BatchOptions {
+    ///     max_in_flight: 4,
+    ///     check_space: true,
+    ///     ..Default::default()
+    /// };
+    /// Filey::new("photos").copy_dir_filtered_with("/mnt/nas/photos", |_| true, &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # backup().unwrap();
+    /// # }
+    /// ```
+    pub fn copy_dir_filtered_with<P, F>(
+        &self,
+        path: P,
+        filter: F,
+        options: &BatchOptions,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> bool,
+    {
+        self.copy_dir_filtered_with_stats(path, filter, options)?;
+        Ok(())
+    }
+
+    /// Same as [`Filey::copy_dir_filtered_with`], returning how many files and bytes were
+    /// copied and how long it took.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The directory doesn't exist.
+    /// * The destination already exists.
+    /// * options.check_space is set and the destination filesystem doesn't have room for the
+    ///   files.
+    /// * options.symlinks is [`SymlinkBehavior::Follow`](crate::SymlinkBehavior::Follow) and a
+    ///   link is broken or leads back to a directory containing it.
+    pub fn copy_dir_filtered_with_stats<P, F>(
+        &self,
+        path: P,
+        filter: F,
+        options: &BatchOptions,
+    ) -> Result<IoStats>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> bool,
+    {
+        let path = path.as_ref();
+        self.run(Operation::with_target(Op::Copy, &self.path, path), || {
+            if path.is_dir() {
+                let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+                    path: self.to_string(),
+                })?;
+                copy::copy_dir_filtered(&self.path, &path.join(file_name), filter, options)
+            } else {
+                copy::copy_dir_filtered(&self.path, path, filter, options)
+            }
+        })
     }
 
     /// (Unix only) Create a new symbolic link on the filesystem.
@@ -450,23 +3364,53 @@ impl Filey {
     /// ```
     #[cfg(target_family = "unix")]
     pub fn symlink<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let path = path.as_ref();
+        self.symlink_with(path, &SymlinkOptions::default())
+    }
 
-        if path.is_dir() {
-            let file_name = self.file_name().ok_or_else(|| GetFileNameError {
-                path: self.to_string(),
-            })?;
-            let link = path.to_path_buf().join(file_name);
-            symlink(self, link)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-            Ok(())
-        } else {
-            symlink(self, path)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-            Ok(())
-        }
+    /// (Unix only) Same as [`Filey::symlink`], with options to replace an existing file at the
+    /// link path and to make the link relative.
+    /// With force, an existing symbolic link at path is replaced even if it points to a
+    /// directory, instead of creating the new link inside that directory.
+    ///
+    /// # Errors
+    /// * A file already exists at the link path and force isn't set.
+    /// * A directory exists at the link path.
+    /// * The user lacks permissions.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, SymlinkOptions};
+    /// # use std::error::Error;
+    /// #
+    /// # fn install() -> Result<(), Box<Error>> {
+    /// let options = SymlinkOptions {
+    ///     force: true,
+    ///     relative: true,
+    /// };
+    /// // Safe to run again: the existing link is replaced.
+    /// Filey::new("dotfiles/vimrc").symlink_with(".vimrc", &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # install().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn symlink_with<P: AsRef<Path>>(&self, path: P, options: &SymlinkOptions) -> Result<()> {
+        let path = path.as_ref();
+        self.run(
+            Operation::with_target(Op::Symlink, &self.path, path),
+            || {
+                if path.is_dir() && !(options.force && path.is_symlink()) {
+                    let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+                        path: self.to_string(),
+                    })?;
+                    links::symlink_with(&self.path, &path.join(file_name), options)
+                } else {
+                    links::symlink_with(&self.path, path, options)
+                }
+            },
+        )
     }
 
     /// Create a new hard link on the filesystem.
@@ -491,42 +3435,275 @@ impl Filey {
     /// # }
     /// ```
     pub fn hard_link<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.hard_link_with(path, &HardLinkOptions::default())
+    }
+
+    /// Same as [`Filey::hard_link`], with options to replace an existing file at the link path
+    /// and to fall back to copying across filesystems.
+    ///
+    /// # Errors
+    /// * [`crate::Error::CrossDevice`] if the link path is on another filesystem and
+    ///   copy_fallback isn't set.
+    /// * A file already exists at the link path and force isn't set.
+    /// * The original path is not a file or doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, HardLinkOptions};
+    /// # use std::error::Error;
+    /// #
+    /// # fn dedupe() -> Result<(), Box<Error>> {
+    /// let options = HardLinkOptions {
+    ///     force: true,
+    ///     copy_fallback: true,
+    /// };
+    /// Filey::new("store/ab12cd").hard_link_with("node_modules/left-pad/index.js", &options)?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # dedupe().unwrap();
+    /// # }
+    /// ```
+    pub fn hard_link_with<P: AsRef<Path>>(&self, path: P, options: &HardLinkOptions) -> Result<()> {
         let path = path.as_ref();
+        self.run(
+            Operation::with_target(Op::HardLink, &self.path, path),
+            || {
+                if path.is_dir() {
+                    let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+                        path: self.to_string(),
+                    })?;
+                    links::hard_link_with(&self.path, &path.join(file_name), options)
+                } else {
+                    links::hard_link_with(&self.path, path, options)
+                }
+            },
+        )
+    }
 
-        if path.is_dir() {
-            let file_name = self.file_name().ok_or_else(|| GetFileNameError {
-                path: self.to_string(),
-            })?;
-            let link = path.to_path_buf().join(file_name);
-            hard_link(self, link)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-            Ok(())
-        } else {
-            hard_link(self, path)
-                .map_err(|e| e.into())
-                .map_err(FileyError)?;
-            Ok(())
+    /// (Unix only) Returns true if the directory is the root of a mounted filesystem, so
+    /// recursive operations can stop at filesystem boundaries like `du -x`.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn mount() -> Result<(), Box<Error>> {
+    /// assert!(Filey::new("/").is_mount_point()?);
+    /// assert!(!Filey::new("/usr/bin").is_mount_point()?);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # mount().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn is_mount_point(&self) -> Result<bool> {
+        mount::is_mount_point(self)
+    }
+
+    /// (Unix only) Returns the number of hard links pointing to the file.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file doesn't exist.
+    #[cfg(target_family = "unix")]
+    pub fn hard_link_count(&self) -> Result<u64> {
+        links::hard_link_count(self)
+    }
+
+    /// (Unix only) Finds other directory entries under search_root which share the inode of the
+    /// file.
+    /// Symbolic links are not followed and other filesystems are not searched.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file or search_root doesn't exist.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn find() -> Result<(), Box<Error>> {
+    /// let file = Filey::new("photos/cat.png");
+    /// for link in file.find_hard_links("backup/")? {
+    ///     println!("{}", link); // backup/2023/cat.png
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # find().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_family = "unix")]
+    pub fn find_hard_links<P: AsRef<Path>>(&self, search_root: P) -> Result<Vec<Self>> {
+        let found = links::find_hard_links(self, search_root)?;
+        Ok(found.into_iter().map(Self::new).collect())
+    }
+
+    /// (Needs the `unicode` feature) Renames the file so that its name is in the given Unicode
+    /// normalization form, and updates the path of self. Files copied from macOS have
+    /// decomposed (NFD) names which don't match the composed (NFC) names typed on Linux.
+    /// Only the final component is renamed, and nothing happens if it's already normalized.
+    ///
+    /// # Errors
+    /// * The file doesn't exist.
+    /// * The user lacks permissions.
+    /// * Another file already has the normalized name.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, NormalizationForm};
+    /// # use std::error::Error;
+    /// #
+    /// # fn normalize() -> Result<(), Box<Error>> {
+    /// let mut file = Filey::new("photos/Cafe\u{301}.jpg");
+    /// file.normalize_unicode(NormalizationForm::Nfc)?;
+    /// assert_eq!(file.to_string().as_str(), "photos/Caf\u{e9}.jpg");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # normalize().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn normalize_unicode(&mut self, form: NormalizationForm) -> Result<&mut Self> {
+        let file_name = self.file_name().ok_or_else(|| GetFileNameError {
+            path: self.to_string(),
+        })?;
+        if form.is_normalized(&file_name) {
+            return Ok(self);
+        }
+
+        let to = self.path.with_file_name(form.normalize(&file_name));
+        // Normalization-insensitive filesystems such as APFS report the new name as existing.
+        let is_same_file = match (symlink_metadata(&self.path), symlink_metadata(&to)) {
+            (Ok(from), Ok(to)) => same_file(&from, &to),
+            _ => false,
+        };
+        if (to.exists() || to.is_symlink()) && !is_same_file {
+            return Err(AlreadyExists {
+                path: to.to_string_lossy().to_string(),
+            });
         }
+        self.run(Operation::with_target(Op::Rename, &self.path, &to), || {
+            rename(&self.path, &to).op2(Op::Rename, &self.path, &to)
+        })?;
+        self.path = to;
+        Ok(self)
+    }
+
+    /// Returns true if both paths are equal ignoring case, as on the default filesystems of
+    /// Windows and macOS where `Readme.md` and `README.md` are the same file.
+    /// The paths are compared lexically, so the filesystem is not accessed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// #
+    /// let readme = Filey::new("docs/Readme.md");
+    /// assert!(readme.eq_ignore_case("DOCS/README.md"));
+    /// assert!(!readme.eq_ignore_case("docs/README"));
+    /// ```
+    pub fn eq_ignore_case<P: AsRef<Path>>(&self, other: P) -> bool {
+        compare::eq_ignore_case(&self.path, other.as_ref())
+    }
+
+    /// (Needs the `unicode` feature) Returns true if both paths are equal once their names are
+    /// in the same Unicode normalization form, so that a decomposed (NFD) name from macOS equals
+    /// the composed (NFC) name typed on Linux. The paths are compared lexically, so the
+    /// filesystem is not accessed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// #
+    /// let photo = Filey::new("photos/Cafe\u{301}.jpg");
+    /// assert!(photo.eq_normalized("photos/Caf\u{e9}.jpg"));
+    /// assert!(!photo.eq_normalized("photos/Cafe.jpg"));
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn eq_normalized<P: AsRef<Path>>(&self, other: P) -> bool {
+        compare::eq_normalized(&self.path, other.as_ref())
+    }
+
+    /// Returns true if both paths refer to the same location, for de-duplicating paths given
+    /// by users. Symbolic links, `.` and `..` are resolved and relative paths are made absolute
+    /// first; a path which doesn't exist is resolved as far as its existing ancestors.
+    /// Case is ignored on Windows and macOS. Only the paths are compared, so two hard links to
+    /// the same file are different locations.
+    ///
+    /// # Errors
+    /// * The user lacks permissions to resolve a path.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Filey;
+    /// # use std::error::Error;
+    /// #
+    /// # fn dedup() -> Result<(), Box<Error>> {
+    /// let mut unique: Vec<Filey> = Vec::new();
+    /// for arg in std::env::args().skip(1) {
+    ///     let file = Filey::new(arg);
+    ///     if !unique.iter().any(|seen| seen.canonical_eq(&file).unwrap_or(false)) {
+    ///         unique.push(file);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # dedup().unwrap();
+    /// # }
+    /// ```
+    pub fn canonical_eq<P: AsRef<Path>>(&self, other: P) -> Result<bool> {
+        compare::canonical_eq(&self.path, other.as_ref())
     }
 
     pub fn exists(&self) -> bool {
-        self.path.is_symlink() || self.path.exists()
+        self.query_symlink_metadata().is_ok()
     }
 
     pub fn is_file(&self) -> bool {
-        self.path.is_file()
+        self.query_metadata()
+            .is_ok_and(|metadata| metadata.is_file())
     }
 
     pub fn is_dir(&self) -> bool {
-        self.path.is_dir()
+        self.query_metadata()
+            .is_ok_and(|metadata| metadata.is_dir())
     }
 
     pub fn is_symlink(&self) -> bool {
-        self.path.is_symlink()
+        self.query_symlink_metadata()
+            .is_ok_and(|metadata| metadata.is_symlink())
     }
 }
 
+#[cfg(target_os = "macos")]
+const QUARANTINE: &str = "com.apple.quarantine";
+
+/// WASI programs only see preopened directories, so there is no home directory to expand to.
+#[cfg(not(target_os = "wasi"))]
 fn home_dir() -> Result<String> {
-    var("HOME").map_err(|e| e.into()).map_err(FileyError)
+    std::env::var("HOME")
+        .map_err(|e| e.into())
+        .map_err(FileyError)
+}
+
+#[cfg(all(feature = "unicode", target_family = "unix"))]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[cfg(all(feature = "unicode", not(target_family = "unix")))]
+fn same_file(_a: &Metadata, _b: &Metadata) -> bool {
+    false
 }
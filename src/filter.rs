@@ -0,0 +1,204 @@
+use crate::Glob;
+use std::{
+    fs::{symlink_metadata, Metadata},
+    hash::{Hash, Hasher},
+    path::Path,
+    time::SystemTime,
+};
+
+/// Rules deciding which entries of a directory tree an operation works on, shared by
+/// [`Filey::walk_with`](crate::Filey::walk_with),
+/// [`Filey::copy_recursive_filtered`](crate::Filey::copy_recursive_filtered),
+/// [`snapshot::take_with`](crate::snapshot::take_with) and (with the `search` feature)
+/// `search::grep_tree`, so that they can be defined once.
+///
+/// Paths are matched relative to the root of the operation. An excluded directory isn't
+/// descended into. The rules which only make sense for files (include, sizes and times) don't
+/// apply to directories, so that the files inside them can still be reached.
+/// The default filter keeps everything.
+///
+/// # Examples
+/// ```
+/// # use filey::{Filey, Filter, Glob, OnConflict, WalkOptions};
+/// # use std::error::Error;
+/// #
+/// # fn sources() -> Result<(), Box<Error>> {
+/// let filter = Filter {
+///     include: vec![Glob::new("*.rs")?],
+///     exclude: vec![Glob::new("target")?],
+///     skip_hidden: true,
+///     ..Default::default()
+/// };
+/// let options = WalkOptions {
+///     filter: filter.clone(),
+///     ..Default::default()
+/// };
+/// for entry in Filey::new("project").walk_with(&options)? {
+///     println!("{}", entry?.path().display());
+/// }
+/// Filey::new("project").copy_recursive_filtered("/backup/src", OnConflict::Overwrite, &filter)?;
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # sources().unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// Keeps only the files matching one of these (or of include_regex). Empty keeps every
+    /// file.
+    pub include: Vec<Glob>,
+    /// Leaves out the entries matching one of these.
+    pub exclude: Vec<Glob>,
+    /// (Needs the `regex` feature) Keeps only the files whose relative path, with `/` as the
+    /// separator, matches one of these (or of include).
+    #[cfg(feature = "regex")]
+    pub include_regex: Vec<regex::Regex>,
+    /// (Needs the `regex` feature) Leaves out the entries whose relative path, with `/` as
+    /// the separator, matches one of these.
+    #[cfg(feature = "regex")]
+    pub exclude_regex: Vec<regex::Regex>,
+    /// Leaves out files smaller than this many bytes.
+    pub min_size: Option<u64>,
+    /// Leaves out files larger than this many bytes.
+    pub max_size: Option<u64>,
+    /// Leaves out files last modified before this time.
+    pub modified_after: Option<SystemTime>,
+    /// Leaves out files last modified after this time.
+    pub modified_before: Option<SystemTime>,
+    /// Leaves out hidden entries: those whose name starts with a dot.
+    pub skip_hidden: bool,
+}
+
+impl Filter {
+    /// Returns true if the entry at relative, the path relative to the root of the
+    /// operation, with the given metadata passes the filter.
+    pub fn matches(&self, relative: &Path, metadata: &Metadata) -> bool {
+        if self.skip_hidden
+            && relative
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            return false;
+        }
+        if self.exclude.iter().any(|glob| glob.is_match(relative)) {
+            return false;
+        }
+        #[cfg(feature = "regex")]
+        if self
+            .exclude_regex
+            .iter()
+            .any(|regex| regex.is_match(&slashed(relative)))
+        {
+            return false;
+        }
+        if metadata.is_dir() {
+            return true;
+        }
+
+        if !self.includes(relative) {
+            return false;
+        }
+        let len = metadata.len();
+        if self.min_size.is_some_and(|min| len < min) || self.max_size.is_some_and(|max| len > max)
+        {
+            return false;
+        }
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            let Ok(modified) = metadata.modified() else {
+                return false;
+            };
+            if self.modified_after.is_some_and(|after| modified < after)
+                || self.modified_before.is_some_and(|before| modified > before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Same as [`Filter::matches`], reading the metadata of root joined with relative
+    /// without following a symbolic link. An entry whose metadata can't be read passes, so
+    /// that the operation reports the problem.
+    ///
+    /// This fits the filter callbacks of operations such as
+    /// [`Filey::copy_dir_filtered`](crate::Filey::copy_dir_filtered).
+    pub fn matches_in(&self, root: &Path, relative: &Path) -> bool {
+        match symlink_metadata(root.join(relative)) {
+            Ok(metadata) => self.matches(relative, &metadata),
+            Err(_) => true,
+        }
+    }
+
+    /// Returns true if the filter keeps everything, so that the metadata needn't be read.
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn includes(&self, relative: &Path) -> bool {
+        #[cfg(feature = "regex")]
+        if !self.include_regex.is_empty() {
+            let slashed = slashed(relative);
+            if self
+                .include_regex
+                .iter()
+                .any(|regex| regex.is_match(&slashed))
+            {
+                return true;
+            }
+            return self.include.iter().any(|glob| glob.is_match(relative));
+        }
+        self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(relative))
+    }
+}
+
+// Regexes are compared by their source, as `regex::Regex` itself isn't comparable.
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "regex")]
+        {
+            fn sources(regexes: &[regex::Regex]) -> Vec<&str> {
+                regexes.iter().map(|regex| regex.as_str()).collect()
+            }
+            if sources(&self.include_regex) != sources(&other.include_regex)
+                || sources(&self.exclude_regex) != sources(&other.exclude_regex)
+            {
+                return false;
+            }
+        }
+        self.include == other.include
+            && self.exclude == other.exclude
+            && self.min_size == other.min_size
+            && self.max_size == other.max_size
+            && self.modified_after == other.modified_after
+            && self.modified_before == other.modified_before
+            && self.skip_hidden == other.skip_hidden
+    }
+}
+
+impl Eq for Filter {}
+
+impl Hash for Filter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.include.hash(state);
+        self.exclude.hash(state);
+        #[cfg(feature = "regex")]
+        for regex in self.include_regex.iter().chain(&self.exclude_regex) {
+            regex.as_str().hash(state);
+        }
+        self.min_size.hash(state);
+        self.max_size.hash(state);
+        self.modified_after.hash(state);
+        self.modified_before.hash(state);
+        self.skip_hidden.hash(state);
+    }
+}
+
+/// Returns path with `/` as the separator on every platform.
+#[cfg(feature = "regex")]
+fn slashed(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
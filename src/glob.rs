@@ -0,0 +1,283 @@
+use crate::{Error::FileyError, Result};
+use std::{
+    fmt,
+    path::{Component, Path},
+    str::FromStr,
+};
+
+/// A shell-style wildcard pattern matched against paths, such as `*.rs` or `src/**/mod.rs`.
+///
+/// * `*` matches any run of characters within one component of the path and `?` matches
+///   one character.
+/// * `[abc]` and `[a-z]` match one of the characters listed, and `[!abc]` one which isn't.
+/// * `**` as a whole component matches any number of directories, including none.
+/// * `\` matches the next character literally.
+///
+/// A pattern without a `/` is matched against the last component of a path, so `*.log`
+/// matches `a/b/c.log`. Otherwise the whole path has to match.
+/// Matching is case-sensitive unless [`GlobOptions::case_insensitive`] is set.
+///
+/// # Examples
+/// ```
+/// # use filey::Glob;
+/// # use std::error::Error;
+/// #
+/// # fn globs() -> Result<(), Box<Error>> {
+/// let glob = Glob::new("src/**/*.rs")?;
+/// assert!(glob.is_match("src/main.rs"));
+/// assert!(glob.is_match("src/a/b/lib.rs"));
+/// assert!(!glob.is_match("tests/main.rs"));
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # globs().unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Glob {
+    pattern: String,
+    segments: Vec<Segment>,
+    /// Matches only the last component of a path.
+    file_name_only: bool,
+    case_insensitive: bool,
+}
+
+/// Options for [`Glob::with_options`] and [`Filey::glob_with`](crate::Filey::glob_with).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GlobOptions {
+    /// Ignores the case of letters, so `*.JPG` matches `photo.jpg`.
+    pub case_insensitive: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Segment {
+    /// `**`
+    AnyDirectories,
+    Name(Vec<Token>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Token {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyChars,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Parses pattern.
+    ///
+    /// # Errors
+    /// * A `[` isn't closed.
+    /// * pattern ends with a single `\`.
+    pub fn new(pattern: &str) -> Result<Self> {
+        Self::with_options(pattern, &GlobOptions::default())
+    }
+
+    /// Same as [`Glob::new`], with options such as ignoring case.
+    ///
+    /// # Errors
+    /// * A `[` isn't closed.
+    /// * pattern ends with a single `\`.
+    pub fn with_options(pattern: &str, options: &GlobOptions) -> Result<Self> {
+        let trimmed = pattern.trim_end_matches('/');
+        let file_name_only = !trimmed.contains('/');
+        let segments = trimmed
+            .trim_start_matches('/')
+            .split('/')
+            .map(|segment| match segment {
+                "**" => Ok(Segment::AnyDirectories),
+                _ => parse_segment(segment, options.case_insensitive)
+                    .map(Segment::Name)
+                    .ok_or_else(|| {
+                        FileyError(anyhow::anyhow!("'{}' is not a valid glob", pattern))
+                    }),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            segments,
+            file_name_only,
+            case_insensitive: options.case_insensitive,
+        })
+    }
+
+    /// Returns the pattern the glob was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns true if path matches the glob. Only the normal components of path are
+    /// considered, so `./src/main.rs` matches `src/*.rs`.
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        let components = path
+            .as_ref()
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) if self.case_insensitive => {
+                    Some(name.to_string_lossy().chars().map(fold_case).collect())
+                }
+                Component::Normal(name) => Some(name.to_string_lossy().chars().collect()),
+                _ => None,
+            })
+            .collect::<Vec<Vec<char>>>();
+        if self.file_name_only {
+            return components
+                .last()
+                .is_some_and(|name| match_segments(&self.segments, std::slice::from_ref(name)));
+        }
+        match_segments(&self.segments, &components)
+    }
+
+    /// Returns how many components a path matching the glob can have at most, or None if a
+    /// `**` lets it have any number.
+    pub(crate) fn max_depth(&self) -> Option<usize> {
+        if self.segments.contains(&Segment::AnyDirectories) {
+            None
+        } else {
+            Some(self.segments.len())
+        }
+    }
+}
+
+impl FromStr for Glob {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// Returns None if segment isn't a valid pattern. With case_insensitive, the characters are
+/// folded to lowercase, and letters in classes match both cases.
+fn parse_segment(segment: &str, case_insensitive: bool) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let fold = |c: char| if case_insensitive { fold_case(c) } else { c };
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '?' => Token::AnyChar,
+            '*' => Token::AnyChars,
+            '\\' => Token::Char(fold(chars.next()?)),
+            '[' => {
+                let negated = matches!(chars.clone().next(), Some('!' | '^'));
+                if negated {
+                    chars.next();
+                }
+                let mut ranges = Vec::new();
+                loop {
+                    let start = match chars.next()? {
+                        // A `]` right after the `[` is a member, not the end.
+                        ']' if !ranges.is_empty() => break,
+                        '\\' => chars.next()?,
+                        c => c,
+                    };
+                    let mut rest = chars.clone();
+                    let end = match (rest.next(), rest.next()) {
+                        (Some('-'), Some(end)) if end != ']' => {
+                            chars = rest;
+                            end
+                        }
+                        _ => start,
+                    };
+                    ranges.push((start, end));
+                    if case_insensitive && (start.is_alphabetic() || end.is_alphabetic()) {
+                        ranges.push((fold(start), fold(end)));
+                    }
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Char(fold(c)),
+        });
+    }
+    Some(tokens)
+}
+
+/// Folds c to lowercase if it has a single-character lowercase form.
+fn fold_case(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+// Both matchers are the usual wildcard algorithm: on a mismatch only the last `**` or `*`
+// is retried one component or character further, as the earlier ones can't match more
+// than what they matched so far, which keeps patterns like `a*a*a*b` from backtracking
+// exponentially.
+fn match_segments(segments: &[Segment], components: &[Vec<char>]) -> bool {
+    let (mut s, mut c) = (0, 0);
+    let mut retry = None;
+    while c < components.len() {
+        match segments.get(s) {
+            Some(Segment::AnyDirectories) => {
+                retry = Some((s + 1, c));
+                s += 1;
+            }
+            Some(Segment::Name(tokens)) if match_tokens(tokens, &components[c]) => {
+                s += 1;
+                c += 1;
+            }
+            _ => match retry {
+                Some((after, skipped)) => {
+                    retry = Some((after, skipped + 1));
+                    (s, c) = (after, skipped + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    segments[s..]
+        .iter()
+        .all(|segment| matches!(segment, Segment::AnyDirectories))
+}
+
+fn match_tokens(tokens: &[Token], name: &[char]) -> bool {
+    let (mut t, mut n) = (0, 0);
+    let mut retry = None;
+    while n < name.len() {
+        match tokens.get(t) {
+            Some(Token::AnyChars) => {
+                retry = Some((t + 1, n));
+                t += 1;
+            }
+            Some(token) if match_char(token, name[n]) => {
+                t += 1;
+                n += 1;
+            }
+            _ => match retry {
+                Some((after, skipped)) => {
+                    retry = Some((after, skipped + 1));
+                    (t, n) = (after, skipped + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..]
+        .iter()
+        .all(|token| matches!(token, Token::AnyChars))
+}
+
+fn match_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => c == *expected,
+        Token::AnyChar => true,
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+        }
+        Token::AnyChars => false,
+    }
+}
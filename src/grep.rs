@@ -0,0 +1,114 @@
+use crate::{op::OpContext, Op, Pattern, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// A match found by [`crate::Filey::grep`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GrepMatch {
+    line_number: u64,
+    byte_offset: u64,
+    len: usize,
+    line: String,
+}
+
+impl GrepMatch {
+    /// Returns the 1-based number of the line containing the match.
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// Returns the offset of the match from the start of the file in bytes.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    /// Returns the length of the match in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the line containing the match without the line ending.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+}
+
+impl Pattern {
+    /// Returns the byte ranges of every match in line.
+    /// Lines which aren't valid UTF-8 are only searched by literal patterns.
+    pub(crate) fn find_in(&self, line: &[u8]) -> Vec<(usize, usize)> {
+        match self {
+            Self::Literal(s) if s.is_empty() => Vec::new(),
+            Self::Literal(s) => {
+                let needle = s.as_bytes();
+                let mut found = Vec::new();
+                let mut at = 0;
+                while at + needle.len() <= line.len() {
+                    if &line[at..at + needle.len()] == needle {
+                        found.push((at, at + needle.len()));
+                        at += needle.len();
+                    } else {
+                        at += 1;
+                    }
+                }
+                found
+            }
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => match std::str::from_utf8(line) {
+                Ok(line) => regex
+                    .find_iter(line)
+                    .map(|m| (m.start(), m.end()))
+                    .collect(),
+                Err(_) => Vec::new(),
+            },
+        }
+    }
+}
+
+/// Searches a reader line by line.
+pub(crate) fn grep_reader<R: Read>(reader: R, pattern: &Pattern) -> io::Result<Vec<GrepMatch>> {
+    let mut reader = BufReader::with_capacity(64 * 1024, reader);
+    let mut found = Vec::new();
+    let mut line = Vec::new();
+    let mut offset = 0_u64;
+    let mut line_number = 0_u64;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let mut content = line.as_slice();
+        while let Some((b'\n' | b'\r', rest)) = content.split_last() {
+            content = rest;
+        }
+        for (start, end) in pattern.find_in(content) {
+            found.push(GrepMatch {
+                line_number,
+                byte_offset: offset + start as u64,
+                len: end - start,
+                line: String::from_utf8_lossy(content).to_string(),
+            });
+        }
+        offset += read as u64;
+    }
+    Ok(found)
+}
+
+pub(crate) fn grep<P: AsRef<Path>>(path: P, pattern: &Pattern) -> Result<Vec<GrepMatch>> {
+    let path = path.as_ref();
+    let file = File::open(path).op(Op::Open, path)?;
+    grep_reader(file, pattern).op(Op::Read, path)
+}
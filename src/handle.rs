@@ -0,0 +1,218 @@
+use crate::{op::OpContext, sys, Filey, Op, Result};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+};
+
+/// An opened file together with the path it was opened from.
+///
+/// Unlike [`Filey`], every operation goes through the same file descriptor,
+/// so the file can't be swapped underneath by renaming or re-linking the path.
+#[derive(Debug)]
+pub struct FileyHandle {
+    path: PathBuf,
+    file: File,
+}
+
+impl FileyHandle {
+    pub(crate) fn new<P: AsRef<Path>>(path: P, file: File) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file,
+        }
+    }
+
+    /// Takes ownership of a file descriptor and resolves the path it refers to.
+    ///
+    /// # Errors
+    /// * The path of the descriptor can't be resolved on this platform.
+    pub fn from_fd(fd: OwnedFd) -> Result<Self> {
+        let path = fd_path(fd.as_raw_fd())?;
+        Ok(Self::new(path, File::from(fd)))
+    }
+
+    /// Returns the path the handle was opened from.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Returns a [`Filey`] pointing to the same path.
+    pub fn filey(&self) -> Filey {
+        Filey::new(&self.path)
+    }
+
+    /// Opens the same file again in read-only mode.
+    /// The new handle has its own file offset.
+    ///
+    /// # Errors
+    /// * The file has been replaced since this handle was opened (on platforms without
+    ///   /proc/self/fd).
+    pub fn reopen(&self) -> Result<Self> {
+        self.reopen_with(OpenOptions::new().read(true))
+    }
+
+    /// Opens the same file again with the given options.
+    /// On Linux the file is reopened via /proc/self/fd, so the path isn't resolved again.
+    /// Elsewhere the path is opened and checked to still refer to the same inode.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    /// * The file has been replaced since this handle was opened (on platforms without
+    ///   /proc/self/fd).
+    pub fn reopen_with(&self, options: &OpenOptions) -> Result<Self> {
+        let file = reopen(&self.file, &self.path, options)?;
+        Ok(Self::new(&self.path, file))
+    }
+
+    /// Opens path relative to this directory handle in read-only mode like openat(2).
+    /// A symbolic link in the final component is not followed.
+    ///
+    /// # Errors
+    /// * This handle is not a directory.
+    /// * path is absolute.
+    /// * The final component of path is a symbolic link.
+    pub fn open_at<P: AsRef<Path>>(&self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let fd = sys::openat(
+            self.as_raw_fd(),
+            &self.path,
+            path,
+            libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW,
+            0,
+        )?;
+        Ok(Self::new(self.path.join(path), File::from(fd)))
+    }
+
+    /// Creates (or truncates) path relative to this directory handle and opens it in write-only
+    /// mode like openat(2).
+    /// A symbolic link in the final component is not followed.
+    ///
+    /// # Errors
+    /// * This handle is not a directory.
+    /// * path is absolute.
+    /// * The final component of path is a symbolic link.
+    pub fn create_at<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let fd = sys::openat(
+            self.as_raw_fd(),
+            &self.path,
+            path,
+            libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC | libc::O_CLOEXEC | libc::O_NOFOLLOW,
+            mode,
+        )?;
+        Ok(Self::new(self.path.join(path), File::from(fd)))
+    }
+
+    /// Removes the file at path relative to this directory handle like unlinkat(2).
+    ///
+    /// # Errors
+    /// * This handle is not a directory.
+    /// * path is absolute.
+    /// * path is a directory.
+    pub fn remove_at<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        sys::unlinkat(self.as_raw_fd(), &self.path, path, 0)
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn into_file(self) -> File {
+        self.file
+    }
+}
+
+impl Read for FileyHandle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for FileyHandle {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for FileyHandle {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl AsFd for FileyHandle {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsRawFd for FileyHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for FileyHandle {
+    fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+}
+
+impl From<FileyHandle> for OwnedFd {
+    fn from(handle: FileyHandle) -> Self {
+        handle.file.into()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn reopen(file: &File, _path: &Path, options: &OpenOptions) -> Result<File> {
+    let proc_path = format!("/proc/self/fd/{}", file.as_raw_fd());
+    options.open(&proc_path).op(Op::Open, &proc_path)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reopen(file: &File, path: &Path, options: &OpenOptions) -> Result<File> {
+    use std::os::unix::fs::MetadataExt;
+
+    let reopened = options.open(path).op(Op::Open, path)?;
+    let old = file.metadata().op(Op::Metadata, path)?;
+    let new = reopened.metadata().op(Op::Metadata, path)?;
+    if (old.dev(), old.ino()) != (new.dev(), new.ino()) {
+        return Err(crate::Error::FileyError(anyhow::anyhow!(
+            "'{}' has been replaced since it was opened",
+            path.display()
+        )));
+    }
+    Ok(reopened)
+}
+
+#[cfg(target_os = "linux")]
+fn fd_path(fd: RawFd) -> Result<PathBuf> {
+    let proc_path = format!("/proc/self/fd/{}", fd);
+    std::fs::read_link(&proc_path).op(Op::ReadLink, &proc_path)
+}
+
+#[cfg(target_os = "macos")]
+fn fd_path(fd: RawFd) -> Result<PathBuf> {
+    use std::{ffi::CStr, os::unix::ffi::OsStrExt};
+
+    let mut buf = vec![0 as libc::c_char; libc::PATH_MAX as usize];
+    crate::sys::check(unsafe { libc::fcntl(fd, libc::F_GETPATH, buf.as_mut_ptr()) })
+        .map_err(|e| e.into())
+        .map_err(crate::Error::FileyError)?;
+    let path = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    Ok(Path::new(std::ffi::OsStr::from_bytes(path.to_bytes())).to_path_buf())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn fd_path(_fd: RawFd) -> Result<PathBuf> {
+    Err(crate::Error::FileyError(anyhow::anyhow!(
+        "Resolving the path of a file descriptor is not supported on this platform"
+    )))
+}
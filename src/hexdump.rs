@@ -0,0 +1,89 @@
+use crate::{op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::{Bound, RangeBounds},
+    path::Path,
+};
+
+/// The number of bytes shown on a row, as in xxd.
+const ROW_LEN: usize = 16;
+
+/// A row of a hex dump: up to 16 bytes and the offset of the first one.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HexRow {
+    offset: u64,
+    bytes: Vec<u8>,
+}
+
+impl fmt::Display for HexRow {
+    /// Formats like xxd: `00000010: 4865 6c6c 6f0a  Hello.`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut hex = String::with_capacity(40);
+        for (i, byte) in self.bytes.iter().enumerate() {
+            if i > 0 && i % 2 == 0 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        let ascii = self
+            .bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        write!(f, "{:08x}: {:<39}  {}", self.offset, hex, ascii)
+    }
+}
+
+impl HexRow {
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Reads the bytes of path within range and splits them into rows.
+pub(crate) fn rows<P: AsRef<Path>, R: RangeBounds<u64>>(path: P, range: R) -> Result<Vec<HexRow>> {
+    let path = path.as_ref();
+    let start = match range.start_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&n) => Some(n + 1),
+        Bound::Excluded(&n) => Some(n),
+        Bound::Unbounded => None,
+    };
+
+    let mut file = File::open(path).op(Op::Open, path)?;
+    file.seek(SeekFrom::Start(start)).op(Op::Read, path)?;
+    let mut bytes = Vec::new();
+    match end {
+        Some(end) => file.take(end.saturating_sub(start)).read_to_end(&mut bytes),
+        None => file.read_to_end(&mut bytes),
+    }
+    .op(Op::Read, path)?;
+
+    Ok(bytes
+        .chunks(ROW_LEN)
+        .enumerate()
+        .map(|(i, chunk)| HexRow {
+            offset: start + (i * ROW_LEN) as u64,
+            bytes: chunk.to_vec(),
+        })
+        .collect())
+}
@@ -0,0 +1,188 @@
+//! Hooks run around every operation through [`Filey`](crate::Filey) which changes the
+//! filesystem, for auditing, metrics or policies.
+//!
+//! Hooks are global and run on the thread performing the operation. Operations performed by a
+//! hook, or internally as part of another operation, don't run hooks again.
+//!
+//! # Examples
+//! ```
+//! # use filey::{hooks, Filey};
+//! # use std::error::Error;
+//! #
+//! # fn sandbox() -> Result<(), Box<Error>> {
+//! hooks::on_before(|operation| {
+//!     match operation.paths().find(|path| !path.starts_with("/srv/app")) {
+//!         Some(path) => Err(format!("'{}' is outside /srv/app", path.display())),
+//!         None => Ok(()),
+//!     }
+//! });
+//! hooks::on_after(|operation, result| {
+//!     if let Err(e) = result {
+//!         eprintln!("{:?} failed: {}", operation.op, e);
+//!     }
+//! });
+//!
+//! // Fails with Error::Refused without touching the file.
+//! assert!(Filey::new("/etc/hosts").remove().is_err());
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # sandbox().unwrap();
+//! # }
+//! ```
+
+use crate::{Error, Op, Result};
+use std::{
+    cell::Cell,
+    iter,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+/// An operation which is about to be or was just performed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Operation<'a> {
+    pub op: Op,
+    /// The path of the [`Filey`](crate::Filey) the operation was called on.
+    pub path: &'a Path,
+    /// The second path of operations on two paths as it was passed: the destination of a copy
+    /// or move, or where a link is created. It may be a directory the entry is placed in.
+    pub target: Option<&'a Path>,
+}
+
+impl<'a> Operation<'a> {
+    pub(crate) fn new(op: Op, path: &'a Path) -> Self {
+        Self {
+            op,
+            path,
+            target: None,
+        }
+    }
+
+    pub(crate) fn with_target(op: Op, path: &'a Path, target: &'a Path) -> Self {
+        Self {
+            op,
+            path,
+            target: Some(target),
+        }
+    }
+
+    /// Returns path and then target, if any.
+    pub fn paths(&self) -> impl Iterator<Item = &'a Path> {
+        iter::once(self.path).chain(self.target)
+    }
+}
+
+/// Identifies a registered hook so that it can be removed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub struct HookId(u64);
+
+type BeforeHook = Arc<dyn Fn(&Operation) -> std::result::Result<(), String> + Send + Sync>;
+type AfterHook = Arc<dyn Fn(&Operation, std::result::Result<(), &Error>) + Send + Sync>;
+
+struct Registry {
+    next_id: u64,
+    before: Vec<(HookId, BeforeHook)>,
+    after: Vec<(HookId, AfterHook)>,
+}
+
+static REGISTRY: RwLock<Registry> = RwLock::new(Registry {
+    next_id: 0,
+    before: Vec::new(),
+    after: Vec::new(),
+});
+
+thread_local! {
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Registers a hook which runs before every operation.
+/// Returning an error refuses the operation, which then fails with [`Error::Refused`] and the
+/// message as its reason.
+pub fn on_before<F>(hook: F) -> HookId
+where
+    F: Fn(&Operation) -> std::result::Result<(), String> + Send + Sync + 'static,
+{
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    let id = next_id(&mut registry);
+    registry.before.push((id, Arc::new(hook)));
+    id
+}
+
+/// Registers a hook which runs after every operation with its result, including operations
+/// refused by a hook registered with [`on_before`].
+pub fn on_after<F>(hook: F) -> HookId
+where
+    F: Fn(&Operation, std::result::Result<(), &Error>) + Send + Sync + 'static,
+{
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    let id = next_id(&mut registry);
+    registry.after.push((id, Arc::new(hook)));
+    id
+}
+
+/// Removes a hook. Returns false if it was already removed.
+pub fn remove(id: HookId) -> bool {
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    let count = registry.before.len() + registry.after.len();
+    registry.before.retain(|(hook, _)| *hook != id);
+    registry.after.retain(|(hook, _)| *hook != id);
+    registry.before.len() + registry.after.len() != count
+}
+
+fn next_id(registry: &mut Registry) -> HookId {
+    registry.next_id += 1;
+    HookId(registry.next_id)
+}
+
+/// Resets the running flag even if an operation or a hook panics.
+struct Running;
+
+impl Running {
+    fn enter() -> Option<Self> {
+        (!RUNNING.replace(true)).then_some(Self)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.set(false);
+    }
+}
+
+/// Performs operation by calling f, running the hooks around it.
+pub(crate) fn run<T, F: FnOnce() -> Result<T>>(operation: Operation, f: F) -> Result<T> {
+    let Some(_running) = Running::enter() else {
+        return f();
+    };
+    // Cloned so that hooks can register or remove hooks.
+    let (before, after): (Vec<_>, Vec<_>) = {
+        let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+        (
+            registry
+                .before
+                .iter()
+                .map(|(_, hook)| hook.clone())
+                .collect(),
+            registry
+                .after
+                .iter()
+                .map(|(_, hook)| hook.clone())
+                .collect(),
+        )
+    };
+
+    let result = before
+        .iter()
+        .try_for_each(|hook| hook(&operation))
+        .map_err(|reason| Error::Refused {
+            op: operation.op,
+            path: operation.path.to_string_lossy().to_string(),
+            reason,
+        })
+        .and_then(|()| f());
+    for hook in &after {
+        hook(&operation, result.as_ref().map(|_| ()));
+    }
+    result
+}
@@ -0,0 +1,178 @@
+//! Editing ignore files such as `.gitignore` and `.dockerignore`.
+//!
+//! # Examples
+//! ```
+//! # use filey::ignore::IgnoreFile;
+//! # use std::error::Error;
+//! #
+//! # fn ignore_output() -> Result<(), Box<Error>> {
+//! let mut gitignore = IgnoreFile::open("project/.gitignore")?;
+//! gitignore.add("/generated");
+//! gitignore.add("*.pb.rs");
+//! gitignore.save()?;
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # ignore_output().unwrap();
+//! # }
+//! ```
+
+use crate::{atomic, Op, Result};
+use std::{
+    collections::HashSet,
+    fs::read_to_string,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// An ignore file, with one pattern per line and comments starting with `#`.
+///
+/// Only the patterns are edited: comments, blank lines and the order of the lines are kept as
+/// they are, and so are the line endings (`\n` or `\r\n`) of the file. Patterns are compared
+/// without trailing whitespace, which ignore files don't take into account either.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct IgnoreFile {
+    path: PathBuf,
+    lines: Vec<String>,
+    crlf: bool,
+}
+
+impl IgnoreFile {
+    /// Reads the ignore file at path. A file which doesn't exist is treated as empty and is
+    /// created by [`IgnoreFile::save`].
+    ///
+    /// # Errors
+    /// * The file isn't valid UTF-8.
+    /// * The user lacks permissions.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = match read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Op::Read.error(path, e)),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            lines: contents
+                .lines()
+                .map(|line| line.trim_end_matches('\r').to_string())
+                .collect(),
+            crlf: contents.contains("\r\n"),
+        })
+    }
+
+    /// Returns the path of the ignore file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the patterns in the order they appear, without comments and blank lines.
+    pub fn patterns(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| pattern(line)).collect()
+    }
+
+    /// Returns true if the file contains pattern.
+    pub fn contains(&self, pattern: &str) -> bool {
+        let pattern = pattern.trim_end();
+        self.patterns().contains(&pattern)
+    }
+
+    /// Appends pattern unless the file already contains it. Returns true if it was added.
+    pub fn add(&mut self, pattern: &str) -> bool {
+        if self.contains(pattern) {
+            return false;
+        }
+        self.lines.push(pattern.trim_end().to_string());
+        true
+    }
+
+    /// Appends the patterns the file doesn't contain yet under a `# comment` line, so that
+    /// generated entries can be told apart. If the comment is already there, the patterns
+    /// are added at the end of the block of lines following it instead. Returns how many
+    /// patterns were added.
+    pub fn add_section(&mut self, comment: &str, patterns: &[&str]) -> usize {
+        let mut missing = Vec::new();
+        for pattern in patterns {
+            let pattern = pattern.trim_end();
+            if !self.contains(pattern) && !missing.contains(&pattern) {
+                missing.push(pattern);
+            }
+        }
+        if missing.is_empty() {
+            return 0;
+        }
+
+        let header = format!("# {}", comment.trim());
+        let count = missing.len();
+        let missing = missing.into_iter().map(str::to_string);
+        match self.lines.iter().position(|line| line.trim_end() == header) {
+            Some(start) => {
+                // The block ends at the next blank line or the end of the file.
+                let end = self.lines[start + 1..]
+                    .iter()
+                    .position(|line| line.trim().is_empty())
+                    .map_or(self.lines.len(), |offset| start + 1 + offset);
+                self.lines.splice(end..end, missing);
+            }
+            None => {
+                if self
+                    .lines
+                    .last()
+                    .is_some_and(|line| !line.trim().is_empty())
+                {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(header);
+                self.lines.extend(missing);
+            }
+        }
+        count
+    }
+
+    /// Removes every line containing pattern. Returns true if any was removed.
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let pattern = pattern.trim_end();
+        let len = self.lines.len();
+        self.lines
+            .retain(|line| self::pattern(line) != Some(pattern));
+        self.lines.len() != len
+    }
+
+    /// Removes the repetitions of patterns, keeping the first occurrence of each.
+    /// Returns how many lines were removed.
+    pub fn dedupe(&mut self) -> usize {
+        let mut seen = HashSet::new();
+        let len = self.lines.len();
+        self.lines.retain(|line| match pattern(line) {
+            Some(pattern) => seen.insert(pattern.to_string()),
+            None => true,
+        });
+        len - self.lines.len()
+    }
+
+    /// Writes the file back atomically, ending it with a line break.
+    ///
+    /// # Errors
+    /// * The parent directory doesn't exist.
+    /// * The user lacks permissions.
+    pub fn save(&self) -> Result<()> {
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+        let mut contents = self.lines.join(newline);
+        if !contents.is_empty() {
+            contents.push_str(newline);
+        }
+        atomic::write_with(&self.path, |file| file.write_all(contents.as_bytes()))?;
+        Ok(())
+    }
+}
+
+/// Returns the pattern on line, or None if it is blank or a comment.
+/// `\#` at the start is an escaped pattern, not a comment.
+fn pattern(line: &str) -> Option<&str> {
+    let line = line.trim_end();
+    if line.trim_start().is_empty() || line.starts_with('#') {
+        None
+    } else {
+        Some(line)
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{atomic, op::OpContext, Error::FileyError, Op, Result};
+use std::{collections::BTreeMap, fmt::Write as _, fs::read_to_string, io::Write, path::Path};
+
+/// Sections of an INI file mapped to their keys and values.
+/// Keys before the first section header belong to the section named "".
+pub type Ini = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Parses INI text.
+/// Lines starting with ';' or '#' are comments, and values are trimmed.
+pub(crate) fn parse(contents: &str) -> Result<Ini> {
+    let mut ini = Ini::new();
+    let mut section = String::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header.strip_suffix(']').ok_or_else(|| {
+                FileyError(anyhow::anyhow!(
+                    "Unclosed section header at line {}",
+                    number + 1
+                ))
+            })?;
+            section = name.trim().to_string();
+            ini.entry(section.clone()).or_default();
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            FileyError(anyhow::anyhow!(
+                "Expected 'key = value' at line {}",
+                number + 1
+            ))
+        })?;
+        ini.entry(section.clone())
+            .or_default()
+            .insert(key.trim().to_string(), value.trim().to_string());
+    }
+    Ok(ini)
+}
+
+/// Formats an INI map, writing the "" section first without a header.
+pub(crate) fn format(ini: &Ini) -> String {
+    let mut s = String::new();
+    if let Some(global) = ini.get("") {
+        for (key, value) in global {
+            let _ = writeln!(s, "{} = {}", key, value);
+        }
+    }
+    for (section, entries) in ini.iter().filter(|(section, _)| !section.is_empty()) {
+        if !s.is_empty() {
+            s.push('\n');
+        }
+        let _ = writeln!(s, "[{}]", section);
+        for (key, value) in entries {
+            let _ = writeln!(s, "{} = {}", key, value);
+        }
+    }
+    s
+}
+
+pub(crate) fn read<P: AsRef<Path>>(path: P) -> Result<Ini> {
+    let path = path.as_ref();
+    let contents = read_to_string(path).op(Op::Read, path)?;
+    parse(&contents)
+}
+
+pub(crate) fn write<P: AsRef<Path>>(path: P, ini: &Ini) -> Result<()> {
+    let contents = format(ini);
+    atomic::write_with(path, |file| file.write_all(contents.as_bytes()))?;
+    Ok(())
+}
@@ -28,15 +28,158 @@
 //! # }
 //! ```
 
+mod absolute;
+mod access;
+mod adapter;
+mod atomic;
+#[cfg(target_family = "windows")]
+mod attributes;
+pub mod audit;
+pub mod backup;
+pub mod cache;
+pub mod checksum;
+mod compare;
+mod config;
+mod copy;
+mod count;
+#[cfg(feature = "csv")]
+mod csv;
+mod deadline;
+#[cfg(target_family = "unix")]
+mod dir;
+mod encoding;
+mod executable;
 mod file_types;
 mod filey;
+mod filter;
+mod glob;
+mod grep;
+#[cfg(target_family = "unix")]
+mod handle;
+mod hexdump;
+pub mod hooks;
+pub mod ignore;
+#[cfg(feature = "ini")]
+mod ini;
+mod links;
+mod list;
+#[cfg(target_family = "unix")]
+mod lock;
+#[cfg(target_family = "unix")]
+pub mod lockfile;
+pub mod logfile;
 mod macros;
+mod merge;
+mod metadata;
+#[cfg(target_family = "unix")]
+mod mount;
+mod op;
+mod options;
+#[cfg(target_family = "unix")]
+mod owner;
+mod patch;
 #[cfg(target_family = "unix")]
 mod permissions;
+#[cfg(target_family = "unix")]
+pub mod pidfile;
+mod plan;
+mod pool;
+pub mod quota;
+mod relocate;
+mod remove;
+mod replace;
+mod resume;
+pub mod roots;
+pub mod scaffold;
+#[cfg(feature = "search")]
+pub mod search;
+#[cfg(target_family = "unix")]
+pub mod seq;
+mod shebang;
+pub mod snapshot;
+pub mod sniff;
+mod space;
+#[cfg(target_family = "unix")]
+mod stat;
+mod stats;
+#[cfg(target_family = "windows")]
+mod streams;
+#[cfg(target_family = "unix")]
+mod sys;
 mod test;
+mod times;
+mod trash;
+#[cfg(target_family = "unix")]
+mod umask;
+#[cfg(feature = "unicode")]
+mod unicode;
 pub mod units;
+#[cfg(target_family = "unix")]
+mod usage;
+mod walk;
+mod watch;
+#[cfg(target_family = "windows")]
+mod win;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod xattr;
 
-pub use crate::{file_types::FileTypes, filey::Filey, permissions::Permissions};
+#[cfg(target_family = "windows")]
+pub use crate::attributes::WindowsAttributes;
+#[cfg(feature = "csv")]
+pub use crate::csv::CsvRecords;
+#[cfg(target_family = "unix")]
+pub use crate::dir::Dir;
+#[cfg(target_family = "unix")]
+pub use crate::handle::FileyHandle;
+#[cfg(feature = "ini")]
+pub use crate::ini::Ini;
+#[cfg(target_family = "unix")]
+pub use crate::lock::{LockMode, RangeLock};
+#[cfg(target_family = "unix")]
+pub use crate::owner::Owner;
+#[cfg(target_family = "unix")]
+pub use crate::permissions::{PermissionBit, PermissionChange, PermissionClass, Permissions};
+#[cfg(target_family = "unix")]
+pub use crate::stat::{Stat, Timespec};
+#[cfg(target_family = "windows")]
+pub use crate::streams::AlternateStream;
+#[cfg(target_family = "unix")]
+pub use crate::umask::{set_process_umask, umask};
+#[cfg(feature = "unicode")]
+pub use crate::unicode::NormalizationForm;
+#[cfg(target_family = "unix")]
+pub use crate::usage::DiskUsage;
+pub use crate::{
+    adapter::{FileyReader, FileyWriter, WriteMode},
+    config::ConfigFormat,
+    encoding::{Bom, Utf8Report},
+    executable::{Arch, BinaryInfo, ExecutableKind},
+    file_types::FileTypes,
+    filey::Filey,
+    filter::Filter,
+    glob::{Glob, GlobOptions},
+    grep::GrepMatch,
+    hexdump::HexRow,
+    list::Entry,
+    merge::{
+        merge, AskCallback, MergeDecision, MergeReport, MergeStrategy, OnConflict, Resolution,
+    },
+    metadata::Metadata,
+    op::Op,
+    options::{
+        BatchOptions, BufferSize, CopyOptions, CreateOptions, HardLinkOptions, ListOptions,
+        MoveOptions, OpenFileOptions, OwnerOptions, RemoveOptions, SymlinkBehavior, SymlinkOptions,
+    },
+    patch::PatchOptions,
+    plan::OperationPlan,
+    relocate::{MoveReport, MoveStrategy},
+    replace::{Pattern, ReplaceOptions},
+    resume::ResumeReport,
+    shebang::Shebang,
+    stats::IoStats,
+    walk::{Walk, WalkOptions},
+    watch::{Change, Watcher},
+};
 
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
@@ -50,6 +193,100 @@ pub enum Error {
     GetFileNameError {
         path: String,
     },
+    #[error("'{}' is not a socket", path)]
+    NotASocket {
+        path: String,
+    },
+    #[error("'{}' is locked by another process", path)]
+    WouldBlock {
+        path: String,
+    },
+    #[error("Timed out after {:?} waiting for '{}'", timeout, path)]
+    Timeout {
+        path: String,
+        timeout: std::time::Duration,
+    },
+    #[error(
+        "'{}' is held by another running process{}",
+        path,
+        pid.map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+    )]
+    AlreadyRunning {
+        path: String,
+        pid: Option<u32>,
+    },
+    #[error("Could not detect the format of '{}'", path)]
+    UnknownFormat {
+        path: String,
+    },
+    #[error(
+        "{} '{}'{}: {}",
+        op,
+        path,
+        target.as_ref().map(|target| format!(" -> '{}'", target)).unwrap_or_default(),
+        source
+    )]
+    Io {
+        op: crate::Op,
+        path: String,
+        target: Option<String>,
+        source: std::io::Error,
+    },
+    #[error(
+        "Could not hard link '{}' to '{}': hard links can't span filesystems",
+        path,
+        target
+    )]
+    CrossDevice {
+        path: String,
+        target: String,
+        source: std::io::Error,
+    },
+    #[error("Hunk #{} failed to apply to '{}'", hunk, path)]
+    PatchFailed {
+        path: String,
+        hunk: usize,
+    },
+    #[error(
+        "Not enough space on the filesystem of '{}': {} needed, {} available",
+        path,
+        crate::units::UnitOfInfo::format(*needed),
+        crate::units::UnitOfInfo::format(*available)
+    )]
+    InsufficientSpace {
+        path: String,
+        needed: u64,
+        available: u64,
+    },
+    #[error("Refused {} '{}': {}", op, path, reason)]
+    Refused {
+        op: crate::Op,
+        path: String,
+        reason: String,
+    },
+}
+
+impl Error {
+    /// Returns the I/O error this error was caused by, if any.
+    /// Errors wrapping an I/O error through several layers of context are searched as well.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self {
+            Self::Io { source, .. } | Self::CrossDevice { source, .. } => Some(source),
+            Self::FileyError(e) => e.chain().find_map(|e| e.downcast_ref::<std::io::Error>()),
+            _ => None,
+        }
+    }
+
+    /// Returns the OS error code (errno on Unix) this error was caused by, if any.
+    /// Useful to branch on values such as EXDEV or ENOSPC.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.io_error()?.raw_os_error()
+    }
+
+    /// Returns the kind of the I/O error this error was caused by, if any.
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        self.io_error().map(|e| e.kind())
+    }
 }
 
 pub type Result<T> = std::result::Result<T, crate::Error>;
@@ -0,0 +1,149 @@
+#[cfg(target_family = "unix")]
+use crate::{absolute, SymlinkOptions};
+use crate::{atomic, op::OpContext, Error, HardLinkOptions, Op, Result};
+use std::{
+    fs::{copy, hard_link},
+    io::ErrorKind,
+    path::Path,
+};
+#[cfg(target_family = "unix")]
+use std::{
+    fs::{metadata, read_dir, symlink_metadata},
+    os::unix::fs::{symlink, MetadataExt},
+    path::PathBuf,
+};
+
+/// Returns the number of hard links pointing to the file.
+#[cfg(target_family = "unix")]
+pub(crate) fn hard_link_count<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let path = path.as_ref();
+    let metadata = metadata(path).op(Op::Metadata, path)?;
+    Ok(metadata.nlink())
+}
+
+/// Searches search_root for other directory entries sharing the inode of path.
+#[cfg(target_family = "unix")]
+pub(crate) fn find_hard_links<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    search_root: Q,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let target = metadata(path).op(Op::Metadata, path)?;
+    let mut found = Vec::new();
+    if target.nlink() < 2 {
+        return Ok(found);
+    }
+
+    let own = absolute::absolutize(path).op(Op::Canonicalize, path)?;
+    search(
+        search_root.as_ref(),
+        (target.dev(), target.ino()),
+        &own,
+        &mut found,
+    )?;
+    Ok(found)
+}
+
+#[cfg(target_family = "unix")]
+fn search(dir: &Path, id: (u64, u64), own: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in read_dir(dir).op(Op::ReadDir, dir)? {
+        let entry = entry.op(Op::ReadDir, dir)?;
+        let path = entry.path();
+        let metadata = symlink_metadata(&path).op(Op::Metadata, &path)?;
+
+        // Hard links can't span filesystems.
+        if metadata.dev() != id.0 {
+            continue;
+        }
+
+        if metadata.is_dir() {
+            search(&path, id, own, found)?;
+        } else if metadata.ino() == id.1 {
+            let absolutized = absolute::absolutize(&path).op(Op::Canonicalize, &path)?;
+            if absolutized != own {
+                found.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Creates a symbolic link at link pointing to target.
+#[cfg(target_family = "unix")]
+pub(crate) fn symlink_with(target: &Path, link: &Path, options: &SymlinkOptions) -> Result<()> {
+    let target = if options.relative {
+        relative_target(target, link)?
+    } else {
+        target.to_path_buf()
+    };
+    if !options.force {
+        return symlink(&target, link).op2(Op::Symlink, link, &target);
+    }
+
+    atomic::replace_with(link, |temp| {
+        symlink(&target, temp).op2(Op::Symlink, link, &target)
+    })
+}
+
+/// Creates a hard link at link pointing to target.
+pub(crate) fn hard_link_with(target: &Path, link: &Path, options: &HardLinkOptions) -> Result<()> {
+    let result = if options.force {
+        atomic::replace_with(link, |temp| {
+            hard_link(target, temp).map_err(|e| link_error(target, link, e))
+        })
+    } else {
+        hard_link(target, link).map_err(|e| link_error(target, link, e))
+    };
+    match result {
+        // Like the link, a forced copy replaces the destination atomically.
+        Err(Error::CrossDevice { .. }) if options.copy_fallback && options.force => {
+            atomic::replace_with(link, |temp| {
+                copy(target, temp).op2(Op::Copy, target, link)?;
+                Ok(())
+            })
+        }
+        Err(Error::CrossDevice { .. }) if options.copy_fallback => {
+            copy(target, link).op2(Op::Copy, target, link)?;
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn link_error(target: &Path, link: &Path, e: std::io::Error) -> Error {
+    if e.kind() == ErrorKind::CrossesDevices {
+        Error::CrossDevice {
+            path: link.to_string_lossy().to_string(),
+            target: target.to_string_lossy().to_string(),
+            source: e,
+        }
+    } else {
+        Op::HardLink.error2(link, target, e)
+    }
+}
+
+/// Returns target relative to the directory containing link.
+/// Both paths are resolved lexically, so symbolic links in them are not followed.
+#[cfg(target_family = "unix")]
+fn relative_target(target: &Path, link: &Path) -> Result<PathBuf> {
+    let target = absolute::absolutize(target).op(Op::Canonicalize, target)?;
+    let link = absolute::absolutize(link).op(Op::Canonicalize, link)?;
+    let base = link.parent().unwrap_or(Path::new("/"));
+
+    let common = target
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    for component in target.components().skip(common) {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Ok(relative)
+}
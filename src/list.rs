@@ -0,0 +1,86 @@
+use crate::{op::OpContext, FileTypes, Filey, ListOptions, Op, Result};
+use std::{
+    ffi::OsString,
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+/// An entry of a directory together with its type.
+///
+/// The type comes from the directory listing itself (d_type on most Unix filesystems), so
+/// no extra stat(2) is needed to tell files, directories and symbolic links apart.
+#[derive(Debug)]
+pub struct Entry {
+    entry: fs::DirEntry,
+    file_type: FileTypes,
+    depth: usize,
+}
+
+impl Entry {
+    pub(crate) fn new(entry: fs::DirEntry) -> Result<Self> {
+        Self::at_depth(entry, 1)
+    }
+
+    pub(crate) fn at_depth(entry: fs::DirEntry, depth: usize) -> Result<Self> {
+        let file_type = entry.file_type().op(Op::Metadata, entry.path())?;
+        Ok(Self {
+            entry,
+            file_type: FileTypes::from_file_type(&file_type),
+            depth,
+        })
+    }
+
+    /// Returns the full path of the entry.
+    pub fn path(&self) -> PathBuf {
+        self.entry.path()
+    }
+
+    /// Returns the name of the entry without the leading directories.
+    pub fn file_name(&self) -> OsString {
+        self.entry.file_name()
+    }
+
+    /// Returns the type of the entry. Symbolic links are not followed.
+    pub fn file_type(&self) -> FileTypes {
+        self.file_type
+    }
+
+    /// Returns how deep the entry is below the directory which was listed or walked.
+    /// Entries directly inside it have depth 1.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Queries the metadata of the entry without following symbolic links.
+    /// On Unix this is a stat relative to the open directory, which avoids resolving the full
+    /// path again.
+    ///
+    /// # Errors
+    /// * The entry has been removed since it was listed.
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.entry.metadata().op(Op::Metadata, self.path())
+    }
+
+    /// Returns a [`Filey`] pointing to the entry.
+    pub fn filey(&self) -> Filey {
+        Filey::new(self.path())
+    }
+}
+
+/// Lists the entries of the directory path, in the order the filesystem returns them.
+pub(crate) fn entries<P: AsRef<Path>>(path: P) -> Result<Vec<Entry>> {
+    let path = path.as_ref();
+    fs::read_dir(path)
+        .op(Op::ReadDir, path)?
+        .map(|entry| Entry::new(entry.op(Op::ReadDir, path)?))
+        .collect()
+}
+
+/// Lists the entries of the directory path, sorted by name if options.sorted is set.
+pub(crate) fn entries_with<P: AsRef<Path>>(path: P, options: &ListOptions) -> Result<Vec<Entry>> {
+    let mut entries = entries(path)?;
+    if options.sorted {
+        entries.sort_by_key(Entry::file_name);
+    }
+    Ok(entries)
+}
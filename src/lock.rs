@@ -0,0 +1,256 @@
+use crate::{
+    op::OpContext,
+    Error::{Timeout, WouldBlock},
+    FileyHandle, Op, Result,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::TryLockError,
+    io,
+    os::fd::{AsRawFd, RawFd},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// The mode of an advisory lock.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LockMode {
+    /// Any number of processes can hold a shared lock at the same time.
+    Shared,
+    /// Only one process can hold an exclusive lock and no shared locks can coexist with it.
+    Exclusive,
+}
+
+impl FileyHandle {
+    /// Acquires an advisory lock on the whole file, blocking until it is available.
+    ///
+    /// # Errors
+    /// * The underlying lock operation failed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, LockMode};
+    /// # use std::error::Error;
+    /// #
+    /// # fn lock() -> Result<(), Box<Error>> {
+    /// let handle = Filey::new("data.db").open()?;
+    /// handle.lock(LockMode::Shared)?;
+    /// // read data.db
+    /// handle.unlock()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # lock().unwrap();
+    /// # }
+    /// ```
+    pub fn lock(&self, mode: LockMode) -> Result<()> {
+        match mode {
+            LockMode::Shared => self.file().lock_shared(),
+            LockMode::Exclusive => self.file().lock(),
+        }
+        .op(Op::Lock, self.path())
+    }
+
+    /// Tries to acquire an advisory lock on the whole file without blocking.
+    ///
+    /// # Errors
+    /// * [`crate::Error::WouldBlock`] if another process holds a conflicting lock.
+    pub fn try_lock(&self, mode: LockMode) -> Result<()> {
+        let result = match mode {
+            LockMode::Shared => self.file().try_lock_shared(),
+            LockMode::Exclusive => self.file().try_lock(),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            Err(TryLockError::WouldBlock) => Err(WouldBlock {
+                path: self.path().to_string_lossy().to_string(),
+            }),
+            Err(TryLockError::Error(e)) => Err(Op::Lock.error(self.path(), e)),
+        }
+    }
+
+    /// Tries to acquire an advisory lock on the whole file, giving up after timeout.
+    ///
+    /// # Errors
+    /// * [`crate::Error::Timeout`] if the lock couldn't be acquired in time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, LockMode};
+    /// # use std::time::Duration;
+    /// # use std::error::Error;
+    /// #
+    /// # fn lock() -> Result<(), Box<Error>> {
+    /// let handle = Filey::new("build.lock").open()?;
+    /// if let Err(e) = handle.try_lock_for(LockMode::Exclusive, Duration::from_secs(5)) {
+    ///     eprintln!("{}", e); // Timed out after 5s waiting for 'build.lock'
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # lock().unwrap();
+    /// # }
+    /// ```
+    pub fn try_lock_for(&self, mode: LockMode, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut interval = Duration::from_millis(1);
+        loop {
+            match self.try_lock(mode) {
+                Err(WouldBlock { path }) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(Timeout { path, timeout });
+                    }
+                    sleep(interval.min(deadline - now));
+                    interval = (interval * 2).min(Duration::from_millis(100));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Locks len bytes starting at start, blocking until the range is available.
+    /// A len of 0 extends the range to the end of the file however large it grows.
+    /// The range is unlocked when the returned guard is dropped.
+    ///
+    /// On Linux this uses open file description locks, so the lock belongs to this handle.
+    /// Elsewhere it falls back to POSIX record locks, which belong to the process.
+    /// An exclusive range lock requires the handle to be opened for writing.
+    ///
+    /// # Errors
+    /// * The handle isn't opened for writing and mode is [`LockMode::Exclusive`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::{Filey, LockMode};
+    /// # use std::error::Error;
+    /// #
+    /// # fn lock_page() -> Result<(), Box<Error>> {
+    /// let handle = Filey::new("data.db").open()?;
+    /// let page = handle.lock_range(LockMode::Shared, 4096, 4096)?;
+    /// // read the second page
+    /// drop(page);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # lock_page().unwrap();
+    /// # }
+    /// ```
+    pub fn lock_range(&self, mode: LockMode, start: u64, len: u64) -> Result<RangeLock<'_>> {
+        set_lock(self.as_raw_fd(), lock_type(mode), start, len, true).op(Op::Lock, self.path())?;
+        Ok(RangeLock {
+            handle: self,
+            start,
+            len,
+        })
+    }
+
+    /// Tries to lock len bytes starting at start without blocking.
+    ///
+    /// # Errors
+    /// * [`crate::Error::WouldBlock`] if a conflicting lock is held on the range.
+    pub fn try_lock_range(&self, mode: LockMode, start: u64, len: u64) -> Result<RangeLock<'_>> {
+        match set_lock(self.as_raw_fd(), lock_type(mode), start, len, false) {
+            Ok(()) => Ok(RangeLock {
+                handle: self,
+                start,
+                len,
+            }),
+            Err(e) if is_would_block(&e) => Err(WouldBlock {
+                path: self.path().to_string_lossy().to_string(),
+            }),
+            Err(e) => Err(Op::Lock.error(self.path(), e)),
+        }
+    }
+
+    /// Releases the advisory lock held through this handle.
+    pub fn unlock(&self) -> Result<()> {
+        self.file().unlock().op(Op::Lock, self.path())
+    }
+}
+
+/// A locked byte range of a file. The range is unlocked on drop.
+#[derive(Debug)]
+pub struct RangeLock<'a> {
+    handle: &'a FileyHandle,
+    start: u64,
+    len: u64,
+}
+
+impl RangeLock<'_> {
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Unlocks the range, reporting errors unlike dropping the guard.
+    pub fn unlock(self) -> Result<()> {
+        let handle = self.handle;
+        let result = set_lock(
+            self.handle.as_raw_fd(),
+            libc::F_UNLCK as libc::c_short,
+            self.start,
+            self.len,
+            false,
+        );
+        std::mem::forget(self);
+        result.op(Op::Lock, handle.path())
+    }
+}
+
+impl Drop for RangeLock<'_> {
+    fn drop(&mut self) {
+        let _ = set_lock(
+            self.handle.as_raw_fd(),
+            libc::F_UNLCK as libc::c_short,
+            self.start,
+            self.len,
+            false,
+        );
+    }
+}
+
+fn lock_type(mode: LockMode) -> libc::c_short {
+    match mode {
+        LockMode::Shared => libc::F_RDLCK as libc::c_short,
+        LockMode::Exclusive => libc::F_WRLCK as libc::c_short,
+    }
+}
+
+fn is_would_block(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::EAGAIN) | Some(libc::EACCES))
+}
+
+#[cfg(target_os = "linux")]
+const SETLK: (libc::c_int, libc::c_int) = (libc::F_OFD_SETLK, libc::F_OFD_SETLKW);
+
+#[cfg(not(target_os = "linux"))]
+const SETLK: (libc::c_int, libc::c_int) = (libc::F_SETLK, libc::F_SETLKW);
+
+fn set_lock(fd: RawFd, kind: libc::c_short, start: u64, len: u64, wait: bool) -> io::Result<()> {
+    let mut flock: libc::flock = unsafe { std::mem::zeroed() };
+    flock.l_type = kind;
+    flock.l_whence = libc::SEEK_SET as _;
+    flock.l_start = start as libc::off_t;
+    flock.l_len = len as libc::off_t;
+
+    let cmd = if wait { SETLK.1 } else { SETLK.0 };
+    loop {
+        if unsafe { libc::fcntl(fd, cmd, &flock) } != -1 {
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
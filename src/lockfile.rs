@@ -0,0 +1,236 @@
+//! Lock files which work on network filesystems.
+//!
+//! Unlike [`crate::LockMode`] locks, a [`LockFile`] is a sentinel file created next to the
+//! locked path, so it doesn't depend on flock(2) being supported by the filesystem.
+//!
+//! # Examples
+//! ```
+//! # use filey::lockfile::LockFile;
+//! # use std::time::Duration;
+//! # use std::error::Error;
+//! #
+//! # fn sync() -> Result<(), Box<Error>> {
+//! // Creates /mnt/nfs/music.db.lock
+//! let lock = LockFile::acquire("/mnt/nfs/music.db", Some(Duration::from_secs(600)))?;
+//! // update music.db
+//! drop(lock); // music.db.lock is removed.
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # sync().unwrap();
+//! # }
+//! ```
+
+use crate::{Error::WouldBlock, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::CStr,
+    fs::{hard_link, read_to_string, remove_file, rename, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The process holding a lock file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockHolder {
+    pid: u32,
+    hostname: String,
+    timestamp: u64,
+}
+
+impl LockHolder {
+    fn current() -> Self {
+        Self {
+            pid: process::id(),
+            hostname: hostname(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let mut pid = None;
+        let mut hostname = None;
+        let mut timestamp = None;
+        for line in s.lines() {
+            match line.split_once('=') {
+                Some(("pid", value)) => pid = value.parse().ok(),
+                Some(("hostname", value)) => hostname = Some(value.to_string()),
+                Some(("timestamp", value)) => timestamp = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(Self {
+            pid: pid?,
+            hostname: hostname?,
+            timestamp: timestamp?,
+        })
+    }
+
+    fn serialize(&self) -> String {
+        format!(
+            "pid={}\nhostname={}\ntimestamp={}\n",
+            self.pid, self.hostname, self.timestamp
+        )
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Returns when the lock was acquired, in seconds since the Unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Returns how long the lock has been held.
+    pub fn age(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Duration::from_secs(now.saturating_sub(self.timestamp))
+    }
+}
+
+/// A `.lock` sentinel file held by the current process.
+/// The sentinel is removed when this is dropped.
+#[derive(Debug)]
+pub struct LockFile {
+    path: PathBuf,
+    contents: String,
+}
+
+impl LockFile {
+    /// Creates `<path>.lock` containing the pid, the hostname and the current time.
+    ///
+    /// If stale_after is given, an existing lock file older than it is considered abandoned
+    /// and is stolen.
+    ///
+    /// # Errors
+    /// * [`crate::Error::WouldBlock`] if the lock is held and isn't stale.
+    /// * The user lacks permissions.
+    pub fn acquire<P: AsRef<Path>>(path: P, stale_after: Option<Duration>) -> Result<Self> {
+        let path = sentinel_path(path.as_ref());
+        let holder = LockHolder::current();
+        let contents = holder.serialize();
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    if let Err(e) = file.write_all(contents.as_bytes()).and(file.sync_all()) {
+                        let _ = remove_file(&path);
+                        return Err(Op::Write.error(&path, e));
+                    }
+                    return Ok(Self { path, contents });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale_after = stale_after.ok_or_else(|| WouldBlock {
+                        path: path.to_string_lossy().to_string(),
+                    })?;
+                    if !steal(&path, stale_after)? {
+                        return Err(WouldBlock {
+                            path: path.to_string_lossy().to_string(),
+                        });
+                    }
+                }
+                Err(e) => return Err(Op::Create.error(&path, e)),
+            }
+        }
+    }
+
+    /// Reads the holder of the lock on path.
+    /// Returns None if path isn't locked.
+    pub fn holder<P: AsRef<Path>>(path: P) -> Result<Option<LockHolder>> {
+        let path = sentinel_path(path.as_ref());
+        match read_to_string(&path) {
+            Ok(contents) => Ok(LockHolder::parse(&contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Op::Read.error(&path, e)),
+        }
+    }
+
+    /// Returns the path of the sentinel file.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // Don't remove a lock which has been stolen by someone else.
+        if read_to_string(&self.path).is_ok_and(|contents| contents == self.contents) {
+            let _ = remove_file(&self.path);
+        }
+    }
+}
+
+fn sentinel_path(path: &Path) -> PathBuf {
+    let mut sentinel = path.as_os_str().to_os_string();
+    sentinel.push(".lock");
+    PathBuf::from(sentinel)
+}
+
+/// Removes the lock file at path if it is older than stale_after.
+/// Returns false if the lock is still fresh.
+fn steal(path: &Path, stale_after: Duration) -> Result<bool> {
+    let contents = match read_to_string(path) {
+        Ok(contents) => contents,
+        // Released in the meantime.
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(Op::Read.error(path, e)),
+    };
+    let age = match LockHolder::parse(&contents) {
+        Some(holder) => holder.age(),
+        // Unreadable lock files are judged by their modification time.
+        None => path
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .unwrap_or_default(),
+    };
+    if age < stale_after {
+        return Ok(false);
+    }
+
+    // Move the stale lock aside first, so that only one process steals it.
+    let mut aside = path.as_os_str().to_os_string();
+    aside.push(format!(".stale.{}", process::id()));
+    let aside = PathBuf::from(aside);
+    match rename(path, &aside) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(Op::Rename.error2(path, &aside, e)),
+    }
+
+    // Someone else replaced the stale lock before the rename, so put the fresh one back.
+    if read_to_string(&aside).ok().as_deref() != Some(contents.as_str()) {
+        let _ = hard_link(&aside, path);
+        let _ = remove_file(&aside);
+        return Ok(false);
+    }
+    let _ = remove_file(&aside);
+    Ok(true)
+}
+
+fn hostname() -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr(), buf.len()) } != 0 {
+        return String::new();
+    }
+    buf[buf.len() - 1] = 0;
+    unsafe { CStr::from_ptr(buf.as_ptr()) }
+        .to_string_lossy()
+        .to_string()
+}
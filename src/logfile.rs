@@ -0,0 +1,164 @@
+//! Log files with size-based rollover.
+//!
+//! # Examples
+//! ```
+//! # use filey::logfile::Appender;
+//! # use filey::units::MIB;
+//! # use std::io::Write;
+//! # use std::error::Error;
+//! #
+//! # fn log() -> Result<(), Box<Error>> {
+//! // Keeps app.log, app.log.1, ..., app.log.5
+//! let mut log = Appender::new("app.log", 10 * MIB, 5)?;
+//! writeln!(log, "started")?;
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # log().unwrap();
+//! # }
+//! ```
+
+use crate::{op::OpContext, Op, Result};
+use std::{
+    ffi::OsString,
+    fs::{remove_file, rename, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// A writer which appends to a log file and rotates it when it grows larger than max_size.
+///
+/// Rotation only happens at the start of a line, so a line is never split across two files
+/// even if it is written in several pieces (as `writeln!` does).
+///
+/// Rotated files are named `<path>.1` (the newest) to `<path>.<keep>` (the oldest), with a `.gz`
+/// suffix if compression is enabled.
+#[derive(Debug)]
+pub struct Appender {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+    compress: bool,
+    line_start: bool,
+}
+
+impl Appender {
+    /// Opens path in append mode, creating it if it doesn't exist.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn new<P: AsRef<Path>>(path: P, max_size: u64, keep: usize) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open(&path).op(Op::Open, &path)?;
+        let size = file.metadata().op(Op::Metadata, &path)?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            keep,
+            compress: false,
+            line_start: true,
+        })
+    }
+
+    /// (Needs the `gzip` feature) Compresses rotated files with gzip.
+    #[cfg(feature = "gzip")]
+    pub fn set_compress(&mut self, value: bool) -> &mut Self {
+        self.compress = value;
+        self
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Rotates the log file now regardless of its size.
+    pub fn rotate(&mut self) -> Result<()> {
+        self.rotate_inner().op(Op::Rename, &self.path)
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{}", n));
+        if self.compress {
+            path.push(".gz");
+        }
+        PathBuf::from(path)
+    }
+
+    fn rotate_inner(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            remove_if_exists(&self.path)?;
+        } else {
+            remove_if_exists(&self.rotated_path(self.keep))?;
+            for n in (1..self.keep).rev() {
+                rename_if_exists(&self.rotated_path(n), &self.rotated_path(n + 1))?;
+            }
+
+            if self.compress {
+                #[cfg(feature = "gzip")]
+                gzip(&self.path, &self.rotated_path(1))?;
+                remove_file(&self.path)?;
+            } else {
+                rename(&self.path, self.rotated_path(1))?;
+            }
+        }
+
+        self.file = open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for Appender {
+    /// Rotates before writing if buf would make the file larger than max_size and the previous
+    /// write ended a line.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.line_start && self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate_inner()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match remove_file(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(from: &Path, to: &Path) -> io::Result<()> {
+    use flate2::{write::GzEncoder, Compression};
+
+    let mut input = File::open(from)?;
+    let mut encoder = GzEncoder::new(File::create(to)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()
+}
@@ -0,0 +1,282 @@
+use crate::{op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{read_dir, remove_dir, remove_dir_all, remove_file, rename, symlink_metadata},
+    path::{Path, PathBuf},
+};
+
+/// What to do when a file being moved or copied already exists at the destination.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OnConflict {
+    /// Leaves both files where they are.
+    Skip,
+    /// Replaces the file at the destination.
+    Overwrite,
+    /// Stops with an error.
+    Fail,
+}
+
+/// Moves from to to. If both are directories, the children of from are moved into to one by
+/// one, merging subdirectories recursively, and from is removed once it's empty.
+pub(crate) fn move_merging(from: &Path, to: &Path, on_conflict: OnConflict) -> Result<()> {
+    let to_metadata = match symlink_metadata(to) {
+        Ok(metadata) => metadata,
+        Err(_) => return rename_path(from, to),
+    };
+    let from_metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+
+    if from_metadata.is_dir() && to_metadata.is_dir() {
+        for entry in read_dir(from).op(Op::ReadDir, from)? {
+            let entry = entry.op(Op::ReadDir, from)?;
+            move_merging(&entry.path(), &to.join(entry.file_name()), on_conflict)?;
+        }
+        // Skipped children are left behind.
+        if is_empty_dir(from)? {
+            remove_dir(from).op(Op::RemoveDir, from)?;
+        }
+        return Ok(());
+    }
+
+    match on_conflict {
+        OnConflict::Skip => Ok(()),
+        OnConflict::Overwrite => {
+            if to_metadata.is_dir() {
+                remove_dir_all(to)
+            } else {
+                remove_file(to)
+            }
+            .op(Op::Remove, to)?;
+            rename_path(from, to)
+        }
+        OnConflict::Fail => Err(crate::Error::AlreadyExists {
+            path: to.to_string_lossy().to_string(),
+        }),
+    }
+}
+
+fn rename_path(from: &Path, to: &Path) -> Result<()> {
+    rename(from, to).op2(Op::Rename, from, to)
+}
+
+/// The callback of [`MergeStrategy::Ask`].
+pub type AskCallback = Box<dyn FnMut(&Path, &Path) -> Resolution>;
+
+/// How [`merge`] resolves a file which exists in both trees.
+pub enum MergeStrategy {
+    /// Keeps the file modified last.
+    KeepNewer,
+    /// Keeps the larger file.
+    KeepLarger,
+    /// Keeps both, moving the source under a free name like `IMG_0001 (1).jpg`.
+    Rename,
+    /// Asks the callback, which receives the source and the destination path.
+    Ask(AskCallback),
+}
+
+impl fmt::Debug for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::KeepNewer => write!(f, "KeepNewer"),
+            Self::KeepLarger => write!(f, "KeepLarger"),
+            Self::Rename => write!(f, "Rename"),
+            Self::Ask(_) => write!(f, "Ask(..)"),
+        }
+    }
+}
+
+/// The resolution of a conflict in [`merge`].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Resolution {
+    /// The source replaced the destination.
+    KeepSource,
+    /// The destination was kept and the source was left where it was.
+    KeepDestination,
+    /// The source was moved under a new name.
+    KeepBoth,
+}
+
+/// What [`merge`] did with one file or directory of the source tree.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MergeDecision {
+    source: PathBuf,
+    destination: PathBuf,
+    resolution: Option<Resolution>,
+}
+
+impl MergeDecision {
+    pub fn source(&self) -> &PathBuf {
+        &self.source
+    }
+
+    /// Returns the path the source was moved to, or the conflicting path if it was kept.
+    pub fn destination(&self) -> &PathBuf {
+        &self.destination
+    }
+
+    /// Returns how a conflict was resolved, or None if there was no conflict.
+    pub fn resolution(&self) -> Option<Resolution> {
+        self.resolution
+    }
+}
+
+/// The decisions made by [`merge`], in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MergeReport {
+    decisions: Vec<MergeDecision>,
+}
+
+impl MergeReport {
+    pub fn decisions(&self) -> &Vec<MergeDecision> {
+        &self.decisions
+    }
+
+    /// Returns the decisions which resolved a conflict.
+    pub fn conflicts(&self) -> impl Iterator<Item = &MergeDecision> {
+        self.decisions.iter().filter(|d| d.resolution.is_some())
+    }
+}
+
+/// Merges the tree src into the tree dst by moving files, resolving files which exist in both
+/// with strategy, and reports every decision.
+/// Directories of src are removed once they're empty; files which lost a conflict are left in
+/// src.
+/// A file conflicting with a directory, or the other way around, is always kept as both,
+/// regardless of strategy, so that a single file never replaces a whole tree.
+///
+/// # Errors
+/// * The user lacks permissions.
+/// * src and dst are on separate filesystems.
+///
+/// # Examples
+/// ```
+/// # use filey::MergeStrategy;
+/// # use std::error::Error;
+/// #
+/// # fn combine() -> Result<(), Box<Error>> {
+/// let report = filey::merge("old-laptop/Music", "Music", MergeStrategy::KeepLarger)?;
+/// for decision in report.conflicts() {
+///     println!(
+///         "{}: {:?}",
+///         decision.source().display(),
+///         decision.resolution().unwrap()
+///     );
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # combine().unwrap();
+/// # }
+/// ```
+pub fn merge<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    mut strategy: MergeStrategy,
+) -> Result<MergeReport> {
+    let mut report = MergeReport::default();
+    merge_tree(src.as_ref(), dst.as_ref(), &mut strategy, &mut report)?;
+    Ok(report)
+}
+
+fn merge_tree(
+    from: &Path,
+    to: &Path,
+    strategy: &mut MergeStrategy,
+    report: &mut MergeReport,
+) -> Result<()> {
+    let mut decide = |destination: &Path, resolution| {
+        report.decisions.push(MergeDecision {
+            source: from.to_path_buf(),
+            destination: destination.to_path_buf(),
+            resolution,
+        })
+    };
+
+    let to_metadata = match symlink_metadata(to) {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            rename_path(from, to)?;
+            decide(to, None);
+            return Ok(());
+        }
+    };
+    let from_metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+
+    if from_metadata.is_dir() && to_metadata.is_dir() {
+        let mut entries = read_dir(from)
+            .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+            .op(Op::ReadDir, from)?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            merge_tree(&entry.path(), &to.join(entry.file_name()), strategy, report)?;
+        }
+        if is_empty_dir(from)? {
+            remove_dir(from).op(Op::RemoveDir, from)?;
+        }
+        return Ok(());
+    }
+
+    let resolution = match strategy {
+        // Times and sizes of files and directories aren't comparable, and replacing either
+        // would lose a whole tree or its contents.
+        _ if from_metadata.is_dir() != to_metadata.is_dir() => Resolution::KeepBoth,
+        MergeStrategy::KeepNewer => {
+            let from_time = from_metadata.modified().ok();
+            let to_time = to_metadata.modified().ok();
+            if from_time > to_time {
+                Resolution::KeepSource
+            } else {
+                Resolution::KeepDestination
+            }
+        }
+        MergeStrategy::KeepLarger => {
+            if from_metadata.len() > to_metadata.len() {
+                Resolution::KeepSource
+            } else {
+                Resolution::KeepDestination
+            }
+        }
+        MergeStrategy::Rename => Resolution::KeepBoth,
+        MergeStrategy::Ask(ask) => ask(from, to),
+    };
+
+    match resolution {
+        Resolution::KeepSource => {
+            remove_file(to).op(Op::Remove, to)?;
+            rename_path(from, to)?;
+            decide(to, Some(resolution));
+        }
+        Resolution::KeepDestination => decide(to, Some(resolution)),
+        Resolution::KeepBoth => {
+            let renamed = free_name(to);
+            rename_path(from, &renamed)?;
+            decide(&renamed, Some(resolution));
+        }
+    }
+    Ok(())
+}
+
+fn is_empty_dir(path: &Path) -> Result<bool> {
+    Ok(read_dir(path).op(Op::ReadDir, path)?.next().is_none())
+}
+
+/// Returns the first of `name (1).ext`, `name (2).ext`, ... which doesn't exist.
+fn free_name(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| symlink_metadata(candidate).is_err())
+        .expect("ran out of names")
+}
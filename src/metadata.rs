@@ -0,0 +1,126 @@
+use crate::FileTypes;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+use std::{fs, time::SystemTime};
+
+/// The metadata of a file returned by [`Filey::metadata`](crate::Filey::metadata).
+///
+/// Unlike [`std::fs::Metadata`] it can be compared, stored and serialized, e.g. to record the
+/// state of a file. The timestamps are None where the platform or the filesystem doesn't
+/// record them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    file_type: FileTypes,
+    size: u64,
+    readonly: bool,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    #[cfg(target_family = "unix")]
+    dev: u64,
+    #[cfg(target_family = "unix")]
+    ino: u64,
+    #[cfg(target_family = "unix")]
+    nlink: u64,
+    #[cfg(target_family = "unix")]
+    uid: u32,
+    #[cfg(target_family = "unix")]
+    gid: u32,
+    #[cfg(target_family = "unix")]
+    mode: u32,
+}
+
+impl From<&fs::Metadata> for Metadata {
+    fn from(metadata: &fs::Metadata) -> Self {
+        Self {
+            file_type: FileTypes::from_metadata(metadata),
+            size: metadata.len(),
+            readonly: metadata.permissions().readonly(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            #[cfg(target_family = "unix")]
+            dev: metadata.dev(),
+            #[cfg(target_family = "unix")]
+            ino: metadata.ino(),
+            #[cfg(target_family = "unix")]
+            nlink: metadata.nlink(),
+            #[cfg(target_family = "unix")]
+            uid: metadata.uid(),
+            #[cfg(target_family = "unix")]
+            gid: metadata.gid(),
+            #[cfg(target_family = "unix")]
+            mode: metadata.mode(),
+        }
+    }
+}
+
+impl Metadata {
+    pub fn file_type(&self) -> FileTypes {
+        self.file_type
+    }
+
+    /// Returns the size in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns true if the file can't be written to according to its permissions.
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Returns the time the file was created.
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    /// Returns the time of last modification.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Returns the time of last access.
+    pub fn accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    /// (Unix only) Returns the ID of the device containing the file.
+    #[cfg(target_family = "unix")]
+    pub fn dev(&self) -> u64 {
+        self.dev
+    }
+
+    /// (Unix only) Returns the inode number.
+    #[cfg(target_family = "unix")]
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// (Unix only) Returns the number of hard links.
+    #[cfg(target_family = "unix")]
+    pub fn nlink(&self) -> u64 {
+        self.nlink
+    }
+
+    /// (Unix only) Returns the user ID of the owner.
+    #[cfg(target_family = "unix")]
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// (Unix only) Returns the group ID of the owner.
+    #[cfg(target_family = "unix")]
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// (Unix only) Returns the file type and the permission bits.
+    #[cfg(target_family = "unix")]
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{op::OpContext, Op, Result};
+use std::{fs::metadata, os::unix::fs::MetadataExt, path::Path};
+
+/// Returns true if path is the root of a mounted filesystem.
+///
+/// A mount point lives on a different device than its parent directory, and "/" is its own
+/// parent. Bind mounts of a directory on the same device are found via /proc/self/mountinfo on
+/// Linux.
+pub(crate) fn is_mount_point<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let path = path.as_ref();
+    let own = metadata(path).op(Op::Metadata, path)?;
+    if !own.is_dir() {
+        return Ok(false);
+    }
+    let parent = path.join("..");
+    let parent = metadata(&parent).op(Op::Metadata, &parent)?;
+    if own.dev() != parent.dev() || own.ino() == parent.ino() {
+        return Ok(true);
+    }
+    in_mountinfo(path)
+}
+
+#[cfg(target_os = "linux")]
+fn in_mountinfo(path: &Path) -> Result<bool> {
+    let canonicalized = path.canonicalize().op(Op::Canonicalize, path)?;
+    let mountinfo = match std::fs::read_to_string("/proc/self/mountinfo") {
+        Ok(mountinfo) => mountinfo,
+        // /proc isn't mounted, e.g. in a minimal container.
+        Err(_) => return Ok(false),
+    };
+    Ok(mountinfo
+        .lines()
+        .filter_map(|line| line.split(' ').nth(4))
+        .any(|mount_point| Path::new(&unescape(mount_point)) == canonicalized))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn in_mountinfo(_path: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+/// Decodes the octal escapes (`\040` for a space) of a field of /proc/self/mountinfo.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(code);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).to_string()
+}
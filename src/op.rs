@@ -0,0 +1,111 @@
+use crate::{Error, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{fmt, io, path::Path};
+
+/// A filesystem operation, recorded in [`Error::Io`] so that error messages say what was being
+/// done and to which paths.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Op {
+    Open,
+    Create,
+    CreateDir,
+    Read,
+    Write,
+    Remove,
+    RemoveDir,
+    Rename,
+    Copy,
+    Symlink,
+    HardLink,
+    Metadata,
+    ReadDir,
+    ReadLink,
+    Canonicalize,
+    SetPermissions,
+    SetOwner,
+    SetAttributes,
+    ReadXattr,
+    SetXattr,
+    RemoveXattr,
+    Lock,
+    Connect,
+    FreeSpace,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Open => write!(f, "opening"),
+            Self::Create => write!(f, "creating"),
+            Self::CreateDir => write!(f, "creating directory"),
+            Self::Read => write!(f, "reading"),
+            Self::Write => write!(f, "writing"),
+            Self::Remove => write!(f, "removing"),
+            Self::RemoveDir => write!(f, "removing directory"),
+            Self::Rename => write!(f, "renaming"),
+            Self::Copy => write!(f, "copying"),
+            Self::Symlink => write!(f, "creating symlink"),
+            Self::HardLink => write!(f, "creating hard link"),
+            Self::Metadata => write!(f, "reading metadata of"),
+            Self::ReadDir => write!(f, "listing"),
+            Self::ReadLink => write!(f, "reading symlink"),
+            Self::Canonicalize => write!(f, "canonicalizing"),
+            Self::SetPermissions => write!(f, "setting permissions of"),
+            Self::SetOwner => write!(f, "changing owner of"),
+            Self::SetAttributes => write!(f, "setting attributes of"),
+            Self::ReadXattr => write!(f, "reading extended attribute of"),
+            Self::SetXattr => write!(f, "setting extended attribute of"),
+            Self::RemoveXattr => write!(f, "removing extended attribute of"),
+            Self::Lock => write!(f, "locking"),
+            Self::Connect => write!(f, "connecting to"),
+            Self::FreeSpace => write!(f, "querying free space of"),
+        }
+    }
+}
+
+impl Op {
+    /// Wraps source into [`Error::Io`] for an operation on path.
+    pub(crate) fn error<P: AsRef<Path>>(self, path: P, source: io::Error) -> Error {
+        Error::Io {
+            op: self,
+            path: path.as_ref().to_string_lossy().to_string(),
+            target: None,
+            source,
+        }
+    }
+
+    /// Wraps source into [`Error::Io`] for an operation from path to target.
+    /// For links, path is the link and target is what it points to.
+    pub(crate) fn error2<P: AsRef<Path>, Q: AsRef<Path>>(
+        self,
+        path: P,
+        target: Q,
+        source: io::Error,
+    ) -> Error {
+        Error::Io {
+            op: self,
+            path: path.as_ref().to_string_lossy().to_string(),
+            target: Some(target.as_ref().to_string_lossy().to_string()),
+            source,
+        }
+    }
+}
+
+/// Attaches an [`Op`] to the error of an I/O result.
+pub(crate) trait OpContext<T> {
+    fn op<P: AsRef<Path>>(self, op: Op, path: P) -> Result<T>;
+
+    fn op2<P: AsRef<Path>, Q: AsRef<Path>>(self, op: Op, path: P, target: Q) -> Result<T>;
+}
+
+impl<T> OpContext<T> for io::Result<T> {
+    fn op<P: AsRef<Path>>(self, op: Op, path: P) -> Result<T> {
+        self.map_err(|e| op.error(path, e))
+    }
+
+    fn op2<P: AsRef<Path>, Q: AsRef<Path>>(self, op: Op, path: P, target: Q) -> Result<T> {
+        self.map_err(|e| op.error2(path, target, e))
+    }
+}
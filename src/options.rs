@@ -0,0 +1,236 @@
+use crate::{op::OpContext, Op, Result};
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+use std::{
+    fs::{create_dir_all, File, OpenOptions},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+/// Options for [`crate::Filey::move_to_with`] and [`crate::Filey::move_to_with_report`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MoveOptions {
+    /// Creates the missing parent directories of the destination first.
+    pub create_parents: bool,
+    /// Fails if the destination is on another filesystem instead of cloning or copying there.
+    pub rename_only: bool,
+}
+
+/// Options for [`crate::Filey::list_with`] and [`crate::Filey::list_entries_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ListOptions {
+    /// Sorts the entries by name, so that the order is the same on every run and filesystem.
+    /// Names are compared byte by byte, so `B` comes before `a`.
+    pub sorted: bool,
+}
+
+/// Options for [`crate::Filey::open_with`], mirroring [`std::fs::OpenOptions`].
+/// The default opens nothing; set at least one of read, write and append.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OpenFileOptions {
+    pub read: bool,
+    pub write: bool,
+    /// Makes every write go to the end of the file. Implies write.
+    pub append: bool,
+    /// Truncates an existing file to 0 bytes. Needs write.
+    pub truncate: bool,
+    /// Creates the file if it doesn't exist. Needs write or append.
+    pub create: bool,
+    /// Creates the file, failing if it already exists, even as a symbolic link. Needs write
+    /// or append.
+    pub create_new: bool,
+}
+
+impl OpenFileOptions {
+    /// Returns true if opening with the options may create or change the file, including
+    /// through the handle returned.
+    pub(crate) fn modifies(&self) -> bool {
+        self.write || self.append || self.truncate || self.create || self.create_new
+    }
+
+    pub(crate) fn to_std(&self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        options
+            .read(self.read)
+            .write(self.write)
+            .append(self.append)
+            .truncate(self.truncate)
+            .create(self.create)
+            .create_new(self.create_new);
+        options
+    }
+}
+
+/// Options for [`crate::Filey::copy_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CopyOptions {
+    /// Creates the missing parent directories of the destination first.
+    pub create_parents: bool,
+    /// Gives up with [`crate::Error::Timeout`] if the copy takes longer than this, e.g. on a
+    /// hung network filesystem. The destination is only replaced by a copy which completes in
+    /// time.
+    pub timeout: Option<Duration>,
+    /// Fails with [`crate::Error::InsufficientSpace`] before copying if the destination
+    /// filesystem has less free space than the size of the file.
+    pub check_space: bool,
+    /// Copies through a buffer of this size. By default the platform's copy routine is used,
+    /// which may copy inside the kernel.
+    pub buffer_size: BufferSize,
+}
+
+/// The size of the buffer an operation reads and writes through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BufferSize {
+    /// The default of the operation.
+    #[default]
+    Default,
+    /// Picked from the block size the filesystem prefers for the file (`st_blksize`): a
+    /// multiple of it, at least 128 KiB. Network filesystems often prefer 1 MiB or more.
+    Auto,
+    /// This many bytes, at least 1.
+    Bytes(usize),
+}
+
+impl BufferSize {
+    /// The smallest buffer [`BufferSize::Auto`] picks.
+    const AUTO_MIN: usize = 128 * 1024;
+
+    /// Returns the size of the buffer for reading or writing file.
+    pub(crate) fn resolve(self, file: &File, default: usize) -> usize {
+        match self {
+            Self::Default => default,
+            Self::Auto => Self::auto(file),
+            Self::Bytes(size) => size.max(1),
+        }
+    }
+
+    #[cfg(target_family = "unix")]
+    fn auto(file: &File) -> usize {
+        match file.metadata().map(|metadata| metadata.blksize() as usize) {
+            Ok(block_size) if block_size > 0 => Self::AUTO_MIN.div_ceil(block_size) * block_size,
+            _ => Self::AUTO_MIN,
+        }
+    }
+
+    #[cfg(not(target_family = "unix"))]
+    fn auto(_file: &File) -> usize {
+        Self::AUTO_MIN
+    }
+}
+
+/// Options for [`crate::Filey::create_with_mode`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CreateOptions {
+    /// Sets the mode exactly as given after creating the file. Otherwise the process's umask
+    /// clears bits from it, e.g. 0o666 becomes 0o644 with the usual umask of 0o022.
+    pub exact_mode: bool,
+}
+
+/// Options for [`crate::Filey::symlink_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SymlinkOptions {
+    /// Replaces a file or a symbolic link which already exists at the link path.
+    /// The link is created under a temporary name and renamed into place, so the path never
+    /// goes missing.
+    pub force: bool,
+    /// Makes the link point to the target relative to the directory containing the link, so
+    /// that moving both together keeps the link working.
+    pub relative: bool,
+}
+
+/// Options for [`crate::Filey::hard_link_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HardLinkOptions {
+    /// Replaces a file which already exists at the link path.
+    /// The link is created under a temporary name and renamed into place, so the path never
+    /// goes missing.
+    pub force: bool,
+    /// Copies the file instead if the link path is on another filesystem, where a hard link
+    /// can't be created. With force, the copy replaces an existing file atomically too.
+    pub copy_fallback: bool,
+}
+
+/// Options for [`crate::Filey::remove_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RemoveOptions {
+    /// Keeps retrying with backoff for up to this long while the file is in use by another
+    /// process, which on Windows makes removal fail with a sharing violation.
+    pub retry_for: Option<Duration>,
+}
+
+/// Options for [`crate::Filey::set_owner_recursive_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OwnerOptions {
+    /// Leaves symbolic links alone. Otherwise the owner of the link itself is changed; links
+    /// are never followed.
+    pub skip_symlinks: bool,
+    /// Leaves entries on other filesystems than the root's alone, like `find -xdev`, so
+    /// mounted filesystems and everything on them are skipped.
+    pub one_file_system: bool,
+}
+
+/// Options for operations which work on many files at once, such as
+/// [`crate::Filey::copy_dir_filtered_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BatchOptions {
+    /// The maximum number of file operations in flight at once. 0 picks a number based on
+    /// the available CPUs; 1 works sequentially, which is usually fastest on spinning disks
+    /// and SMB shares.
+    pub max_in_flight: usize,
+    /// Fails with [`crate::Error::InsufficientSpace`] before changing anything if the
+    /// destination filesystem has less free space than the files need.
+    pub check_space: bool,
+    /// How copies of directory trees treat symbolic links.
+    pub symlinks: SymlinkBehavior,
+    /// The buffer each file is copied through.
+    pub buffer_size: BufferSize,
+}
+
+impl BatchOptions {
+    /// Returns options which work on one file at a time.
+    pub fn sequential() -> Self {
+        Self {
+            max_in_flight: 1,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn workers(&self, items: usize) -> usize {
+        // Threads can't be spawned on WASI without the threads proposal.
+        if cfg!(all(target_os = "wasi", not(target_feature = "atomics"))) {
+            return 1;
+        }
+        let limit = match self.max_in_flight {
+            0 => thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            n => n,
+        };
+        limit.min(items).max(1)
+    }
+}
+
+/// How an operation on a directory tree treats the symbolic links in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SymlinkBehavior {
+    /// Recreates the links themselves, e.g. when backing up a dotfiles tree.
+    #[default]
+    Preserve,
+    /// Works on what the links point to, so the copy contains no links, e.g. when exporting a
+    /// build artifact. A broken link or a link leading back to a directory containing it is
+    /// an error.
+    Follow,
+    /// Leaves the links out.
+    Skip,
+}
+
+/// Creates the parent directories of path if they don't exist.
+pub(crate) fn create_parents(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            create_dir_all(parent).op(Op::CreateDir, parent)
+        }
+        _ => Ok(()),
+    }
+}
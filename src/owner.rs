@@ -0,0 +1,151 @@
+use crate::{op::OpContext, Op, OwnerOptions, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::CStr,
+    fmt,
+    fs::{metadata, read_dir, symlink_metadata, Metadata},
+    os::{
+        raw::c_char,
+        unix::fs::{lchown, MetadataExt},
+    },
+    path::Path,
+};
+
+/// The owner and the group of a file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Owner {
+    uid: u32,
+    gid: u32,
+    user: Option<String>,
+    group: Option<String>,
+}
+
+impl fmt::Display for Owner {
+    /// Formats like `user:group`.
+    /// Falls back to the numeric id if the name can't be resolved.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.user {
+            Some(user) => write!(f, "{}", user)?,
+            None => write!(f, "{}", self.uid)?,
+        }
+        match &self.group {
+            Some(group) => write!(f, ":{}", group),
+            None => write!(f, ":{}", self.gid),
+        }
+    }
+}
+
+impl Owner {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata = metadata(path).op(Op::Metadata, path)?;
+        Ok(Self::from_ids(metadata.uid(), metadata.gid()))
+    }
+
+    /// Resolves the names of uid and gid via the passwd and group databases.
+    pub fn from_ids(uid: u32, gid: u32) -> Self {
+        Self {
+            uid,
+            gid,
+            user: user_name(uid),
+            group: group_name(gid),
+        }
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns the user name.
+    /// Returns None if uid has no entry in the passwd database.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the group name.
+    /// Returns None if gid has no entry in the group database.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+/// Changes the owner and the group of path and everything under it like `chown -R`.
+/// None keeps the current id.
+pub(crate) fn set_recursive(
+    path: &Path,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    options: &OwnerOptions,
+) -> Result<()> {
+    let metadata = symlink_metadata(path).op(Op::Metadata, path)?;
+    set_tree(path, &metadata, metadata.dev(), (uid, gid), options)
+}
+
+fn set_tree(
+    path: &Path,
+    metadata: &Metadata,
+    device: u64,
+    (uid, gid): (Option<u32>, Option<u32>),
+    options: &OwnerOptions,
+) -> Result<()> {
+    if options.one_file_system && metadata.dev() != device {
+        return Ok(());
+    }
+    if metadata.is_symlink() && options.skip_symlinks {
+        return Ok(());
+    }
+    if metadata.is_dir() {
+        for entry in read_dir(path).op(Op::ReadDir, path)? {
+            let child = entry.op(Op::ReadDir, path)?.path();
+            let metadata = symlink_metadata(&child).op(Op::Metadata, &child)?;
+            set_tree(&child, &metadata, device, (uid, gid), options)?;
+        }
+    }
+    lchown(path, uid, gid).op(Op::SetOwner, path)
+}
+
+/// Looks up the name of uid in the passwd database.
+pub(crate) fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0 as c_char; 1024];
+    loop {
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let ret =
+            unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if ret == libc::ERANGE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if ret != 0 || result.is_null() {
+            return None;
+        }
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+        return Some(name.to_string_lossy().to_string());
+    }
+}
+
+/// Looks up the name of gid in the group database.
+pub(crate) fn group_name(gid: u32) -> Option<String> {
+    let mut buf = vec![0 as c_char; 1024];
+    loop {
+        let mut group: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let ret =
+            unsafe { libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if ret == libc::ERANGE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if ret != 0 || result.is_null() {
+            return None;
+        }
+        let name = unsafe { CStr::from_ptr(group.gr_name) };
+        return Some(name.to_string_lossy().to_string());
+    }
+}
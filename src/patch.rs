@@ -0,0 +1,265 @@
+use crate::{
+    atomic,
+    op::OpContext,
+    Error::{FileyError, PatchFailed},
+    Op, Result,
+};
+use std::{fs::read_to_string, io::Write, path::Path};
+
+/// Options for [`crate::Filey::apply_patch_with`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PatchOptions {
+    /// The maximum number of leading and trailing context lines which may be ignored when a hunk
+    /// doesn't match exactly, like `patch --fuzz`.
+    pub fuzz: usize,
+    /// Checks that every hunk applies without modifying the file.
+    pub dry_run: bool,
+}
+
+impl Default for PatchOptions {
+    fn default() -> Self {
+        Self {
+            fuzz: 2,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Hunk {
+    old_start: usize,
+    /// The number of old lines, 0 for a hunk which only inserts after line old_start.
+    old_count: usize,
+    /// (is the line in the old file, is the line in the new file, text)
+    lines: Vec<(bool, bool, String)>,
+    old_missing_newline: bool,
+    new_missing_newline: bool,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter(|(old, _, _)| *old)
+            .map(|(_, _, s)| s.as_str())
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter(|(_, new, _)| *new)
+            .map(|(_, _, s)| s.as_str())
+            .collect()
+    }
+
+    /// Returns the number of context lines at the start and the end.
+    fn context(&self) -> (usize, usize) {
+        let is_context = |(old, new, _): &&(bool, bool, String)| *old && *new;
+        let leading = self.lines.iter().take_while(is_context).count();
+        let trailing = self.lines.iter().rev().take_while(is_context).count();
+        (leading, trailing)
+    }
+}
+
+fn parse(diff: &str) -> Result<Vec<Hunk>> {
+    let malformed = |line: &str| FileyError(anyhow::anyhow!("Malformed hunk header '{}'", line));
+    let mut hunks: Vec<Hunk> = Vec::new();
+    // The old and new lines the current hunk has yet to read. Lines past them, such as the
+    // headers of the next file, aren't part of the hunk even if they start with '-' or '+'.
+    let mut remaining = (0, 0);
+    let mut in_hunk = false;
+    let mut files = 0;
+    let mut previous = "";
+    for line in diff.lines() {
+        let is_old_header = previous.starts_with("--- ");
+        previous = line;
+        // Between hunks, '--- ' and '+++ ' lines start the changes of another file, which
+        // mustn't be applied to this one.
+        if remaining == (0, 0) && is_old_header && line.starts_with("+++ ") {
+            files += 1;
+            if files > 1 {
+                return Err(FileyError(anyhow::anyhow!(
+                    "The diff changes more than one file, including '{}'",
+                    &line[4..]
+                )));
+            }
+            in_hunk = false;
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("@@ -") {
+            if remaining != (0, 0) {
+                return Err(FileyError(anyhow::anyhow!(
+                    "Hunk {} is shorter than its header says",
+                    hunks.len()
+                )));
+            }
+            let (old, new) = header.split_once(" +").ok_or_else(|| malformed(line))?;
+            let new = new.split_once(" @@").map_or(new, |(new, _)| new);
+            let (old_start, old_count) = range(old).ok_or_else(|| malformed(line))?;
+            let (_, new_count) = range(new).ok_or_else(|| malformed(line))?;
+            hunks.push(Hunk {
+                old_start,
+                old_count,
+                ..Hunk::default()
+            });
+            remaining = (old_count, new_count);
+            in_hunk = true;
+            continue;
+        }
+
+        let Some(hunk) = hunks.last_mut().filter(|_| in_hunk) else {
+            // Headers such as 'diff', '---' and '+++' between the hunks.
+            continue;
+        };
+        if line.starts_with("\\") {
+            match hunk.lines.last() {
+                Some((true, false, _)) => hunk.old_missing_newline = true,
+                Some((false, true, _)) => hunk.new_missing_newline = true,
+                _ => {
+                    hunk.old_missing_newline = true;
+                    hunk.new_missing_newline = true;
+                }
+            }
+            continue;
+        }
+        if remaining == (0, 0) {
+            in_hunk = false;
+            continue;
+        }
+        let (old, new, s) = if let Some(s) = line.strip_prefix('-') {
+            (true, false, s)
+        } else if let Some(s) = line.strip_prefix('+') {
+            (false, true, s)
+        } else if let Some(s) = line.strip_prefix(' ') {
+            (true, true, s)
+        } else if line.is_empty() {
+            // Some tools strip the space of empty context lines.
+            (true, true, "")
+        } else {
+            return Err(FileyError(anyhow::anyhow!(
+                "Unexpected line '{}' in hunk {}",
+                line,
+                hunks.len()
+            )));
+        };
+        if (old && remaining.0 == 0) || (new && remaining.1 == 0) {
+            return Err(FileyError(anyhow::anyhow!(
+                "Hunk {} is longer than its header says",
+                hunks.len()
+            )));
+        }
+        remaining.0 -= usize::from(old);
+        remaining.1 -= usize::from(new);
+        hunk.lines.push((old, new, s.to_string()));
+    }
+    if remaining != (0, 0) {
+        return Err(FileyError(anyhow::anyhow!(
+            "Hunk {} is shorter than its header says",
+            hunks.len()
+        )));
+    }
+    Ok(hunks)
+}
+
+/// Parses the `start,count` of a hunk header, where the count defaults to 1.
+fn range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Finds old in lines, searching outward from expected.
+fn find(lines: &[String], old: &[&str], expected: usize) -> Option<usize> {
+    if old.len() > lines.len() {
+        return None;
+    }
+    let last = lines.len() - old.len();
+    let matches = |at: usize| {
+        lines[at..at + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(a, b)| a == b)
+    };
+    let expected = expected.min(last);
+    (0..=last)
+        .flat_map(|distance| {
+            let before = expected.checked_sub(distance);
+            let after = Some(expected + distance).filter(|&at| at <= last && distance > 0);
+            before.into_iter().chain(after)
+        })
+        .find(|&at| matches(at))
+}
+
+/// Applies a unified diff to contents and returns the patched text.
+fn apply(path: &Path, contents: &str, diff: &str, fuzz: usize) -> Result<(String, usize)> {
+    let hunks = parse(diff)?;
+    let mut ends_with_newline = contents.is_empty() || contents.ends_with('\n');
+    // lines() drops the '\r' of CRLF line endings, which are put back when joining.
+    let crlf = contents.contains("\r\n");
+    let mut lines = contents.lines().map(|s| s.to_string()).collect::<Vec<_>>();
+    let mut delta = 0_isize;
+
+    for (i, hunk) in hunks.iter().enumerate() {
+        let old = hunk.old_lines();
+        let new = hunk.new_lines();
+        // A hunk without old lines starts after old_start rather than at it.
+        let start = if hunk.old_count == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (start as isize + delta).max(0) as usize;
+        let (leading, trailing) = hunk.context();
+
+        let found = (0..=fuzz).find_map(|f| {
+            let skip_start = f.min(leading);
+            let skip_end = f.min(trailing);
+            if skip_start + skip_end > old.len() {
+                return None;
+            }
+            let old = &old[skip_start..old.len() - skip_end];
+            let at = find(&lines, old, expected + skip_start)?;
+            Some((at, old.len(), skip_start, skip_end))
+        });
+        let Some((at, len, skip_start, skip_end)) = found else {
+            return Err(PatchFailed {
+                path: path.to_string_lossy().to_string(),
+                hunk: i + 1,
+            });
+        };
+
+        let new = &new[skip_start.min(new.len())..new.len() - skip_end.min(new.len())];
+        lines.splice(at..at + len, new.iter().map(|s| s.to_string()));
+        delta += new.len() as isize - len as isize;
+
+        if hunk.new_missing_newline {
+            ends_with_newline = false;
+        } else if hunk.old_missing_newline {
+            ends_with_newline = true;
+        }
+    }
+
+    let newline = if crlf { "\r\n" } else { "\n" };
+    let mut patched = lines.join(newline);
+    if ends_with_newline && !lines.is_empty() {
+        patched.push_str(newline);
+    }
+    Ok((patched, hunks.len()))
+}
+
+/// Applies diff to the file at path and returns the number of hunks.
+pub(crate) fn apply_patch<P: AsRef<Path>>(
+    path: P,
+    diff: &str,
+    options: &PatchOptions,
+) -> Result<usize> {
+    let path = path.as_ref();
+    let contents = read_to_string(path).op(Op::Read, path)?;
+    let (patched, hunks) = apply(path, &contents, diff, options.fuzz)?;
+    if !options.dry_run {
+        atomic::write_with(path, |file| file.write_all(patched.as_bytes()))?;
+    }
+    Ok(hunks)
+}
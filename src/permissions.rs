@@ -1,8 +1,10 @@
-use crate::{Error::FileyError, Result};
+use crate::{op::OpContext, Error::FileyError, Op, Result};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{fs::metadata, os::unix::fs::PermissionsExt, path::Path};
+use std::{fmt, fs::metadata, os::unix::fs::PermissionsExt, path::Path};
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Permission {
     execute: bool,
     write: bool,
@@ -66,7 +68,8 @@ impl Permission {
     }
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Permissions {
     user: Permission,
     group: Permission,
@@ -83,7 +86,8 @@ impl Permissions {
     }
 
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let metadata = metadata(path).map_err(|e| e.into()).map_err(FileyError)?;
+        let path = path.as_ref();
+        let metadata = metadata(path).op(Op::Metadata, path)?;
         let permissions = format!("{:o}", metadata.permissions().mode());
         let permissions = permissions.chars().skip(2).collect::<Vec<char>>();
         let length = permissions.len();
@@ -106,6 +110,97 @@ impl Permissions {
     pub fn others(&self) -> &Permission {
         &self.others
     }
+
+    /// Returns the bits which differ in other, in the order of `ls -l`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::Permissions;
+    /// # use std::error::Error;
+    /// #
+    /// # fn compare() -> Result<(), Box<Error>> {
+    /// let before = Permissions::from_path("deploy.sh")?;
+    /// // chmod go+w deploy.sh
+    /// let after = Permissions::from_path("deploy.sh")?;
+    /// for change in before.diff(&after) {
+    ///     println!("{}", change); // g+w, o+w
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # fn main() {
+    /// # compare().unwrap();
+    /// # }
+    /// ```
+    pub fn diff(&self, other: &Self) -> Vec<PermissionChange> {
+        let classes = [
+            (PermissionClass::User, &self.user, &other.user),
+            (PermissionClass::Group, &self.group, &other.group),
+            (PermissionClass::Others, &self.others, &other.others),
+        ];
+        let mut changes = Vec::new();
+        for (class, from, to) in classes {
+            let bits = [
+                (PermissionBit::Read, from.read, to.read),
+                (PermissionBit::Write, from.write, to.write),
+                (PermissionBit::Execute, from.execute, to.execute),
+            ];
+            for (bit, from, to) in bits {
+                if from != to {
+                    changes.push(PermissionChange {
+                        class,
+                        bit,
+                        added: to,
+                    });
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Whose permission a [`PermissionChange`] is about.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PermissionClass {
+    User,
+    Group,
+    Others,
+}
+
+/// The permission a [`PermissionChange`] is about.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PermissionBit {
+    Read,
+    Write,
+    Execute,
+}
+
+/// A bit which differs between two [`Permissions`], returned by [`Permissions::diff`].
+/// It is displayed like a chmod argument, e.g. `o+w`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PermissionChange {
+    pub class: PermissionClass,
+    pub bit: PermissionBit,
+    /// True if the bit was granted, false if it was revoked.
+    pub added: bool,
+}
+
+impl fmt::Display for PermissionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = match self.class {
+            PermissionClass::User => 'u',
+            PermissionClass::Group => 'g',
+            PermissionClass::Others => 'o',
+        };
+        let bit = match self.bit {
+            PermissionBit::Read => 'r',
+            PermissionBit::Write => 'w',
+            PermissionBit::Execute => 'x',
+        };
+        write!(f, "{}{}{}", class, if self.added { '+' } else { '-' }, bit)
+    }
 }
 
 fn char_to_u32(c: char) -> Result<u32> {
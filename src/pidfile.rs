@@ -0,0 +1,145 @@
+//! PID files for daemons.
+//!
+//! # Examples
+//! ```
+//! # use std::error::Error;
+//! #
+//! # fn run() -> Result<(), Box<Error>> {
+//! let pid_file = filey::pidfile::acquire("/run/user/1000/app.pid")?;
+//! // The daemon runs while pid_file is alive.
+//! drop(pid_file); // app.pid is removed.
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # run().unwrap();
+//! # }
+//! ```
+
+use crate::{
+    atomic,
+    op::OpContext,
+    Error::{AlreadyRunning, WouldBlock},
+    FileyHandle, LockMode, Op, Result,
+};
+use std::{
+    fs::{remove_file, OpenOptions},
+    io::{Read, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A PID file held by the current process.
+/// The file is removed when this is dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    handle: FileyHandle,
+    pid: u32,
+}
+
+/// Writes the PID of the current process to path and holds an exclusive lock on it.
+///
+/// A PID file left behind by a process which no longer exists is considered stale and is
+/// replaced.
+///
+/// # Errors
+/// * [`crate::Error::AlreadyRunning`] if another living process holds the PID file.
+/// * The user lacks permissions.
+pub fn acquire<P: AsRef<Path>>(path: P) -> Result<PidFile> {
+    let path = path.as_ref();
+    let pid = process::id();
+
+    // Another process may replace the file between opening and locking it, in which case
+    // the lock is on a file which isn't at path anymore and the new one is tried instead.
+    // The lock is held until the new file below has replaced it.
+    let (_existing, holder) = loop {
+        let existing = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .op(Op::Open, path)?;
+        let mut existing = FileyHandle::new(path, existing);
+
+        let mut contents = String::new();
+        existing.read_to_string(&mut contents).op(Op::Read, path)?;
+        let holder = contents.trim().parse::<u32>().ok();
+
+        match existing.try_lock(LockMode::Exclusive) {
+            Err(WouldBlock { .. }) => {
+                return Err(AlreadyRunning {
+                    path: path.to_string_lossy().to_string(),
+                    pid: holder,
+                })
+            }
+            result => result?,
+        }
+        if is_current(&existing) {
+            break (existing, holder);
+        }
+    };
+
+    // The PID file may have been written by a process which doesn't take the lock.
+    if let Some(holder) = holder {
+        if holder != pid && is_alive(holder) {
+            return Err(AlreadyRunning {
+                path: path.to_string_lossy().to_string(),
+                pid: Some(holder),
+            });
+        }
+    }
+
+    // Lock the new file before it replaces the old one, so that there is no moment when the
+    // PID file at path is unlocked.
+    let mut handle = None;
+    atomic::write_with(path, |file| {
+        file.lock()?;
+        writeln!(file, "{}", pid)?;
+        handle = Some(FileyHandle::new(path, file.try_clone()?));
+        Ok(())
+    })?;
+    let handle = handle.expect("atomic::write_with calls the closure on success");
+
+    Ok(PidFile { handle, pid })
+}
+
+impl PidFile {
+    pub fn path(&self) -> &PathBuf {
+        self.handle.path()
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Only remove the file if it is still the one this process wrote.
+        if is_current(&self.handle) {
+            let _ = remove_file(self.handle.path());
+        }
+    }
+}
+
+/// Checks whether the file of handle is still the one at its path.
+fn is_current(handle: &FileyHandle) -> bool {
+    let ours = handle.file().metadata();
+    let current = std::fs::symlink_metadata(handle.path());
+    match (ours, current) {
+        (Ok(ours), Ok(current)) => (ours.dev(), ours.ino()) == (current.dev(), current.ino()),
+        _ => false,
+    }
+}
+
+/// Checks whether a process with the given PID exists.
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
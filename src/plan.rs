@@ -0,0 +1,146 @@
+use crate::{access, op::OpContext, space, Error::GetFileNameError, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{read_dir, symlink_metadata, Metadata},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// What an operation would do, computed without changing anything.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OperationPlan {
+    /// The directories which would be created (for a copy, at the destination) or removed.
+    pub directories: Vec<PathBuf>,
+    /// The files and symbolic links which would be created (for a copy, at the destination) or
+    /// removed.
+    pub files: Vec<PathBuf>,
+    /// The number of bytes which would be copied or freed.
+    pub bytes: u64,
+    /// The destination paths which already exist.
+    pub conflicts: Vec<PathBuf>,
+    /// The paths which the current user can't read, write or remove as the operation needs.
+    pub permission_problems: Vec<PathBuf>,
+}
+
+impl OperationPlan {
+    /// Returns true if no conflicts or permission problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && self.permission_problems.is_empty()
+    }
+}
+
+/// Plans copying from to to like [`crate::Filey::copy`] for a file or
+/// [`crate::Filey::copy_dir_filtered`] for a directory.
+pub(crate) fn copy(from: &Path, to: &Path) -> Result<OperationPlan> {
+    let metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+    let to = if to.is_dir() {
+        let file_name = from.file_name().ok_or_else(|| GetFileNameError {
+            path: from.to_string_lossy().to_string(),
+        })?;
+        to.join(file_name)
+    } else {
+        to.to_path_buf()
+    };
+
+    let mut plan = OperationPlan::default();
+    let parent = space::nearest_existing(to.parent().unwrap_or(Path::new("")));
+    if !access::can_write(parent) {
+        plan.permission_problems.push(parent.to_path_buf());
+    }
+
+    if metadata.is_dir() {
+        copy_tree(from, &to, &mut plan)?;
+    } else {
+        copy_entry(from, &to, &metadata, &mut plan);
+    }
+    Ok(plan)
+}
+
+fn copy_tree(from: &Path, to: &Path, plan: &mut OperationPlan) -> Result<()> {
+    if symlink_metadata(to).is_ok() {
+        plan.conflicts.push(to.to_path_buf());
+    }
+    plan.directories.push(to.to_path_buf());
+
+    let Some(entries) = list(from, plan)? else {
+        return Ok(());
+    };
+    for (source, metadata) in entries {
+        let destination = to.join(source.file_name().unwrap_or_default());
+        if metadata.is_dir() {
+            copy_tree(&source, &destination, plan)?;
+        } else {
+            copy_entry(&source, &destination, &metadata, plan);
+        }
+    }
+    Ok(())
+}
+
+fn copy_entry(source: &Path, destination: &Path, metadata: &Metadata, plan: &mut OperationPlan) {
+    if symlink_metadata(destination).is_ok() {
+        plan.conflicts.push(destination.to_path_buf());
+    }
+    if !metadata.is_symlink() {
+        if !access::can_read(source) {
+            plan.permission_problems.push(source.to_path_buf());
+        }
+        plan.bytes += metadata.len();
+    }
+    plan.files.push(destination.to_path_buf());
+}
+
+/// Plans removing path like [`crate::Filey::remove`].
+pub(crate) fn remove(path: &Path) -> Result<OperationPlan> {
+    let mut plan = OperationPlan::default();
+    remove_entry(path, &mut plan)?;
+    Ok(plan)
+}
+
+fn remove_entry(path: &Path, plan: &mut OperationPlan) -> Result<()> {
+    let metadata = symlink_metadata(path).op(Op::Metadata, path)?;
+    if !access::can_remove(path) {
+        plan.permission_problems.push(path.to_path_buf());
+    }
+    if !metadata.is_dir() {
+        plan.files.push(path.to_path_buf());
+        if metadata.is_file() {
+            plan.bytes += metadata.len();
+        }
+        return Ok(());
+    }
+
+    plan.directories.push(path.to_path_buf());
+    if let Some(entries) = list(path, plan)? {
+        for (entry, _) in entries {
+            remove_entry(&entry, plan)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the entries of a directory in order. A directory which can't be read is recorded as a
+/// permission problem and None is returned.
+fn list(path: &Path, plan: &mut OperationPlan) -> Result<Option<Vec<(PathBuf, Metadata)>>> {
+    let entries =
+        match read_dir(path).and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                plan.permission_problems.push(path.to_path_buf());
+                return Ok(None);
+            }
+            Err(e) => return Err(Op::ReadDir.error(path, e)),
+        };
+    let mut entries = entries
+        .into_iter()
+        .map(|entry| {
+            let path = entry.path();
+            symlink_metadata(&path)
+                .op(Op::Metadata, &path)
+                .map(|metadata| (path, metadata))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(Some(entries))
+}
@@ -0,0 +1,37 @@
+use crate::BatchOptions;
+use std::{sync::Mutex, thread};
+
+/// Calls f with each item on at most options.max_in_flight threads and returns the results in
+/// the order of items.
+pub(crate) fn map<T, R, F>(items: Vec<T>, options: &BatchOptions, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = options.workers(items.len());
+    if workers == 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let len = items.len();
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new(Vec::with_capacity(len));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                // Take the next item without holding the lock while working on it.
+                let next = queue.lock().unwrap().next();
+                let Some((i, item)) = next else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
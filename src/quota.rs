@@ -0,0 +1,163 @@
+//! Size budgets for directories such as caches.
+//!
+//! # Examples
+//! ```
+//! # use filey::quota::{Policy, Watchdog};
+//! # use filey::units::MIB;
+//! # use std::error::Error;
+//! #
+//! # fn trim() -> Result<(), Box<Error>> {
+//! let watchdog = Watchdog::new("/var/cache/thumbnails", 500 * MIB, Policy::OldestFirst);
+//! let report = watchdog.enforce()?;
+//! println!("freed {} bytes from {} files", report.freed, report.removed.len());
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # trim().unwrap();
+//! # }
+//! ```
+
+use crate::{op::OpContext, FileTypes, Op, Result, Walk, WalkOptions};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fs::remove_file,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Which files [`Watchdog::enforce`] removes first.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Policy {
+    /// The files modified longest ago, so that recently written entries survive.
+    OldestFirst,
+    /// The largest files, so that as few files as possible are removed. Files of the same
+    /// size go oldest first.
+    LargestFirst,
+}
+
+/// What [`Watchdog::enforce`] did.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrimReport {
+    /// The files removed, in the order they were removed.
+    pub removed: Vec<PathBuf>,
+    /// The bytes freed by removing them.
+    pub freed: u64,
+    /// The bytes the files in the directory take up now.
+    pub usage: u64,
+}
+
+/// Keeps the files in a directory tree within a size budget by removing files according to a
+/// [`Policy`].
+///
+/// Usage is the sum of the lengths of the regular files in the tree. Symbolic links aren't
+/// followed, and directories are left in place even when they become empty.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct Watchdog {
+    dir: PathBuf,
+    max_bytes: u64,
+    policy: Policy,
+}
+
+impl Watchdog {
+    /// Constructs a watchdog keeping dir within max_bytes. Nothing is measured or removed
+    /// until [`Watchdog::enforce`] is called.
+    pub fn new<P: AsRef<Path>>(dir: P, max_bytes: u64, policy: Policy) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_bytes,
+            policy,
+        }
+    }
+
+    /// Returns the directory being kept within the budget.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the budget in bytes.
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Returns the bytes the files in the directory take up.
+    ///
+    /// # Errors
+    /// * The directory doesn't exist.
+    /// * The user lacks permissions.
+    pub fn usage(&self) -> Result<u64> {
+        Ok(self.files()?.iter().map(|file| file.len).sum())
+    }
+
+    /// Removes files according to the policy until the directory is within the budget.
+    /// A file which another process removes in the meantime is skipped, so several
+    /// processes can share a cache directory.
+    ///
+    /// # Errors
+    /// * The directory doesn't exist.
+    /// * The user lacks permissions.
+    pub fn enforce(&self) -> Result<TrimReport> {
+        let mut files = self.files()?;
+        let mut usage = files.iter().map(|file| file.len).sum::<u64>();
+        match self.policy {
+            Policy::OldestFirst => {
+                files.sort_by(|a, b| (a.modified, &a.path).cmp(&(b.modified, &b.path)))
+            }
+            Policy::LargestFirst => files.sort_by(|a, b| {
+                (Reverse(a.len), a.modified, &a.path).cmp(&(Reverse(b.len), b.modified, &b.path))
+            }),
+        }
+
+        let mut report = TrimReport::default();
+        for file in files {
+            if usage <= self.max_bytes {
+                break;
+            }
+            match remove_file(&file.path) {
+                Ok(()) => {
+                    report.freed += file.len;
+                    report.removed.push(file.path);
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(Op::Remove.error(&file.path, e)),
+            }
+            usage -= file.len;
+        }
+        report.usage = usage;
+        Ok(report)
+    }
+
+    fn files(&self) -> Result<Vec<File>> {
+        let options = WalkOptions {
+            file_types: vec![FileTypes::File],
+            ..Default::default()
+        };
+        let mut files = Vec::new();
+        for entry in Walk::new(&self.dir, &options)? {
+            let entry = entry?;
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                // Removed since it was listed.
+                Err(_) if !entry.path().exists() => continue,
+                Err(e) => return Err(e),
+            };
+            files.push(File {
+                path: entry.path(),
+                len: metadata.len(),
+                modified: metadata.modified().op(Op::Metadata, entry.path())?,
+            });
+        }
+        Ok(files)
+    }
+}
+
+/// A regular file in the directory.
+struct File {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
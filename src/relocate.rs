@@ -0,0 +1,118 @@
+use crate::{copy::copy_symlink, op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{copy, create_dir, read_dir, remove_dir_all, remove_file, rename, symlink_metadata},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// How [`Filey::move_to_with_report`](crate::Filey::move_to_with_report) moved a file or a
+/// directory.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MoveStrategy {
+    /// Renamed within the same filesystem, without touching the data.
+    Rename,
+    /// Cloned to another filesystem (or e.g. Btrfs subvolume) sharing the data blocks, then
+    /// removed.
+    Reflink,
+    /// Copied byte by byte to another filesystem, then removed.
+    Copy,
+}
+
+/// What [`Filey::move_to_with_report`](crate::Filey::move_to_with_report) did.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MoveReport {
+    /// Where the file or directory is now.
+    pub path: PathBuf,
+    pub strategy: MoveStrategy,
+}
+
+/// Moves from to to, falling back to cloning or copying and then removing from if they are on
+/// separate filesystems and rename_only isn't set.
+pub(crate) fn move_path(from: &Path, to: &Path, rename_only: bool) -> Result<MoveStrategy> {
+    match rename(from, to) {
+        Ok(()) => Ok(MoveStrategy::Rename),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices && !rename_only => {
+            let mut cloned_all = true;
+            transfer(from, to, &mut cloned_all)?;
+            let metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+            if metadata.is_dir() {
+                remove_dir_all(from).op(Op::RemoveDir, from)?;
+            } else {
+                remove_file(from).op(Op::Remove, from)?;
+            }
+            Ok(if cloned_all {
+                MoveStrategy::Reflink
+            } else {
+                MoveStrategy::Copy
+            })
+        }
+        Err(e) => Err(Op::Rename.error2(from, to, e)),
+    }
+}
+
+/// Recreates from at to, cloning files where the filesystem allows it. cloned_all is cleared
+/// if a file had to be copied.
+fn transfer(from: &Path, to: &Path, cloned_all: &mut bool) -> Result<()> {
+    let metadata = symlink_metadata(from).op(Op::Metadata, from)?;
+    if metadata.is_symlink() {
+        return copy_symlink(from, to);
+    }
+    if !metadata.is_dir() {
+        if reflink(from, to).is_err() {
+            *cloned_all = false;
+            copy(from, to).op2(Op::Copy, from, to)?;
+        }
+        return Ok(());
+    }
+
+    create_dir(to).op(Op::CreateDir, to)?;
+    for entry in read_dir(from).op(Op::ReadDir, from)? {
+        let entry = entry.op(Op::ReadDir, from)?;
+        transfer(&entry.path(), &to.join(entry.file_name()), cloned_all)?;
+    }
+    std::fs::set_permissions(to, metadata.permissions()).op(Op::SetPermissions, to)
+}
+
+/// Creates to as a copy-on-write clone of the file from with the same permissions. Nothing is
+/// left at to if cloning fails.
+#[cfg(target_os = "linux")]
+fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+    use std::{
+        fs::{File, OpenOptions},
+        os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    };
+
+    let source = File::open(from)?;
+    let permissions = source.metadata()?.permissions();
+    let destination = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(to)?;
+    let cloned = crate::sys::check(unsafe {
+        libc::ioctl(destination.as_raw_fd(), libc::FICLONE, source.as_raw_fd())
+    })
+    .and_then(|()| destination.set_permissions(permissions));
+    if cloned.is_err() {
+        let _ = remove_file(to);
+    }
+    cloned
+}
+
+/// Creates to as a copy-on-write clone of the file from with the same permissions.
+#[cfg(target_os = "macos")]
+fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+    let to_io = |e: crate::Error| io::Error::new(ErrorKind::InvalidInput, e.to_string());
+    let c_from = crate::sys::to_cstring(from).map_err(to_io)?;
+    let c_to = crate::sys::to_cstring(to).map_err(to_io)?;
+    crate::sys::check(unsafe { libc::clonefile(c_from.as_ptr(), c_to.as_ptr(), 0) })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(ErrorKind::Unsupported.into())
+}
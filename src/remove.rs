@@ -0,0 +1,63 @@
+use crate::{op::OpContext, Error, Op, RemoveOptions, Result};
+use std::{
+    fs::{remove_dir_all, remove_file},
+    path::Path,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// Removes a file or a directory recursively.
+pub(crate) fn remove(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        remove_dir_all(path).op(Op::RemoveDir, path)
+    } else {
+        remove_file(path).op(Op::Remove, path)
+    }
+}
+
+/// Removes path, retrying with backoff for up to options.retry_for while it is in use.
+/// Returns the last error once the time is up.
+pub(crate) fn remove_with(path: &Path, options: &RemoveOptions) -> Result<()> {
+    let retry_for = match options.retry_for {
+        Some(retry_for) => retry_for,
+        None => return remove(path),
+    };
+    let deadline = Instant::now() + retry_for;
+    let mut interval = Duration::from_millis(10);
+    loop {
+        match remove(path) {
+            Err(e) if is_in_use(&e) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(e);
+                }
+                sleep(interval.min(deadline - now));
+                interval = (interval * 2).min(Duration::from_millis(500));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns true if e is likely to go away once another process lets go of the file, like an
+/// antivirus scanner or the search indexer on Windows.
+#[cfg(target_family = "windows")]
+fn is_in_use(e: &Error) -> bool {
+    use windows_sys::Win32::Foundation::{ERROR_LOCK_VIOLATION, ERROR_SHARING_VIOLATION};
+
+    // Access denied is left out: a real permission error would only use up the time.
+    matches!(
+        e.raw_os_error().map(|code| code as u32),
+        Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+    )
+}
+
+#[cfg(target_family = "unix")]
+fn is_in_use(e: &Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::EBUSY | libc::ENOTEMPTY))
+}
+
+#[cfg(not(any(target_family = "unix", target_family = "windows")))]
+fn is_in_use(_e: &Error) -> bool {
+    false
+}
@@ -0,0 +1,110 @@
+use crate::{atomic, op::OpContext, Op, Result};
+use std::{
+    ffi::OsString,
+    fs::{copy, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+/// A pattern for [`crate::Filey::replace_in_file`].
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Matches the string as is.
+    Literal(String),
+    /// (Needs the `regex` feature) Matches a regular expression.
+    /// The replacement can refer to capture groups like `$1` or `${name}`.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl From<&str> for Pattern {
+    fn from(s: &str) -> Self {
+        Self::Literal(s.to_string())
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(s: String) -> Self {
+        Self::Literal(s)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Regex> for Pattern {
+    fn from(regex: regex::Regex) -> Self {
+        Self::Regex(regex)
+    }
+}
+
+impl Pattern {
+    /// Replaces every match in line and returns the number of replacements.
+    fn replace(&self, line: &str, replacement: &str) -> (String, usize) {
+        match self {
+            Self::Literal(s) if s.is_empty() => (line.to_string(), 0),
+            Self::Literal(s) => (
+                line.replace(s, replacement),
+                line.matches(s.as_str()).count(),
+            ),
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => {
+                let count = regex.find_iter(line).count();
+                if count == 0 {
+                    (line.to_string(), 0)
+                } else {
+                    (regex.replace_all(line, replacement).to_string(), count)
+                }
+            }
+        }
+    }
+}
+
+/// Options for [`crate::Filey::replace_in_file_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ReplaceOptions {
+    /// If set, the original file is copied to `<path><backup_suffix>` before it is replaced.
+    pub backup_suffix: Option<String>,
+}
+
+/// Replaces pattern line by line and writes the result atomically.
+/// The file is left untouched (and no backup is made) if nothing matches.
+pub(crate) fn replace_in_file<P: AsRef<Path>>(
+    path: P,
+    pattern: &Pattern,
+    replacement: &str,
+    options: &ReplaceOptions,
+) -> Result<usize> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path).op(Op::Open, path)?);
+    let mut replaced = String::new();
+    let mut count = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).op(Op::Read, path)? == 0 {
+            break;
+        }
+        // Keep the line ending out of the pattern's reach, like sed.
+        let content = line.trim_end_matches(['\n', '\r']);
+        let (new, n) = pattern.replace(content, replacement);
+        replaced.push_str(&new);
+        replaced.push_str(&line[content.len()..]);
+        count += n;
+    }
+
+    if count == 0 {
+        return Ok(0);
+    }
+
+    if let Some(suffix) = &options.backup_suffix {
+        let backup = backup_path(path, suffix);
+        copy(path, &backup).op2(Op::Copy, path, &backup)?;
+    }
+    atomic::write_with(path, |file| file.write_all(replaced.as_bytes()))?;
+    Ok(count)
+}
+
+fn backup_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(suffix);
+    PathBuf::from(backup)
+}
@@ -0,0 +1,163 @@
+use crate::{
+    checksum::{self, Algorithm, Digest},
+    op::OpContext,
+    Op, Result,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{read_to_string, remove_file, File, Metadata, OpenOptions},
+    io::{self, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+/// The unit in which progress is recorded and verified.
+pub(crate) const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// What [`Filey::copy_resumable`](crate::Filey::copy_resumable) did.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResumeReport {
+    /// The bytes kept from an interrupted copy after verifying them.
+    pub verified: u64,
+    /// The bytes copied by this call.
+    pub copied: u64,
+}
+
+/// Copies from to to, recording the digest of every chunk copied in state so that an
+/// interrupted copy resumes after the last chunk which is still intact at to.
+///
+/// The state starts with a header identifying the source by its size and modification time,
+/// followed by a line with the index and SHA-256 digest of each chunk. A state for another
+/// version of the source is ignored. Chunks of zeros are skipped, leaving holes in to.
+pub(crate) fn copy_resumable(from: &Path, to: &Path, state: &Path) -> Result<ResumeReport> {
+    let mut source = File::open(from).op(Op::Open, from)?;
+    let metadata = source.metadata().op(Op::Metadata, from)?;
+    let header = header(&metadata);
+    let recorded = read_state(state, &header)?;
+
+    let mut destination = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(to)
+        .op(Op::Open, to)?;
+    let mut buf = vec![0; CHUNK_SIZE as usize];
+    let mut verified = 0;
+    for digest in &recorded {
+        let offset = verified as u64 * CHUNK_SIZE;
+        if offset >= metadata.len() {
+            break;
+        }
+        let expected = (metadata.len() - offset).min(CHUNK_SIZE) as usize;
+        destination.seek(SeekFrom::Start(offset)).op(Op::Read, to)?;
+        let n = read_full(&mut destination, &mut buf).op(Op::Read, to)?;
+        if n < expected || checksum::hash_bytes(&buf[..expected], Algorithm::Sha256) != *digest {
+            break;
+        }
+        verified += 1;
+    }
+
+    // Everything after the verified chunks is rewritten, so holes read as zeros.
+    let offset = verified as u64 * CHUNK_SIZE;
+    destination.set_len(offset).op(Op::Write, to)?;
+    let mut progress = BufWriter::new(File::create(state).op(Op::Create, state)?);
+    writeln!(progress, "{}", header).op(Op::Write, state)?;
+    for (index, digest) in recorded.iter().take(verified).enumerate() {
+        writeln!(progress, "{} {}", index, digest).op(Op::Write, state)?;
+    }
+    progress.flush().op(Op::Write, state)?;
+
+    source.seek(SeekFrom::Start(offset)).op(Op::Read, from)?;
+    let mut position = offset;
+    for index in verified.. {
+        let n = read_full(&mut source, &mut buf).op(Op::Read, from)?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buf[..n];
+        if chunk.iter().any(|&byte| byte != 0) {
+            destination
+                .seek(SeekFrom::Start(position))
+                .and_then(|_| destination.write_all(chunk))
+                .op(Op::Write, to)?;
+        }
+        position += n as u64;
+        let digest = checksum::hash_bytes(chunk, Algorithm::Sha256);
+        writeln!(progress, "{} {}", index, digest)
+            .and_then(|()| progress.flush())
+            .op(Op::Write, state)?;
+    }
+
+    // Extends the file over a trailing hole.
+    destination.set_len(position).op(Op::Write, to)?;
+    destination
+        .set_permissions(metadata.permissions())
+        .op(Op::SetPermissions, to)?;
+    destination.sync_all().op(Op::Write, to)?;
+    drop(progress);
+    remove_file(state).op(Op::Remove, state)?;
+    Ok(ResumeReport {
+        verified: offset,
+        copied: position - offset,
+    })
+}
+
+/// Identifies the version of the source a state was recorded for.
+pub(crate) fn header(metadata: &Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!(
+        "filey-copy-resumable {} {}.{:09} {}",
+        metadata.len(),
+        modified.as_secs(),
+        modified.subsec_nanos(),
+        CHUNK_SIZE
+    )
+}
+
+/// Returns the digests of the chunks recorded in state, in order.
+/// A line cut off by an interruption ends the list.
+fn read_state(state: &Path, header: &str) -> Result<Vec<Digest>> {
+    let contents = match read_to_string(state) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Op::Read.error(state, e)),
+    };
+    let mut lines = contents.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some(header) {
+        return Ok(Vec::new());
+    }
+    let mut digests = Vec::new();
+    for line in lines {
+        let digest = line
+            .strip_suffix('\n')
+            .and_then(|line| line.split_once(' '))
+            .filter(|(index, _)| index.parse() == Ok(digests.len()))
+            .and_then(|(_, digest)| Digest::from_hex(digest));
+        match digest {
+            Some(digest) => digests.push(digest),
+            None => break,
+        }
+    }
+    Ok(digests)
+}
+
+/// Reads until buf is full or the end of the file.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
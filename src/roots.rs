@@ -0,0 +1,224 @@
+//! Operations over several directory trees at once, such as a collection spread across
+//! several disks.
+//!
+//! # Examples
+//! ```
+//! # use filey::checksum::Algorithm;
+//! # use filey::roots::Roots;
+//! # use std::error::Error;
+//! #
+//! # fn music() -> Result<(), Box<Error>> {
+//! let music = Roots::new(["/mnt/disk1/music", "/mnt/disk2/music", "/mnt/disk2/music/live"])?;
+//! println!("{} bytes", music.size()?);
+//! for copies in music.duplicates(Algorithm::Sha256)? {
+//!     println!("{:?}", copies);
+//! }
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # music().unwrap();
+//! # }
+//! ```
+
+#[cfg(feature = "search")]
+use crate::search::{grep_tree, FileMatches, SearchOptions};
+#[cfg(feature = "search")]
+use crate::Pattern;
+use crate::{
+    checksum::{self, Algorithm, TreeDigest},
+    list::Entry,
+    op::OpContext,
+    FileTypes, Op, Result, Walk, WalkOptions,
+};
+use std::{
+    collections::BTreeMap,
+    fs::canonicalize,
+    path::{Path, PathBuf},
+    vec,
+};
+
+/// A set of root directories which operations treat as one tree.
+///
+/// Overlapping roots are merged when the set is constructed: a root which is the same
+/// directory as another, or inside another, is dropped, so that no entry is visited twice.
+/// Symbolic links and relative paths are resolved to find overlaps, but the remaining roots
+/// keep the paths they were given as.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Roots {
+    roots: Vec<PathBuf>,
+}
+
+impl Roots {
+    /// Constructs a set of roots, dropping the ones overlapping an earlier or enclosing root.
+    ///
+    /// # Errors
+    /// * A root doesn't exist.
+    /// * The user lacks permissions.
+    pub fn new<I, P>(roots: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut resolved = Vec::new();
+        for root in roots {
+            let root = root.as_ref();
+            let canonical = canonicalize(root).op(Op::Canonicalize, root)?;
+            resolved.push((root.to_path_buf(), canonical));
+        }
+
+        let mut kept: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for (i, (root, canonical)) in resolved.iter().enumerate() {
+            let enclosed = resolved.iter().enumerate().any(|(j, (_, other))| {
+                // Of two identical roots, the first is kept.
+                canonical.starts_with(other) && (canonical != other || j < i)
+            });
+            if !enclosed {
+                kept.push((root.clone(), canonical.clone()));
+            }
+        }
+        Ok(Self {
+            roots: kept.into_iter().map(|(root, _)| root).collect(),
+        })
+    }
+
+    /// Returns the roots left after merging overlapping ones, in the order they were given.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Walks every root in turn like [`Filey::walk`](crate::Filey::walk).
+    ///
+    /// # Errors
+    /// * The user lacks permissions to read a root.
+    pub fn walk(&self) -> Result<MultiWalk> {
+        self.walk_with(&WalkOptions::default())
+    }
+
+    /// Walks every root in turn like [`Filey::walk_with`](crate::Filey::walk_with). The
+    /// paths the filter of the options matches are relative to the root they are in.
+    ///
+    /// # Errors
+    /// * The user lacks permissions to read a root.
+    pub fn walk_with(&self, options: &WalkOptions) -> Result<MultiWalk> {
+        let walks = self
+            .roots
+            .iter()
+            .map(|root| Walk::new(root, options))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(MultiWalk {
+            walks: walks.into_iter(),
+            current: None,
+        })
+    }
+
+    /// Returns the total size in bytes of the regular files under the roots.
+    /// Symbolic links aren't followed.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn size(&self) -> Result<u64> {
+        self.files()?
+            .iter()
+            .map(|entry| Ok(entry.metadata()?.len()))
+            .sum()
+    }
+
+    /// Hashes each root like [`checksum::hash_tree`], returning the digests in the order of
+    /// [`Roots::paths`].
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn hash_trees(&self, algorithm: Algorithm, jobs: usize) -> Result<Vec<TreeDigest>> {
+        self.roots
+            .iter()
+            .map(|root| checksum::hash_tree(root, algorithm, jobs))
+            .collect()
+    }
+
+    /// Finds the regular files under the roots with the same contents. Returns groups of at
+    /// least two paths, each sorted, with the groups sorted by their first path.
+    ///
+    /// Only files of the same size are hashed, and on Linux and macOS digests are cached in
+    /// extended attributes like [`Filey::cached_hash`](crate::Filey::cached_hash), so running
+    /// it again over a large collection is fast.
+    ///
+    /// # Errors
+    /// * The user lacks permissions.
+    pub fn duplicates(&self, algorithm: Algorithm) -> Result<Vec<Vec<PathBuf>>> {
+        let mut by_size = BTreeMap::<u64, Vec<PathBuf>>::new();
+        for entry in self.files()? {
+            by_size
+                .entry(entry.metadata()?.len())
+                .or_default()
+                .push(entry.path());
+        }
+
+        let mut by_digest = BTreeMap::<_, Vec<PathBuf>>::new();
+        for paths in by_size.into_values().filter(|paths| paths.len() > 1) {
+            for path in paths {
+                let digest = checksum::hash_file_cached(&path, algorithm)?;
+                by_digest.entry(digest).or_default().push(path);
+            }
+        }
+        let mut groups = by_digest
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .map(|mut paths| {
+                paths.sort();
+                paths
+            })
+            .collect::<Vec<_>>();
+        groups.sort();
+        Ok(groups)
+    }
+
+    /// (Needs the `search` feature) Searches every file under the roots like
+    /// [`grep_tree`](crate::search::grep_tree), returning the files with matches sorted by
+    /// path.
+    ///
+    /// # Errors
+    /// * A root doesn't exist anymore.
+    #[cfg(feature = "search")]
+    pub fn grep<P: Into<Pattern>>(
+        &self,
+        pattern: P,
+        options: &SearchOptions,
+    ) -> Result<Vec<FileMatches>> {
+        let pattern = pattern.into();
+        let mut files = Vec::new();
+        for root in &self.roots {
+            files.extend(grep_tree(root, pattern.clone(), options)?);
+        }
+        files.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(files)
+    }
+
+    fn files(&self) -> Result<Vec<Entry>> {
+        let options = WalkOptions {
+            file_types: vec![FileTypes::File],
+            ..Default::default()
+        };
+        self.walk_with(&options)?.collect()
+    }
+}
+
+/// An iterator over the entries of several directory trees, returned by [`Roots::walk`].
+/// The trees are walked one after another.
+#[derive(Debug)]
+pub struct MultiWalk {
+    walks: vec::IntoIter<Walk>,
+    current: Option<Walk>,
+}
+
+impl Iterator for MultiWalk {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(entry);
+            }
+            self.current = Some(self.walks.next()?);
+        }
+    }
+}
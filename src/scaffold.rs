@@ -0,0 +1,158 @@
+//! Generating directory trees such as new projects from templates.
+//!
+//! # Examples
+//! ```
+//! # use filey::scaffold;
+//! # use std::collections::HashMap;
+//! # use std::error::Error;
+//! #
+//! # fn new_project() -> Result<(), Box<Error>> {
+//! // templates/lib/{{name}}/Cargo.toml contains `name = "{{name}}"`.
+//! let vars = HashMap::from([("name", "parser"), ("author", "Alice")]);
+//! scaffold::render("templates/lib", "projects", &vars)?;
+//! // projects/parser/Cargo.toml now contains `name = "parser"`.
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # new_project().unwrap();
+//! # }
+//! ```
+
+use crate::{copy::copy_symlink, op::OpContext, Error::FileyError, Op, Result};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{
+        create_dir, create_dir_all, read, read_dir, set_permissions, symlink_metadata, OpenOptions,
+    },
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+/// Copies the directory tree template_root into dest, replacing every `{{name}}` placeholder
+/// (spaces inside the braces are allowed) in the names of the entries and in the contents of
+/// the files with the value of the variable name in vars. Returns the paths created, relative
+/// to dest, parents first.
+///
+/// Files which aren't valid UTF-8, such as images, are copied unchanged, and so are the
+/// targets of symbolic links. Permissions are kept, so scripts stay executable.
+/// dest is created if it doesn't exist, but nothing in it is overwritten.
+///
+/// # Errors
+/// * template_root doesn't exist or isn't a directory.
+/// * A placeholder names a variable which isn't in vars.
+/// * A name becomes empty, `.` or `..`, or gets a path separator after substitution, which
+///   could place the entry outside dest.
+/// * An entry already exists in dest.
+/// * The user lacks permissions.
+pub fn render<P, Q>(template_root: P, dest: Q, vars: &HashMap<&str, &str>) -> Result<Vec<PathBuf>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let (template_root, dest) = (template_root.as_ref(), dest.as_ref());
+    let metadata = symlink_metadata(template_root).op(Op::Metadata, template_root)?;
+    if !metadata.is_dir() {
+        return Err(FileyError(anyhow::anyhow!(
+            "'{}' is not a directory",
+            template_root.display()
+        )));
+    }
+    create_dir_all(dest).op(Op::CreateDir, dest)?;
+    let mut created = Vec::new();
+    render_dir(template_root, dest, Path::new(""), vars, &mut created)?;
+    Ok(created)
+}
+
+fn render_dir(
+    from: &Path,
+    dest: &Path,
+    relative: &Path,
+    vars: &HashMap<&str, &str>,
+    created: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut entries = read_dir(from)
+        .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+        .op(Op::ReadDir, from)?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let source = entry.path();
+        let name = match entry.file_name().into_string() {
+            Ok(name) => OsString::from(substitute_name(&name, vars, &source)?),
+            Err(name) => name,
+        };
+        let relative = relative.join(name);
+        let to = dest.join(&relative);
+        let metadata = symlink_metadata(&source).op(Op::Metadata, &source)?;
+        if metadata.is_symlink() {
+            copy_symlink(&source, &to)?;
+            created.push(relative);
+        } else if metadata.is_dir() {
+            create_dir(&to).op(Op::CreateDir, &to)?;
+            created.push(relative.clone());
+            render_dir(&source, dest, &relative, vars, created)?;
+            set_permissions(&to, metadata.permissions()).op(Op::SetPermissions, &to)?;
+        } else {
+            let contents = read(&source).op(Op::Read, &source)?;
+            let contents = match String::from_utf8(contents) {
+                Ok(text) => substitute(&text, vars, &source)?.into_bytes(),
+                Err(e) => e.into_bytes(),
+            };
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&to)
+                .op(Op::Create, &to)?;
+            file.write_all(&contents).op(Op::Write, &to)?;
+            file.set_permissions(metadata.permissions())
+                .op(Op::SetPermissions, &to)?;
+            created.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the placeholders in the name of the entry at path, checking that the result is
+/// still a single name, as the values may come from user input.
+fn substitute_name(name: &str, vars: &HashMap<&str, &str>, path: &Path) -> Result<String> {
+    let rendered = substitute(name, vars, path)?;
+    let mut components = Path::new(&rendered).components();
+    let is_single_name = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(normal)), None) if normal == rendered.as_str()
+    );
+    if !is_single_name || rendered.contains(['/', '\\']) {
+        return Err(FileyError(anyhow::anyhow!(
+            "'{}' renders to '{}', which is not a valid file name",
+            path.display(),
+            rendered
+        )));
+    }
+    Ok(rendered)
+}
+
+/// Replaces the placeholders in text, which comes from path.
+/// A `{{` without a matching `}}` is left as is.
+fn substitute(text: &str, vars: &HashMap<&str, &str>, path: &Path) -> Result<String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        let value = vars.get(name).ok_or_else(|| {
+            FileyError(anyhow::anyhow!(
+                "'{}' uses the undefined variable '{}'",
+                path.display(),
+                name
+            ))
+        })?;
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value);
+        rest = &rest[start + 2 + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
@@ -0,0 +1,169 @@
+//! Content search across directory trees.
+//!
+//! # Examples
+//! ```
+//! # use filey::search::{grep_tree, SearchOptions};
+//! # use std::error::Error;
+//! #
+//! # fn todo() -> Result<(), Box<Error>> {
+//! for file in grep_tree("src", "TODO", &SearchOptions::default())? {
+//!     for m in file.matches() {
+//!         println!("{}:{}: {}", file.path().display(), m.line_number(), m.line());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # todo().unwrap();
+//! # }
+//! ```
+
+use crate::{grep::grep_reader, op::OpContext, Filter, GrepMatch, Op, Pattern, Result};
+use ignore::{WalkBuilder, WalkState};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Options for [`grep_tree`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SearchOptions {
+    /// Skips files ignored by .gitignore, .ignore and the global git excludes.
+    pub respect_gitignore: bool,
+    /// Searches hidden files and directories.
+    pub hidden: bool,
+    /// Searches files which look binary (contain a NUL byte in the first 8 KiB).
+    pub binary: bool,
+    /// Follows symbolic links.
+    pub follow_symlinks: bool,
+    /// The maximum depth to descend, where the root is 0.
+    pub max_depth: Option<usize>,
+    /// The number of threads. 0 picks a number based on the available CPUs.
+    pub threads: usize,
+    /// Searches only the files the filter accepts, and doesn't descend into the directories
+    /// it rejects.
+    pub filter: Filter,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            respect_gitignore: true,
+            hidden: false,
+            binary: false,
+            follow_symlinks: false,
+            max_depth: None,
+            threads: 0,
+            filter: Filter::default(),
+        }
+    }
+}
+
+/// The matches found in one file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileMatches {
+    path: PathBuf,
+    matches: Vec<GrepMatch>,
+}
+
+impl FileMatches {
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn matches(&self) -> &[GrepMatch] {
+        &self.matches
+    }
+}
+
+/// Searches every file under root for pattern in parallel.
+/// Only files with at least one match are returned, sorted by path.
+/// Files which can't be read are skipped.
+///
+/// # Errors
+/// * root doesn't exist.
+pub fn grep_tree<P, Q>(root: P, pattern: Q, options: &SearchOptions) -> Result<Vec<FileMatches>>
+where
+    P: AsRef<Path>,
+    Q: Into<Pattern>,
+{
+    let root = root.as_ref();
+    root.metadata().op(Op::Metadata, root)?;
+    let pattern = pattern.into();
+
+    let mut builder = WalkBuilder::new(root);
+    if !options.filter.is_empty() {
+        let (root, filter) = (root.to_path_buf(), options.filter.clone());
+        builder.filter_entry(move |entry| {
+            let Ok(relative) = entry.path().strip_prefix(&root) else {
+                return true;
+            };
+            relative.as_os_str().is_empty()
+                || entry
+                    .metadata()
+                    .map_or(true, |metadata| filter.matches(relative, &metadata))
+        });
+    }
+    let walker = builder
+        .standard_filters(options.respect_gitignore)
+        .hidden(!options.hidden)
+        .follow_links(options.follow_symlinks)
+        .max_depth(options.max_depth)
+        .threads(options.threads)
+        .require_git(false)
+        .build_parallel();
+
+    let results = Mutex::new(Vec::new());
+    walker.run(|| {
+        let pattern = &pattern;
+        let results = &results;
+        Box::new(move |entry| {
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return WalkState::Continue;
+            }
+            if let Ok(Some(matches)) = search_file(entry.path(), pattern, options.binary) {
+                results.lock().unwrap().push(FileMatches {
+                    path: entry.into_path(),
+                    matches,
+                });
+            }
+            WalkState::Continue
+        })
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(results)
+}
+
+/// Returns None if the file is binary (and binary is false) or has no matches.
+fn search_file(path: &Path, pattern: &Pattern, binary: bool) -> Result<Option<Vec<GrepMatch>>> {
+    let mut file = File::open(path).op(Op::Open, path)?;
+    if !binary && is_binary(&mut file).op(Op::Read, path)? {
+        return Ok(None);
+    }
+    let matches = grep_reader(file, pattern).op(Op::Read, path)?;
+    Ok(Some(matches).filter(|m| !m.is_empty()))
+}
+
+/// Detects binary files the way grep does: by a NUL byte near the start.
+pub(crate) fn is_binary(file: &mut File) -> std::io::Result<bool> {
+    let mut buf = [0; 8192];
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    file.seek(SeekFrom::Start(0))?;
+    Ok(buf[..len].contains(&0))
+}
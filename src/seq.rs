@@ -0,0 +1,98 @@
+//! Sequence files shared across processes.
+//!
+//! # Examples
+//! ```
+//! # use std::error::Error;
+//! #
+//! # fn build() -> Result<(), Box<Error>> {
+//! let build_number = filey::seq::next("build_number")?;
+//! println!("{}", build_number); // 42
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # build().unwrap();
+//! # }
+//! ```
+
+use crate::{
+    atomic,
+    op::OpContext,
+    Error::{FileyError, GetFileNameError},
+    FileyHandle, LockMode, Op, Result,
+};
+use std::{
+    fs::{read_to_string, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Increments the number stored in path and returns the new value.
+/// A missing file counts as 0, so the first value is 1.
+///
+/// The read-modify-write is done under an exclusive lock on a companion `.<name>.seq.lock` file
+/// and the new value is written atomically, so concurrent callers never get the same number.
+///
+/// # Errors
+/// * The file doesn't contain a number.
+/// * The user lacks permissions.
+pub fn next<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let path = path.as_ref();
+    let lock = open_lock(path)?;
+    lock.lock(LockMode::Exclusive)?;
+
+    let next = read(path)?
+        .checked_add(1)
+        .ok_or_else(|| FileyError(anyhow::anyhow!("'{}' overflowed", path.display())))?;
+    atomic::write_with(path, |file| writeln!(file, "{}", next))?;
+
+    lock.unlock()?;
+    Ok(next)
+}
+
+/// Returns the number stored in path without incrementing it.
+/// A missing file counts as 0.
+///
+/// # Errors
+/// * The file doesn't contain a number.
+/// * The user lacks permissions.
+pub fn current<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let path = path.as_ref();
+    let lock = open_lock(path)?;
+    lock.lock(LockMode::Shared)?;
+    let current = read(path)?;
+    lock.unlock()?;
+    Ok(current)
+}
+
+fn read(path: &Path) -> Result<u64> {
+    match read_to_string(path) {
+        Ok(s) => s.trim().parse::<u64>().map_err(|e| {
+            FileyError(
+                anyhow::anyhow!(e)
+                    .context(format!("'{}' doesn't contain a number", path.display())),
+            )
+        }),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(Op::Read.error(path, e)),
+    }
+}
+
+/// The data file itself can't be locked because it is replaced on every write.
+fn open_lock(path: &Path) -> Result<FileyHandle> {
+    let lock_path = lock_path(path)?;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .op(Op::Open, &lock_path)?;
+    Ok(FileyHandle::new(lock_path, file))
+}
+
+fn lock_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| GetFileNameError {
+        path: path.to_string_lossy().to_string(),
+    })?;
+    Ok(path.with_file_name(format!(".{}.seq.lock", file_name.to_string_lossy())))
+}
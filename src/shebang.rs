@@ -0,0 +1,101 @@
+use crate::{op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+/// The interpreter line at the start of a script, such as `#!/usr/bin/env python3`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Shebang {
+    interpreter: PathBuf,
+    args: Vec<String>,
+}
+
+impl fmt::Display for Shebang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#!{}", self.interpreter.display())?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl Shebang {
+    pub fn new<P: AsRef<Path>>(interpreter: P, args: Vec<String>) -> Self {
+        Self {
+            interpreter: interpreter.as_ref().to_path_buf(),
+            args,
+        }
+    }
+
+    /// Parses the first line of a script.
+    /// Returns None if line doesn't start with `#!` or names no interpreter.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line
+            .strip_prefix("#!")?
+            .trim_end_matches(['\r', '\n'])
+            .split_whitespace();
+        let interpreter = PathBuf::from(words.next()?);
+        Some(Self {
+            interpreter,
+            args: words.map(|word| word.to_string()).collect(),
+        })
+    }
+
+    /// Returns the path of the interpreter, e.g. `/usr/bin/env`.
+    pub fn interpreter(&self) -> &PathBuf {
+        &self.interpreter
+    }
+
+    /// Returns the arguments following the interpreter.
+    pub fn args(&self) -> &Vec<String> {
+        &self.args
+    }
+
+    /// Returns the name of the program that actually runs the script.
+    /// For `#!/usr/bin/env python3` this is `python3` rather than `env`.
+    pub fn program(&self) -> Option<&str> {
+        let name = self.interpreter.file_name()?.to_str()?;
+        if name == "env" {
+            self.args
+                .iter()
+                .map(|arg| arg.as_str())
+                .find(|arg| !arg.starts_with('-') && !arg.contains('='))
+        } else {
+            Some(name)
+        }
+    }
+
+    pub fn set_interpreter<P: AsRef<Path>>(&mut self, interpreter: P) -> &mut Self {
+        self.interpreter = interpreter.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) -> &mut Self {
+        self.args = args;
+        self
+    }
+}
+
+// Longer lines aren't treated as shebangs by any common kernel.
+const MAX_LINE: u64 = 4096;
+
+/// Reads the shebang of path if it has one.
+pub(crate) fn shebang<P: AsRef<Path>>(path: P) -> Result<Option<Shebang>> {
+    let path = path.as_ref();
+    let file = File::open(path).op(Op::Open, path)?;
+    let line = first_line(file).op(Op::Read, path)?;
+    Ok(line.and_then(|line| Shebang::parse(&line)))
+}
+
+fn first_line(file: File) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    BufReader::new(file.take(MAX_LINE)).read_until(b'\n', &mut line)?;
+    Ok(String::from_utf8(line).ok())
+}
@@ -0,0 +1,482 @@
+//! Snapshots of directory trees for finding what changed between two points in time and for
+//! recreating a tree elsewhere.
+//!
+//! # Examples
+//! ```
+//! # use filey::snapshot;
+//! # use std::error::Error;
+//! #
+//! # fn changes() -> Result<(), Box<Error>> {
+//! let yesterday: snapshot::Manifest =
+//!     serde_json::from_str(&std::fs::read_to_string("yesterday.json")?)?;
+//! let today = snapshot::take("/srv/data")?;
+//! for path in snapshot::diff(&yesterday, &today).modified {
+//!     println!("{}", path.display());
+//! }
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # changes().unwrap();
+//! # }
+//! ```
+
+use crate::{
+    checksum::{self, Algorithm, Digest},
+    compare,
+    op::OpContext,
+    pool, BatchOptions, ConfigFormat,
+    Error::FileyError,
+    FileTypes, Filter, Op, Result, SymlinkBehavior,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{
+        canonicalize, create_dir, create_dir_all, read_dir, read_link, symlink_metadata, File,
+        Metadata,
+    },
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The state of every entry in a directory tree.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Manifest {
+    /// The algorithm of the digests in the entries, if files were hashed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub algorithm: Option<Algorithm>,
+    /// The entries keyed by their paths relative to the root, which itself isn't included.
+    pub entries: BTreeMap<PathBuf, ManifestEntry>,
+}
+
+/// The state of a single entry in a [`Manifest`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestEntry {
+    pub file_type: FileTypes,
+    /// The length of a file or symbolic link; 0 for directories, whose size depends on the
+    /// filesystem.
+    pub size: u64,
+    /// The permission bits (`mode & 0o7777`) on Unix; None elsewhere.
+    pub mode: Option<u32>,
+    /// The path a symbolic link points to; None for other types.
+    pub target: Option<PathBuf>,
+    /// None if the platform doesn't record modification times or
+    /// [`SnapshotOptions::omit_modified`] is set.
+    pub modified: Option<SystemTime>,
+    /// The digest of the contents of a regular file, if hashing was requested.
+    pub digest: Option<Digest>,
+}
+
+/// Options for [`take_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SnapshotOptions {
+    /// Hashes the contents of every regular file with this algorithm, so that
+    /// [`diff`] can tell whether a file whose modification time changed really changed.
+    pub hash: Option<Algorithm>,
+    /// Limits the number of files hashed at once. Its symlinks field sets whether symbolic
+    /// links are recorded as links, recorded as what they point to, or left out.
+    pub batch: BatchOptions,
+    /// Leaves out modification times, so that manifests of identical trees are identical.
+    pub omit_modified: bool,
+    /// Leaves out the entries the filter rejects, so that [`diff`] ignores them.
+    pub filter: Filter,
+}
+
+/// Options for [`crate::Filey::manifest`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ManifestOptions {
+    /// The format to serialize the manifest in. Defaults to JSON.
+    pub format: ConfigFormat,
+    pub snapshot: SnapshotOptions,
+}
+
+impl Default for ManifestOptions {
+    fn default() -> Self {
+        Self {
+            format: ConfigFormat::Json,
+            snapshot: SnapshotOptions::default(),
+        }
+    }
+}
+
+/// Options for [`diff_with`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DiffOptions {
+    /// Matches paths which only differ in case, like the default filesystems of Windows and
+    /// macOS do, so that a tree copied from one of them isn't reported as renamed entirely.
+    pub ignore_case: bool,
+}
+
+/// The differences between two manifests.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Changes {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+}
+
+impl Changes {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Records the type, size and modification time of every entry under root.
+/// Symbolic links are recorded as links and not followed.
+///
+/// # Errors
+/// * root doesn't exist or isn't a directory.
+/// * The user lacks permissions.
+pub fn take<P: AsRef<Path>>(root: P) -> Result<Manifest> {
+    take_with(root, &SnapshotOptions::default())
+}
+
+/// Records every entry under root like [`take`], hashing the regular files if options.hash is
+/// set and treating symbolic links according to options.batch.symlinks.
+///
+/// # Errors
+/// * root doesn't exist or isn't a directory.
+/// * The user lacks permissions.
+/// * Links are followed and one is broken or leads back to a directory containing it.
+pub fn take_with<P: AsRef<Path>>(root: P, options: &SnapshotOptions) -> Result<Manifest> {
+    let root = root.as_ref();
+    let mut entries = BTreeMap::new();
+    scan(root, Path::new(""), options, &mut Vec::new(), &mut entries)?;
+
+    if let Some(algorithm) = options.hash {
+        let files = entries
+            .iter()
+            .filter(|(_, entry)| entry.file_type == FileTypes::File)
+            .map(|(relative, _)| relative.clone())
+            .collect();
+        let digests = pool::map(files, &options.batch, |relative| {
+            checksum::hash_file(root.join(&relative), algorithm).map(|digest| (relative, digest))
+        });
+        for digest in digests {
+            let (relative, digest) = digest?;
+            if let Some(entry) = entries.get_mut(&relative) {
+                entry.digest = Some(digest);
+            }
+        }
+    }
+
+    Ok(Manifest {
+        algorithm: options.hash,
+        entries,
+    })
+}
+
+/// Compares two manifests of the same tree.
+///
+/// A file counts as modified if its type or size changed, or if its contents changed: when both
+/// manifests hold a digest for it, the digests are compared, otherwise the modification times.
+/// A symbolic link counts as modified if its type or target changed, and a directory only if
+/// its type changed.
+pub fn diff(old: &Manifest, new: &Manifest) -> Changes {
+    diff_with(old, new, &DiffOptions::default())
+}
+
+/// Compares two manifests like [`diff`] with options. When case is ignored, a modified entry
+/// is reported under its path in new.
+pub fn diff_with(old: &Manifest, new: &Manifest, options: &DiffOptions) -> Changes {
+    let key = |path: &Path| {
+        if options.ignore_case {
+            compare::fold_case(path)
+        } else {
+            path.to_path_buf()
+        }
+    };
+    let new_paths = new
+        .entries
+        .keys()
+        .map(|path| (key(path), path))
+        .collect::<BTreeMap<_, _>>();
+    let old_keys = old
+        .entries
+        .keys()
+        .map(|path| key(path))
+        .collect::<BTreeSet<_>>();
+
+    let mut changes = Changes::default();
+    for (path, old_entry) in &old.entries {
+        match new_paths.get(&key(path)) {
+            None => changes.removed.push(path.clone()),
+            Some(&new_path) if is_modified(old_entry, &new.entries[new_path]) => {
+                changes.modified.push(new_path.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    changes.added = new
+        .entries
+        .keys()
+        .filter(|path| !old_keys.contains(&key(path)))
+        .cloned()
+        .collect();
+    changes
+}
+
+/// Recreates the tree described by manifest under dest, which is created if it doesn't exist.
+///
+/// Directories, symbolic links and permission bits come from the manifest, and the contents of
+/// each regular file are read from the reader which source returns for its relative path.
+/// A file whose entry has a digest is checked against it, and files get their recorded
+/// modification times. Sockets are skipped.
+///
+/// # Errors
+/// * A path in the manifest is absolute or contains `..`, which would place the entry
+///   outside dest.
+/// * An entry already exists under dest.
+/// * source fails.
+/// * The contents of a file don't match its digest.
+/// * The user lacks permissions.
+///
+/// # Examples
+/// ```
+/// # use filey::{snapshot, Filey};
+/// # use std::{error::Error, fs::File};
+/// #
+/// # fn scaffold() -> Result<(), Box<Error>> {
+/// let manifest: snapshot::Manifest = Filey::new("templates/web.json").read_config()?;
+/// snapshot::materialize(&manifest, "new-project", |path, _| {
+///     Ok(File::open(std::path::Path::new("templates/web").join(path))?)
+/// })?;
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # scaffold().unwrap();
+/// # }
+/// ```
+pub fn materialize<P, F, R>(manifest: &Manifest, dest: P, mut source: F) -> Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path, &ManifestEntry) -> Result<R>,
+    R: Read,
+{
+    let dest = dest.as_ref();
+    // Checked up front, so that nothing is written for a manifest which is rejected.
+    if let Some(relative) = manifest
+        .entries
+        .keys()
+        .find(|relative| !is_contained(relative))
+    {
+        return Err(FileyError(anyhow::anyhow!(
+            "'{}' in the manifest is not a relative path within the destination",
+            relative.display()
+        )));
+    }
+    create_dir_all(dest).op(Op::CreateDir, dest)?;
+
+    // Parents sort before their children, so each directory exists before it is filled.
+    let mut symlinks = Vec::new();
+    let mut directories = Vec::new();
+    for (relative, entry) in &manifest.entries {
+        let path = dest.join(relative);
+        match entry.file_type {
+            FileTypes::Directory => {
+                create_dir(&path).op(Op::CreateDir, &path)?;
+                directories.push((path, entry));
+            }
+            FileTypes::File => {
+                let mut reader = source(relative, entry)?;
+                let mut file = File::create_new(&path).op(Op::Create, &path)?;
+                io::copy(&mut reader, &mut file).op(Op::Write, &path)?;
+                verify(&path, manifest.algorithm, entry)?;
+                if let Some(modified) = entry.modified {
+                    file.set_modified(modified).op(Op::SetAttributes, &path)?;
+                }
+                set_mode(&path, entry.mode)?;
+            }
+            // Created once every directory exists, which a link to a directory needs on Windows.
+            FileTypes::Symlink => symlinks.push((path, entry)),
+            FileTypes::Socket => {}
+        }
+    }
+
+    for (path, entry) in symlinks {
+        if let Some(target) = &entry.target {
+            create_symlink(target, &path)?;
+        }
+    }
+    // Children first, so a read-only directory can still be filled.
+    for (path, entry) in directories.into_iter().rev() {
+        set_mode(&path, entry.mode)?;
+    }
+    Ok(())
+}
+
+/// Returns true if relative only consists of normal components, so that joining it to a
+/// directory stays inside it.
+fn is_contained(relative: &Path) -> bool {
+    relative.components().next().is_some()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn verify(path: &Path, algorithm: Option<Algorithm>, entry: &ManifestEntry) -> Result<()> {
+    let (Some(algorithm), Some(expected)) = (algorithm, &entry.digest) else {
+        return Ok(());
+    };
+    let actual = checksum::hash_file(path, algorithm)?;
+    if &actual != expected {
+        return Err(FileyError(anyhow::anyhow!(
+            "'{}' doesn't match its digest: expected {}, got {}",
+            path.display(),
+            expected,
+            actual
+        )));
+    }
+    Ok(())
+}
+
+fn is_modified(old: &ManifestEntry, new: &ManifestEntry) -> bool {
+    if old.file_type != new.file_type {
+        return true;
+    }
+    if old.file_type == FileTypes::Directory {
+        return false;
+    }
+    if old.file_type == FileTypes::Symlink {
+        return old.target != new.target;
+    }
+    if old.size != new.size {
+        return true;
+    }
+    match (&old.digest, &new.digest) {
+        (Some(old), Some(new)) => old != new,
+        _ => old.modified != new.modified,
+    }
+}
+
+fn scan(
+    path: &Path,
+    relative: &Path,
+    options: &SnapshotOptions,
+    ancestors: &mut Vec<PathBuf>,
+    entries: &mut BTreeMap<PathBuf, ManifestEntry>,
+) -> Result<()> {
+    let symlinks = options.batch.symlinks;
+    // Following links, a link to a directory containing it would be scanned endlessly.
+    if symlinks == SymlinkBehavior::Follow {
+        let canonical = canonicalize(path).op(Op::Canonicalize, path)?;
+        if ancestors.contains(&canonical) {
+            return Err(FileyError(anyhow::anyhow!(
+                "'{}' leads back to a directory containing it",
+                path.display()
+            )));
+        }
+        ancestors.push(canonical);
+    }
+
+    for entry in read_dir(path).op(Op::ReadDir, path)? {
+        let entry = entry.op(Op::ReadDir, path)?;
+        let source = entry.path();
+        let relative = relative.join(entry.file_name());
+        let mut metadata = symlink_metadata(&source).op(Op::Metadata, &source)?;
+        if metadata.is_symlink() {
+            match symlinks {
+                SymlinkBehavior::Preserve => {}
+                SymlinkBehavior::Skip => continue,
+                SymlinkBehavior::Follow => {
+                    metadata = std::fs::metadata(&source).op(Op::Metadata, &source)?
+                }
+            }
+        }
+        if !options.filter.matches(&relative, &metadata) {
+            continue;
+        }
+        let file_type = FileTypes::from_metadata(&metadata);
+        let target = match file_type {
+            FileTypes::Symlink => Some(read_link(&source).op(Op::ReadLink, &source)?),
+            _ => None,
+        };
+        let modified = if options.omit_modified {
+            None
+        } else {
+            metadata.modified().ok()
+        };
+        entries.insert(
+            relative.clone(),
+            ManifestEntry {
+                file_type,
+                size: match file_type {
+                    FileTypes::Directory => 0,
+                    _ => metadata.len(),
+                },
+                mode: mode(&metadata),
+                target,
+                modified,
+                digest: None,
+            },
+        );
+        if file_type == FileTypes::Directory {
+            scan(&source, &relative, options, ancestors, entries)?;
+        }
+    }
+    if symlinks == SymlinkBehavior::Follow {
+        ancestors.pop();
+    }
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn mode(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(target_family = "unix"))]
+fn mode(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(target_family = "unix")]
+fn set_mode(path: &Path, mode: Option<u32>) -> Result<()> {
+    use std::{
+        fs::{set_permissions, Permissions},
+        os::unix::fs::PermissionsExt,
+    };
+
+    match mode {
+        Some(mode) => {
+            set_permissions(path, Permissions::from_mode(mode)).op(Op::SetPermissions, path)
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(target_family = "unix"))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn create_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link).op2(Op::Symlink, link, target)
+}
+
+#[cfg(target_family = "windows")]
+fn create_symlink(target: &Path, link: &Path) -> Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+
+    let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+    let result = if resolved.is_dir() {
+        symlink_dir(target, link)
+    } else {
+        symlink_file(target, link)
+    };
+    result.op2(Op::Symlink, link, target)
+}
+
+#[cfg(not(any(target_family = "unix", target_family = "windows")))]
+fn create_symlink(target: &Path, link: &Path) -> Result<()> {
+    Err(Op::Symlink.error2(link, target, io::Error::from(io::ErrorKind::Unsupported)))
+}
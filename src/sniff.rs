@@ -0,0 +1,184 @@
+//! File format detection by magic bytes.
+//!
+//! # Examples
+//! ```
+//! # use filey::{sniff::{Registry, Signature}, Filey};
+//! # use std::error::Error;
+//! #
+//! # fn detect() -> Result<(), Box<Error>> {
+//! let mut registry = Registry::default();
+//! registry.register(Signature::at(4, b"ACME"), "acme-archive");
+//! let format = Filey::new("backup.bin").detect_format_with(&registry)?;
+//! assert_eq!(format.as_deref(), Some("acme-archive"));
+//! # Ok(())
+//! # }
+//! # fn main() {
+//! # detect().unwrap();
+//! # }
+//! ```
+
+use crate::{op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Read, path::Path};
+
+/// A sequence of magic bytes expected at an offset from the start of a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Signature {
+    offset: usize,
+    magic: Vec<u8>,
+}
+
+impl Signature {
+    /// Creates a signature expected at the start of a file.
+    pub fn new<B: AsRef<[u8]>>(magic: B) -> Self {
+        Self::at(0, magic)
+    }
+
+    /// Creates a signature expected at offset.
+    pub fn at<B: AsRef<[u8]>>(offset: usize, magic: B) -> Self {
+        Self {
+            offset,
+            magic: magic.as_ref().to_vec(),
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn magic(&self) -> &[u8] {
+        &self.magic
+    }
+
+    /// Returns true if bytes contain the magic at the offset.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes
+            .get(self.offset..self.end())
+            .is_some_and(|head| head == self.magic)
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.magic.len()
+    }
+}
+
+impl From<&[u8]> for Signature {
+    fn from(magic: &[u8]) -> Self {
+        Self::new(magic)
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Signature {
+    fn from(magic: &[u8; N]) -> Self {
+        Self::new(magic)
+    }
+}
+
+impl From<&str> for Signature {
+    fn from(magic: &str) -> Self {
+        Self::new(magic)
+    }
+}
+
+const BUILTIN: &[(usize, &[u8], &str)] = &[
+    (0, b"\x7fELF", "elf"),
+    (0, b"MZ", "pe"),
+    (0, b"\xfe\xed\xfa\xce", "mach-o"),
+    (0, b"\xfe\xed\xfa\xcf", "mach-o"),
+    (0, b"\xce\xfa\xed\xfe", "mach-o"),
+    (0, b"\xcf\xfa\xed\xfe", "mach-o"),
+    (0, b"\0asm", "wasm"),
+    (0, b"#!", "script"),
+    (0, b"\x1f\x8b", "gzip"),
+    (0, b"BZh", "bzip2"),
+    (0, b"\xfd7zXZ\0", "xz"),
+    (0, b"\x28\xb5\x2f\xfd", "zstd"),
+    (0, b"PK\x03\x04", "zip"),
+    (0, b"PK\x05\x06", "zip"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7z"),
+    (257, b"ustar", "tar"),
+    (0, b"%PDF-", "pdf"),
+    (0, b"\x89PNG\r\n\x1a\n", "png"),
+    (0, b"\xff\xd8\xff", "jpeg"),
+    (0, b"GIF87a", "gif"),
+    (0, b"GIF89a", "gif"),
+    (0, b"SQLite format 3\0", "sqlite"),
+];
+
+/// A table of signatures and the labels of the formats they identify.
+///
+/// [`Registry::default`] contains the built-in formats, [`Registry::new`] is empty.
+/// When several signatures match, the longest wins, and among equally long ones the one
+/// registered last, so applications can override built-in labels.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Registry {
+    entries: Vec<(Signature, String)>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        for (offset, magic, label) in BUILTIN {
+            registry.register(Signature::at(*offset, magic), *label);
+        }
+        registry
+    }
+}
+
+impl Registry {
+    /// Creates a registry without any signature.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a signature identifying the format label.
+    pub fn register<S: Into<Signature>, L: Into<String>>(
+        &mut self,
+        signature: S,
+        label: L,
+    ) -> &mut Self {
+        self.entries.push((signature.into(), label.into()));
+        self
+    }
+
+    /// Returns the registered signatures and labels in registration order.
+    pub fn entries(&self) -> &Vec<(Signature, String)> {
+        &self.entries
+    }
+
+    /// Returns the label of the format of bytes, the first bytes of a file.
+    pub fn detect(&self, bytes: &[u8]) -> Option<&str> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, (signature, _))| signature.matches(bytes))
+            .max_by_key(|(i, (signature, _))| (signature.magic.len(), *i))
+            .map(|(_, (_, label))| label.as_str())
+    }
+
+    /// Reads as many bytes of path as the signatures need and returns the label of its format.
+    pub fn detect_path<P: AsRef<Path>>(&self, path: P) -> Result<Option<String>> {
+        let path = path.as_ref();
+        let len = self
+            .entries
+            .iter()
+            .map(|(signature, _)| signature.end())
+            .max()
+            .unwrap_or(0);
+        let file = File::open(path).op(Op::Open, path)?;
+        let mut head = Vec::with_capacity(len);
+        file.take(len as u64)
+            .read_to_end(&mut head)
+            .op(Op::Read, path)?;
+        Ok(self.detect(&head).map(|label| label.to_string()))
+    }
+}
+
+/// Detects the format of path with the built-in signatures.
+pub fn detect_format<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    Registry::default().detect_path(path)
+}
@@ -0,0 +1,65 @@
+#[cfg(any(target_family = "unix", target_family = "windows"))]
+use crate::op::OpContext;
+use crate::{Error::InsufficientSpace, Op, Result};
+use std::path::Path;
+
+/// Returns the number of bytes available to the current user on the filesystem containing path.
+#[cfg(target_family = "unix")]
+pub(crate) fn available<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let path = path.as_ref();
+    let c_path = crate::sys::to_cstring(path)?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    crate::sys::check(unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) })
+        .op(Op::FreeSpace, path)?;
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Returns the number of bytes available to the current user on the volume containing path.
+#[cfg(target_family = "windows")]
+pub(crate) fn available<P: AsRef<Path>>(path: P) -> Result<u64> {
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path = path.as_ref();
+    let wide = crate::win::to_wide(path)?;
+    let mut available = 0;
+    crate::win::check(unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    })
+    .op(Op::FreeSpace, path)?;
+    Ok(available)
+}
+
+#[cfg(not(any(target_family = "unix", target_family = "windows")))]
+pub(crate) fn available<P: AsRef<Path>>(path: P) -> Result<u64> {
+    Err(Op::FreeSpace.error(path, std::io::ErrorKind::Unsupported.into()))
+}
+
+/// Fails with [`crate::Error::InsufficientSpace`] unless the filesystem that destination is (or
+/// will be) created on has at least needed bytes available.
+/// destination doesn't need to exist; its nearest existing ancestor is checked.
+pub(crate) fn ensure<P: AsRef<Path>>(destination: P, needed: u64) -> Result<()> {
+    let destination = destination.as_ref();
+    let available = available(nearest_existing(destination))?;
+    if available < needed {
+        return Err(InsufficientSpace {
+            path: destination.to_string_lossy().to_string(),
+            needed,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Returns path or its nearest ancestor which exists. A relative path falls back to the current
+/// directory.
+pub(crate) fn nearest_existing(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.exists())
+        .unwrap_or(Path::new("."))
+}
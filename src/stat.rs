@@ -0,0 +1,148 @@
+use crate::{op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{metadata, symlink_metadata, Metadata},
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+/// A point in time as seconds and nanoseconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Timespec {
+    sec: i64,
+    nsec: i64,
+}
+
+impl Timespec {
+    pub fn new(sec: i64, nsec: i64) -> Self {
+        Self { sec, nsec }
+    }
+
+    pub fn sec(&self) -> i64 {
+        self.sec
+    }
+
+    pub fn nsec(&self) -> i64 {
+        self.nsec
+    }
+}
+
+/// The information returned by stat(2).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stat {
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    nlink: u64,
+    uid: u32,
+    gid: u32,
+    rdev: u64,
+    size: u64,
+    blksize: u64,
+    blocks: u64,
+    atime: Timespec,
+    mtime: Timespec,
+    ctime: Timespec,
+}
+
+impl From<&Metadata> for Stat {
+    fn from(metadata: &Metadata) -> Self {
+        Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            mode: metadata.mode(),
+            nlink: metadata.nlink(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            rdev: metadata.rdev(),
+            size: metadata.size(),
+            blksize: metadata.blksize(),
+            blocks: metadata.blocks(),
+            atime: Timespec::new(metadata.atime(), metadata.atime_nsec()),
+            mtime: Timespec::new(metadata.mtime(), metadata.mtime_nsec()),
+            ctime: Timespec::new(metadata.ctime(), metadata.ctime_nsec()),
+        }
+    }
+}
+
+impl Stat {
+    /// Queries the information of a file, following symbolic links.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata = metadata(path).op(Op::Metadata, path)?;
+        Ok(Self::from(&metadata))
+    }
+
+    /// Queries the information of a file without following symbolic links.
+    pub fn from_path_no_follow<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata = symlink_metadata(path).op(Op::Metadata, path)?;
+        Ok(Self::from(&metadata))
+    }
+
+    /// Returns the ID of the device containing the file.
+    pub fn dev(&self) -> u64 {
+        self.dev
+    }
+
+    /// Returns the inode number.
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// Returns the file type and the permission bits.
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Returns the number of hard links.
+    pub fn nlink(&self) -> u64 {
+        self.nlink
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns the device ID if the file is a special file.
+    pub fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    /// Returns the total size in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the block size for filesystem I/O.
+    pub fn blksize(&self) -> u64 {
+        self.blksize
+    }
+
+    /// Returns the number of 512-byte blocks allocated.
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Returns the time of last access.
+    pub fn atime(&self) -> Timespec {
+        self.atime
+    }
+
+    /// Returns the time of last modification.
+    pub fn mtime(&self) -> Timespec {
+        self.mtime
+    }
+
+    /// Returns the time of last status change.
+    pub fn ctime(&self) -> Timespec {
+        self.ctime
+    }
+}
@@ -0,0 +1,47 @@
+use crate::units::UnitOfInfo;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+/// How much data an operation such as [`Filey::copy_with_stats`](crate::Filey::copy_with_stats)
+/// processed and how long it took.
+///
+/// It is displayed like `3.2 GiB in 41.0 s (80.0 MiB/s)`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IoStats {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// The number of files read or written.
+    pub files: u64,
+    pub elapsed: Duration,
+}
+
+impl IoStats {
+    /// Returns the bytes processed: the larger of the bytes read and written.
+    pub fn bytes(&self) -> u64 {
+        self.bytes_read.max(self.bytes_written)
+    }
+
+    /// Returns the bytes processed per second, or 0 if no time has elapsed.
+    pub fn throughput(&self) -> u64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            (self.bytes() as f64 / secs) as u64
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for IoStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} in {:.1} s ({}/s)",
+            UnitOfInfo::format(self.bytes()),
+            self.elapsed.as_secs_f64(),
+            UnitOfInfo::format(self.throughput())
+        )
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{win, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    io, mem,
+    os::windows::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    ptr,
+};
+use windows_sys::Win32::{
+    Foundation::{ERROR_HANDLE_EOF, HANDLE, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard,
+        WIN32_FIND_STREAM_DATA,
+    },
+};
+
+/// An NTFS alternate data stream, such as `Zone.Identifier`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AlternateStream {
+    name: String,
+    size: u64,
+}
+
+impl AlternateStream {
+    /// Returns the name of the stream without the leading colon and the `:$DATA` type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the size of the stream in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Returns the path which opens the stream name of path, like `file.txt:Zone.Identifier`.
+pub(crate) fn stream_path<P: AsRef<Path>>(path: P, name: &str) -> PathBuf {
+    let mut path = path.as_ref().as_os_str().to_os_string();
+    path.push(":");
+    path.push(name);
+    PathBuf::from(path)
+}
+
+struct FindHandle(HANDLE);
+
+impl Drop for FindHandle {
+    fn drop(&mut self) {
+        unsafe { FindClose(self.0) };
+    }
+}
+
+/// Lists the alternate data streams of path, excluding the unnamed main stream.
+pub(crate) fn streams<P: AsRef<Path>>(path: P) -> Result<Vec<AlternateStream>> {
+    let path = path.as_ref();
+    let wide = win::to_wide(path)?;
+    // All-zero is a valid value of this plain C struct.
+    let mut data: WIN32_FIND_STREAM_DATA = unsafe { mem::zeroed() };
+    let data_ptr = ptr::addr_of_mut!(data).cast();
+    let handle = unsafe { FindFirstStreamW(wide.as_ptr(), FindStreamInfoStandard, data_ptr, 0) };
+    let mut streams = Vec::new();
+    if handle == INVALID_HANDLE_VALUE {
+        let e = io::Error::last_os_error();
+        // Directories usually have no stream at all.
+        if e.raw_os_error() == Some(ERROR_HANDLE_EOF as i32) {
+            return Ok(streams);
+        }
+        return Err(Op::ReadDir.error(path, e));
+    }
+    let handle = FindHandle(handle);
+
+    loop {
+        let len = data
+            .cStreamName
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(data.cStreamName.len());
+        let raw = OsString::from_wide(&data.cStreamName[..len])
+            .to_string_lossy()
+            .to_string();
+        // Stream names look like ":name:$DATA"; the main stream is "::$DATA".
+        let name = raw.strip_prefix(':').unwrap_or(&raw);
+        let name = name.strip_suffix(":$DATA").unwrap_or(name);
+        if !name.is_empty() {
+            streams.push(AlternateStream {
+                name: name.to_string(),
+                size: data.StreamSize as u64,
+            });
+        }
+
+        if unsafe { FindNextStreamW(handle.0, data_ptr) } == 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() == Some(ERROR_HANDLE_EOF as i32) {
+                break;
+            }
+            return Err(Op::ReadDir.error(path, e));
+        }
+    }
+    Ok(streams)
+}
@@ -0,0 +1,106 @@
+use crate::{op::OpContext, Error::FileyError, Op, Result};
+use std::{
+    ffi::CString,
+    io,
+    os::{
+        fd::{FromRawFd, OwnedFd, RawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::Path,
+};
+
+/// Converts a path into a nul-terminated string for libc.
+pub(crate) fn to_cstring<P: AsRef<Path>>(path: P) -> Result<CString> {
+    CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|e| e.into())
+        .map_err(FileyError)
+}
+
+/// Maps the return value of a libc call into io::Result, reading errno on failure.
+pub(crate) fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Creates a FIFO special file.
+pub(crate) fn mkfifo<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
+    let path = path.as_ref();
+    let c_path = to_cstring(path)?;
+    check(unsafe { libc::mkfifo(c_path.as_ptr(), mode as libc::mode_t) }).op(Op::Create, path)
+}
+
+/// Creates a filesystem node (file, device special file, or named pipe).
+pub(crate) fn mknod<P: AsRef<Path>>(path: P, mode: u32, dev: u64) -> Result<()> {
+    let path = path.as_ref();
+    let c_path = to_cstring(path)?;
+    check(unsafe { libc::mknod(c_path.as_ptr(), mode as libc::mode_t, dev as libc::dev_t) })
+        .op(Op::Create, path)
+}
+
+/// Rejects absolute paths, which would make the *at() family ignore the directory descriptor.
+fn relative_cstring<P: AsRef<Path>>(path: P) -> Result<CString> {
+    let path = path.as_ref();
+    if path.is_absolute() {
+        return Err(FileyError(anyhow::anyhow!(
+            "'{}' must be relative to the directory handle",
+            path.display()
+        )));
+    }
+    to_cstring(path)
+}
+
+/// Opens path relative to the directory descriptor dirfd, which was opened from dir.
+/// Errors report dir joined with path.
+pub(crate) fn openat<P: AsRef<Path>>(
+    dirfd: RawFd,
+    dir: &Path,
+    path: P,
+    flags: libc::c_int,
+    mode: u32,
+) -> Result<OwnedFd> {
+    let path = path.as_ref();
+    let c_path = relative_cstring(path)?;
+    let fd = unsafe { libc::openat(dirfd, c_path.as_ptr(), flags, mode as libc::c_uint) };
+    check(fd).op(Op::Open, dir.join(path))?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Removes path relative to the directory descriptor dirfd, which was opened from dir.
+pub(crate) fn unlinkat<P: AsRef<Path>>(
+    dirfd: RawFd,
+    dir: &Path,
+    path: P,
+    flags: libc::c_int,
+) -> Result<()> {
+    let path = path.as_ref();
+    let c_path = relative_cstring(path)?;
+    check(unsafe { libc::unlinkat(dirfd, c_path.as_ptr(), flags) }).op(Op::Remove, dir.join(path))
+}
+
+/// Creates the directory path relative to the directory descriptor dirfd, which was opened
+/// from dir.
+pub(crate) fn mkdirat<P: AsRef<Path>>(dirfd: RawFd, dir: &Path, path: P, mode: u32) -> Result<()> {
+    let path = path.as_ref();
+    let c_path = relative_cstring(path)?;
+    check(unsafe { libc::mkdirat(dirfd, c_path.as_ptr(), mode as libc::mode_t) })
+        .op(Op::CreateDir, dir.join(path))
+}
+
+/// Renames from relative to the directory descriptor from_dirfd to to relative to to_dirfd,
+/// which were opened from from_dir and to_dir.
+pub(crate) fn renameat<P: AsRef<Path>, Q: AsRef<Path>>(
+    from_dirfd: RawFd,
+    from_dir: &Path,
+    from: P,
+    to_dirfd: RawFd,
+    to_dir: &Path,
+    to: Q,
+) -> Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    let (c_from, c_to) = (relative_cstring(from)?, relative_cstring(to)?);
+    check(unsafe { libc::renameat(from_dirfd, c_from.as_ptr(), to_dirfd, c_to.as_ptr()) })
+        .map_err(|e| Op::Rename.error2(from_dir.join(from), to_dir.join(to), e))
+}
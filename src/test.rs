@@ -21,6 +21,18 @@ mod tests {
         }
     }
 
+    fn init_at(test_dir: &str) {
+        if !Path::new(test_dir).exists() {
+            create_dir_all(test_dir).unwrap();
+        }
+    }
+
+    fn quit_at(test_dir: &str) {
+        if Path::new(test_dir).exists() {
+            remove_dir_all(test_dir).unwrap();
+        }
+    }
+
     #[test]
     fn test_file_name() {
         assert_eq!(
@@ -49,7 +61,7 @@ mod tests {
     fn test_create_files() {
         init();
         let file_a = Path::new("test_dir/file_a");
-        Filey::new(&file_a).create_file().unwrap();
+        Filey::new(file_a).create_file().unwrap();
         assert!(file_a.exists() && file_a.is_file());
         let file_b = Path::new("test_dir/file_b");
         let file_c = Path::new("test_dir/file_c");
@@ -63,7 +75,7 @@ mod tests {
     fn test_create_directories() {
         init();
         let dir_a = Path::new("test_dir/dir_a");
-        Filey::new(&dir_a).create_dir().unwrap();
+        Filey::new(dir_a).create_dir().unwrap();
         assert!(dir_a.exists() && dir_a.is_dir());
         let dir_b = Path::new("test_dir/dir_b");
         let dir_c = Path::new("test_dir/dir_c");
@@ -79,7 +91,7 @@ mod tests {
         let file_a = "test_dir/file_a";
         File::create(file_a).unwrap();
         let file_a_symlink = Path::new("test_dir/file_a_symlink");
-        Filey::new(file_a).symlink(&file_a_symlink).unwrap();
+        Filey::new(file_a).symlink(file_a_symlink).unwrap();
         assert!(file_a_symlink.is_symlink());
         quit();
     }
@@ -90,7 +102,7 @@ mod tests {
         let file_a = "test_dir/file_a";
         File::create(file_a).unwrap();
         let file_a_hard_link = Path::new("test_dir/file_a_hard_link");
-        Filey::new(file_a).hard_link(&file_a_hard_link).unwrap();
+        Filey::new(file_a).hard_link(file_a_hard_link).unwrap();
         assert!(file_a_hard_link.exists());
         quit();
     }
@@ -156,7 +168,7 @@ mod tests {
         let file_a = "test_dir/file_a";
         File::create(file_a).unwrap();
         let copied_file_a = Path::new("test_dir/copied_file_a");
-        Filey::new(file_a).copy(&copied_file_a).unwrap();
+        Filey::new(file_a).copy(copied_file_a).unwrap();
         assert!(copied_file_a.exists());
         quit();
     }
@@ -174,12 +186,12 @@ mod tests {
         }
         for i in &files {
             let path = Path::new(i);
-            Filey::new(&path).remove().unwrap();
+            Filey::new(path).remove().unwrap();
             assert!(!path.exists());
         }
         for i in &dirs {
             let path = Path::new(i);
-            Filey::new(&path).remove().unwrap();
+            Filey::new(path).remove().unwrap();
             assert!(!path.exists());
         }
         quit();
@@ -191,12 +203,3993 @@ mod tests {
         let mut file_a = Filey::new("test_dir/file_a");
         file_a.create_file().unwrap();
         let renamed_file_a = Path::new("test_dir/renamed_file_a");
-        file_a.move_to(&renamed_file_a).unwrap();
+        file_a.move_to(renamed_file_a).unwrap();
         assert!(renamed_file_a.exists());
         let file_a_in_dir_a = Path::new("test_dir/dir_a/renamed_file_a");
         create_dir_all("test_dir/dir_a").unwrap();
-        file_a.move_to(&file_a_in_dir_a).unwrap();
+        file_a.move_to(file_a_in_dir_a).unwrap();
         assert!(file_a_in_dir_a.exists());
         quit();
     }
+
+    #[test]
+    fn test_owner() {
+        init_at("test_dir_owner");
+        let file_a = "test_dir_owner/file_a";
+        File::create(file_a).unwrap();
+        let owner = Filey::new(file_a).owner().unwrap();
+        assert_eq!(owner.uid(), unsafe { libc::getuid() });
+        assert_eq!(owner.gid(), unsafe { libc::getgid() });
+        // The names are the ones id(1) reports for the current user.
+        let id = |flag: &str| {
+            let output = std::process::Command::new("id").arg(flag).output().unwrap();
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        assert_eq!(owner.user(), Some(id("-un").as_str()));
+        assert_eq!(owner.group(), Some(id("-gn").as_str()));
+        assert_eq!(owner.to_string(), format!("{}:{}", id("-un"), id("-gn")));
+        assert_eq!(Filey::new(file_a).owner_name().unwrap(), Some(id("-un")));
+        assert_eq!(Filey::new(file_a).group_name().unwrap(), Some(id("-gn")));
+
+        // Ids without a name are shown as numbers. Changing owners needs root.
+        if unsafe { libc::geteuid() } == 0 {
+            let path = std::ffi::CString::new(file_a).unwrap();
+            assert_eq!(unsafe { libc::chown(path.as_ptr(), 4000000, 4000000) }, 0);
+            let owner = Filey::new(file_a).owner().unwrap();
+            assert_eq!(owner.user(), None);
+            assert_eq!(owner.to_string(), "4000000:4000000");
+            assert_eq!(Filey::new(file_a).owner_name().unwrap(), None);
+        }
+        quit_at("test_dir_owner");
+    }
+
+    #[test]
+    fn test_stat() {
+        init_at("test_dir_stat");
+        let file_a = "test_dir_stat/file_a";
+        std::fs::write(file_a, "hello").unwrap();
+        let file_a_symlink = "test_dir_stat/file_a_symlink";
+        symlink("file_a", file_a_symlink).unwrap();
+        let stat = Filey::new(file_a_symlink).stat().unwrap();
+        let lstat = Filey::new(file_a_symlink).lstat().unwrap();
+        assert_eq!(stat.size(), 5);
+        assert_eq!(stat.mode() & 0o170000, 0o100000);
+        assert_eq!(lstat.mode() & 0o170000, 0o120000);
+        assert_eq!(stat.ino(), Filey::new(file_a).lstat().unwrap().ino());
+        assert_ne!(stat.ino(), lstat.ino());
+        quit_at("test_dir_stat");
+    }
+
+    #[test]
+    fn test_disk_usage() {
+        init_at("test_dir_disk_usage/dir_a");
+        let sparse = File::create("test_dir_disk_usage/sparse").unwrap();
+        sparse.set_len(1024 * 1024).unwrap();
+        std::fs::write("test_dir_disk_usage/dir_a/file_a", "hello").unwrap();
+        let usage = Filey::new("test_dir_disk_usage/sparse")
+            .disk_usage()
+            .unwrap();
+        assert_eq!(usage.apparent(), 1024 * 1024);
+        assert!(usage.on_disk() < usage.apparent());
+        let total = Filey::new("test_dir_disk_usage").disk_usage().unwrap();
+        assert!(total.apparent() >= 1024 * 1024 + 5);
+        quit_at("test_dir_disk_usage");
+    }
+
+    #[test]
+    fn test_find_hard_links() {
+        init_at("test_dir_hard_links/dir_a");
+        let file_a = "test_dir_hard_links/file_a";
+        File::create(file_a).unwrap();
+        std::fs::hard_link(file_a, "test_dir_hard_links/dir_a/file_b").unwrap();
+        File::create("test_dir_hard_links/dir_a/file_c").unwrap();
+        let file = Filey::new(file_a);
+        assert_eq!(file.hard_link_count().unwrap(), 2);
+        assert_eq!(
+            file.find_hard_links("test_dir_hard_links").unwrap(),
+            vec![Filey::new("test_dir_hard_links/dir_a/file_b")]
+        );
+        quit_at("test_dir_hard_links");
+    }
+
+    #[test]
+    fn test_create_fifo() {
+        use std::os::unix::fs::FileTypeExt;
+
+        init_at("test_dir_fifo");
+        let fifo = Filey::new("test_dir_fifo/fifo").create_fifo(0o600).unwrap();
+        let file_type = std::fs::metadata(&fifo).unwrap().file_type();
+        assert!(file_type.is_fifo());
+        assert!(fifo.create_fifo(0o600).is_err());
+        quit_at("test_dir_fifo");
+    }
+
+    #[test]
+    fn test_remove_stale_socket() {
+        use std::os::unix::net::UnixListener;
+
+        init_at("test_dir_socket");
+        let socket = Filey::new("test_dir_socket/app.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        assert_eq!(socket.file_type(), Some(FileTypes::Socket));
+        assert!(!socket.remove_stale_socket().unwrap());
+        assert!(socket.exists());
+        drop(listener);
+        assert!(socket.remove_stale_socket().unwrap());
+        assert!(!socket.exists());
+        File::create("test_dir_socket/file_a").unwrap();
+        assert!(Filey::new("test_dir_socket/file_a")
+            .remove_stale_socket()
+            .is_err());
+        quit_at("test_dir_socket");
+    }
+
+    #[test]
+    fn test_from_fd() {
+        use std::{io::Read, os::fd::OwnedFd};
+
+        init_at("test_dir_from_fd");
+        std::fs::write("test_dir_from_fd/file_a", "hello").unwrap();
+        let handle = Filey::new("test_dir_from_fd/file_a").open().unwrap();
+        let mut handle = Filey::from_fd(OwnedFd::from(handle)).unwrap();
+        assert_eq!(
+            handle.path(),
+            &Path::new("test_dir_from_fd/file_a").canonicalize().unwrap()
+        );
+        let mut s = String::new();
+        handle.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "hello");
+        quit_at("test_dir_from_fd");
+    }
+
+    #[test]
+    fn test_reopen_and_open_at() {
+        use std::io::{Read, Write};
+
+        init_at("test_dir_open_at");
+        let dir = Filey::new("test_dir_open_at").open().unwrap();
+        let mut file_a = dir.create_at("file_a", 0o644).unwrap();
+        file_a.write_all(b"hello").unwrap();
+        let mut s = String::new();
+        dir.open_at("file_a")
+            .unwrap()
+            .reopen()
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "hello");
+        assert!(dir.open_at("/etc/passwd").is_err());
+        symlink("file_a", "test_dir_open_at/file_a_symlink").unwrap();
+        assert!(dir.open_at("file_a_symlink").is_err());
+        dir.remove_at("file_a").unwrap();
+        assert!(!Path::new("test_dir_open_at/file_a").exists());
+        quit_at("test_dir_open_at");
+    }
+
+    #[test]
+    fn test_lock() {
+        use crate::{Error, LockMode};
+        use std::time::Duration;
+
+        init_at("test_dir_lock");
+        let file_a = Filey::new("test_dir_lock/file_a").create_file().unwrap();
+        let a = file_a.open().unwrap();
+        let b = file_a.open().unwrap();
+        a.lock(LockMode::Shared).unwrap();
+        b.try_lock(LockMode::Shared).unwrap();
+        b.unlock().unwrap();
+        a.unlock().unwrap();
+        a.lock(LockMode::Exclusive).unwrap();
+        assert!(matches!(
+            b.try_lock(LockMode::Shared),
+            Err(Error::WouldBlock { .. })
+        ));
+        assert!(matches!(
+            b.try_lock_for(LockMode::Exclusive, Duration::from_millis(20)),
+            Err(Error::Timeout { .. })
+        ));
+        a.unlock().unwrap();
+        b.try_lock_for(LockMode::Exclusive, Duration::from_millis(20))
+            .unwrap();
+        quit_at("test_dir_lock");
+    }
+
+    #[test]
+    fn test_lock_range() {
+        use crate::{Error, LockMode};
+        use std::{fs::OpenOptions, os::fd::OwnedFd};
+
+        init_at("test_dir_lock_range");
+        let open = || {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open("test_dir_lock_range/file_a")
+                .unwrap();
+            Filey::from_fd(OwnedFd::from(file)).unwrap()
+        };
+        let a = open();
+        let b = open();
+        let first = a.lock_range(LockMode::Exclusive, 0, 100).unwrap();
+        b.try_lock_range(LockMode::Exclusive, 100, 100).unwrap();
+        assert!(matches!(
+            b.try_lock_range(LockMode::Shared, 50, 10),
+            Err(Error::WouldBlock { .. })
+        ));
+        drop(first);
+        b.try_lock_range(LockMode::Shared, 50, 10)
+            .unwrap()
+            .unlock()
+            .unwrap();
+        quit_at("test_dir_lock_range");
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        init_at("test_dir_write_atomic");
+        let file_a = Filey::new("test_dir_write_atomic/file_a");
+        file_a.write_atomic("hello").unwrap();
+        file_a.write_atomic("world").unwrap();
+        assert_eq!(std::fs::read_to_string(&file_a).unwrap(), "world");
+        assert_eq!(
+            std::fs::read_dir("test_dir_write_atomic").unwrap().count(),
+            1
+        );
+        quit_at("test_dir_write_atomic");
+    }
+
+    #[test]
+    fn test_pidfile() {
+        use crate::{pidfile, Error};
+
+        init_at("test_dir_pidfile");
+        let path = "test_dir_pidfile/app.pid";
+        std::fs::write(path, "4194304\n").unwrap();
+        let pid_file = pidfile::acquire(path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        assert!(matches!(
+            pidfile::acquire(path),
+            Err(Error::AlreadyRunning { pid: Some(_), .. })
+        ));
+        drop(pid_file);
+        assert!(!Path::new(path).exists());
+
+        // Of processes racing for the file, only one may get it.
+        for _ in 0..20 {
+            let barrier = std::sync::Barrier::new(8);
+            let acquired = std::thread::scope(|scope| {
+                let threads = (0..8)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            barrier.wait();
+                            pidfile::acquire(path)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                threads
+                    .into_iter()
+                    .map(|thread| thread.join().unwrap())
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(acquired.iter().filter(|result| result.is_ok()).count(), 1);
+        }
+        quit_at("test_dir_pidfile");
+    }
+
+    #[test]
+    fn test_lockfile() {
+        use crate::{lockfile::LockFile, Error};
+        use std::time::Duration;
+
+        init_at("test_dir_lockfile");
+        let path = "test_dir_lockfile/music.db";
+        let lock = LockFile::acquire(path, None).unwrap();
+        assert_eq!(lock.path(), Path::new("test_dir_lockfile/music.db.lock"));
+        let holder = LockFile::holder(path).unwrap().unwrap();
+        assert_eq!(holder.pid(), std::process::id());
+        assert!(matches!(
+            LockFile::acquire(path, Some(Duration::from_secs(60))),
+            Err(Error::WouldBlock { .. })
+        ));
+        drop(lock);
+        assert_eq!(LockFile::holder(path).unwrap(), None);
+
+        std::fs::write(
+            "test_dir_lockfile/music.db.lock",
+            "pid=1\nhostname=nas\ntimestamp=0\n",
+        )
+        .unwrap();
+        let stolen = LockFile::acquire(path, Some(Duration::from_secs(60))).unwrap();
+        assert_eq!(
+            LockFile::holder(path).unwrap().unwrap().pid(),
+            std::process::id()
+        );
+        drop(stolen);
+        quit_at("test_dir_lockfile");
+    }
+
+    #[test]
+    fn test_seq() {
+        use crate::seq;
+        use std::thread;
+
+        init_at("test_dir_seq");
+        let path = "test_dir_seq/build_number";
+        assert_eq!(seq::current(path).unwrap(), 0);
+        assert_eq!(seq::next(path).unwrap(), 1);
+        let threads = (0..8)
+            .map(|_| thread::spawn(move || seq::next(path).unwrap()))
+            .collect::<Vec<_>>();
+        let mut numbers = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect::<Vec<_>>();
+        numbers.sort();
+        assert_eq!(numbers, (2..10).collect::<Vec<_>>());
+        assert_eq!(seq::current(path).unwrap(), 9);
+        quit_at("test_dir_seq");
+    }
+
+    #[cfg(all(feature = "json", feature = "toml", feature = "yaml", feature = "ron"))]
+    #[test]
+    fn test_config() {
+        use crate::ConfigFormat;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            theme: String,
+            font_size: u32,
+        }
+
+        init_at("test_dir_config");
+        let config = Config {
+            theme: "dark".to_string(),
+            font_size: 12,
+        };
+        for name in ["config.json", "config.toml", "config.yml", "config.ron"] {
+            let file = Filey::new(format!("test_dir_config/{}", name));
+            file.write_config(&config).unwrap();
+            assert_eq!(file.read_config::<Config>().unwrap(), config);
+        }
+        let toml = Filey::new("test_dir_config/apprc");
+        std::fs::write(&toml, "theme = \"light\"\nfont_size = 10\n").unwrap();
+        toml.write_config(&config).unwrap();
+        let contents = std::fs::read_to_string(&toml).unwrap();
+        assert_eq!(ConfigFormat::sniff(&contents), Some(ConfigFormat::Toml));
+        assert_eq!(toml.read_config::<Config>().unwrap(), config);
+        assert!(Filey::new("test_dir_config/new")
+            .write_config(&config)
+            .is_err());
+        quit_at("test_dir_config");
+    }
+
+    #[cfg(feature = "ini")]
+    #[test]
+    fn test_ini() {
+        init_at("test_dir_ini");
+        let file = Filey::new("test_dir_ini/config");
+        std::fs::write(
+            &file,
+            "root = true\n; comment\n[user]\n  name = Tom \nemail=tom@example.com\n\n[core]\n",
+        )
+        .unwrap();
+        let mut ini = file.read_ini().unwrap();
+        assert_eq!(ini[""]["root"], "true");
+        assert_eq!(ini["user"]["name"], "Tom");
+        assert_eq!(ini["user"]["email"], "tom@example.com");
+        assert!(ini["core"].is_empty());
+        ini.get_mut("core")
+            .unwrap()
+            .insert("editor".to_string(), "vim".to_string());
+        file.write_ini(&ini).unwrap();
+        assert_eq!(file.read_ini().unwrap(), ini);
+        std::fs::write(&file, "[user\n").unwrap();
+        assert!(file.read_ini().is_err());
+        quit_at("test_dir_ini");
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Sale {
+            item: String,
+            price: u64,
+        }
+
+        init_at("test_dir_csv");
+        let file = Filey::new("test_dir_csv/sales.csv");
+        let sales = vec![
+            Sale {
+                item: "apple".to_string(),
+                price: 120,
+            },
+            Sale {
+                item: "pear, large".to_string(),
+                price: 300,
+            },
+        ];
+        file.write_csv(&sales).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "item,price\napple,120\n\"pear, large\",300\n"
+        );
+        let read = file
+            .csv_records::<Sale>()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(read, sales);
+        std::fs::write(&file, "item,price\napple,free\n").unwrap();
+        assert!(file.csv_records::<Sale>().unwrap().next().unwrap().is_err());
+        quit_at("test_dir_csv");
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_ron() {
+        use std::collections::BTreeMap;
+
+        init_at("test_dir_ron");
+        let file = Filey::new("test_dir_ron/assets.ron");
+        let mut assets = BTreeMap::new();
+        assets.insert("player".to_string(), (32_u32, 48_u32));
+        file.write_ron(&assets).unwrap();
+        assert_eq!(
+            file.read_ron::<BTreeMap<String, (u32, u32)>>().unwrap(),
+            assets
+        );
+        quit_at("test_dir_ron");
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn test_bincode() {
+        use std::collections::HashMap;
+
+        init_at("test_dir_bincode");
+        let file = Filey::new("test_dir_bincode/index.bin");
+        let index = (0..100_u64)
+            .map(|i| (format!("file_{}", i), i * 1024))
+            .collect::<HashMap<_, _>>();
+        file.write_bincode(&index).unwrap();
+        assert_eq!(file.read_bincode::<HashMap<String, u64>>().unwrap(), index);
+        assert!(file.read_bincode::<Vec<String>>().is_err());
+        quit_at("test_dir_bincode");
+    }
+
+    #[test]
+    fn test_logfile() {
+        use crate::logfile::Appender;
+        use std::io::Write;
+
+        init_at("test_dir_logfile");
+        std::fs::write("test_dir_logfile/app.log", "old\n").unwrap();
+        let mut log = Appender::new("test_dir_logfile/app.log", 10, 2).unwrap();
+        for i in 0..4 {
+            writeln!(log, "line {}", i).unwrap();
+        }
+        let read = |name: &str| std::fs::read_to_string(format!("test_dir_logfile/{}", name));
+        assert_eq!(read("app.log").unwrap(), "line 3\n");
+        assert_eq!(read("app.log.1").unwrap(), "line 2\n");
+        assert_eq!(read("app.log.2").unwrap(), "line 1\n");
+        assert!(read("app.log.3").is_err());
+        quit_at("test_dir_logfile");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_logfile_gzip() {
+        use crate::logfile::Appender;
+        use flate2::read::GzDecoder;
+        use std::io::{Read, Write};
+
+        init_at("test_dir_logfile_gzip");
+        let mut log = Appender::new("test_dir_logfile_gzip/app.log", 10, 1).unwrap();
+        log.set_compress(true);
+        writeln!(log, "first").unwrap();
+        writeln!(log, "second").unwrap();
+        let mut s = String::new();
+        GzDecoder::new(File::open("test_dir_logfile_gzip/app.log.1.gz").unwrap())
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "first\n");
+        quit_at("test_dir_logfile_gzip");
+    }
+
+    #[test]
+    fn test_replace_in_file() {
+        use crate::ReplaceOptions;
+
+        init_at("test_dir_replace");
+        let file = Filey::new("test_dir_replace/sshd_config");
+        std::fs::write(&file, "Port 22\r\nPermitRootLogin yes\nyes yes\n").unwrap();
+        let options = ReplaceOptions {
+            backup_suffix: Some(".bak".to_string()),
+        };
+        assert_eq!(file.replace_in_file_with("yes", "no", &options).unwrap(), 3);
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "Port 22\r\nPermitRootLogin no\nno no\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string("test_dir_replace/sshd_config.bak").unwrap(),
+            "Port 22\r\nPermitRootLogin yes\nyes yes\n"
+        );
+        assert_eq!(file.replace_in_file("maybe", "no").unwrap(), 0);
+        quit_at("test_dir_replace");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_replace_in_file_regex() {
+        use regex::Regex;
+
+        init_at("test_dir_replace_regex");
+        let file = Filey::new("test_dir_replace_regex/versions");
+        std::fs::write(&file, "serde = 1.0\ntoml = 0.8\n").unwrap();
+        let pattern = Regex::new(r"^(\w+) = (\d+)\.(\d+)$").unwrap();
+        assert_eq!(file.replace_in_file(pattern, "$1 = \"$2.$3\"").unwrap(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "serde = \"1.0\"\ntoml = \"0.8\"\n"
+        );
+        quit_at("test_dir_replace_regex");
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        use crate::{Error, PatchOptions};
+
+        init_at("test_dir_patch");
+        let file = Filey::new("test_dir_patch/poem");
+        let original = "new line\na\nb\nc\nd\ne\nf\ng\n";
+        std::fs::write(&file, original).unwrap();
+        // The hunk was made before "new line" was added, so it has moved by one line.
+        let diff = "--- a/poem\n+++ b/poem\n@@ -2,5 +2,5 @@\n b\n c\n-d\n+D\n e\n f\n";
+        let dry_run = PatchOptions {
+            dry_run: true,
+            ..PatchOptions::default()
+        };
+        assert_eq!(file.apply_patch_with(diff, &dry_run).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), original);
+        assert_eq!(file.apply_patch(diff).unwrap(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "new line\na\nb\nc\nD\ne\nf\ng\n"
+        );
+
+        // The first context line differs, which needs fuzz.
+        let fuzzy = "@@ -3,3 +3,3 @@\n x\n c\n-D\n+d\n";
+        let strict = PatchOptions {
+            fuzz: 0,
+            ..PatchOptions::default()
+        };
+        assert!(matches!(
+            file.apply_patch_with(fuzzy, &strict),
+            Err(Error::PatchFailed { hunk: 1, .. })
+        ));
+        assert_eq!(file.apply_patch(fuzzy).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), original);
+
+        let no_newline = "@@ -8,1 +8,1 @@\n-g\n+G\n\\ No newline at end of file\n";
+        file.apply_patch(no_newline).unwrap();
+        assert!(std::fs::read_to_string(&file).unwrap().ends_with("f\nG"));
+
+        // Hunks without old lines insert after the line in their header.
+        std::fs::write(&file, "a\nb\nc\n").unwrap();
+        file.apply_patch("@@ -0,0 +1 @@\n+first\n@@ -2,0 +4 @@\n+inserted\n")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "first\na\nb\ninserted\nc\n"
+        );
+
+        // The '-- ' signature of git format-patch isn't a removed line of the last hunk.
+        std::fs::write(&file, "a\nb\n").unwrap();
+        let mail = "@@ -1,2 +1,2 @@\n a\n-b\n+B\n-- \n2.43.0\n\n";
+        assert_eq!(file.apply_patch(mail).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\nB\n");
+        let short = "@@ -1,2 +1,2 @@\n a\n-B\n";
+        assert!(file.apply_patch(short).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\nB\n");
+
+        // The hunks of another file aren't applied to this one.
+        let two_files = "--- a/one.txt\n+++ b/one.txt\n@@ -1 +1 @@\n-a\n+A\n\
+                         --- a/two.txt\n+++ b/two.txt\n@@ -2 +2 @@\n-B\n+HACKED\n";
+        let e = file.apply_patch(two_files).unwrap_err();
+        assert!(e.to_string().contains("b/two.txt"), "{}", e);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\nB\n");
+
+        // CRLF line endings are kept.
+        std::fs::write(&file, "a\r\nb\r\nc\r\n").unwrap();
+        assert_eq!(file.apply_patch("@@ -2 +2 @@\n-b\n+B\n").unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\r\nB\r\nc\r\n");
+        // So are they in a diff made from the file.
+        assert_eq!(file.apply_patch("@@ -3 +3 @@\r\n-c\r\n+C\r\n").unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\r\nB\r\nC\r\n");
+        quit_at("test_dir_patch");
+    }
+
+    #[test]
+    fn test_grep() {
+        init_at("test_dir_grep");
+        let file = Filey::new("test_dir_grep/main.rs");
+        std::fs::write(&file, b"fn main() {\r\n    // TODO: TODO\n}\n\xff TODO\n").unwrap();
+        let found = file.grep("TODO").unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|m| (m.line_number(), m.byte_offset(), m.len()))
+                .collect::<Vec<_>>(),
+            vec![(2, 20, 4), (2, 26, 4), (4, 35, 4)]
+        );
+        assert_eq!(found[0].line(), "    // TODO: TODO");
+        assert!(file.grep("FIXME").unwrap().is_empty());
+        quit_at("test_dir_grep");
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_grep_tree() {
+        use crate::search::{grep_tree, SearchOptions};
+
+        init_at("test_dir_grep_tree/src/target");
+        let write = |name: &str, contents: &[u8]| {
+            std::fs::write(format!("test_dir_grep_tree/{}", name), contents).unwrap()
+        };
+        write(".gitignore", b"target/\n");
+        write("src/a.rs", b"// TODO\n");
+        write("src/b.rs", b"fn b() {}\n// TODO\n// TODO\n");
+        write("src/c.rs", b"fn c() {}\n");
+        write("src/target/d.rs", b"// TODO\n");
+        write("src/e.bin", b"\0TODO\n");
+        write("src/.f.rs", b"// TODO\n");
+
+        let found = grep_tree("test_dir_grep_tree", "TODO", &SearchOptions::default()).unwrap();
+        let summary = |found: &[crate::search::FileMatches]| {
+            found
+                .iter()
+                .map(|f| (f.path().to_string_lossy().to_string(), f.matches().len()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(&found),
+            vec![
+                ("test_dir_grep_tree/src/a.rs".to_string(), 1),
+                ("test_dir_grep_tree/src/b.rs".to_string(), 2)
+            ]
+        );
+        let everything = SearchOptions {
+            respect_gitignore: false,
+            hidden: true,
+            binary: true,
+            ..SearchOptions::default()
+        };
+        let found = grep_tree("test_dir_grep_tree", "TODO", &everything).unwrap();
+        assert_eq!(found.len(), 5);
+        quit_at("test_dir_grep_tree");
+    }
+
+    #[test]
+    fn test_hexdump() {
+        init_at("test_dir_hexdump");
+        let file = Filey::new("test_dir_hexdump/hello.txt");
+        std::fs::write(&file, "Hello, world!\n\tHello again\n").unwrap();
+        assert_eq!(
+            file.hexdump(..).unwrap(),
+            "00000000: 4865 6c6c 6f2c 2077 6f72 6c64 210a 0948  Hello, world!..H\n\
+             00000010: 656c 6c6f 2061 6761 696e 0a              ello again.\n"
+        );
+        let rows = file.hex_rows(7..=11).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].offset(), 7);
+        assert_eq!(rows[0].bytes(), b"world");
+        quit_at("test_dir_hexdump");
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        init_at("test_dir_base64");
+        let file = Filey::new("test_dir_base64/data.bin");
+        std::fs::write(&file, b"\x00\xffhello").unwrap();
+        assert_eq!(file.read_base64().unwrap(), "AP9oZWxsbw==");
+        let copy = Filey::new("test_dir_base64/copy.bin");
+        copy.write_base64("AP9o\r\nZWxsbw==\n").unwrap();
+        assert_eq!(std::fs::read(&copy).unwrap(), b"\x00\xffhello");
+        assert!(copy.write_base64("not base64!").is_err());
+        assert_eq!(std::fs::read(&copy).unwrap(), b"\x00\xffhello");
+        quit_at("test_dir_base64");
+    }
+
+    #[test]
+    fn test_bom() {
+        use crate::Bom;
+
+        init_at("test_dir_bom");
+        let file = Filey::new("test_dir_bom/export.csv");
+        std::fs::write(&file, b"\xef\xbb\xbfa,b\n").unwrap();
+        assert!(file.has_bom().unwrap());
+        assert_eq!(file.bom().unwrap(), Some(Bom::Utf8));
+        assert_eq!(file.strip_bom().unwrap(), Some(Bom::Utf8));
+        assert_eq!(std::fs::read(&file).unwrap(), b"a,b\n");
+        assert_eq!(file.strip_bom().unwrap(), None);
+        assert_eq!(Bom::detect(b"\xff\xfe\x00\x00"), Some(Bom::Utf32Le));
+        assert_eq!(Bom::detect(b"\xff\xfea\x00"), Some(Bom::Utf16Le));
+        quit_at("test_dir_bom");
+    }
+
+    #[test]
+    fn test_validate_utf8() {
+        init_at("test_dir_validate_utf8");
+        let file = Filey::new("test_dir_validate_utf8/data.txt");
+        std::fs::write(&file, "héllo wörld\n").unwrap();
+        assert!(file.validate_utf8().unwrap().is_valid());
+
+        // Invalid bytes straddling the internal chunk boundary and a truncated sequence at EOF.
+        let mut bytes = vec![b'a'; 8191];
+        bytes.extend_from_slice(&[0xff, 0xfe, b'b']);
+        bytes.extend_from_slice("é".as_bytes());
+        bytes.extend_from_slice(&[b'c', 0xe2, 0x82]);
+        std::fs::write(&file, &bytes).unwrap();
+        let report = file.validate_utf8().unwrap();
+        assert_eq!(report.first_invalid(), Some(8191));
+        assert_eq!(report.invalid_ranges(), &[8191..8193, 8197..8199]);
+        assert_eq!(report.len(), 8199);
+        quit_at("test_dir_validate_utf8");
+    }
+
+    #[test]
+    fn test_shebang() {
+        init_at("test_dir_shebang");
+        let script = Filey::new("test_dir_shebang/build.py");
+        std::fs::write(&script, "#!/usr/bin/env python3 -u\r\nprint()\n").unwrap();
+        let shebang = script.shebang().unwrap().unwrap();
+        assert_eq!(shebang.interpreter(), Path::new("/usr/bin/env"));
+        assert_eq!(
+            shebang.args(),
+            &vec!["python3".to_string(), "-u".to_string()]
+        );
+        assert_eq!(shebang.program(), Some("python3"));
+        assert_eq!(shebang.to_string(), "#!/usr/bin/env python3 -u");
+        assert!(script.is_script().unwrap());
+
+        let text = Filey::new("test_dir_shebang/README");
+        std::fs::write(&text, "# not a script\n").unwrap();
+        assert!(!text.is_script().unwrap());
+        std::fs::write(&text, "#!\n").unwrap();
+        assert_eq!(text.shebang().unwrap(), None);
+        quit_at("test_dir_shebang");
+    }
+
+    #[test]
+    fn test_executable_kind() {
+        use crate::{Arch, BinaryInfo, ExecutableKind};
+
+        init_at("test_dir_executable_kind");
+        let file = Filey::new("test_dir_executable_kind/bin");
+
+        let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+        elf.resize(18, 0);
+        elf.extend_from_slice(&183u16.to_le_bytes());
+        std::fs::write(&file, &elf).unwrap();
+        assert_eq!(
+            file.executable_kind().unwrap(),
+            ExecutableKind::Elf(BinaryInfo::new(true, Arch::Aarch64))
+        );
+
+        let mut pe = b"MZ".to_vec();
+        pe.resize(0x3c, 0);
+        pe.extend_from_slice(&0x80u32.to_le_bytes());
+        pe.resize(0x80, 0);
+        pe.extend_from_slice(b"PE\0\0");
+        pe.extend_from_slice(&0x8664u16.to_le_bytes());
+        pe.resize(0x80 + 24, 0);
+        pe.extend_from_slice(&0x20bu16.to_le_bytes());
+        std::fs::write(&file, &pe).unwrap();
+        assert_eq!(
+            file.executable_kind().unwrap().to_string(),
+            "PE 64-bit x86-64"
+        );
+
+        let mut fat = b"\xca\xfe\xba\xbe".to_vec();
+        fat.extend_from_slice(&2u32.to_be_bytes());
+        for cputype in [0x0100_0007u32, 0x0100_000c] {
+            fat.extend_from_slice(&cputype.to_be_bytes());
+            fat.extend_from_slice(&[0; 16]);
+        }
+        std::fs::write(&file, &fat).unwrap();
+        assert_eq!(
+            file.executable_kind().unwrap().to_string(),
+            "Mach-O universal binary: 64-bit x86-64, 64-bit AArch64"
+        );
+
+        // A Java class file shares the magic of universal binaries.
+        std::fs::write(&file, b"\xca\xfe\xba\xbe\x00\x00\x00\x34").unwrap();
+        assert_eq!(
+            file.executable_kind().unwrap(),
+            ExecutableKind::NotExecutable
+        );
+
+        std::fs::write(&file, "#!/bin/sh\necho hi\n").unwrap();
+        assert!(matches!(
+            file.executable_kind().unwrap(),
+            ExecutableKind::Script(_)
+        ));
+        quit_at("test_dir_executable_kind");
+    }
+
+    #[test]
+    fn test_detect_format() {
+        use crate::sniff::{Registry, Signature};
+
+        init_at("test_dir_detect_format");
+        let file = Filey::new("test_dir_detect_format/data");
+        std::fs::write(&file, b"\x89PNG\r\n\x1a\n....").unwrap();
+        assert_eq!(file.detect_format().unwrap().as_deref(), Some("png"));
+
+        let mut tar = vec![0; 257];
+        tar.extend_from_slice(b"ustar\x0000");
+        std::fs::write(&file, &tar).unwrap();
+        assert_eq!(file.detect_format().unwrap().as_deref(), Some("tar"));
+
+        std::fs::write(&file, b"MZ\0\0ACME").unwrap();
+        let mut registry = Registry::default();
+        registry
+            .register(Signature::at(4, b"ACME"), "acme")
+            .register(b"MZ", "dos");
+        assert_eq!(file.detect_format().unwrap().as_deref(), Some("pe"));
+        assert_eq!(
+            file.detect_format_with(&registry).unwrap().as_deref(),
+            Some("acme")
+        );
+        assert_eq!(registry.detect(b"MZ"), Some("dos"));
+        assert_eq!(Registry::new().detect(b"MZ"), None);
+        quit_at("test_dir_detect_format");
+    }
+
+    #[test]
+    fn test_is_mount_point() {
+        init_at("test_dir_is_mount_point");
+        assert!(Filey::new("/").is_mount_point().unwrap());
+        assert!(!Filey::new("test_dir_is_mount_point")
+            .is_mount_point()
+            .unwrap());
+        create_file!("test_dir_is_mount_point/file");
+        assert!(!Filey::new("test_dir_is_mount_point/file")
+            .is_mount_point()
+            .unwrap());
+        quit_at("test_dir_is_mount_point");
+    }
+
+    #[test]
+    fn test_canonicalized_keep_symlink() {
+        init_at("test_dir_canonicalized_keep_symlink");
+        let root = Path::new("test_dir_canonicalized_keep_symlink")
+            .canonicalize()
+            .unwrap();
+        create_dir!("test_dir_canonicalized_keep_symlink/real");
+        symlink("real", "test_dir_canonicalized_keep_symlink/dir").unwrap();
+        symlink("target", "test_dir_canonicalized_keep_symlink/real/link").unwrap();
+
+        let link = Filey::new("test_dir_canonicalized_keep_symlink/dir/link")
+            .canonicalized_keep_symlink()
+            .unwrap();
+        assert_eq!(link.path(), &root.join("real/link"));
+        let dir = Filey::new("test_dir_canonicalized_keep_symlink/dir")
+            .canonicalized_keep_symlink()
+            .unwrap();
+        assert_eq!(dir.path(), &root.join("dir"));
+        let parent = Filey::new("test_dir_canonicalized_keep_symlink/dir/..")
+            .canonicalized_keep_symlink()
+            .unwrap();
+        assert_eq!(parent.path(), &root);
+        quit_at("test_dir_canonicalized_keep_symlink");
+    }
+
+    #[test]
+    fn test_metadata_no_follow() {
+        init_at("test_dir_metadata_no_follow");
+        let target = Filey::new("test_dir_metadata_no_follow/target");
+        std::fs::write(&target, vec![0; 100]).unwrap();
+        symlink("target", "test_dir_metadata_no_follow/link").unwrap();
+        let link = Filey::new("test_dir_metadata_no_follow/link");
+        assert_eq!(link.size().unwrap(), 100);
+        assert_eq!(link.size_no_follow().unwrap(), "target".len() as u64);
+        assert!(link.metadata_no_follow().unwrap().is_symlink());
+        assert!(target.metadata_no_follow().unwrap().is_file());
+        quit_at("test_dir_metadata_no_follow");
+    }
+
+    #[test]
+    fn test_which_follow() {
+        init_at("test_dir_which_follow");
+        create_dir!("test_dir_which_follow/dir");
+        symlink("dir", "test_dir_which_follow/link").unwrap();
+        symlink("missing", "test_dir_which_follow/dangling").unwrap();
+
+        let link = "test_dir_which_follow/link";
+        assert_eq!(FileTypes::which(link), Some(FileTypes::Symlink));
+        assert_eq!(FileTypes::which_follow(link), Some(FileTypes::Directory));
+        let dangling = Filey::new("test_dir_which_follow/dangling");
+        assert_eq!(dangling.file_type(), Some(FileTypes::Symlink));
+        assert_eq!(dangling.file_type_follow(), None);
+        quit_at("test_dir_which_follow");
+    }
+
+    #[test]
+    fn test_set_hidden() {
+        init_at("test_dir_set_hidden");
+        create_dir!("test_dir_set_hidden/cache");
+        let mut cache = Filey::new("test_dir_set_hidden/cache");
+        assert!(!cache.is_hidden().unwrap());
+        cache.set_hidden(true).unwrap();
+        assert_eq!(cache.path(), Path::new("test_dir_set_hidden/.cache"));
+        assert!(cache.is_hidden().unwrap());
+        assert!(cache.exists());
+        cache.set_hidden(true).unwrap();
+        assert_eq!(cache.path(), Path::new("test_dir_set_hidden/.cache"));
+
+        create_dir!("test_dir_set_hidden/cache");
+        assert!(cache.set_hidden(false).is_err());
+
+        // Only one leading dot is removed.
+        create_dir!("test_dir_set_hidden/..config");
+        let mut config = Filey::new("test_dir_set_hidden/..config");
+        config.set_hidden(false).unwrap();
+        assert_eq!(config.path(), Path::new("test_dir_set_hidden/.config"));
+        assert!(config.is_hidden().unwrap());
+
+        // Names which aren't valid Unicode are kept as they are.
+        #[cfg(target_family = "unix")]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+            let name = OsStr::from_bytes(b"caf\xe9");
+            let hidden = OsStr::from_bytes(b".caf\xe9");
+            let dir = Path::new("test_dir_set_hidden");
+            File::create(dir.join(name)).unwrap();
+            let mut file = Filey::new(dir.join(name));
+            file.set_hidden(true).unwrap();
+            assert_eq!(file.path(), &dir.join(hidden));
+            assert!(dir.join(hidden).exists());
+            file.set_hidden(false).unwrap();
+            assert_eq!(file.path(), &dir.join(name));
+            assert!(dir.join(name).exists());
+        }
+        quit_at("test_dir_set_hidden");
+    }
+
+    #[test]
+    fn test_eq_ignore_case() {
+        let readme = Filey::new("docs/Readme.md");
+        assert!(readme.eq_ignore_case("DOCS/README.md"));
+        assert!(readme.eq_ignore_case("docs//README.MD"));
+        assert!(!readme.eq_ignore_case("docs/README"));
+        assert!(!readme.eq_ignore_case("docs/Readme.md/x"));
+        assert!(Filey::new("Übersicht.txt").eq_ignore_case("üBERSICHT.TXT"));
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_normalize_unicode() {
+        use crate::NormalizationForm;
+
+        init_at("test_dir_normalize_unicode");
+        let nfd = "test_dir_normalize_unicode/Cafe\u{301}.txt";
+        let nfc = "test_dir_normalize_unicode/Caf\u{e9}.txt";
+        create_file!(nfd);
+        let mut file = Filey::new(nfd);
+        file.normalize_unicode(NormalizationForm::Nfc).unwrap();
+        assert_eq!(file.path(), Path::new(nfc));
+        assert!(Path::new(nfc).exists());
+        file.normalize_unicode(NormalizationForm::Nfc).unwrap();
+        assert_eq!(file.path(), Path::new(nfc));
+
+        assert!(Filey::new(nfd).eq_normalized(nfc));
+        assert!(Filey::new(nfc).eq_normalized(nfd));
+        assert!(Filey::new(nfd).eq_normalized(nfd));
+        assert!(!Filey::new(nfd).eq_normalized(nfc.replace('\u{e9}', "e")));
+        assert!(!Filey::new(nfd).eq_normalized("test_dir_normalize_unicode"));
+
+        create_file!(nfd);
+        assert!(Filey::new(nfd)
+            .normalize_unicode(NormalizationForm::Nfc)
+            .is_err());
+        quit_at("test_dir_normalize_unicode");
+    }
+
+    #[test]
+    fn test_copy_dir_filtered() {
+        init_at("test_dir_copy_dir_filtered");
+        create_dir!(
+            "test_dir_copy_dir_filtered/project/src",
+            "test_dir_copy_dir_filtered/project/target/debug",
+            "test_dir_copy_dir_filtered/project/.git"
+        );
+        create_file!(
+            "test_dir_copy_dir_filtered/project/src/main.rs",
+            "test_dir_copy_dir_filtered/project/target/debug/app",
+            "test_dir_copy_dir_filtered/project/.git/HEAD"
+        );
+        symlink("src/main.rs", "test_dir_copy_dir_filtered/project/link").unwrap();
+
+        Filey::new("test_dir_copy_dir_filtered/project")
+            .copy_dir_filtered("test_dir_copy_dir_filtered/backup", |entry| {
+                entry != Path::new("target") && entry != Path::new(".git")
+            })
+            .unwrap();
+        let backup = Path::new("test_dir_copy_dir_filtered/backup");
+        assert!(backup.join("src/main.rs").is_file());
+        assert!(backup.join("link").is_symlink());
+        assert!(!backup.join("target").exists());
+        assert!(!backup.join(".git").exists());
+
+        create_dir!("test_dir_copy_dir_filtered/into");
+        Filey::new("test_dir_copy_dir_filtered/project/src")
+            .copy_dir_filtered("test_dir_copy_dir_filtered/into", |_| true)
+            .unwrap();
+        assert!(Path::new("test_dir_copy_dir_filtered/into/src/main.rs").is_file());
+        quit_at("test_dir_copy_dir_filtered");
+    }
+
+    #[test]
+    fn test_move_into_merging() {
+        use crate::OnConflict;
+        use std::fs::{read_to_string, write};
+
+        init_at("test_dir_move_into_merging");
+        create_dir!(
+            "test_dir_move_into_merging/src/a",
+            "test_dir_move_into_merging/dst/a"
+        );
+        write("test_dir_move_into_merging/src/a/new", "new").unwrap();
+        write("test_dir_move_into_merging/src/a/both", "src").unwrap();
+        write("test_dir_move_into_merging/dst/a/both", "dst").unwrap();
+        write("test_dir_move_into_merging/dst/a/old", "old").unwrap();
+
+        let mut src = Filey::new("test_dir_move_into_merging/src");
+        assert!(src
+            .move_into_merging("test_dir_move_into_merging/dst", OnConflict::Fail)
+            .is_err());
+        src.move_into_merging("test_dir_move_into_merging/dst", OnConflict::Skip)
+            .unwrap();
+        assert_eq!(src.path(), Path::new("test_dir_move_into_merging/dst"));
+        assert_eq!(
+            read_to_string("test_dir_move_into_merging/dst/a/new").unwrap(),
+            "new"
+        );
+        assert_eq!(
+            read_to_string("test_dir_move_into_merging/dst/a/both").unwrap(),
+            "dst"
+        );
+        assert!(Path::new("test_dir_move_into_merging/dst/a/old").exists());
+        assert!(Path::new("test_dir_move_into_merging/src/a/both").exists());
+
+        Filey::new("test_dir_move_into_merging/src")
+            .move_into_merging("test_dir_move_into_merging/dst", OnConflict::Overwrite)
+            .unwrap();
+        assert_eq!(
+            read_to_string("test_dir_move_into_merging/dst/a/both").unwrap(),
+            "src"
+        );
+        assert!(!Path::new("test_dir_move_into_merging/src").exists());
+        quit_at("test_dir_move_into_merging");
+    }
+
+    #[test]
+    fn test_merge() {
+        use crate::{merge, MergeStrategy, Resolution};
+        use std::fs::{read_to_string, write};
+
+        init_at("test_dir_merge");
+        create_dir!("test_dir_merge/src/album", "test_dir_merge/dst/album");
+        write("test_dir_merge/src/album/a.mp3", "large").unwrap();
+        write("test_dir_merge/dst/album/a.mp3", "s").unwrap();
+        write("test_dir_merge/src/album/b.mp3", "s").unwrap();
+        write("test_dir_merge/dst/album/b.mp3", "large").unwrap();
+        write("test_dir_merge/src/album/c.mp3", "c").unwrap();
+
+        let report = merge(
+            "test_dir_merge/src",
+            "test_dir_merge/dst",
+            MergeStrategy::KeepLarger,
+        )
+        .unwrap();
+        let resolutions: Vec<_> = report.decisions().iter().map(|d| d.resolution()).collect();
+        assert_eq!(
+            resolutions,
+            vec![
+                Some(Resolution::KeepSource),
+                Some(Resolution::KeepDestination),
+                None
+            ]
+        );
+        assert_eq!(report.conflicts().count(), 2);
+        assert_eq!(
+            read_to_string("test_dir_merge/dst/album/a.mp3").unwrap(),
+            "large"
+        );
+        assert_eq!(
+            read_to_string("test_dir_merge/dst/album/b.mp3").unwrap(),
+            "large"
+        );
+        assert!(Path::new("test_dir_merge/dst/album/c.mp3").exists());
+        assert!(Path::new("test_dir_merge/src/album/b.mp3").exists());
+
+        let report = merge(
+            "test_dir_merge/src",
+            "test_dir_merge/dst",
+            MergeStrategy::Rename,
+        )
+        .unwrap();
+        assert_eq!(
+            report.decisions()[0].destination(),
+            Path::new("test_dir_merge/dst/album/b (1).mp3")
+        );
+        assert!(!Path::new("test_dir_merge/src").exists());
+
+        write("test_dir_merge/x", "x").unwrap();
+        write("test_dir_merge/y", "y").unwrap();
+        let mut asked = Vec::new();
+        let report = merge(
+            "test_dir_merge/x",
+            "test_dir_merge/y",
+            MergeStrategy::Ask(Box::new(move |from, _| {
+                asked.push(from.to_path_buf());
+                Resolution::KeepSource
+            })),
+        )
+        .unwrap();
+        assert_eq!(
+            report.decisions()[0].resolution(),
+            Some(Resolution::KeepSource)
+        );
+        assert_eq!(read_to_string("test_dir_merge/y").unwrap(), "x");
+
+        // A file never replaces a directory, even if it is newer or larger.
+        create_dir!("test_dir_merge/src2", "test_dir_merge/dst2/photos");
+        write("test_dir_merge/dst2/photos/1.jpg", "").unwrap();
+        write("test_dir_merge/src2/photos", "a large file").unwrap();
+        for strategy in [
+            MergeStrategy::KeepLarger,
+            MergeStrategy::KeepNewer,
+            MergeStrategy::Ask(Box::new(|_, _| Resolution::KeepSource)),
+        ] {
+            let report = merge("test_dir_merge/src2", "test_dir_merge/dst2", strategy).unwrap();
+            assert_eq!(
+                report.decisions()[0].resolution(),
+                Some(Resolution::KeepBoth)
+            );
+            assert!(Path::new("test_dir_merge/dst2/photos/1.jpg").exists());
+            create_dir!("test_dir_merge/src2");
+            write("test_dir_merge/src2/photos", "a large file").unwrap();
+        }
+        assert!(Path::new("test_dir_merge/dst2/photos (1)").is_file());
+        quit_at("test_dir_merge");
+    }
+
+    #[test]
+    fn test_counts() {
+        init_at("test_dir_counts");
+        let file = Filey::new("test_dir_counts/words.txt");
+        std::fs::write(&file, "one two\tthree\n\n  four\nfive").unwrap();
+        assert_eq!(file.line_count().unwrap(), 3);
+        assert_eq!(file.word_count().unwrap(), 5);
+        assert_eq!(file.byte_count().unwrap(), 26);
+
+        // Words spanning the internal chunk boundary are counted once.
+        std::fs::write(&file, "a".repeat(64 * 1024 + 10)).unwrap();
+        assert_eq!(file.word_count().unwrap(), 1);
+        assert_eq!(file.line_count().unwrap(), 0);
+        quit_at("test_dir_counts");
+    }
+
+    #[test]
+    fn test_enable_cache() {
+        init_at("test_dir_enable_cache");
+        let path = "test_dir_enable_cache/file";
+        std::fs::write(path, "abc").unwrap();
+        let mut file = Filey::new(path);
+        file.enable_cache();
+        assert!(file.exists());
+        assert_eq!(file.file_type(), Some(FileTypes::File));
+        assert_eq!(file.size().unwrap(), 3);
+
+        std::fs::write(path, "abcdef").unwrap();
+        assert_eq!(file.size().unwrap(), 3);
+        file.invalidate();
+        assert_eq!(file.size().unwrap(), 6);
+        assert_eq!(file, Filey::new(path));
+
+        // Failed lookups aren't cached.
+        let mut missing = Filey::new("test_dir_enable_cache/missing");
+        missing.enable_cache();
+        assert!(!missing.exists());
+        create_file!("test_dir_enable_cache/missing");
+        assert!(missing.exists());
+
+        // Changes made through the Filey discard the cache.
+        let earlier = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86400);
+        assert_ne!(missing.modified().unwrap(), earlier);
+        missing.set_modified(earlier).unwrap();
+        assert_eq!(missing.modified().unwrap(), earlier);
+        missing.move_to("test_dir_enable_cache/moved").unwrap();
+        assert!(!Path::new("test_dir_enable_cache/missing").exists());
+        assert!(missing.exists());
+        assert_eq!(missing.path(), Path::new("test_dir_enable_cache/moved"));
+        missing.remove().unwrap();
+        assert!(!missing.exists());
+        assert!(file.exists());
+        file.remove().unwrap();
+        assert!(!file.exists());
+        assert_eq!(file.file_type(), None);
+        quit_at("test_dir_enable_cache");
+    }
+
+    #[test]
+    fn test_list_entries() {
+        init_at("test_dir_list_entries");
+        create_dir!("test_dir_list_entries/dir");
+        create_file!("test_dir_list_entries/file");
+        symlink("dir", "test_dir_list_entries/link").unwrap();
+
+        let dir = Filey::new("test_dir_list_entries");
+        let mut entries: Vec<_> = dir
+            .list_entries()
+            .unwrap()
+            .iter()
+            .map(|entry| (entry.file_name(), entry.file_type()))
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("dir".into(), FileTypes::Directory),
+                ("file".into(), FileTypes::File),
+                ("link".into(), FileTypes::Symlink)
+            ]
+        );
+        let mut list = dir.list().unwrap();
+        list.sort();
+        assert_eq!(list[1], Filey::new("test_dir_list_entries/file"));
+        assert!(Filey::new("test_dir_list_entries/file").list().is_err());
+        quit_at("test_dir_list_entries");
+    }
+
+    #[test]
+    fn test_borrowing_accessors() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let file = Filey::new("src/lib.rs");
+        assert_eq!(file.file_name_str(), Some("lib.rs"));
+        assert_eq!(file.file_stem_str(), Some("lib"));
+        assert_eq!(file.to_str(), Some("src/lib.rs"));
+        assert_eq!(file.to_string(), "src/lib.rs");
+        assert_eq!(Filey::new("/").file_name_str(), None);
+
+        let invalid = Filey::new(OsStr::from_bytes(b"dir/\xffname"));
+        assert_eq!(invalid.file_name_str(), None);
+        assert_eq!(invalid.to_string(), "dir/\u{fffd}name");
+    }
+
+    #[test]
+    fn test_unit_of_info() {
+        use crate::units::{UnitOfInfo, EIB, KB, MIB};
+
+        assert_eq!(UnitOfInfo::binary_for(0), UnitOfInfo::Byte);
+        assert_eq!(UnitOfInfo::binary_for(1023), UnitOfInfo::Byte);
+        assert_eq!(UnitOfInfo::binary_for(1024), UnitOfInfo::KiB);
+        assert_eq!(UnitOfInfo::binary_for(MIB - 1), UnitOfInfo::KiB);
+        assert_eq!(UnitOfInfo::binary_for(u64::MAX), UnitOfInfo::EiB);
+        assert_eq!(UnitOfInfo::decimal_for(999), UnitOfInfo::Byte);
+        assert_eq!(UnitOfInfo::decimal_for(KB), UnitOfInfo::KB);
+
+        assert_eq!(UnitOfInfo::format(0), "0 B");
+        assert_eq!(UnitOfInfo::format(1536), "1.5 KiB");
+        assert_eq!(UnitOfInfo::format(5 * MIB + MIB / 4), "5.3 MiB");
+        assert_eq!(UnitOfInfo::format(u64::MAX), "16.0 EiB");
+        assert_eq!(UnitOfInfo::format(EIB), "1.0 EiB");
+        assert_eq!(UnitOfInfo::format_decimal(1_250_000), "1.3 MB");
+        assert_eq!(UnitOfInfo::MiB.format_as(MIB / 2), "0.5 MiB");
+    }
+
+    #[test]
+    fn test_op_context() {
+        use crate::{Error, Op};
+
+        init_at("test_dir_op_context");
+        File::create("test_dir_op_context/target").unwrap();
+        File::create("test_dir_op_context/link").unwrap();
+        let e = Filey::new("test_dir_op_context/target")
+            .symlink("test_dir_op_context/link")
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            Error::Io {
+                op: Op::Symlink,
+                ..
+            }
+        ));
+        assert!(e.to_string().starts_with(
+            "creating symlink 'test_dir_op_context/link' -> 'test_dir_op_context/target': "
+        ));
+
+        let e = Filey::new("test_dir_op_context/missing")
+            .size()
+            .unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("reading metadata of 'test_dir_op_context/missing': "));
+        quit_at("test_dir_op_context");
+    }
+
+    #[test]
+    fn test_error_source() {
+        use crate::Error;
+        use std::{error::Error as _, io};
+
+        let e = Filey::new("test_dir_error_source/missing")
+            .size()
+            .unwrap_err();
+        assert_eq!(e.raw_os_error(), Some(libc::ENOENT));
+        assert_eq!(e.io_kind(), Some(io::ErrorKind::NotFound));
+        let source = e.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(source.raw_os_error(), Some(libc::ENOENT));
+
+        let e = Error::FileyError(io::Error::from_raw_os_error(libc::ENOSPC).into());
+        assert_eq!(e.raw_os_error(), Some(libc::ENOSPC));
+        let e = Error::FileyError(
+            anyhow::Error::new(io::Error::from_raw_os_error(libc::EXDEV)).context("moving"),
+        );
+        assert_eq!(e.raw_os_error(), Some(libc::EXDEV));
+        let e = Error::AlreadyExists {
+            path: "a".to_string(),
+        };
+        assert_eq!(e.raw_os_error(), None);
+    }
+
+    #[test]
+    fn test_move_to_with_create_parents() {
+        use crate::MoveOptions;
+
+        init_at("test_dir_move_parents");
+        File::create("test_dir_move_parents/a.txt").unwrap();
+        let mut file = Filey::new("test_dir_move_parents/a.txt");
+        assert!(file
+            .move_to("test_dir_move_parents/archive/2024/06/a.txt")
+            .is_err());
+        let options = MoveOptions {
+            create_parents: true,
+            ..Default::default()
+        };
+        file.move_to_with("test_dir_move_parents/archive/2024/06/a.txt", &options)
+            .unwrap();
+        assert!(Path::new("test_dir_move_parents/archive/2024/06/a.txt").exists());
+        assert_eq!(
+            file,
+            Filey::new("test_dir_move_parents/archive/2024/06/a.txt")
+        );
+        quit_at("test_dir_move_parents");
+    }
+
+    #[test]
+    fn test_copy_with_create_parents() {
+        use crate::CopyOptions;
+
+        init_at("test_dir_copy_parents");
+        std::fs::write("test_dir_copy_parents/a.txt", "a").unwrap();
+        let file = Filey::new("test_dir_copy_parents/a.txt");
+        assert!(file.copy("test_dir_copy_parents/out/a/b.txt").is_err());
+        let options = CopyOptions {
+            create_parents: true,
+            ..Default::default()
+        };
+        file.copy_with("test_dir_copy_parents/out/a/b.txt", &options)
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string("test_dir_copy_parents/out/a/b.txt").unwrap(),
+            "a"
+        );
+        assert!(file.exists());
+        quit_at("test_dir_copy_parents");
+    }
+
+    #[test]
+    fn test_symlink_with() {
+        use crate::SymlinkOptions;
+        use std::fs::{create_dir, read_link};
+
+        init_at("test_dir_symlink_with/dotfiles/nvim");
+        File::create("test_dir_symlink_with/dotfiles/vimrc").unwrap();
+        create_dir("test_dir_symlink_with/home").unwrap();
+        File::create("test_dir_symlink_with/home/.vimrc").unwrap();
+
+        let vimrc = Filey::new("test_dir_symlink_with/dotfiles/vimrc");
+        let options = SymlinkOptions {
+            force: true,
+            relative: true,
+        };
+        assert!(vimrc
+            .symlink_with(
+                "test_dir_symlink_with/home/.vimrc",
+                &SymlinkOptions::default()
+            )
+            .is_err());
+        for _ in 0..2 {
+            vimrc
+                .symlink_with("test_dir_symlink_with/home/.vimrc", &options)
+                .unwrap();
+        }
+        assert_eq!(
+            read_link("test_dir_symlink_with/home/.vimrc").unwrap(),
+            Path::new("../dotfiles/vimrc")
+        );
+
+        // An existing link to a directory is replaced rather than descended into.
+        let nvim = Filey::new("test_dir_symlink_with/dotfiles/nvim");
+        for _ in 0..2 {
+            nvim.symlink_with("test_dir_symlink_with/home/nvim", &options)
+                .unwrap();
+        }
+        assert!(!Path::new("test_dir_symlink_with/dotfiles/nvim/nvim").is_symlink());
+        assert_eq!(
+            read_link("test_dir_symlink_with/home/nvim").unwrap(),
+            Path::new("../dotfiles/nvim")
+        );
+        quit_at("test_dir_symlink_with");
+    }
+
+    #[test]
+    fn test_hard_link_with() {
+        use crate::{Error, HardLinkOptions};
+        use std::os::unix::fs::MetadataExt;
+
+        init_at("test_dir_hard_link_with");
+        std::fs::write("test_dir_hard_link_with/a", "a").unwrap();
+        std::fs::write("test_dir_hard_link_with/b", "b").unwrap();
+        let a = Filey::new("test_dir_hard_link_with/a");
+        assert!(a
+            .hard_link_with("test_dir_hard_link_with/b", &HardLinkOptions::default())
+            .is_err());
+        let force = HardLinkOptions {
+            force: true,
+            ..Default::default()
+        };
+        a.hard_link_with("test_dir_hard_link_with/b", &force)
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string("test_dir_hard_link_with/b").unwrap(),
+            "a"
+        );
+        assert_eq!(a.hard_link_count().unwrap(), 2);
+
+        // Only checked where /dev/shm is a separate filesystem.
+        let shm = Path::new("/dev/shm");
+        let other = format!("/dev/shm/filey_test_hard_link_{}", std::process::id());
+        if shm
+            .metadata()
+            .is_ok_and(|m| m.dev() != a.metadata_no_follow().unwrap().dev())
+        {
+            let e = a
+                .hard_link_with(&other, &HardLinkOptions::default())
+                .unwrap_err();
+            assert!(matches!(e, Error::CrossDevice { .. }));
+            assert_eq!(e.raw_os_error(), Some(libc::EXDEV));
+            let fallback = HardLinkOptions {
+                copy_fallback: true,
+                ..Default::default()
+            };
+            a.hard_link_with(&other, &fallback).unwrap();
+            assert_eq!(std::fs::read_to_string(&other).unwrap(), "a");
+            assert!(a.hard_link_with(&other, &fallback).is_err());
+
+            // The forced copy goes to a new file renamed over the old one.
+            std::fs::write(&other, "old").unwrap();
+            let old_ino = Path::new(&other).metadata().unwrap().ino();
+            std::fs::write("test_dir_hard_link_with/a", "new").unwrap();
+            let forced = HardLinkOptions {
+                force: true,
+                copy_fallback: true,
+            };
+            a.hard_link_with(&other, &forced).unwrap();
+            assert_eq!(std::fs::read_to_string(&other).unwrap(), "new");
+            assert_ne!(Path::new(&other).metadata().unwrap().ino(), old_ino);
+            std::fs::remove_file(&other).unwrap();
+        }
+        quit_at("test_dir_hard_link_with");
+    }
+
+    #[test]
+    fn test_remove_eventually() {
+        use std::time::{Duration, Instant};
+
+        init_at("test_dir_remove_eventually/dir");
+        File::create("test_dir_remove_eventually/dir/file").unwrap();
+        let dir = Filey::new("test_dir_remove_eventually/dir");
+        dir.remove_eventually(Duration::from_secs(5)).unwrap();
+        assert!(!dir.exists());
+
+        // Errors which won't go away by waiting are returned at once.
+        let start = Instant::now();
+        let e = dir.remove_eventually(Duration::from_secs(5)).unwrap_err();
+        assert_eq!(e.io_kind(), Some(std::io::ErrorKind::NotFound));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        quit_at("test_dir_remove_eventually");
+    }
+
+    #[test]
+    fn test_timeouts() {
+        use crate::{BufferSize, CopyOptions, Error};
+        use std::{io::Write, thread::sleep, time::Duration};
+
+        init_at("test_dir_timeouts");
+        File::create("test_dir_timeouts/file").unwrap();
+        let dir = Filey::new("test_dir_timeouts");
+        assert_eq!(
+            dir.list_with_timeout(Duration::from_secs(10)).unwrap(),
+            vec![Filey::new("test_dir_timeouts/file")]
+        );
+
+        // Opening a FIFO blocks until someone opens the other end, like a hung mount.
+        Filey::new("test_dir_timeouts/fifo")
+            .create_fifo(0o600)
+            .unwrap();
+        let options = CopyOptions {
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let e = Filey::new("test_dir_timeouts/fifo")
+            .copy_with("test_dir_timeouts/copy", &options)
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout { .. }));
+
+        // Unblock the worker; its late copy is removed again.
+        drop(
+            File::options()
+                .write(true)
+                .open("test_dir_timeouts/fifo")
+                .unwrap(),
+        );
+        sleep(Duration::from_millis(200));
+        assert!(!Path::new("test_dir_timeouts/copy").exists());
+
+        // A destination which was there before isn't removed. With its own buffer the copy
+        // reads the FIFO rather than refusing it, so the late worker does copy something.
+        std::fs::write("test_dir_timeouts/existing", "old").unwrap();
+        let buffered = CopyOptions {
+            buffer_size: BufferSize::Bytes(4096),
+            ..options
+        };
+        let e = Filey::new("test_dir_timeouts/fifo")
+            .copy_with("test_dir_timeouts/existing", &buffered)
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout { .. }));
+        File::options()
+            .write(true)
+            .open("test_dir_timeouts/fifo")
+            .unwrap()
+            .write_all(b"late")
+            .unwrap();
+        sleep(Duration::from_millis(200));
+        // Nor overwritten by the late copy, which leaves no temporary file behind.
+        assert_eq!(
+            std::fs::read_to_string("test_dir_timeouts/existing").unwrap(),
+            "old"
+        );
+        assert!(std::fs::read_dir("test_dir_timeouts")
+            .unwrap()
+            .all(|entry| !entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .ends_with(".tmp")));
+        dir.remove_eventually(Duration::from_secs(5)).unwrap();
+        quit_at("test_dir_timeouts");
+    }
+
+    #[test]
+    fn test_copy_dir_filtered_with() {
+        use crate::BatchOptions;
+        use std::fs::{read_to_string, write};
+
+        init_at("test_dir_copy_dir_filtered_with");
+        create_dir!("test_dir_copy_dir_filtered_with/project/a/b");
+        for i in 0..20 {
+            write(
+                format!("test_dir_copy_dir_filtered_with/project/a/b/{}.txt", i),
+                i.to_string(),
+            )
+            .unwrap();
+        }
+
+        for max_in_flight in [0, 1, 3] {
+            let to = format!("test_dir_copy_dir_filtered_with/copy_{}", max_in_flight);
+            Filey::new("test_dir_copy_dir_filtered_with/project")
+                .copy_dir_filtered_with(
+                    &to,
+                    |_| true,
+                    &BatchOptions {
+                        max_in_flight,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            for i in 0..20 {
+                assert_eq!(
+                    read_to_string(format!("{}/a/b/{}.txt", to, i)).unwrap(),
+                    i.to_string()
+                );
+            }
+        }
+
+        quit_at("test_dir_copy_dir_filtered_with");
+    }
+
+    #[test]
+    fn test_hash_tree() {
+        use crate::checksum::{self, Algorithm};
+        use std::fs::write;
+
+        init_at("test_dir_hash_tree");
+        create_dir!("test_dir_hash_tree/a/sub", "test_dir_hash_tree/b/sub");
+        write("test_dir_hash_tree/empty", "").unwrap();
+        write("test_dir_hash_tree/abc", "abc").unwrap();
+        assert_eq!(
+            checksum::hash_file("test_dir_hash_tree/empty", Algorithm::Sha256)
+                .unwrap()
+                .to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            checksum::hash_file("test_dir_hash_tree/abc", Algorithm::Sha256)
+                .unwrap()
+                .to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        write("test_dir_hash_tree/long", "a".repeat(1000)).unwrap();
+        assert_eq!(
+            checksum::hash_file("test_dir_hash_tree/long", Algorithm::Sha256)
+                .unwrap()
+                .to_string(),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+        write("test_dir_hash_tree/a.txt", "a").unwrap();
+        assert_eq!(
+            checksum::hash_file("test_dir_hash_tree/a.txt", Algorithm::Fnv1a64)
+                .unwrap()
+                .to_string(),
+            "af63dc4c8601ec8c"
+        );
+
+        for tree in ["a", "b"] {
+            for i in 0..10 {
+                write(
+                    format!("test_dir_hash_tree/{}/sub/{}", tree, i),
+                    i.to_string(),
+                )
+                .unwrap();
+            }
+            symlink("sub/0", format!("test_dir_hash_tree/{}/link", tree)).unwrap();
+        }
+        let a = checksum::hash_tree("test_dir_hash_tree/a", Algorithm::Sha256, 0).unwrap();
+        let b = checksum::hash_tree("test_dir_hash_tree/b", Algorithm::Sha256, 1).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.files.len(), 11);
+        assert_eq!(
+            a.files[Path::new("sub/3")],
+            checksum::hash_file("test_dir_hash_tree/a/sub/3", Algorithm::Sha256).unwrap()
+        );
+
+        write("test_dir_hash_tree/b/sub/3", "changed").unwrap();
+        let b = checksum::hash_tree("test_dir_hash_tree/b", Algorithm::Sha256, 4).unwrap();
+        assert_ne!(a.root, b.root);
+        assert_ne!(a.files[Path::new("sub/3")], b.files[Path::new("sub/3")]);
+        assert_eq!(a.files[Path::new("sub/4")], b.files[Path::new("sub/4")]);
+        quit_at("test_dir_hash_tree");
+    }
+
+    #[test]
+    fn test_snapshot() {
+        use crate::{
+            checksum::Algorithm,
+            snapshot::{self, DiffOptions, SnapshotOptions},
+        };
+        use std::{
+            fs::{remove_file, write, File},
+            path::PathBuf,
+            time::{Duration, SystemTime},
+        };
+
+        init_at("test_dir_snapshot");
+        create_dir!("test_dir_snapshot/tree/sub");
+        write("test_dir_snapshot/tree/same", "same").unwrap();
+        write("test_dir_snapshot/tree/touched", "touched").unwrap();
+        write("test_dir_snapshot/tree/sub/edited", "before").unwrap();
+        write("test_dir_snapshot/tree/removed", "removed").unwrap();
+        let options = SnapshotOptions {
+            hash: Some(Algorithm::Sha256),
+            ..Default::default()
+        };
+        let old = snapshot::take("test_dir_snapshot/tree").unwrap();
+        let old_hashed = snapshot::take_with("test_dir_snapshot/tree", &options).unwrap();
+        assert_eq!(old.entries.len(), 5);
+        assert!(old_hashed.entries[Path::new("same")].digest.is_some());
+        assert!(old_hashed.entries[Path::new("sub")].digest.is_none());
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open("test_dir_snapshot/tree/touched")
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        write("test_dir_snapshot/tree/sub/edited", "after!").unwrap();
+        remove_file("test_dir_snapshot/tree/removed").unwrap();
+        write("test_dir_snapshot/tree/sub/added", "added").unwrap();
+
+        let new = snapshot::take("test_dir_snapshot/tree").unwrap();
+        let changes = snapshot::diff(&old, &new);
+        assert_eq!(changes.added, vec![PathBuf::from("sub/added")]);
+        assert_eq!(changes.removed, vec![PathBuf::from("removed")]);
+        assert_eq!(
+            changes.modified,
+            vec![PathBuf::from("sub/edited"), PathBuf::from("touched")]
+        );
+
+        // With digests a touched file is unchanged.
+        let new_hashed = snapshot::take_with("test_dir_snapshot/tree", &options).unwrap();
+        let changes = snapshot::diff(&old_hashed, &new_hashed);
+        assert_eq!(changes.modified, vec![PathBuf::from("sub/edited")]);
+        assert!(snapshot::diff(&new, &new).is_empty());
+
+        // A tree whose names only changed in case, as after a round trip through Windows.
+        std::fs::rename("test_dir_snapshot/tree/sub", "test_dir_snapshot/tree/SUB").unwrap();
+        write("test_dir_snapshot/tree/SUB/added", "changed").unwrap();
+        let renamed = snapshot::take("test_dir_snapshot/tree").unwrap();
+        assert_eq!(snapshot::diff(&new, &renamed).added.len(), 3);
+        let ignore_case = DiffOptions { ignore_case: true };
+        let changes = snapshot::diff_with(&new, &renamed, &ignore_case);
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert_eq!(changes.modified, vec![PathBuf::from("SUB/added")]);
+        quit_at("test_dir_snapshot");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_manifest() {
+        use crate::{
+            checksum::{self, Algorithm},
+            snapshot::{Manifest, ManifestOptions},
+            ConfigFormat,
+        };
+        use std::{
+            fs::{set_permissions, write, Permissions},
+            os::unix::fs::PermissionsExt,
+        };
+
+        init_at("test_dir_manifest");
+        create_dir!("test_dir_manifest/a/bin", "test_dir_manifest/b/bin");
+        for tree in ["a", "b"] {
+            write(format!("test_dir_manifest/{}/bin/run", tree), "#!/bin/sh\n").unwrap();
+            set_permissions(
+                format!("test_dir_manifest/{}/bin/run", tree),
+                Permissions::from_mode(0o755),
+            )
+            .unwrap();
+            symlink("bin/run", format!("test_dir_manifest/{}/run", tree)).unwrap();
+        }
+
+        let mut options = ManifestOptions::default();
+        options.snapshot.hash = Some(Algorithm::Sha256);
+        options.snapshot.omit_modified = true;
+        let a = Filey::new("test_dir_manifest/a")
+            .manifest(&options)
+            .unwrap();
+        let b = Filey::new("test_dir_manifest/b")
+            .manifest(&options)
+            .unwrap();
+        assert_eq!(a, b);
+
+        let manifest: Manifest = ConfigFormat::Json.deserialize(&a).unwrap();
+        let run = &manifest.entries[Path::new("bin/run")];
+        assert_eq!(run.file_type, FileTypes::File);
+        assert_eq!(run.size, 10);
+        assert_eq!(run.mode, Some(0o755));
+        assert_eq!(
+            run.digest,
+            Some(checksum::hash_file("test_dir_manifest/a/bin/run", Algorithm::Sha256).unwrap())
+        );
+        let link = &manifest.entries[Path::new("run")];
+        assert_eq!(link.file_type, FileTypes::Symlink);
+        assert_eq!(link.target.as_deref(), Some(Path::new("bin/run")));
+        assert_eq!(manifest.entries[Path::new("bin")].size, 0);
+
+        #[cfg(feature = "toml")]
+        {
+            options.format = ConfigFormat::Toml;
+            options.snapshot.omit_modified = false;
+            let toml = Filey::new("test_dir_manifest/a")
+                .manifest(&options)
+                .unwrap();
+            let from_toml: Manifest = ConfigFormat::Toml.deserialize(&toml).unwrap();
+            assert_eq!(from_toml.entries.len(), manifest.entries.len());
+            assert!(from_toml.entries[Path::new("run")].modified.is_some());
+        }
+        quit_at("test_dir_manifest");
+    }
+
+    #[test]
+    fn test_materialize() {
+        use crate::{
+            checksum::Algorithm,
+            snapshot::{self, SnapshotOptions},
+        };
+        use std::{
+            fs::{read_to_string, set_permissions, write, File, Permissions},
+            os::unix::fs::PermissionsExt,
+            path::PathBuf,
+        };
+
+        init_at("test_dir_materialize");
+        create_dir!("test_dir_materialize/template/bin");
+        write("test_dir_materialize/template/bin/run", "run").unwrap();
+        write("test_dir_materialize/template/README", "readme").unwrap();
+        set_permissions(
+            "test_dir_materialize/template/bin/run",
+            Permissions::from_mode(0o750),
+        )
+        .unwrap();
+        symlink("bin", "test_dir_materialize/template/tools").unwrap();
+        set_permissions(
+            "test_dir_materialize/template/bin",
+            Permissions::from_mode(0o555),
+        )
+        .unwrap();
+
+        let options = SnapshotOptions {
+            hash: Some(Algorithm::Sha256),
+            ..Default::default()
+        };
+        let manifest = snapshot::take_with("test_dir_materialize/template", &options).unwrap();
+        snapshot::materialize(&manifest, "test_dir_materialize/copy", |path, _| {
+            Ok(File::open(Path::new("test_dir_materialize/template").join(path)).unwrap())
+        })
+        .unwrap();
+        let copy = snapshot::take_with("test_dir_materialize/copy", &options).unwrap();
+        assert!(snapshot::diff(&manifest, &copy).is_empty());
+        assert_eq!(
+            copy.entries[Path::new("bin/run")],
+            manifest.entries[Path::new("bin/run")]
+        );
+        assert_eq!(copy.entries[Path::new("bin")].mode, Some(0o555));
+        assert_eq!(
+            read_to_string("test_dir_materialize/copy/tools/run").unwrap(),
+            "run"
+        );
+
+        // Contents which don't match the digest are rejected.
+        assert!(
+            snapshot::materialize(&manifest, "test_dir_materialize/bad", |_, _| Ok(
+                "tampered".as_bytes()
+            ))
+            .is_err()
+        );
+
+        // Paths leaving the destination are rejected before anything is written.
+        for key in ["../x", "/x", "bin/../../x"] {
+            let mut escaping = manifest.clone();
+            let entry = escaping.entries[Path::new("README")].clone();
+            escaping.entries.insert(PathBuf::from(key), entry);
+            assert!(snapshot::materialize(
+                &escaping,
+                "test_dir_materialize/escaping",
+                |path, _| {
+                    Ok(File::open(Path::new("test_dir_materialize/template").join(path)).unwrap())
+                }
+            )
+            .is_err());
+            assert!(!Path::new("test_dir_materialize/escaping").exists());
+            assert!(!Path::new("test_dir_materialize/x").exists());
+        }
+
+        for tree in ["template", "copy", "bad"] {
+            let bin = format!("test_dir_materialize/{}/bin", tree);
+            if Path::new(&bin).exists() {
+                set_permissions(bin, Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        quit_at("test_dir_materialize");
+    }
+
+    #[test]
+    fn test_link_dest() {
+        use crate::backup;
+        use std::{
+            fs::{read_to_string, write},
+            os::unix::fs::MetadataExt,
+            path::PathBuf,
+        };
+
+        init_at("test_dir_link_dest");
+        create_dir!("test_dir_link_dest/src/docs");
+        write("test_dir_link_dest/src/docs/same", "same").unwrap();
+        write("test_dir_link_dest/src/changed", "before").unwrap();
+        symlink("docs/same", "test_dir_link_dest/src/link").unwrap();
+
+        let first = backup::link_dest(
+            "test_dir_link_dest/src",
+            "test_dir_link_dest/1",
+            None::<&str>,
+        )
+        .unwrap();
+        assert!(first.linked.is_empty());
+        assert_eq!(first.copied.len(), 2);
+
+        write("test_dir_link_dest/src/changed", "after").unwrap();
+        write("test_dir_link_dest/src/added", "added").unwrap();
+        let second = backup::link_dest(
+            "test_dir_link_dest/src",
+            "test_dir_link_dest/2",
+            Some("test_dir_link_dest/1"),
+        )
+        .unwrap();
+        assert_eq!(second.linked, vec![PathBuf::from("docs/same")]);
+        assert_eq!(
+            second.copied,
+            vec![PathBuf::from("added"), PathBuf::from("changed")]
+        );
+
+        let ino = |path: &str| Path::new(path).metadata().unwrap().ino();
+        assert_eq!(
+            ino("test_dir_link_dest/1/docs/same"),
+            ino("test_dir_link_dest/2/docs/same")
+        );
+        assert_ne!(
+            ino("test_dir_link_dest/1/changed"),
+            ino("test_dir_link_dest/2/changed")
+        );
+        assert_eq!(
+            read_to_string("test_dir_link_dest/1/changed").unwrap(),
+            "before"
+        );
+        assert_eq!(
+            read_to_string("test_dir_link_dest/2/changed").unwrap(),
+            "after"
+        );
+        assert!(Path::new("test_dir_link_dest/2/link").is_symlink());
+
+        // Copied files keep their modification times, so a third backup links everything.
+        let third = backup::link_dest(
+            "test_dir_link_dest/src",
+            "test_dir_link_dest/3",
+            Some("test_dir_link_dest/2"),
+        )
+        .unwrap();
+        assert!(third.copied.is_empty());
+        assert_eq!(third.linked.len(), 3);
+        quit_at("test_dir_link_dest");
+    }
+
+    #[test]
+    fn test_check_space() {
+        use crate::{BatchOptions, CopyOptions, Error};
+
+        init_at("test_dir_check_space");
+        create_dir!("test_dir_check_space/tree");
+        let available = Filey::new("test_dir_check_space")
+            .available_space()
+            .unwrap();
+        assert!(available > 0);
+
+        // A sparse file larger than the free space.
+        File::create("test_dir_check_space/tree/huge")
+            .unwrap()
+            .set_len(available + (1 << 30))
+            .unwrap();
+        let options = CopyOptions {
+            check_space: true,
+            ..Default::default()
+        };
+        let e = Filey::new("test_dir_check_space/tree/huge")
+            .copy_with("test_dir_check_space/copy", &options)
+            .unwrap_err();
+        assert!(matches!(e, Error::InsufficientSpace { needed, .. } if needed > available));
+        assert!(!Path::new("test_dir_check_space/copy").exists());
+
+        let options = BatchOptions {
+            check_space: true,
+            ..Default::default()
+        };
+        let e = Filey::new("test_dir_check_space/tree")
+            .copy_dir_filtered_with("test_dir_check_space/new/tree", |_| true, &options)
+            .unwrap_err();
+        assert!(matches!(e, Error::InsufficientSpace { .. }));
+        assert!(!Path::new("test_dir_check_space/new").exists());
+
+        Filey::new("test_dir_check_space/tree")
+            .copy_dir_filtered_with(
+                "test_dir_check_space/small",
+                |entry| entry != Path::new("huge"),
+                &options,
+            )
+            .unwrap();
+        quit_at("test_dir_check_space");
+    }
+
+    #[test]
+    fn test_plan() {
+        use std::fs::write;
+
+        init_at("test_dir_plan");
+        create_dir!("test_dir_plan/src/sub", "test_dir_plan/dest/src");
+        write("test_dir_plan/src/a", "aaaa").unwrap();
+        write("test_dir_plan/src/sub/b", "bb").unwrap();
+        symlink("a", "test_dir_plan/src/link").unwrap();
+        write("test_dir_plan/dest/src/a", "old").unwrap();
+
+        let plan = Filey::new("test_dir_plan/src")
+            .plan_copy("test_dir_plan/new")
+            .unwrap();
+        assert!(plan.is_clean());
+        assert_eq!(plan.bytes, 6);
+        assert_eq!(plan.directories.len(), 2);
+        assert_eq!(plan.files.len(), 3);
+        assert!(plan
+            .files
+            .contains(&Path::new("test_dir_plan/new/sub/b").to_path_buf()));
+        assert!(!Path::new("test_dir_plan/new").exists());
+
+        // An existing directory is copied into, where src and src/a already exist.
+        let plan = Filey::new("test_dir_plan/src")
+            .plan_copy("test_dir_plan/dest")
+            .unwrap();
+        assert_eq!(
+            plan.conflicts,
+            vec![
+                Path::new("test_dir_plan/dest/src").to_path_buf(),
+                Path::new("test_dir_plan/dest/src/a").to_path_buf()
+            ]
+        );
+
+        let plan = Filey::new("test_dir_plan/src/a")
+            .plan_copy("test_dir_plan/dest/src")
+            .unwrap();
+        assert_eq!(plan.bytes, 4);
+        assert_eq!(plan.conflicts.len(), 1);
+
+        let plan = Filey::new("test_dir_plan/src").plan_remove().unwrap();
+        assert!(plan.is_clean());
+        assert_eq!(plan.bytes, 6);
+        assert_eq!(plan.directories.len(), 2);
+        assert_eq!(plan.files.len(), 3);
+        assert!(Path::new("test_dir_plan/src/sub/b").exists());
+        assert!(Filey::new("test_dir_plan/missing").plan_remove().is_err());
+        quit_at("test_dir_plan");
+    }
+
+    #[test]
+    fn test_check_writable_tree() {
+        use std::fs::{set_permissions, write, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+
+        init_at("test_dir_check_writable_tree");
+        create_dir!("test_dir_check_writable_tree/app/locked");
+        write("test_dir_check_writable_tree/app/free", "").unwrap();
+        write("test_dir_check_writable_tree/app/locked/stuck", "").unwrap();
+        let app = Filey::new("test_dir_check_writable_tree/app");
+        assert!(app.check_writable_tree().unwrap().is_empty());
+
+        set_permissions(
+            "test_dir_check_writable_tree/app/locked",
+            Permissions::from_mode(0o555),
+        )
+        .unwrap();
+        let blocked = app.check_writable_tree().unwrap();
+        // Root may modify anything.
+        if unsafe { libc::geteuid() } == 0 {
+            assert!(blocked.is_empty());
+        } else {
+            assert_eq!(
+                blocked,
+                vec![
+                    Path::new("test_dir_check_writable_tree/app/locked").to_path_buf(),
+                    Path::new("test_dir_check_writable_tree/app/locked/stuck").to_path_buf()
+                ]
+            );
+        }
+        set_permissions(
+            "test_dir_check_writable_tree/app/locked",
+            Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        assert!(Filey::new("test_dir_check_writable_tree/missing")
+            .check_writable_tree()
+            .is_err());
+        quit_at("test_dir_check_writable_tree");
+    }
+
+    #[test]
+    fn test_hooks() {
+        use crate::{hooks, Error, Op};
+        use std::{
+            path::PathBuf,
+            sync::{Arc, Mutex},
+        };
+
+        init_at("test_dir_hooks");
+        let refused = hooks::on_before(|operation| {
+            if operation.op == Op::Remove && operation.path.ends_with("keep") {
+                Err("keep is protected".to_string())
+            } else if operation.op == Op::Write && operation.path.ends_with("locked") {
+                Err("locked is read-only".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let recorder = {
+            let log = log.clone();
+            hooks::on_after(move |operation, result| {
+                if operation.path.starts_with("test_dir_hooks") {
+                    let target = operation.target.map(|target| target.to_path_buf());
+                    log.lock()
+                        .unwrap()
+                        .push((operation.op, target, result.is_ok()));
+                }
+            })
+        };
+
+        let keep = Filey::new("test_dir_hooks/keep").create_file().unwrap();
+        keep.write_atomic("data").unwrap();
+        match keep.remove() {
+            Err(Error::Refused { op, reason, .. }) => {
+                assert_eq!(op, Op::Remove);
+                assert_eq!(reason, "keep is protected");
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(keep.exists());
+        // Writing through the Write impl is checked as well.
+        let mut locked = Filey::new("test_dir_hooks/locked");
+        let e = std::io::Write::write_all(&mut locked, b"data").unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(!locked.exists());
+        keep.copy("test_dir_hooks/copy").unwrap();
+        // A failing operation is reported with its error.
+        assert!(Filey::new("test_dir_hooks/copy").create_file().is_err());
+
+        assert!(hooks::remove(refused));
+        assert!(hooks::remove(recorder));
+        assert!(!hooks::remove(recorder));
+        keep.remove().unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                (Op::Create, None, true),
+                (Op::Write, None, true),
+                (Op::Remove, None, false),
+                (Op::Write, None, false),
+                (Op::Copy, Some(PathBuf::from("test_dir_hooks/copy")), true),
+                (Op::Create, None, false),
+            ]
+        );
+        quit_at("test_dir_hooks");
+    }
+
+    #[test]
+    fn test_audit() {
+        use crate::{audit::AuditLog, hooks};
+
+        init_at("test_dir_audit");
+        let refused = hooks::on_before(|operation| {
+            if operation.path.ends_with("test_dir_audit/locked \"file\"") {
+                Err("locked".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        let audit = AuditLog::open("test_dir_audit/audit.jsonl").unwrap();
+        let file = Filey::new("test_dir_audit/a");
+        file.write_atomic("12345").unwrap();
+        file.copy("test_dir_audit/b").unwrap();
+        assert!(Filey::new("test_dir_audit/locked \"file\"")
+            .create_file()
+            .is_err());
+        drop(audit);
+        hooks::remove(refused);
+        file.remove().unwrap();
+
+        let log = std::fs::read_to_string("test_dir_audit/audit.jsonl").unwrap();
+        let lines = log
+            .lines()
+            .filter(|line| line.contains("test_dir_audit/"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        let pid = format!(",\"pid\":{}}}", std::process::id());
+        assert!(lines
+            .iter()
+            .all(|line| line.starts_with("{\"timestamp\":\"20") && line.ends_with(&pid)));
+        assert!(lines[0].contains(
+            "\"op\":\"Write\",\"paths\":[\"test_dir_audit/a\"],\"size\":5,\"result\":\"ok\""
+        ));
+        assert!(lines[1].contains(
+            "\"op\":\"Copy\",\"paths\":[\"test_dir_audit/a\",\"test_dir_audit/b\"],\"size\":5,"
+        ));
+        assert!(lines[2].contains(
+            "\"paths\":[\"test_dir_audit/locked \\\"file\\\"\"],\"size\":null,\"result\":\"refused\",\"error\":"
+        ));
+        quit_at("test_dir_audit");
+    }
+
+    #[test]
+    fn test_read_write_impl() {
+        use std::io::{Read, Write};
+
+        init_at("test_dir_read_write_impl");
+        let mut file = Filey::new("test_dir_read_write_impl/numbers");
+        for i in 0..1000 {
+            writeln!(file, "{}", i).unwrap();
+        }
+        file.flush().unwrap();
+        let expected = (0..1000).map(|i| format!("{}\n", i)).collect::<String>();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), expected);
+
+        // A clone gets its own handles.
+        let mut clone = file.clone();
+        writeln!(file, "1000").unwrap();
+        drop(file);
+        let mut contents = String::new();
+        Read::read_to_string(&mut clone, &mut contents).unwrap();
+        assert_eq!(contents, expected + "1000\n");
+        quit_at("test_dir_read_write_impl");
+    }
+
+    #[test]
+    fn test_into_reader_writer() {
+        use crate::WriteMode;
+        use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+
+        init_at("test_dir_into_reader_writer");
+        let path = "test_dir_into_reader_writer/file";
+        let mut writer = Filey::new(path).into_writer(WriteMode::Truncate).unwrap();
+        writer.write_all(b"hello world\n").unwrap();
+        writer.seek(SeekFrom::Start(6)).unwrap();
+        writer.write_all(b"there").unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "hello there\n");
+
+        let mut file = Filey::new(path);
+        write!(file, "first").unwrap();
+        let mut writer = file.into_writer(WriteMode::Append).unwrap();
+        writer.write_all(b" second\n").unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "first second\n");
+
+        let mut file = Filey::new(path);
+        let mut buf = [0; 6];
+        file.read_exact(&mut buf).unwrap();
+        let mut reader = file.into_reader().unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "second\n");
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(reader.lines().next().unwrap().unwrap(), "first second");
+
+        assert!(Filey::new("test_dir_into_reader_writer/none")
+            .into_reader()
+            .is_err());
+        quit_at("test_dir_into_reader_writer");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_count_entries() {
+        init_at("test_dir_count_entries");
+        let root = Filey::new("test_dir_count_entries");
+        std::fs::create_dir_all("test_dir_count_entries/a/b").unwrap();
+        std::fs::write("test_dir_count_entries/.hidden", "").unwrap();
+        std::fs::write("test_dir_count_entries/a/file", "").unwrap();
+        std::fs::write("test_dir_count_entries/a/b/file", "").unwrap();
+        std::os::unix::fs::symlink("a", "test_dir_count_entries/link").unwrap();
+
+        assert_eq!(root.count_entries(false).unwrap(), 3);
+        assert_eq!(root.count_entries(true).unwrap(), 6);
+        assert_eq!(root.count_files(false).unwrap(), 1);
+        assert_eq!(root.count_files(true).unwrap(), 3);
+        assert_eq!(root.count_dirs(false).unwrap(), 1);
+        assert_eq!(root.count_dirs(true).unwrap(), 2);
+        assert!(Filey::new("test_dir_count_entries/.hidden")
+            .count_entries(false)
+            .is_err());
+        quit_at("test_dir_count_entries");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_canonical_eq() {
+        init_at("test_dir_canonical_eq");
+        std::fs::create_dir("test_dir_canonical_eq/dir").unwrap();
+        std::fs::write("test_dir_canonical_eq/dir/file", "").unwrap();
+        std::os::unix::fs::symlink("dir", "test_dir_canonical_eq/link").unwrap();
+        std::fs::hard_link(
+            "test_dir_canonical_eq/dir/file",
+            "test_dir_canonical_eq/hard",
+        )
+        .unwrap();
+
+        let file = Filey::new("test_dir_canonical_eq/dir/file");
+        let absolute = std::env::current_dir().unwrap().join(file.path());
+        assert!(file.canonical_eq(&absolute).unwrap());
+        assert!(file
+            .canonical_eq("test_dir_canonical_eq/link/../dir/./file")
+            .unwrap());
+        assert!(file
+            .canonical_eq("test_dir_canonical_eq/link/file")
+            .unwrap());
+        assert!(!file.canonical_eq("test_dir_canonical_eq/hard").unwrap());
+        assert!(!file.canonical_eq("test_dir_canonical_eq/dir").unwrap());
+        // Paths which don't exist are resolved through their existing ancestors.
+        assert!(Filey::new("test_dir_canonical_eq/link/new")
+            .canonical_eq("test_dir_canonical_eq/dir/new")
+            .unwrap());
+        assert!(!Filey::new("test_dir_canonical_eq/dir/new")
+            .canonical_eq("test_dir_canonical_eq/dir/other")
+            .unwrap());
+        quit_at("test_dir_canonical_eq");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cached_hash() {
+        use crate::{
+            checksum::{self, Algorithm},
+            hooks, xattr, Op,
+        };
+
+        init_at("test_dir_cached_hash");
+        let path = "test_dir_cached_hash/file";
+        std::fs::write(path, "contents").unwrap();
+        let file = Filey::new(path);
+        let digest = checksum::hash_file(path, Algorithm::Sha256).unwrap();
+        assert_eq!(file.cached_hash(Algorithm::Sha256).unwrap(), digest);
+
+        // Extended attributes may not be supported where the tests run.
+        if let Ok(Some(cached)) = xattr::get(path, "user.filey.checksum") {
+            let cached = String::from_utf8(cached).unwrap();
+            assert!(cached.starts_with("sha256 8 "));
+            assert!(cached.ends_with(&digest.to_string()));
+
+            // The stored digest is returned without reading the file.
+            let fake = format!("{}{}", &cached[..cached.len() - 64], "00".repeat(32));
+            xattr::set(path, "user.filey.checksum", fake.as_bytes()).unwrap();
+            assert_eq!(
+                file.cached_hash(Algorithm::Sha256).unwrap().to_string(),
+                "00".repeat(32)
+            );
+            // Another algorithm or a changed file doesn't match the fingerprint.
+            assert_eq!(
+                file.cached_hash(Algorithm::Fnv1a64).unwrap(),
+                checksum::hash_file(path, Algorithm::Fnv1a64).unwrap()
+            );
+            std::fs::write(path, "changed!").unwrap();
+            assert_eq!(
+                file.cached_hash(Algorithm::Sha256).unwrap(),
+                checksum::hash_file(path, Algorithm::Sha256).unwrap()
+            );
+
+            // Storing the digest goes through the hooks, and refusing it only skips caching.
+            let refused = hooks::on_before(|operation| {
+                if operation.op == Op::SetXattr && operation.path.ends_with("refused") {
+                    Err("no caching".to_string())
+                } else {
+                    Ok(())
+                }
+            });
+            let path = "test_dir_cached_hash/refused";
+            std::fs::write(path, "contents").unwrap();
+            assert_eq!(
+                Filey::new(path).cached_hash(Algorithm::Sha256).unwrap(),
+                digest
+            );
+            assert!(hooks::remove(refused));
+            assert_eq!(xattr::get(path, "user.filey.checksum").unwrap(), None);
+        }
+        quit_at("test_dir_cached_hash");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_set_owner_recursive() {
+        use crate::OwnerOptions;
+        use std::os::unix::fs::MetadataExt;
+
+        init_at("test_dir_set_owner_recursive");
+        // Changing owners needs root.
+        if unsafe { libc::geteuid() } != 0 {
+            return quit_at("test_dir_set_owner_recursive");
+        }
+        let uid = |path: &str| std::fs::symlink_metadata(path).unwrap().uid();
+        std::fs::create_dir_all("test_dir_set_owner_recursive/a/b").unwrap();
+        std::fs::write("test_dir_set_owner_recursive/a/b/file", "").unwrap();
+        std::os::unix::fs::symlink("a", "test_dir_set_owner_recursive/link").unwrap();
+
+        let root = Filey::new("test_dir_set_owner_recursive");
+        let options = OwnerOptions {
+            skip_symlinks: true,
+            ..Default::default()
+        };
+        root.set_owner_recursive_with(Some(65534), None, &options)
+            .unwrap();
+        assert_eq!(uid("test_dir_set_owner_recursive"), 65534);
+        assert_eq!(uid("test_dir_set_owner_recursive/a/b/file"), 65534);
+        assert_eq!(uid("test_dir_set_owner_recursive/link"), 0);
+
+        root.set_owner_recursive(Some(65533), Some(65533)).unwrap();
+        assert_eq!(uid("test_dir_set_owner_recursive/link"), 65533);
+        assert_eq!(
+            std::fs::metadata("test_dir_set_owner_recursive/a/b/file")
+                .unwrap()
+                .gid(),
+            65533
+        );
+        assert!(Filey::new("test_dir_set_owner_recursive/missing")
+            .set_owner_recursive(Some(0), None)
+            .is_err());
+        quit_at("test_dir_set_owner_recursive");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_create_with_mode() {
+        use crate::{set_process_umask, umask, CreateOptions};
+        use std::os::unix::fs::PermissionsExt;
+
+        init_at("test_dir_create_with_mode");
+        // Restores the same mask, as other tests run in parallel.
+        let mask = umask();
+        assert_eq!(set_process_umask(mask), mask);
+        assert_eq!(umask(), mask);
+
+        let mode = |path: &str| std::fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        let masked = Filey::new("test_dir_create_with_mode/masked");
+        masked
+            .create_with_mode(0o777, &CreateOptions::default())
+            .unwrap();
+        assert_eq!(mode("test_dir_create_with_mode/masked"), 0o777 & !mask);
+        let exact = Filey::new("test_dir_create_with_mode/exact");
+        exact
+            .create_with_mode(0o777, &CreateOptions { exact_mode: true })
+            .unwrap();
+        assert_eq!(mode("test_dir_create_with_mode/exact"), 0o777);
+        assert!(exact
+            .create_with_mode(0o600, &CreateOptions::default())
+            .is_err());
+        quit_at("test_dir_create_with_mode");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_permissions_diff_and_audit() {
+        use crate::{audit, PermissionBit, PermissionClass, Permissions};
+        use std::{fs::set_permissions, os::unix::fs::PermissionsExt, path::PathBuf};
+
+        init_at("test_dir_permissions_audit");
+        let chmod = |path: &str, mode: u32| {
+            set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap()
+        };
+        std::fs::create_dir("test_dir_permissions_audit/shared").unwrap();
+        std::fs::write("test_dir_permissions_audit/shared/file", "").unwrap();
+        std::fs::write("test_dir_permissions_audit/tool", "").unwrap();
+        std::os::unix::fs::symlink("tool", "test_dir_permissions_audit/link").unwrap();
+
+        chmod("test_dir_permissions_audit/tool", 0o644);
+        let before = Permissions::from_path("test_dir_permissions_audit/tool").unwrap();
+        chmod("test_dir_permissions_audit/tool", 0o4705);
+        let after = Permissions::from_path("test_dir_permissions_audit/tool").unwrap();
+        let changes = before.diff(&after);
+        assert_eq!(
+            changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            ["u+x", "g-r", "o+x"]
+        );
+        assert_eq!(changes[1].class, PermissionClass::Group);
+        assert_eq!(changes[1].bit, PermissionBit::Read);
+        assert!(!changes[1].added);
+        assert!(after.diff(&after).is_empty());
+
+        chmod("test_dir_permissions_audit", 0o755);
+        chmod("test_dir_permissions_audit/shared", 0o1777);
+        chmod("test_dir_permissions_audit/shared/file", 0o644);
+        let findings = audit::world_writable("test_dir_permissions_audit").unwrap();
+        assert_eq!(
+            findings
+                .iter()
+                .map(|f| (f.path.clone(), f.mode, f.world_writable, f.setuid))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    PathBuf::from("test_dir_permissions_audit/shared"),
+                    0o1777,
+                    true,
+                    false
+                ),
+                (
+                    PathBuf::from("test_dir_permissions_audit/tool"),
+                    0o4705,
+                    false,
+                    true
+                ),
+            ]
+        );
+        assert!(audit::world_writable("test_dir_permissions_audit/missing").is_err());
+        quit_at("test_dir_permissions_audit");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_dir() {
+        use crate::Dir;
+        use std::io::{Read, Write};
+
+        init_at("test_dir_dir/scratch");
+        let dir = Dir::open("test_dir_dir/scratch").unwrap();
+        dir.create("file", 0o600)
+            .unwrap()
+            .write_all(b"contents")
+            .unwrap();
+        assert!(dir.create("file", 0o600).is_err());
+        let sub = dir.create_dir("sub", 0o700).unwrap();
+        assert_eq!(sub.path(), Path::new("test_dir_dir/scratch/sub"));
+        dir.rename_into("file", &sub, "moved").unwrap();
+        sub.rename("moved", "file").unwrap();
+
+        // Moving the directory doesn't affect the handles.
+        std::fs::rename("test_dir_dir/scratch", "test_dir_dir/moved").unwrap();
+        std::fs::create_dir("test_dir_dir/scratch").unwrap();
+        let mut contents = String::new();
+        sub.open_file("file")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "contents");
+        assert!(Path::new("test_dir_dir/moved/sub/file").exists());
+
+        std::os::unix::fs::symlink("/etc", "test_dir_dir/moved/link").unwrap();
+        assert!(dir.open_dir("link").is_err());
+        assert!(dir.open_file("link").is_err());
+        assert!(dir.open_file("sub/file").is_err());
+        assert!(dir.open_file("..").is_err());
+        // A trailing slash would make openat(2) follow the link.
+        std::fs::create_dir("test_dir_dir/outside").unwrap();
+        std::os::unix::fs::symlink("../outside", "test_dir_dir/moved/escape").unwrap();
+        assert!(dir.open_dir("escape/").is_err());
+        assert!(dir.create_dir("escape/", 0o700).is_err());
+        assert!(dir.remove_dir("escape/").is_err());
+        assert!(std::fs::read_dir("test_dir_dir/outside")
+            .unwrap()
+            .next()
+            .is_none());
+        dir.remove("escape").unwrap();
+        dir.remove("link").unwrap();
+        assert!(dir.remove_dir("sub").is_err());
+        sub.remove("file").unwrap();
+        dir.remove_dir("sub").unwrap();
+        assert!(!Path::new("test_dir_dir/moved/sub").exists());
+        assert!(Dir::open("test_dir_dir/missing").is_err());
+
+        // Errors report the path of the entry, not just its name.
+        let e = dir.open_file("missing").unwrap_err().to_string();
+        assert!(e.contains("test_dir_dir/scratch/missing"), "{}", e);
+        let e = dir.rename("missing", "other").unwrap_err().to_string();
+        assert!(e.contains("test_dir_dir/scratch/other"), "{}", e);
+        quit_at("test_dir_dir");
+    }
+
+    #[test]
+    fn test_move_to_with_report() {
+        use crate::{MoveOptions, MoveStrategy};
+
+        init_at("test_dir_move_report/into");
+        std::fs::write("test_dir_move_report/file", "contents").unwrap();
+        let mut file = Filey::new("test_dir_move_report/file");
+        let report = file
+            .move_to_with_report("test_dir_move_report/into", &MoveOptions::default())
+            .unwrap();
+        assert_eq!(report.strategy, MoveStrategy::Rename);
+        assert_eq!(report.path, Path::new("test_dir_move_report/into/file"));
+        assert_eq!(file.path(), &report.path);
+
+        // A tmpfs is another filesystem, which can't clone files.
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let dev = |path: &str| std::fs::metadata(path).unwrap().dev();
+            if Path::new("/dev/shm").is_dir() && dev("/dev/shm") != dev("test_dir_move_report") {
+                let elsewhere = format!("/dev/shm/test_dir_move_report_{}", std::process::id());
+                let options = MoveOptions {
+                    rename_only: true,
+                    ..Default::default()
+                };
+                let mut dir = Filey::new("test_dir_move_report/into");
+                assert!(dir.move_to_with_report(&elsewhere, &options).is_err());
+                let report = dir
+                    .move_to_with_report(&elsewhere, &MoveOptions::default())
+                    .unwrap();
+                assert_eq!(report.strategy, MoveStrategy::Copy);
+                assert!(!Path::new("test_dir_move_report/into").exists());
+                assert_eq!(
+                    std::fs::read_to_string(Path::new(&elsewhere).join("file")).unwrap(),
+                    "contents"
+                );
+                remove_dir_all(&elsewhere).unwrap();
+            }
+        }
+        quit_at("test_dir_move_report");
+    }
+
+    #[test]
+    fn test_copy_resumable() {
+        use crate::{
+            checksum::{self, Algorithm},
+            resume::{self, CHUNK_SIZE},
+        };
+        use std::fmt::Write as _;
+
+        init_at("test_dir_copy_resumable");
+        let chunk = CHUNK_SIZE as usize;
+        // The second chunk is zeros, which is skipped.
+        let mut data = vec![0u8; chunk * 3 + chunk / 2];
+        for (i, byte) in data.iter_mut().enumerate() {
+            if !(chunk..chunk * 2).contains(&i) {
+                *byte = (i % 251) as u8;
+            }
+        }
+        std::fs::write("test_dir_copy_resumable/source", &data).unwrap();
+        let source = Filey::new("test_dir_copy_resumable/source");
+        let (copy, state) = (
+            "test_dir_copy_resumable/copy",
+            "test_dir_copy_resumable/state",
+        );
+
+        let report = source.copy_resumable(copy, state).unwrap();
+        assert_eq!((report.verified, report.copied), (0, data.len() as u64));
+        assert_eq!(std::fs::read(copy).unwrap(), data);
+        assert!(!Path::new(state).exists());
+
+        // Interrupted after two chunks, the second of which has been damaged since.
+        let metadata = std::fs::metadata("test_dir_copy_resumable/source").unwrap();
+        let mut recorded = resume::header(&metadata);
+        for (index, chunk) in data.chunks(chunk).take(2).enumerate() {
+            let digest = checksum::hash_bytes(chunk, Algorithm::Sha256);
+            write!(recorded, "\n{} {}", index, digest).unwrap();
+        }
+        recorded.push_str("\n2 trunc");
+        std::fs::write(state, recorded).unwrap();
+        let mut damaged = data[..chunk * 2].to_vec();
+        damaged[chunk + 7] = 1;
+        std::fs::write(copy, damaged).unwrap();
+        let report = source.copy_resumable(copy, state).unwrap();
+        assert_eq!(
+            (report.verified, report.copied),
+            (CHUNK_SIZE, data.len() as u64 - CHUNK_SIZE)
+        );
+        assert_eq!(std::fs::read(copy).unwrap(), data);
+
+        // Progress for another version of the file is ignored.
+        std::fs::write(state, "filey-copy-resumable 1 0.0 1048576\n0 00\n").unwrap();
+        let report = source.copy_resumable(copy, state).unwrap();
+        assert_eq!(report.verified, 0);
+        assert_eq!(std::fs::read(copy).unwrap(), data);
+        quit_at("test_dir_copy_resumable");
+    }
+
+    #[test]
+    fn test_io_stats() {
+        use crate::{
+            checksum::{self, Algorithm},
+            BatchOptions, CopyOptions, IoStats,
+        };
+        use std::time::Duration;
+
+        init_at("test_dir_io_stats/tree/sub");
+        std::fs::write("test_dir_io_stats/tree/a", vec![1; 1000]).unwrap();
+        std::fs::write("test_dir_io_stats/tree/sub/b", vec![2; 24]).unwrap();
+
+        let stats = Filey::new("test_dir_io_stats/tree/a")
+            .copy_with_stats("test_dir_io_stats/a", &CopyOptions::default())
+            .unwrap();
+        assert_eq!(
+            (stats.bytes_read, stats.bytes_written, stats.files),
+            (1000, 1000, 1)
+        );
+        let stats = Filey::new("test_dir_io_stats/tree")
+            .copy_dir_filtered_with_stats(
+                "test_dir_io_stats/copy",
+                |_| true,
+                &BatchOptions::default(),
+            )
+            .unwrap();
+        assert_eq!((stats.bytes_written, stats.files), (1024, 2));
+
+        let (digest, stats) =
+            checksum::hash_file_with_stats("test_dir_io_stats/a", Algorithm::Fnv1a64).unwrap();
+        assert_eq!(
+            digest,
+            checksum::hash_file("test_dir_io_stats/a", Algorithm::Fnv1a64).unwrap()
+        );
+        assert_eq!((stats.bytes_read, stats.bytes_written), (1000, 0));
+        let (_, stats) =
+            checksum::hash_tree_with_stats("test_dir_io_stats/tree", Algorithm::Fnv1a64, 2)
+                .unwrap();
+        assert_eq!((stats.bytes_read, stats.files), (1024, 2));
+
+        let stats = IoStats {
+            bytes_read: 3 * 1024 * 1024,
+            files: 1,
+            elapsed: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        assert_eq!(stats.throughput(), 2 * 1024 * 1024);
+        assert_eq!(stats.to_string(), "3.0 MiB in 1.5 s (2.0 MiB/s)");
+        assert_eq!(IoStats::default().throughput(), 0);
+        quit_at("test_dir_io_stats");
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        use crate::{
+            checksum::{self, Algorithm, HashOptions},
+            BatchOptions, BufferSize, CopyOptions, WriteMode,
+        };
+        use std::io::{BufRead, Write};
+
+        init_at("test_dir_buffer_size");
+        let data = (0..100_000).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+        std::fs::write("test_dir_buffer_size/source", &data).unwrap();
+        let source = Filey::new("test_dir_buffer_size/source");
+        init_at("test_dir_buffer_size/tree/sub");
+        std::fs::write("test_dir_buffer_size/tree/sub/file", &data).unwrap();
+
+        for (i, buffer_size) in [BufferSize::Bytes(7), BufferSize::Auto]
+            .into_iter()
+            .enumerate()
+        {
+            let copy = format!("test_dir_buffer_size/copy_{}", i);
+            let options = CopyOptions {
+                buffer_size,
+                ..Default::default()
+            };
+            let stats = source.copy_with_stats(&copy, &options).unwrap();
+            assert_eq!(stats.bytes_written, data.len() as u64);
+            assert_eq!(std::fs::read(&copy).unwrap(), data);
+
+            let options = BatchOptions {
+                buffer_size,
+                ..Default::default()
+            };
+            Filey::new("test_dir_buffer_size/tree")
+                .copy_dir_filtered_with(format!("{}_tree", copy), |_| true, &options)
+                .unwrap();
+            assert_eq!(
+                std::fs::read(format!("{}_tree/sub/file", copy)).unwrap(),
+                data
+            );
+
+            let options = HashOptions { buffer_size };
+            assert_eq!(
+                checksum::hash_file_with(
+                    "test_dir_buffer_size/source",
+                    Algorithm::Sha256,
+                    &options
+                )
+                .unwrap(),
+                checksum::hash_file("test_dir_buffer_size/source", Algorithm::Sha256).unwrap()
+            );
+        }
+
+        let file = File::open("test_dir_buffer_size/source").unwrap();
+        let auto = BufferSize::Auto.resolve(&file, 0);
+        assert!(auto >= 128 * 1024);
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(auto % file.metadata().unwrap().blksize() as usize, 0);
+        }
+        assert_eq!(BufferSize::Bytes(0).resolve(&file, 0), 1);
+        assert_eq!(BufferSize::Default.resolve(&file, 42), 42);
+
+        let mut writer = Filey::new("test_dir_buffer_size/lines")
+            .into_writer_with(WriteMode::Truncate, BufferSize::Bytes(3))
+            .unwrap();
+        writeln!(writer, "first\nsecond").unwrap();
+        writer.sync().unwrap();
+        let lines = Filey::new("test_dir_buffer_size/lines")
+            .into_reader_with(BufferSize::Bytes(2))
+            .unwrap()
+            .lines()
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, ["first", "second"]);
+        quit_at("test_dir_buffer_size");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_walk() {
+        use crate::{Error, WalkOptions};
+        use std::time::Duration;
+
+        init_at("test_dir_walk/a/b");
+        std::fs::write("test_dir_walk/top", "").unwrap();
+        std::fs::write("test_dir_walk/a/middle", "").unwrap();
+        std::fs::write("test_dir_walk/a/b/bottom", "").unwrap();
+        std::os::unix::fs::symlink("a", "test_dir_walk/link").unwrap();
+        // Leads back to an ancestor.
+        std::os::unix::fs::symlink("..", "test_dir_walk/a/b/up").unwrap();
+
+        let root = Filey::new("test_dir_walk");
+        let walk = |options: &WalkOptions| {
+            let mut paths = root
+                .walk_with(options)
+                .unwrap()
+                .map(|entry| {
+                    let entry = entry.unwrap();
+                    let path = entry.path();
+                    (
+                        path.strip_prefix("test_dir_walk").unwrap().to_path_buf(),
+                        entry.depth(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+        let all = walk(&WalkOptions::default());
+        assert_eq!(
+            all.iter()
+                .map(|(path, depth)| (path.to_str().unwrap(), *depth))
+                .collect::<Vec<_>>(),
+            [
+                ("a", 1),
+                ("a/b", 2),
+                ("a/b/bottom", 3),
+                ("a/b/up", 3),
+                ("a/middle", 2),
+                ("link", 1),
+                ("top", 1)
+            ]
+        );
+        assert_eq!(root.walk().unwrap().count(), all.len());
+
+        let options = WalkOptions {
+            max_depth: Some(2),
+            file_types: vec![FileTypes::File],
+            ..Default::default()
+        };
+        assert_eq!(
+            walk(&options),
+            [
+                (Path::new("a/middle").to_path_buf(), 2),
+                (Path::new("top").to_path_buf(), 1)
+            ]
+        );
+
+        // Following links descends into link but not back up through a/b/up.
+        let options = WalkOptions {
+            follow_symlinks: true,
+            file_types: vec![FileTypes::File],
+            ..Default::default()
+        };
+        assert_eq!(
+            walk(&options)
+                .iter()
+                .map(|(path, _)| path.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            [
+                "a/b/bottom",
+                "a/middle",
+                "link/b/bottom",
+                "link/middle",
+                "top"
+            ]
+        );
+
+        // With a timeout each directory is read on a worker within the deadline.
+        let options = WalkOptions {
+            timeout: Some(Duration::from_secs(10)),
+            sorted: true,
+            ..Default::default()
+        };
+        assert_eq!(walk(&options), all);
+        // A deadline which has already passed gives up before the worker is done, unless it
+        // happens to be quicker than the check, which doesn't happen many times in a row.
+        let options = WalkOptions {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        assert!((0..20).any(|_| matches!(root.walk_with(&options), Err(Error::Timeout { .. }))));
+
+        assert!(Filey::new("test_dir_walk/top").walk().is_err());
+        quit_at("test_dir_walk");
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_copy_symlink_behavior() {
+        use crate::{BatchOptions, SymlinkBehavior};
+        use std::fs::{read_link, read_to_string, symlink_metadata};
+
+        init_at("test_dir_symlink_behavior/tree/dir");
+        std::fs::write("test_dir_symlink_behavior/tree/dir/file", "data").unwrap();
+        symlink("dir/file", "test_dir_symlink_behavior/tree/file_link").unwrap();
+        symlink("dir", "test_dir_symlink_behavior/tree/dir_link").unwrap();
+
+        let tree = Filey::new("test_dir_symlink_behavior/tree");
+        let copy = |to: &str, symlinks: SymlinkBehavior| {
+            let options = BatchOptions {
+                symlinks,
+                ..Default::default()
+            };
+            tree.copy_dir_filtered_with(to, |_| true, &options)
+        };
+
+        copy(
+            "test_dir_symlink_behavior/preserve",
+            SymlinkBehavior::Preserve,
+        )
+        .unwrap();
+        assert_eq!(
+            read_link("test_dir_symlink_behavior/preserve/file_link").unwrap(),
+            Path::new("dir/file")
+        );
+        assert_eq!(
+            read_link("test_dir_symlink_behavior/preserve/dir_link").unwrap(),
+            Path::new("dir")
+        );
+
+        copy("test_dir_symlink_behavior/skip", SymlinkBehavior::Skip).unwrap();
+        assert!(Path::new("test_dir_symlink_behavior/skip/dir/file").exists());
+        assert!(symlink_metadata("test_dir_symlink_behavior/skip/file_link").is_err());
+        assert!(symlink_metadata("test_dir_symlink_behavior/skip/dir_link").is_err());
+
+        copy("test_dir_symlink_behavior/follow", SymlinkBehavior::Follow).unwrap();
+        for path in ["file_link", "dir_link/file"] {
+            let path = Path::new("test_dir_symlink_behavior/follow").join(path);
+            assert_eq!(read_to_string(&path).unwrap(), "data");
+        }
+        assert!(
+            !symlink_metadata("test_dir_symlink_behavior/follow/dir_link")
+                .unwrap()
+                .is_symlink()
+        );
+
+        // A link back to an ancestor and a broken link can't be followed.
+        symlink("..", "test_dir_symlink_behavior/tree/dir/up").unwrap();
+        assert!(copy("test_dir_symlink_behavior/loop", SymlinkBehavior::Follow).is_err());
+        std::fs::remove_file("test_dir_symlink_behavior/tree/dir/up").unwrap();
+        symlink("missing", "test_dir_symlink_behavior/tree/broken").unwrap();
+        assert!(copy("test_dir_symlink_behavior/broken", SymlinkBehavior::Follow).is_err());
+        copy("test_dir_symlink_behavior/kept", SymlinkBehavior::Preserve).unwrap();
+        quit_at("test_dir_symlink_behavior");
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_copy_recursive() {
+        use crate::{Error, OnConflict};
+        use std::{
+            fs::{metadata, read_link, read_to_string, set_permissions, write},
+            os::unix::fs::PermissionsExt,
+        };
+
+        init_at("test_dir_copy_recursive/src/sub");
+        write("test_dir_copy_recursive/src/a", "new a").unwrap();
+        write("test_dir_copy_recursive/src/sub/b", "new b").unwrap();
+        set_permissions(
+            "test_dir_copy_recursive/src/sub/b",
+            std::fs::Permissions::from_mode(0o640),
+        )
+        .unwrap();
+        set_permissions(
+            "test_dir_copy_recursive/src/sub",
+            std::fs::Permissions::from_mode(0o750),
+        )
+        .unwrap();
+        symlink("sub/b", "test_dir_copy_recursive/src/link").unwrap();
+
+        let src = Filey::new("test_dir_copy_recursive/src");
+        src.copy_recursive("test_dir_copy_recursive/copy", OnConflict::Fail)
+            .unwrap();
+        let mode = |path: &str| metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode("test_dir_copy_recursive/copy/sub"), 0o750);
+        assert_eq!(mode("test_dir_copy_recursive/copy/sub/b"), 0o640);
+        assert_eq!(
+            read_link("test_dir_copy_recursive/copy/link").unwrap(),
+            Path::new("sub/b")
+        );
+
+        // An existing tree is merged into.
+        init_at("test_dir_copy_recursive/dst/sub");
+        write("test_dir_copy_recursive/dst/a", "old a").unwrap();
+        write("test_dir_copy_recursive/dst/keep", "keep").unwrap();
+        let e = src
+            .copy_recursive("test_dir_copy_recursive/dst", OnConflict::Fail)
+            .unwrap_err();
+        assert!(matches!(e, Error::AlreadyExists { .. }));
+
+        src.copy_recursive("test_dir_copy_recursive/dst", OnConflict::Skip)
+            .unwrap();
+        let read = |path: &str| read_to_string(format!("test_dir_copy_recursive/dst/{}", path));
+        assert_eq!(read("a").unwrap(), "old a");
+        assert_eq!(read("sub/b").unwrap(), "new b");
+        assert_eq!(read("keep").unwrap(), "keep");
+
+        src.copy_recursive("test_dir_copy_recursive/dst", OnConflict::Overwrite)
+            .unwrap();
+        assert_eq!(read("a").unwrap(), "new a");
+        assert_eq!(read("link").unwrap(), "new b");
+        assert_eq!(read("keep").unwrap(), "keep");
+
+        // A single file is copied too.
+        Filey::new("test_dir_copy_recursive/src/a")
+            .copy_recursive("test_dir_copy_recursive/single", OnConflict::Fail)
+            .unwrap();
+        assert_eq!(
+            read_to_string("test_dir_copy_recursive/single").unwrap(),
+            "new a"
+        );
+
+        // A directory can't be copied into itself, even through a link.
+        symlink("src", "test_dir_copy_recursive/link_to_src").unwrap();
+        for dest in ["src/sub/copy", "src", "link_to_src/copy"] {
+            assert!(src
+                .copy_recursive(
+                    format!("test_dir_copy_recursive/{}", dest),
+                    OnConflict::Overwrite
+                )
+                .is_err());
+        }
+        assert!(!Path::new("test_dir_copy_recursive/src/sub/copy").exists());
+        assert_eq!(
+            read_to_string("test_dir_copy_recursive/src/a").unwrap(),
+            "new a"
+        );
+        quit_at("test_dir_copy_recursive");
+    }
+
+    #[test]
+    fn test_glob() {
+        use crate::Glob;
+
+        let glob = |pattern: &str| Glob::new(pattern).unwrap();
+        assert!(glob("*.rs").is_match("src/a/lib.rs"));
+        assert!(!glob("*.rs").is_match("src/lib.rs.bak"));
+        assert!(glob("src/*.rs").is_match("./src/main.rs"));
+        assert!(!glob("src/*.rs").is_match("src/a/main.rs"));
+        assert!(glob("src/**/*.rs").is_match("src/main.rs"));
+        assert!(glob("src/**/*.rs").is_match("src/a/b/main.rs"));
+        assert!(glob("**/target").is_match("a/target"));
+        assert!(glob("file?.[a-c]").is_match("file1.b"));
+        assert!(!glob("file?.[!a-c]").is_match("file1.b"));
+        assert!(glob("[]]").is_match("]"));
+        assert!(glob(r"\*").is_match("*"));
+        assert!(!glob(r"\*").is_match("a"));
+        assert!(Glob::new("[abc").is_err());
+        assert_eq!(glob("**/*.rs").to_string(), "**/*.rs");
+
+        assert!(glob("a*b*.rs").is_match("axxbyyb.rs"));
+        assert!(glob("*a*").is_match("a"));
+        assert!(!glob("*a*?").is_match("a"));
+        assert!(glob("a/**/b/**/c").is_match("a/b/x/b/c"));
+        assert!(!glob("a/**/b/**/c").is_match("a/x/c"));
+        // Patterns which would backtrack exponentially return at once.
+        let name = "a".repeat(64);
+        assert!(!glob(&format!("{}c", "a*".repeat(32))).is_match(format!("{}b", name)));
+        assert!(glob(&format!("{}b", "*a".repeat(32))).is_match(format!("{}b", name)));
+        let path = "x/".repeat(64);
+        assert!(!glob(&format!("{}z", "**/x/".repeat(16))).is_match(format!("{}y", path)));
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_filter() {
+        use crate::{snapshot, Filter, Glob, OnConflict, WalkOptions};
+        use std::fs::write;
+
+        init_at("test_dir_filter/src/target");
+        init_at("test_dir_filter/src/.git");
+        write("test_dir_filter/src/main.rs", "fn main() {}").unwrap();
+        write("test_dir_filter/src/empty.rs", "").unwrap();
+        write("test_dir_filter/src/notes.txt", "notes").unwrap();
+        write("test_dir_filter/src/target/out.rs", "").unwrap();
+        write("test_dir_filter/src/.git/config.rs", "").unwrap();
+
+        let filter = Filter {
+            include: vec![Glob::new("*.rs").unwrap()],
+            exclude: vec![Glob::new("target").unwrap()],
+            min_size: Some(1),
+            skip_hidden: true,
+            ..Default::default()
+        };
+        let src = Filey::new("test_dir_filter/src");
+
+        let options = WalkOptions {
+            filter: filter.clone(),
+            file_types: vec![FileTypes::File],
+            ..Default::default()
+        };
+        let walked = src
+            .walk_with(&options)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(walked, [Path::new("test_dir_filter/src/main.rs")]);
+
+        src.copy_recursive_filtered("test_dir_filter/copy", OnConflict::Fail, &filter)
+            .unwrap();
+        let copied = snapshot::take("test_dir_filter/copy").unwrap();
+        assert_eq!(
+            copied.entries.keys().collect::<Vec<_>>(),
+            [Path::new("main.rs")]
+        );
+
+        let options = snapshot::SnapshotOptions {
+            filter,
+            ..Default::default()
+        };
+        let taken = snapshot::take_with("test_dir_filter/src", &options).unwrap();
+        assert_eq!(
+            taken.entries.keys().collect::<Vec<_>>(),
+            [Path::new("main.rs")]
+        );
+        assert!(Filter::default().matches_in(Path::new("test_dir_filter/src"), Path::new(".git")));
+        #[cfg(feature = "search")]
+        {
+            use crate::search::{grep_tree, SearchOptions};
+
+            let options = SearchOptions {
+                filter: options.filter,
+                ..Default::default()
+            };
+            let found = grep_tree("test_dir_filter/src", "fn", &options).unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].path(), Path::new("test_dir_filter/src/main.rs"));
+        }
+        quit_at("test_dir_filter");
+    }
+
+    #[test]
+    fn test_sorted_listing() {
+        use crate::{ListOptions, WalkOptions};
+
+        init_at("test_dir_sorted/d");
+        for name in ["b", "a", "C", "d/z", "d/y"] {
+            std::fs::write(format!("test_dir_sorted/{}", name), "").unwrap();
+        }
+        let dir = Filey::new("test_dir_sorted");
+        let names = dir
+            .list_with(&ListOptions { sorted: true })
+            .unwrap()
+            .iter()
+            .map(|file| file.file_name().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["C", "a", "b", "d"]);
+
+        let options = WalkOptions {
+            sorted: true,
+            ..Default::default()
+        };
+        let walked = dir
+            .walk_with(&options)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        let expected = ["C", "a", "b", "d", "d/y", "d/z"]
+            .iter()
+            .map(|name| Path::new("test_dir_sorted").join(name))
+            .collect::<Vec<_>>();
+        assert_eq!(walked, expected);
+        quit_at("test_dir_sorted");
+    }
+
+    #[test]
+    fn test_filey_glob() {
+        use crate::GlobOptions;
+
+        init_at("test_dir_glob/src/nested");
+        for name in [
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/MAIN.RS",
+            "src/nested/Cargo.toml",
+        ] {
+            std::fs::write(format!("test_dir_glob/{}", name), "").unwrap();
+        }
+        let dir = Filey::new("test_dir_glob");
+        let paths = |files: Vec<Filey>| {
+            files
+                .iter()
+                .map(|file| file.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            paths(dir.glob("*.toml").unwrap()),
+            ["test_dir_glob/Cargo.toml"]
+        );
+        assert_eq!(
+            paths(dir.glob("**/Cargo.toml").unwrap()),
+            [
+                "test_dir_glob/Cargo.toml",
+                "test_dir_glob/src/nested/Cargo.toml"
+            ]
+        );
+        assert_eq!(
+            paths(dir.glob("src/*.rs").unwrap()),
+            ["test_dir_glob/src/lib.rs"]
+        );
+        let options = GlobOptions {
+            case_insensitive: true,
+        };
+        assert_eq!(
+            paths(dir.glob_with("SRC/*.[r]s", &options).unwrap()),
+            ["test_dir_glob/src/MAIN.RS", "test_dir_glob/src/lib.rs"]
+        );
+        assert!(dir.glob("[").is_err());
+        quit_at("test_dir_glob");
+    }
+
+    #[test]
+    fn test_on_change() {
+        use crate::Change;
+        use std::{sync::mpsc, time::Duration};
+
+        init_at("test_dir_on_change");
+        let path = "test_dir_on_change/config";
+        let (sender, receiver) = mpsc::channel();
+        let watcher = Filey::new(path)
+            .on_change(Duration::from_millis(50), move |change| {
+                sender.send(change).unwrap();
+            })
+            .unwrap();
+        let next = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        std::fs::write(path, "a = 1").unwrap();
+        assert!(matches!(next(), Change::Modified(contents) if contents == b"a = 1"));
+        std::fs::write(path, "a = 22").unwrap();
+        assert!(matches!(next(), Change::Modified(contents) if contents == b"a = 22"));
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(next(), Change::Removed));
+
+        watcher.stop();
+        std::fs::write(path, "a = 3").unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+        // Through a symbolic link, edits of the target are seen, and so is a replacement with
+        // the same size and modification time.
+        let link = "test_dir_on_change/link";
+        symlink("config", link).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = Filey::new(link)
+            .on_change(Duration::from_millis(50), move |change| {
+                sender.send(change).unwrap();
+            })
+            .unwrap();
+        let next = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        std::fs::write(path, "a = 4").unwrap();
+        assert!(matches!(next(), Change::Modified(contents) if contents == b"a = 4"));
+        let modified = std::fs::metadata(path).unwrap().modified().unwrap();
+        let temp = "test_dir_on_change/config.tmp";
+        std::fs::write(temp, "a = 5").unwrap();
+        File::options()
+            .write(true)
+            .open(temp)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        std::fs::rename(temp, path).unwrap();
+        assert!(matches!(next(), Change::Modified(contents) if contents == b"a = 5"));
+        quit_at("test_dir_on_change");
+    }
+
+    #[test]
+    fn test_read_to_string_and_vec() {
+        init_at("test_dir_read_to");
+        std::fs::write("test_dir_read_to/text", "hello\n").unwrap();
+        std::fs::write("test_dir_read_to/binary", [0xff, 0x00]).unwrap();
+
+        let text = Filey::new("test_dir_read_to/text");
+        assert_eq!(text.read_to_string().unwrap(), "hello\n");
+        assert_eq!(text.read_to_vec().unwrap(), b"hello\n");
+        let binary = Filey::new("test_dir_read_to/binary");
+        assert_eq!(binary.read_to_vec().unwrap(), [0xff, 0x00]);
+        assert!(binary.read_to_string().is_err());
+        assert!(Filey::new("test_dir_read_to/missing")
+            .read_to_vec()
+            .is_err());
+        quit_at("test_dir_read_to");
+    }
+
+    #[test]
+    fn test_quota_watchdog() {
+        use crate::quota::{Policy, Watchdog};
+        use std::{
+            fs::{write, File},
+            time::{Duration, SystemTime},
+        };
+
+        init_at("test_dir_quota/sub");
+        let now = SystemTime::now();
+        // name, size, age in seconds
+        for (name, size, age) in [
+            ("a", 100, 30),
+            ("b", 300, 20),
+            ("sub/c", 200, 10),
+            ("d", 50, 0),
+        ] {
+            let path = format!("test_dir_quota/{}", name);
+            write(&path, vec![0; size]).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        let oldest = Watchdog::new("test_dir_quota", 500, Policy::OldestFirst);
+        assert_eq!(oldest.usage().unwrap(), 650);
+        let report = oldest.enforce().unwrap();
+        assert_eq!(
+            report.removed,
+            [Path::new("test_dir_quota/a"), Path::new("test_dir_quota/b")]
+        );
+        assert_eq!((report.freed, report.usage), (400, 250));
+        assert!(oldest.enforce().unwrap().removed.is_empty());
+
+        let largest = Watchdog::new("test_dir_quota", 100, Policy::LargestFirst);
+        let report = largest.enforce().unwrap();
+        assert_eq!(report.removed, [Path::new("test_dir_quota/sub/c")]);
+        assert_eq!(report.usage, 50);
+        assert!(Path::new("test_dir_quota/sub").is_dir());
+        quit_at("test_dir_quota");
+    }
+
+    #[test]
+    fn test_append() {
+        use std::io::Write;
+
+        init_at("test_dir_append");
+        let log = Filey::new("test_dir_append/log");
+        log.append("first\n").unwrap();
+        log.append(b"second\n").unwrap();
+        let mut writer = log.open_append().unwrap();
+        writeln!(writer, "third").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        log.append("fourth\n").unwrap();
+        assert_eq!(
+            log.read_to_string().unwrap(),
+            "first\nsecond\nthird\nfourth\n"
+        );
+        assert!(Filey::new("test_dir_append/missing/log")
+            .append("x")
+            .is_err());
+        quit_at("test_dir_append");
+    }
+
+    #[test]
+    fn test_cache_dir() {
+        use crate::cache::CacheDir;
+        use std::{
+            fs::File,
+            time::{Duration, SystemTime},
+        };
+
+        let cache = CacheDir::new("test_dir_cache_dir/cache").unwrap();
+        assert_eq!(cache.get("a").unwrap(), None);
+        cache.put("a", "first").unwrap();
+        cache.put("a", "second").unwrap();
+        cache.put("b", [1, 2, 3]).unwrap();
+        assert_eq!(cache.get("a").unwrap().unwrap(), b"second");
+        assert_eq!(cache.get("b").unwrap().unwrap(), [1, 2, 3]);
+
+        // Sharded by the first two hex digits of the digest of the key.
+        let path = cache.path("a");
+        assert_eq!(path.parent().unwrap().parent().unwrap(), cache.root());
+        assert_eq!(path.parent().unwrap().file_name().unwrap().len(), 2);
+        assert_eq!(path.file_name().unwrap().len(), 62);
+
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(cache.evict_older_than(Duration::from_secs(60)).unwrap(), 1);
+        assert_eq!(cache.get("a").unwrap(), None);
+        assert!(cache.get("b").unwrap().is_some());
+
+        assert!(cache.remove("b").unwrap());
+        assert!(!cache.remove("b").unwrap());
+        quit_at("test_dir_cache_dir");
+    }
+
+    #[test]
+    fn test_open_with() {
+        use crate::OpenFileOptions;
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        init_at("test_dir_open_with");
+        let file = Filey::new("test_dir_open_with/file");
+        let create_new = OpenFileOptions {
+            write: true,
+            create_new: true,
+            ..Default::default()
+        };
+        file.open_with(&create_new)
+            .unwrap()
+            .write_all(b"0123456789")
+            .unwrap();
+        assert!(file.open_with(&create_new).is_err());
+
+        // Reading and writing in place keeps the rest of the file.
+        let read_write = OpenFileOptions {
+            read: true,
+            write: true,
+            ..Default::default()
+        };
+        let mut handle = file.open_with(&read_write).unwrap();
+        handle.seek(SeekFrom::Start(2)).unwrap();
+        handle.write_all(b"ab").unwrap();
+        let mut contents = String::new();
+        handle.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "456789");
+        assert_eq!(file.read_to_string().unwrap(), "01ab456789");
+
+        let append = OpenFileOptions {
+            append: true,
+            ..Default::default()
+        };
+        file.open_with(&append).unwrap().write_all(b"!").unwrap();
+        assert_eq!(file.read_to_string().unwrap(), "01ab456789!");
+        assert!(file.open_with(&OpenFileOptions::default()).is_err());
+        assert!(Filey::new("test_dir_open_with/missing")
+            .open_with(&read_write)
+            .is_err());
+
+        // Opening for writing goes through the hooks, but opening for reading doesn't.
+        let refused = crate::hooks::on_before(|operation| {
+            if operation.path.starts_with("test_dir_open_with") {
+                Err("read-only".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        let read = OpenFileOptions {
+            read: true,
+            ..Default::default()
+        };
+        assert!(file.open_with(&read).is_ok());
+        assert!(file.open_with(&read_write).is_err());
+        assert!(file.open_with(&append).is_err());
+        crate::hooks::remove(refused);
+        quit_at("test_dir_open_with");
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_scaffold() {
+        use crate::scaffold;
+        use std::{collections::HashMap, fs::write, os::unix::fs::PermissionsExt};
+
+        init_at("test_dir_scaffold/template/{{name}}/src");
+        write(
+            "test_dir_scaffold/template/{{name}}/Cargo.toml",
+            "name = \"{{ name }}\"\nauthors = [\"{{author}}\"]\n",
+        )
+        .unwrap();
+        write(
+            "test_dir_scaffold/template/{{name}}/src/{{name}}.rs",
+            "// {{ unclosed",
+        )
+        .unwrap();
+        write(
+            "test_dir_scaffold/template/{{name}}/logo.bin",
+            [0xff, b'{', b'{'],
+        )
+        .unwrap();
+        write("test_dir_scaffold/template/run.sh", "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(
+            "test_dir_scaffold/template/run.sh",
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        symlink("{{name}}", "test_dir_scaffold/template/link").unwrap();
+
+        let vars = HashMap::from([("name", "demo"), ("author", "Alice")]);
+        let created =
+            scaffold::render("test_dir_scaffold/template", "test_dir_scaffold/out", &vars).unwrap();
+        assert_eq!(
+            created,
+            [
+                "link",
+                "run.sh",
+                "demo",
+                "demo/Cargo.toml",
+                "demo/logo.bin",
+                "demo/src",
+                "demo/src/demo.rs"
+            ]
+            .map(Path::new)
+        );
+        let read = |path: &str| std::fs::read(format!("test_dir_scaffold/out/{}", path)).unwrap();
+        assert_eq!(
+            read("demo/Cargo.toml"),
+            b"name = \"demo\"\nauthors = [\"Alice\"]\n"
+        );
+        assert_eq!(read("demo/src/demo.rs"), b"// {{ unclosed");
+        assert_eq!(read("demo/logo.bin"), [0xff, b'{', b'{']);
+        let mode = std::fs::metadata("test_dir_scaffold/out/run.sh")
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            std::fs::read_link("test_dir_scaffold/out/link").unwrap(),
+            Path::new("{{name}}")
+        );
+
+        // Nothing is overwritten, and every variable has to be defined.
+        assert!(
+            scaffold::render("test_dir_scaffold/template", "test_dir_scaffold/out", &vars).is_err()
+        );
+        let vars = HashMap::from([("name", "other")]);
+        assert!(scaffold::render(
+            "test_dir_scaffold/template",
+            "test_dir_scaffold/other",
+            &vars
+        )
+        .is_err());
+
+        // Values can't move an entry out of the destination.
+        init_at("test_dir_scaffold/escape/template");
+        write("test_dir_scaffold/escape/template/{{name}}", "").unwrap();
+        for name in ["../escaped", "/tmp/escaped", "a/b", "..", ".", ""] {
+            let vars = HashMap::from([("name", name)]);
+            assert!(scaffold::render(
+                "test_dir_scaffold/escape/template",
+                "test_dir_scaffold/escape/out",
+                &vars
+            )
+            .is_err());
+        }
+        assert!(!Path::new("test_dir_scaffold/escape/escaped").exists());
+        assert!(!Path::new("/tmp/escaped").exists());
+        quit_at("test_dir_scaffold");
+    }
+
+    #[test]
+    fn test_ignore_file() {
+        use crate::ignore::IgnoreFile;
+
+        init_at("test_dir_ignore");
+        let path = "test_dir_ignore/.gitignore";
+        let mut file = IgnoreFile::open(path).unwrap();
+        assert!(file.patterns().is_empty());
+        assert!(file.add("target"));
+        file.save().unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "target\n");
+
+        std::fs::write(
+            path,
+            "# Build\r\n/target  \r\n\r\n# Editors\r\n*.swp\r\n.idea\r\n\\#notes\r\n*.swp\r\n",
+        )
+        .unwrap();
+        let mut file = IgnoreFile::open(path).unwrap();
+        assert_eq!(
+            file.patterns(),
+            ["/target", "*.swp", ".idea", "\\#notes", "*.swp"]
+        );
+        assert!(file.contains("/target"));
+        assert!(!file.contains("# Build"));
+        assert!(!file.add("/target"));
+        assert_eq!(file.dedupe(), 1);
+        assert!(file.remove(".idea"));
+        assert!(!file.remove(".idea"));
+        assert_eq!(file.add_section("Build", &["/dist", "/target", "/dist"]), 1);
+        assert_eq!(file.add_section("Generated by codegen", &["/gen"]), 1);
+        assert_eq!(
+            file.add_section("Generated by codegen", &["/gen", "*.pb.rs"]),
+            1
+        );
+        file.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "# Build\r\n/target  \r\n/dist\r\n\r\n# Editors\r\n*.swp\r\n\\#notes\r\n\r\n\
+             # Generated by codegen\r\n/gen\r\n*.pb.rs\r\n"
+        );
+        quit_at("test_dir_ignore");
+    }
+
+    #[test]
+    fn test_metadata() {
+        init_at("test_dir_metadata");
+        let file = Filey::new("test_dir_metadata/a.txt");
+        std::fs::write("test_dir_metadata/a.txt", "hello").unwrap();
+        let metadata = file.metadata().unwrap();
+        let std_metadata = std::fs::metadata("test_dir_metadata/a.txt").unwrap();
+        assert_eq!(metadata.file_type(), FileTypes::File);
+        assert_eq!(metadata.size(), 5);
+        assert!(!metadata.readonly());
+        assert_eq!(metadata.modified(), std_metadata.modified().ok());
+        assert_eq!(metadata, crate::Metadata::from(&std_metadata));
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(metadata.ino(), std_metadata.ino());
+            assert_eq!(metadata.nlink(), 1);
+            assert_eq!(metadata.uid(), std_metadata.uid());
+
+            // Symbolic links are followed.
+            symlink("a.txt", "test_dir_metadata/link").unwrap();
+            let link = Filey::new("test_dir_metadata/link").metadata().unwrap();
+            assert_eq!(link.ino(), metadata.ino());
+        }
+        assert_eq!(
+            Filey::new("test_dir_metadata")
+                .metadata()
+                .unwrap()
+                .file_type(),
+            FileTypes::Directory
+        );
+        assert!(Filey::new("test_dir_metadata/missing").metadata().is_err());
+        quit_at("test_dir_metadata");
+    }
+
+    #[test]
+    fn test_times() {
+        use std::time::{Duration, SystemTime};
+
+        init_at("test_dir_times");
+        let file = Filey::new("test_dir_times/stamp");
+        assert!(file.modified().is_err());
+        file.touch().unwrap();
+        assert!(file.exists());
+        let touched = file.modified().unwrap();
+        assert!(SystemTime::now().duration_since(touched).unwrap() < Duration::from_secs(60));
+        assert!(file.accessed().is_ok());
+
+        let past = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        file.set_modified(past).unwrap();
+        assert_eq!(file.modified().unwrap(), past);
+
+        // Touching an existing file keeps its contents.
+        std::fs::write("test_dir_times/stamp", "kept").unwrap();
+        file.set_modified(past).unwrap();
+        file.touch().unwrap();
+        assert!(file.modified().unwrap() > past);
+        assert_eq!(
+            std::fs::read_to_string("test_dir_times/stamp").unwrap(),
+            "kept"
+        );
+
+        // A file the owner can write to but not read, which touch(1) handles too.
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let write_only = Filey::new("test_dir_times/write_only");
+            write_only.touch().unwrap();
+            std::fs::set_permissions(&write_only, std::fs::Permissions::from_mode(0o200)).unwrap();
+            write_only.set_modified(past).unwrap();
+            assert_eq!(write_only.modified().unwrap(), past);
+            write_only.touch().unwrap();
+            assert!(write_only.modified().unwrap() > past);
+        }
+
+        let dir = Filey::new("test_dir_times");
+        dir.set_modified(past).unwrap();
+        assert_eq!(dir.modified().unwrap(), past);
+        dir.touch().unwrap();
+        assert!(dir.modified().unwrap() > past);
+        assert!(Filey::new("test_dir_times/missing/stamp").touch().is_err());
+        quit_at("test_dir_times");
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_roots() {
+        use crate::{checksum::Algorithm, roots::Roots};
+        use std::{fs::write, path::PathBuf};
+
+        init_at("test_dir_roots/disk1/music/live");
+        init_at("test_dir_roots/disk2/music");
+        write("test_dir_roots/disk1/music/a.flac", "aaaa").unwrap();
+        write("test_dir_roots/disk1/music/live/b.flac", "bb").unwrap();
+        write("test_dir_roots/disk2/music/a copy.flac", "aaaa").unwrap();
+        write("test_dir_roots/disk2/music/c.flac", "cccc").unwrap();
+        symlink("disk2", "test_dir_roots/link").unwrap();
+
+        // Nested, repeated and linked roots are merged into the enclosing ones.
+        let roots = Roots::new([
+            "test_dir_roots/disk1/music/live",
+            "test_dir_roots/disk2/music",
+            "test_dir_roots/disk1/music",
+            "test_dir_roots/link/music",
+            "test_dir_roots/disk2/music/",
+        ])
+        .unwrap();
+        assert_eq!(
+            roots.paths(),
+            ["test_dir_roots/disk2/music", "test_dir_roots/disk1/music"].map(PathBuf::from)
+        );
+        assert!(Roots::new(["test_dir_roots/missing"]).is_err());
+
+        assert_eq!(roots.size().unwrap(), 14);
+        let mut names = roots
+            .walk()
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "test_dir_roots/disk1/music/a.flac",
+                "test_dir_roots/disk1/music/live",
+                "test_dir_roots/disk1/music/live/b.flac",
+                "test_dir_roots/disk2/music/a copy.flac",
+                "test_dir_roots/disk2/music/c.flac",
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            roots.duplicates(Algorithm::Sha256).unwrap(),
+            [[
+                "test_dir_roots/disk1/music/a.flac",
+                "test_dir_roots/disk2/music/a copy.flac"
+            ]
+            .map(PathBuf::from)]
+        );
+        let digests = roots.hash_trees(Algorithm::Fnv1a64, 1).unwrap();
+        assert_eq!(digests.len(), 2);
+        assert_eq!(digests[0].files.len(), 2);
+        assert_eq!(digests[1].files.len(), 2);
+
+        #[cfg(feature = "search")]
+        {
+            use crate::search::SearchOptions;
+            let files = roots.grep("cc", &SearchOptions::default()).unwrap();
+            assert_eq!(files.len(), 1);
+            assert_eq!(
+                files[0].path(),
+                Path::new("test_dir_roots/disk2/music/c.flac")
+            );
+        }
+        quit_at("test_dir_roots");
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_trash() {
+        init_at("test_dir_trash");
+        // Nothing else reads XDG_DATA_HOME, so pointing it into the test directory is safe.
+        create_dir_all("test_dir_trash/data").unwrap();
+        let data_home = Path::new("test_dir_trash/data").canonicalize().unwrap();
+        std::env::set_var("XDG_DATA_HOME", &data_home);
+        let files = data_home.join("Trash/files");
+        let info = data_home.join("Trash/info");
+
+        std::fs::write("test_dir_trash/notes 100%.txt", "a").unwrap();
+        let trashed = Filey::new("test_dir_trash/notes 100%.txt")
+            .trash()
+            .unwrap()
+            .unwrap();
+        assert_eq!(trashed, files.join("notes 100%.txt"));
+        assert!(!Path::new("test_dir_trash/notes 100%.txt").exists());
+        assert_eq!(std::fs::read_to_string(&trashed).unwrap(), "a");
+        let contents = std::fs::read_to_string(info.join("notes 100%.txt.trashinfo")).unwrap();
+        let original = Path::new("test_dir_trash").canonicalize().unwrap();
+        assert!(contents.starts_with(&format!(
+            "[Trash Info]\nPath={}/notes%20100%25.txt\nDeletionDate=",
+            original.display()
+        )));
+
+        // A second file of the same name doesn't replace the first one.
+        std::fs::write("test_dir_trash/notes 100%.txt", "b").unwrap();
+        let trashed = Filey::new("test_dir_trash/notes 100%.txt")
+            .trash()
+            .unwrap()
+            .unwrap();
+        assert_eq!(trashed, files.join("notes 100% (1).txt"));
+        assert!(info.join("notes 100% (1).txt.trashinfo").exists());
+        assert_eq!(std::fs::read_to_string(&trashed).unwrap(), "b");
+
+        create_dir_all("test_dir_trash/project/src").unwrap();
+        let trashed = Filey::new("test_dir_trash/project")
+            .trash()
+            .unwrap()
+            .unwrap();
+        assert!(trashed.join("src").is_dir());
+        assert!(!Path::new("test_dir_trash/project").exists());
+
+        assert!(Filey::new("test_dir_trash/missing").trash().is_err());
+        assert!(!info.join("missing.trashinfo").exists());
+        std::env::remove_var("XDG_DATA_HOME");
+        quit_at("test_dir_trash");
+    }
+
+    #[test]
+    fn test_snapshot_symlinks() {
+        use crate::{
+            snapshot::{self, SnapshotOptions},
+            BatchOptions, SymlinkBehavior,
+        };
+
+        init_at("test_dir_snapshot_symlinks/tree/dir");
+        std::fs::write("test_dir_snapshot_symlinks/tree/dir/file", "abc").unwrap();
+        symlink("dir", "test_dir_snapshot_symlinks/tree/link").unwrap();
+        let take = |symlinks| {
+            let options = SnapshotOptions {
+                batch: BatchOptions {
+                    symlinks,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            snapshot::take_with("test_dir_snapshot_symlinks/tree", &options)
+        };
+        let paths = |symlinks| {
+            take(symlinks)
+                .unwrap()
+                .entries
+                .into_iter()
+                .map(|(path, entry)| (path, entry.file_type))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            paths(SymlinkBehavior::Preserve),
+            [
+                ("dir".into(), FileTypes::Directory),
+                ("dir/file".into(), FileTypes::File),
+                ("link".into(), FileTypes::Symlink),
+            ]
+        );
+        assert_eq!(
+            paths(SymlinkBehavior::Follow),
+            [
+                ("dir".into(), FileTypes::Directory),
+                ("dir/file".into(), FileTypes::File),
+                ("link".into(), FileTypes::Directory),
+                ("link/file".into(), FileTypes::File),
+            ]
+        );
+        assert_eq!(
+            paths(SymlinkBehavior::Skip),
+            [
+                ("dir".into(), FileTypes::Directory),
+                ("dir/file".into(), FileTypes::File),
+            ]
+        );
+
+        symlink("..", "test_dir_snapshot_symlinks/tree/dir/parent").unwrap();
+        assert!(take(SymlinkBehavior::Follow).is_err());
+        quit_at("test_dir_snapshot_symlinks");
+    }
+
+    #[cfg(target_family = "windows")]
+    #[test]
+    fn test_streams() {
+        use std::io::Read;
+
+        init_at("test_dir_streams");
+        let path = "test_dir_streams/file";
+        std::fs::write(path, "main").unwrap();
+        let file = Filey::new(path);
+        // Streams need NTFS, which may not be where the tests run.
+        if std::fs::write(format!("{}:Zone.Identifier", path), "[ZoneTransfer]").is_ok() {
+            let streams = file.streams().unwrap();
+            assert_eq!(streams.len(), 1);
+            assert_eq!(streams[0].name(), "Zone.Identifier");
+            assert_eq!(streams[0].size(), 14);
+
+            let mut contents = String::new();
+            file.open_stream("Zone.Identifier")
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            assert_eq!(contents, "[ZoneTransfer]");
+            assert!(file.open_stream("missing").is_err());
+
+            file.remove_stream("Zone.Identifier").unwrap();
+            assert!(file.streams().unwrap().is_empty());
+            assert!(file.remove_stream("Zone.Identifier").is_err());
+            // The main stream is left alone.
+            assert_eq!(std::fs::read_to_string(path).unwrap(), "main");
+        }
+        assert!(Filey::new("test_dir_streams/missing").streams().is_err());
+        quit_at("test_dir_streams");
+    }
+
+    #[cfg(target_family = "windows")]
+    #[test]
+    fn test_extended_length() {
+        use std::path::PathBuf;
+
+        init_at("test_dir_extended_length");
+        let mut expected = std::ffi::OsString::from(r"\\?\");
+        expected.push(std::env::current_dir().unwrap());
+        expected.push(r"\test_dir_extended_length\b");
+        assert_eq!(
+            Filey::new("test_dir_extended_length/a/../b")
+                .to_extended_length()
+                .unwrap(),
+            PathBuf::from(expected)
+        );
+        assert_eq!(
+            Filey::new(r"\\fileserver\builds\node_modules")
+                .to_extended_length()
+                .unwrap(),
+            Path::new(r"\\?\UNC\fileserver\builds\node_modules")
+        );
+        // Paths already in the extended-length form are kept as they are.
+        assert_eq!(
+            Filey::new(r"\\?\C:\dir\..\file")
+                .to_extended_length()
+                .unwrap(),
+            Path::new(r"\\?\C:\dir\..\file")
+        );
+
+        // A path longer than MAX_PATH can be passed to the Windows API.
+        let dir = (0..4).fold(PathBuf::from("test_dir_extended_length"), |dir, i| {
+            dir.join(format!("{}{}", i, "x".repeat(80)))
+        });
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file");
+        assert!(path.as_os_str().len() > 260);
+        std::fs::write(&path, "").unwrap();
+        let file = Filey::new(&path);
+        let mut attributes = file.windows_attributes().unwrap();
+        assert!(!attributes.is_hidden());
+        attributes.set_hidden(true);
+        file.set_windows_attributes(&attributes).unwrap();
+        assert!(file.windows_attributes().unwrap().is_hidden());
+        quit_at("test_dir_extended_length");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_quarantine() {
+        use crate::xattr;
+
+        init_at("test_dir_quarantine");
+        let path = "test_dir_quarantine/download.dmg";
+        std::fs::write(path, "").unwrap();
+        let file = Filey::new(path);
+        assert!(!file.is_quarantined().unwrap());
+        assert!(!file.remove_quarantine().unwrap());
+
+        xattr::set(path, "com.apple.quarantine", b"0081;00000000;Safari;").unwrap();
+        assert!(file.is_quarantined().unwrap());
+        assert!(file.remove_quarantine().unwrap());
+        assert!(!file.is_quarantined().unwrap());
+        assert!(Filey::new("test_dir_quarantine/missing")
+            .is_quarantined()
+            .is_err());
+        quit_at("test_dir_quarantine");
+    }
 }
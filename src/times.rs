@@ -0,0 +1,40 @@
+use crate::{op::OpContext, Op, Result};
+use std::{
+    fs::{File, FileTimes, OpenOptions},
+    io,
+    path::Path,
+};
+
+/// Sets the timestamps of the file or directory at path, following symbolic links.
+pub(crate) fn set_times(path: &Path, times: FileTimes) -> Result<()> {
+    let file = open(path).op(Op::Open, path)?;
+    file.set_times(times).op(Op::SetAttributes, path)
+}
+
+/// Opens path, which may be a directory, so that its timestamps can be set.
+/// futimens(2) works on any descriptor, so a file the owner can only write to, e.g. with mode
+/// 0o200, is opened for writing instead, like touch(1) manages.
+#[cfg(not(target_family = "windows"))]
+fn open(path: &Path) -> io::Result<File> {
+    match OpenOptions::new().read(true).open(path) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            OpenOptions::new().write(true).open(path).map_err(|_| e)
+        }
+        result => result,
+    }
+}
+
+// Directories can only be opened with FILE_FLAG_BACKUP_SEMANTICS, and writing attributes
+// doesn't need write access to the contents, so read-only files can be touched too.
+#[cfg(target_family = "windows")]
+fn open(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_BACKUP_SEMANTICS, FILE_WRITE_ATTRIBUTES,
+    };
+
+    OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
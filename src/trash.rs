@@ -0,0 +1,184 @@
+#[cfg(not(target_os = "wasi"))]
+use crate::op::OpContext;
+#[cfg(target_family = "unix")]
+use crate::{
+    absolute::absolutize,
+    relocate,
+    Error::{FileyError, GetFileNameError},
+};
+use crate::{Op, Result};
+use std::path::Path;
+#[cfg(target_family = "unix")]
+use std::{ffi::OsString, fs::create_dir_all, path::PathBuf};
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+use std::{
+    fs::{remove_file, OpenOptions},
+    io::{ErrorKind, Write},
+    os::unix::ffi::OsStrExt,
+};
+
+/// Moves path into the trash of the current user, from which it can be restored with the
+/// file manager. Returns where it is now, or None on Windows, where the Recycle Bin doesn't
+/// expose paths.
+///
+/// On Linux and other Unix systems this follows the freedesktop.org trash specification
+/// (`$XDG_DATA_HOME/Trash`). A file on another filesystem is copied into the trash and then
+/// removed, as the home trash is the only one used.
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+pub(crate) fn trash(path: &Path) -> Result<Option<PathBuf>> {
+    let trash = match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) if !data_home.is_empty() => PathBuf::from(data_home).join("Trash"),
+        _ => home()?.join(".local/share/Trash"),
+    };
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    create_dir_all(&files).op(Op::CreateDir, &files)?;
+    create_dir_all(&info).op(Op::CreateDir, &info)?;
+
+    let absolute = absolutize(path).op(Op::Canonicalize, path)?;
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(absolute.as_os_str().as_bytes()),
+        deletion_date()
+    );
+    // Creating the info file claims the name, so that concurrent trashing can't collide.
+    for name in candidates(path)? {
+        let mut info_name = name.clone();
+        info_name.push(".trashinfo");
+        let info_path = info.join(info_name);
+        let destination = files.join(&name);
+        if destination.symlink_metadata().is_ok() {
+            continue;
+        }
+        let mut info_file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(Op::Create.error(&info_path, e)),
+        };
+        let moved = info_file
+            .write_all(contents.as_bytes())
+            .op(Op::Write, &info_path)
+            .and_then(|()| relocate::move_path(path, &destination, false));
+        return match moved {
+            Ok(_) => Ok(Some(destination)),
+            Err(e) => {
+                let _ = remove_file(&info_path);
+                Err(e)
+            }
+        };
+    }
+    unreachable!("candidates() doesn't end")
+}
+
+/// Moves path into `~/.Trash`, from which it can be dragged back in the Finder.
+#[cfg(target_os = "macos")]
+pub(crate) fn trash(path: &Path) -> Result<Option<PathBuf>> {
+    let trash = home()?.join(".Trash");
+    create_dir_all(&trash).op(Op::CreateDir, &trash)?;
+    let absolute = absolutize(path).op(Op::Canonicalize, path)?;
+    let destination = candidates(&absolute)?
+        .map(|name| trash.join(name))
+        .find(|destination| destination.symlink_metadata().is_err())
+        .expect("candidates() doesn't end");
+    relocate::move_path(&absolute, &destination, false)?;
+    Ok(Some(destination))
+}
+
+/// Moves path into the Recycle Bin with SHFileOperationW.
+#[cfg(target_family = "windows")]
+pub(crate) fn trash(path: &Path) -> Result<Option<std::path::PathBuf>> {
+    use std::{io, os::windows::ffi::OsStrExt, ptr};
+    use windows_sys::Win32::UI::Shell::{
+        SHFileOperationW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_SILENT, FO_DELETE,
+        SHFILEOPSTRUCTW,
+    };
+
+    // SHFileOperationW takes a list of absolute paths ended by an empty one, and doesn't
+    // understand the extended-length form.
+    let absolute = std::path::absolute(path).op(Op::Canonicalize, path)?;
+    path.symlink_metadata().op(Op::Metadata, path)?;
+    let from: Vec<u16> = absolute.as_os_str().encode_wide().chain([0, 0]).collect();
+    let mut operation = SHFILEOPSTRUCTW {
+        hwnd: 0,
+        wFunc: FO_DELETE,
+        pFrom: from.as_ptr(),
+        pTo: ptr::null(),
+        fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT) as u16,
+        fAnyOperationsAborted: 0,
+        hNameMappings: ptr::null_mut(),
+        lpszProgressTitle: ptr::null(),
+    };
+    // Returns its own error codes rather than setting the last error.
+    let code = unsafe { SHFileOperationW(&mut operation) };
+    if code != 0 || operation.fAnyOperationsAborted != 0 {
+        return Err(Op::Remove.error(path, io::Error::other(format!("error code {:#x}", code))));
+    }
+    Ok(None)
+}
+
+#[cfg(target_os = "wasi")]
+pub(crate) fn trash(path: &Path) -> Result<Option<std::path::PathBuf>> {
+    Err(Op::Remove.error(path, std::io::ErrorKind::Unsupported.into()))
+}
+
+#[cfg(target_family = "unix")]
+fn home() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| FileyError(anyhow::anyhow!("HOME is not set")))
+}
+
+/// Returns the name of path followed by `name (1).ext`, `name (2).ext`, ...
+#[cfg(target_family = "unix")]
+fn candidates(path: &Path) -> Result<impl Iterator<Item = OsString>> {
+    let name = path.file_name().ok_or_else(|| GetFileNameError {
+        path: path.to_string_lossy().to_string(),
+    })?;
+    let stem = Path::new(name)
+        .file_stem()
+        .unwrap_or(name)
+        .to_string_lossy()
+        .to_string();
+    let extension = Path::new(name)
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let name = name.to_os_string();
+    Ok(std::iter::once(name)
+        .chain((1..).map(move |n| OsString::from(format!("{} ({}){}", stem, n, extension)))))
+}
+
+/// Percent-encodes a path for a .trashinfo file, keeping the separators.
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Returns the current local time as `YYYY-MM-DDThh:mm:ss`.
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+fn deletion_date() -> String {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+    unsafe { libc::localtime_r(&now, &mut tm) };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
@@ -0,0 +1,66 @@
+use std::sync::Mutex;
+
+/// Serializes the umask(2) calls of this crate, as reading the mask means setting it.
+static UMASK: Mutex<()> = Mutex::new(());
+
+/// (Unix only) Returns the file mode creation mask of the process, the permission bits which
+/// are cleared from the mode of every new file and directory.
+///
+/// On Linux it is read from /proc/self/status. Elsewhere, or without /proc, the mask is
+/// briefly set to 0 and restored, so a file created by another thread meanwhile may get
+/// wider permissions than intended.
+///
+/// # Examples
+/// ```
+/// # use filey::umask;
+/// #
+/// # fn main() {
+/// println!("{:03o}", umask()); // 022
+/// # }
+/// ```
+pub fn umask() -> u32 {
+    if let Some(mask) = from_proc() {
+        return mask;
+    }
+    let _guard = UMASK.lock().unwrap_or_else(|e| e.into_inner());
+    let mask = unsafe { libc::umask(0) };
+    unsafe { libc::umask(mask) };
+    mask as u32
+}
+
+/// (Unix only) Sets the file mode creation mask of the process and returns the previous one.
+/// The mask is shared by all threads.
+///
+/// # Examples
+/// ```
+/// # use filey::{set_process_umask, Filey};
+/// # use std::error::Error;
+/// #
+/// # fn private_files() -> Result<(), Box<Error>> {
+/// let previous = set_process_umask(0o077);
+/// Filey::new("secrets.txt").create_file()?; // rw-------
+/// set_process_umask(previous);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// # private_files().unwrap();
+/// # }
+/// ```
+pub fn set_process_umask(mask: u32) -> u32 {
+    let _guard = UMASK.lock().unwrap_or_else(|e| e.into_inner());
+    unsafe { libc::umask((mask & 0o777) as libc::mode_t) as u32 }
+}
+
+#[cfg(target_os = "linux")]
+fn from_proc() -> Option<u32> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let mask = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Umask:"))?;
+    u32::from_str_radix(mask.trim(), 8).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn from_proc() -> Option<u32> {
+    None
+}
@@ -0,0 +1,44 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use unicode_normalization::{is_nfc, is_nfd, UnicodeNormalization};
+
+/// A Unicode normalization form of file names.
+///
+/// macOS stores names decomposed (NFD) while Linux and Windows keep whatever they are given,
+/// which is almost always composed (NFC).
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NormalizationForm {
+    /// Canonical composition: `é` is stored as one code point.
+    Nfc,
+    /// Canonical decomposition: `é` is stored as `e` followed by a combining accent.
+    Nfd,
+}
+
+impl fmt::Display for NormalizationForm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Nfc => write!(f, "NFC"),
+            Self::Nfd => write!(f, "NFD"),
+        }
+    }
+}
+
+impl NormalizationForm {
+    /// Returns true if s is already in this form.
+    pub fn is_normalized(&self, s: &str) -> bool {
+        match self {
+            Self::Nfc => is_nfc(s),
+            Self::Nfd => is_nfd(s),
+        }
+    }
+
+    /// Returns s in this form.
+    pub fn normalize(&self, s: &str) -> String {
+        match self {
+            Self::Nfc => s.nfc().collect(),
+            Self::Nfd => s.nfd().collect(),
+        }
+    }
+}
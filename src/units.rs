@@ -1,3 +1,9 @@
+//! Units of information and human-readable sizes.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
 pub const KIB: u64 = 2_u64.pow(10);
 pub const MIB: u64 = 2_u64.pow(20);
 pub const GIB: u64 = 2_u64.pow(30);
@@ -11,3 +17,129 @@ pub const GB: u64 = 10_u64.pow(9);
 pub const TB: u64 = 10_u64.pow(12);
 pub const PB: u64 = 10_u64.pow(15);
 pub const EB: u64 = 10_u64.pow(18);
+
+/// A unit of information.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitOfInfo {
+    Byte,
+    KiB,
+    MiB,
+    GiB,
+    TiB,
+    PiB,
+    EiB,
+    KB,
+    MB,
+    GB,
+    TB,
+    PB,
+    EB,
+}
+
+impl fmt::Display for UnitOfInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Self::Byte => "B",
+            Self::KiB => "KiB",
+            Self::MiB => "MiB",
+            Self::GiB => "GiB",
+            Self::TiB => "TiB",
+            Self::PiB => "PiB",
+            Self::EiB => "EiB",
+            Self::KB => "KB",
+            Self::MB => "MB",
+            Self::GB => "GB",
+            Self::TB => "TB",
+            Self::PB => "PB",
+            Self::EB => "EB",
+        };
+        f.write_str(symbol)
+    }
+}
+
+const BINARY: [UnitOfInfo; 7] = [
+    UnitOfInfo::Byte,
+    UnitOfInfo::KiB,
+    UnitOfInfo::MiB,
+    UnitOfInfo::GiB,
+    UnitOfInfo::TiB,
+    UnitOfInfo::PiB,
+    UnitOfInfo::EiB,
+];
+
+const DECIMAL: [UnitOfInfo; 7] = [
+    UnitOfInfo::Byte,
+    UnitOfInfo::KB,
+    UnitOfInfo::MB,
+    UnitOfInfo::GB,
+    UnitOfInfo::TB,
+    UnitOfInfo::PB,
+    UnitOfInfo::EB,
+];
+
+impl UnitOfInfo {
+    /// Returns the number of bytes in one of this unit.
+    pub fn bytes(&self) -> u64 {
+        match self {
+            Self::Byte => 1,
+            Self::KiB => KIB,
+            Self::MiB => MIB,
+            Self::GiB => GIB,
+            Self::TiB => TIB,
+            Self::PiB => PIB,
+            Self::EiB => EIB,
+            Self::KB => KB,
+            Self::MB => MB,
+            Self::GB => GB,
+            Self::TB => TB,
+            Self::PB => PB,
+            Self::EB => EB,
+        }
+    }
+
+    /// Returns the largest binary unit not larger than size.
+    pub fn binary_for(size: u64) -> Self {
+        // Each binary unit is 10 bits wider than the previous one.
+        let bits = 63 - (size | 1).leading_zeros() as usize;
+        BINARY[bits / 10]
+    }
+
+    /// Returns the largest decimal unit not larger than size.
+    pub fn decimal_for(size: u64) -> Self {
+        DECIMAL
+            .into_iter()
+            .rev()
+            .find(|unit| size >= unit.bytes())
+            .unwrap_or(Self::Byte)
+    }
+
+    /// Formats size in this unit with one decimal place, like `1.5 MiB`.
+    /// Bytes are formatted without a decimal place.
+    pub fn format_as(&self, size: u64) -> String {
+        if *self == Self::Byte {
+            return format!("{} B", size);
+        }
+        let unit = self.bytes() as u128;
+        let tenths = (size as u128 * 10 + unit / 2) / unit;
+        format!("{}.{} {}", tenths / 10, tenths % 10, self)
+    }
+
+    /// Formats size in the largest binary unit not larger than it, like `ls -lh`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use filey::units::UnitOfInfo;
+    /// #
+    /// assert_eq!(UnitOfInfo::format(512), "512 B");
+    /// assert_eq!(UnitOfInfo::format(1536), "1.5 KiB");
+    /// ```
+    pub fn format(size: u64) -> String {
+        Self::binary_for(size).format_as(size)
+    }
+
+    /// Formats size in the largest decimal unit not larger than it.
+    pub fn format_decimal(size: u64) -> String {
+        Self::decimal_for(size).format_as(size)
+    }
+}
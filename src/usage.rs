@@ -0,0 +1,60 @@
+use crate::{op::OpContext, Op, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{read_dir, symlink_metadata},
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+/// The logical length and the allocated bytes of a file or a directory tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiskUsage {
+    apparent: u64,
+    on_disk: u64,
+}
+
+impl DiskUsage {
+    pub fn new(apparent: u64, on_disk: u64) -> Self {
+        Self { apparent, on_disk }
+    }
+
+    /// Measures a file or a directory recursively like du(1).
+    /// Symbolic links are not followed and hard links are counted once.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut usage = Self::default();
+        let mut seen = HashSet::new();
+        usage.add(path.as_ref(), &mut seen)?;
+        Ok(usage)
+    }
+
+    fn add(&mut self, path: &Path, seen: &mut HashSet<(u64, u64)>) -> Result<()> {
+        let metadata = symlink_metadata(path).op(Op::Metadata, path)?;
+
+        if metadata.nlink() < 2 || seen.insert((metadata.dev(), metadata.ino())) {
+            self.apparent += metadata.len();
+            self.on_disk += metadata.blocks() * 512;
+        }
+
+        if metadata.is_dir() {
+            for entry in read_dir(path).op(Op::ReadDir, path)? {
+                let entry = entry.op(Op::ReadDir, path)?;
+                self.add(&entry.path(), seen)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the logical length in bytes.
+    pub fn apparent(&self) -> u64 {
+        self.apparent
+    }
+
+    /// Returns the allocated bytes (blocks × 512).
+    /// This can be smaller than [`DiskUsage::apparent`] for sparse or compressed files.
+    pub fn on_disk(&self) -> u64 {
+        self.on_disk
+    }
+}
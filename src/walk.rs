@@ -0,0 +1,198 @@
+use crate::{deadline, list::Entry, op::OpContext, Error, FileTypes, Filter, Op, Result};
+use std::{
+    fs::{self, DirEntry, ReadDir},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+    vec,
+};
+
+/// Options for [`Filey::walk_with`](crate::Filey::walk_with).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WalkOptions {
+    /// Doesn't descend below this depth; 1 yields only the entries directly inside the
+    /// directory like [`Filey::list_entries`](crate::Filey::list_entries).
+    pub max_depth: Option<usize>,
+    /// Descends into symbolic links to directories. A link leading back to a directory being
+    /// walked isn't descended into again.
+    pub follow_symlinks: bool,
+    /// Yields only entries of these types, e.g. only files. Directories of other types are
+    /// still descended into. Empty yields every entry.
+    pub file_types: Vec<FileTypes>,
+    /// Leaves out the entries the filter rejects; a directory left out isn't descended into.
+    pub filter: Filter,
+    /// Yields the entries of each directory sorted by name, so that the order is the same on
+    /// every run and filesystem. Each directory is read whole before its first entry is
+    /// yielded.
+    pub sorted: bool,
+    /// Gives up reading a directory with [`Error::Timeout`] if it takes longer than this, e.g.
+    /// on a hung network filesystem. Each directory is then read whole before its first entry
+    /// is yielded, and the error is yielded in place of its contents like other errors.
+    pub timeout: Option<Duration>,
+}
+
+/// An iterator over the entries of a directory tree, returned by
+/// [`Filey::walk`](crate::Filey::walk).
+///
+/// Each directory is yielded before its contents, which come in the order the filesystem
+/// returns them unless [`WalkOptions::sorted`] is set. An error reading a directory is yielded
+/// in place of its contents, and the walk continues with the rest of the tree.
+#[derive(Debug)]
+pub struct Walk {
+    root: PathBuf,
+    options: WalkOptions,
+    /// The directories being read, innermost last.
+    stack: Vec<Directory>,
+    /// An error opening a directory, yielded after the directory itself.
+    pending: Option<Error>,
+}
+
+#[derive(Debug)]
+struct Directory {
+    path: PathBuf,
+    entries: Entries,
+    /// The resolved path, to recognize symbolic link loops.
+    canonical: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+enum Entries {
+    Streamed(Box<ReadDir>),
+    /// Read whole, to sort them or within a deadline.
+    Read(vec::IntoIter<DirEntry>),
+}
+
+impl Iterator for Entries {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Streamed(entries) => entries.next(),
+            Self::Read(entries) => entries.next().map(Ok),
+        }
+    }
+}
+
+impl Walk {
+    pub(crate) fn new(root: &Path, options: &WalkOptions) -> Result<Self> {
+        let mut walk = Self {
+            root: root.to_path_buf(),
+            options: options.clone(),
+            stack: Vec::new(),
+            pending: None,
+        };
+        walk.push(root.to_path_buf())?;
+        Ok(walk)
+    }
+
+    fn push(&mut self, path: PathBuf) -> Result<()> {
+        let canonical = if self.options.follow_symlinks {
+            Some(fs::canonicalize(&path).op(Op::Canonicalize, &path)?)
+        } else {
+            None
+        };
+        if canonical.is_some()
+            && self
+                .stack
+                .iter()
+                .any(|directory| directory.canonical == canonical)
+        {
+            return Ok(());
+        }
+        let entries = match (self.options.timeout, self.options.sorted) {
+            (None, false) => {
+                Entries::Streamed(Box::new(fs::read_dir(&path).op(Op::ReadDir, &path)?))
+            }
+            (timeout, sorted) => {
+                let mut entries = match timeout {
+                    Some(timeout) => deadline::dir_entries(&path, timeout)?,
+                    None => fs::read_dir(&path)
+                        .op(Op::ReadDir, &path)?
+                        .collect::<io::Result<Vec<_>>>()
+                        .op(Op::ReadDir, &path)?,
+                };
+                if sorted {
+                    entries.sort_by_key(DirEntry::file_name);
+                }
+                Entries::Read(entries.into_iter())
+            }
+        };
+        self.stack.push(Directory {
+            path,
+            entries,
+            canonical,
+        });
+        Ok(())
+    }
+
+    /// Returns true if the filter of the options accepts entry. A symbolic link is checked
+    /// as what it points to if links are followed.
+    fn accepts(&self, entry: &Entry) -> bool {
+        let filter = &self.options.filter;
+        if filter.is_empty() {
+            return true;
+        }
+        let path = entry.path();
+        let metadata = if self.options.follow_symlinks {
+            fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path))
+        } else {
+            fs::symlink_metadata(&path)
+        };
+        let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+        metadata.map_or(true, |metadata| filter.matches(relative, &metadata))
+    }
+
+    fn descends_into(&self, entry: &Entry) -> bool {
+        let within_depth = self
+            .options
+            .max_depth
+            .is_none_or(|max_depth| entry.depth() < max_depth);
+        within_depth
+            && match entry.file_type() {
+                FileTypes::Directory => true,
+                FileTypes::Symlink => self.options.follow_symlinks && entry.path().is_dir(),
+                _ => false,
+            }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.pending.take() {
+            return Some(Err(e));
+        }
+        loop {
+            let depth = self.stack.len();
+            let directory = self.stack.last_mut()?;
+            let entry = match directory.entries.next() {
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+                Some(entry) => entry
+                    .op(Op::ReadDir, &directory.path)
+                    .and_then(|entry| Entry::at_depth(entry, depth)),
+            };
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(e)),
+            };
+            if !self.accepts(&entry) {
+                continue;
+            }
+
+            if self.descends_into(&entry) {
+                self.pending = self.push(entry.path()).err();
+            }
+            let file_types = &self.options.file_types;
+            if file_types.is_empty() || file_types.contains(&entry.file_type()) {
+                return Some(Ok(entry));
+            }
+            if let Some(e) = self.pending.take() {
+                return Some(Err(e));
+            }
+        }
+    }
+}
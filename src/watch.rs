@@ -0,0 +1,127 @@
+use crate::{op::OpContext, Error, Error::FileyError, Op, Result};
+use std::{
+    fs::{metadata, read, Metadata},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// What [`Filey::on_change`](crate::Filey::on_change) saw happen to the file.
+#[derive(Debug)]
+pub enum Change {
+    /// The file was created or written to; holds its contents after the change.
+    Modified(Vec<u8>),
+    /// The file was removed.
+    Removed,
+    /// The file changed but couldn't be read, e.g. because the user lacks permissions.
+    Failed(Error),
+}
+
+/// A background watcher started by [`Filey::on_change`](crate::Filey::on_change).
+/// Watching stops when it is dropped.
+#[derive(Debug)]
+pub struct Watcher {
+    path: PathBuf,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Returns the path of the file being watched.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stops watching and waits for a callback which is running to return.
+    pub fn stop(self) {}
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The size, modification time and identity of a file, or None if it doesn't exist.
+/// Links are followed, so that editing the target of a symbolic link is a change.
+type Fingerprint = Option<(u64, Option<SystemTime>, Option<(u64, u64)>)>;
+
+fn fingerprint(path: &Path) -> Fingerprint {
+    let metadata = metadata(path).ok()?;
+    Some((
+        metadata.len(),
+        metadata.modified().ok(),
+        identity(&metadata),
+    ))
+}
+
+/// Returns the device and inode numbers, which change when an editor saves by replacing the
+/// file, even if the size and modification time stay the same.
+#[cfg(target_family = "unix")]
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(target_family = "unix"))]
+fn identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Polls path on a worker thread and calls callback once it has changed and then stayed the
+/// same for debounce.
+pub(crate) fn on_change<F>(path: &Path, debounce: Duration, mut callback: F) -> Result<Watcher>
+where
+    F: FnMut(Change) + Send + 'static,
+{
+    let stopped = Arc::new(AtomicBool::new(false));
+    let worker_stopped = Arc::clone(&stopped);
+    let worker_path = path.to_path_buf();
+    // Often enough to notice when the file settles without busy-looping on short debounces.
+    let interval = (debounce / 4).clamp(Duration::from_millis(10), Duration::from_millis(250));
+
+    // Taken before returning, so that a change made right after is noticed.
+    let mut reported = fingerprint(path);
+    let thread = thread::Builder::new()
+        .name("filey-watch".to_string())
+        .spawn(move || {
+            let mut seen = reported;
+            let mut settling_since = Instant::now();
+            while !worker_stopped.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                let current = fingerprint(&worker_path);
+                if current != seen {
+                    seen = current;
+                    settling_since = Instant::now();
+                    continue;
+                }
+                if seen == reported || settling_since.elapsed() < debounce {
+                    continue;
+                }
+                reported = seen;
+                callback(match seen {
+                    None => Change::Removed,
+                    Some(_) => match read(&worker_path).op(Op::Read, &worker_path) {
+                        Ok(contents) => Change::Modified(contents),
+                        Err(e) => Change::Failed(e),
+                    },
+                });
+            }
+        })
+        .map_err(|e| e.into())
+        .map_err(FileyError)?;
+
+    Ok(Watcher {
+        path: path.to_path_buf(),
+        stopped,
+        thread: Some(thread),
+    })
+}
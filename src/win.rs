@@ -0,0 +1,92 @@
+use crate::{op::OpContext, Op, Result};
+use std::{
+    ffi::OsString,
+    io,
+    iter::once,
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    ptr,
+};
+use windows_sys::Win32::{Foundation::BOOL, Storage::FileSystem::GetFullPathNameW};
+
+// Longer paths need the extended-length prefix. Leave room for a file name appended by the
+// callee, as CreateDirectoryW does.
+const MAX_PATH: usize = 248;
+
+const VERBATIM_PREFIX: &[u16] = &[b'\\' as u16, b'\\' as u16, b'?' as u16, b'\\' as u16];
+const DEVICE_PREFIX: &[u16] = &[b'\\' as u16, b'\\' as u16, b'.' as u16, b'\\' as u16];
+const UNC_PREFIX: &[u16] = &[b'\\' as u16, b'\\' as u16];
+
+/// Converts a path into a nul-terminated UTF-16 string for the Windows API.
+/// Paths longer than MAX_PATH are converted into the extended-length form.
+pub(crate) fn to_wide<P: AsRef<Path>>(path: P) -> Result<Vec<u16>> {
+    let path = path.as_ref();
+    let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+    let wide = if wide.len() < MAX_PATH {
+        wide
+    } else {
+        extended_length(&wide).op(Op::Canonicalize, path)?
+    };
+    Ok(wide.into_iter().chain(once(0)).collect())
+}
+
+/// Converts path into the extended-length form: `\\?\C:\...` or `\\?\UNC\server\share\...`.
+pub(crate) fn to_extended_length<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+    let extended = extended_length(&wide).op(Op::Canonicalize, path)?;
+    Ok(PathBuf::from(OsString::from_wide(&extended)))
+}
+
+fn extended_length(path: &[u16]) -> io::Result<Vec<u16>> {
+    if path.starts_with(VERBATIM_PREFIX) || path.starts_with(DEVICE_PREFIX) {
+        return Ok(path.to_vec());
+    }
+
+    // The extended-length form is passed to the filesystem as is, so it has to be absolute
+    // and free of "." and ".." components and forward slashes.
+    let full = full_path(path)?;
+    let mut extended = VERBATIM_PREFIX.to_vec();
+    match full.strip_prefix(UNC_PREFIX) {
+        Some(share) => {
+            extended.extend("UNC\\".encode_utf16());
+            extended.extend_from_slice(share);
+        }
+        None => extended.extend_from_slice(&full),
+    }
+    Ok(extended)
+}
+
+/// Resolves path into an absolute, normalized path like GetFullPathNameW.
+fn full_path(path: &[u16]) -> io::Result<Vec<u16>> {
+    let path: Vec<u16> = path.iter().copied().chain(once(0)).collect();
+    let mut buf = vec![0u16; 512];
+    loop {
+        let len = unsafe {
+            GetFullPathNameW(
+                path.as_ptr(),
+                buf.len() as u32,
+                buf.as_mut_ptr(),
+                ptr::null_mut(),
+            )
+        } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // On success the length excludes the nul; otherwise it's the required buffer size.
+        if len < buf.len() {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+        buf.resize(len, 0);
+    }
+}
+
+/// Maps the return value of a Windows API call into io::Result, reading GetLastError on failure.
+pub(crate) fn check(ret: BOOL) -> io::Result<()> {
+    if ret == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{sys, Error::FileyError, Op, Result};
+use libc::{c_char, c_int, c_void, size_t, ssize_t};
+use std::{ffi::CString, io, path::Path};
+
+/// The errno of a missing attribute.
+#[cfg(target_os = "linux")]
+const ENOATTR: c_int = libc::ENODATA;
+#[cfg(target_os = "macos")]
+const ENOATTR: c_int = libc::ENOATTR;
+
+// macOS takes an extra position (for resource forks) and options.
+#[cfg(target_os = "linux")]
+unsafe fn getxattr(
+    path: *const c_char,
+    name: *const c_char,
+    value: *mut c_void,
+    size: size_t,
+) -> ssize_t {
+    libc::getxattr(path, name, value, size)
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn getxattr(
+    path: *const c_char,
+    name: *const c_char,
+    value: *mut c_void,
+    size: size_t,
+) -> ssize_t {
+    libc::getxattr(path, name, value, size, 0, 0)
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn setxattr(
+    path: *const c_char,
+    name: *const c_char,
+    value: *const c_void,
+    size: size_t,
+) -> c_int {
+    libc::setxattr(path, name, value, size, 0)
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn setxattr(
+    path: *const c_char,
+    name: *const c_char,
+    value: *const c_void,
+    size: size_t,
+) -> c_int {
+    libc::setxattr(path, name, value, size, 0, 0)
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn removexattr(path: *const c_char, name: *const c_char) -> c_int {
+    libc::removexattr(path, name)
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn removexattr(path: *const c_char, name: *const c_char) -> c_int {
+    libc::removexattr(path, name, 0)
+}
+
+fn to_name(name: &str) -> Result<CString> {
+    CString::new(name).map_err(|e| e.into()).map_err(FileyError)
+}
+
+/// Reads the extended attribute name of path. Returns None if it isn't set.
+pub(crate) fn get<P: AsRef<Path>>(path: P, name: &str) -> Result<Option<Vec<u8>>> {
+    let path = path.as_ref();
+    let c_path = sys::to_cstring(path)?;
+    let name = to_name(name)?;
+    loop {
+        let size = unsafe { getxattr(c_path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            return missing_or_error(Op::ReadXattr, path);
+        }
+        let mut value = vec![0u8; size as usize];
+        let read = unsafe {
+            getxattr(
+                c_path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        if read < 0 {
+            let e = io::Error::last_os_error();
+            // The attribute grew between the two calls.
+            if e.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return match e.raw_os_error() {
+                Some(ENOATTR) => Ok(None),
+                _ => Err(Op::ReadXattr.error(path, e)),
+            };
+        }
+        value.truncate(read as usize);
+        return Ok(Some(value));
+    }
+}
+
+/// Sets the extended attribute name of path to value, replacing an existing value.
+/// On Linux, name needs a namespace such as `user.`.
+pub(crate) fn set<P: AsRef<Path>>(path: P, name: &str, value: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    let c_path = sys::to_cstring(path)?;
+    let name = to_name(name)?;
+    let ret = unsafe {
+        setxattr(
+            c_path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+        )
+    };
+    sys::check(ret).map_err(|e| Op::SetXattr.error(path, e))
+}
+
+/// Removes the extended attribute name of path. Returns false if it wasn't set.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn remove<P: AsRef<Path>>(path: P, name: &str) -> Result<bool> {
+    let path = path.as_ref();
+    let c_path = sys::to_cstring(path)?;
+    let name = to_name(name)?;
+    if unsafe { removexattr(c_path.as_ptr(), name.as_ptr()) } == -1 {
+        return missing_or_error(Op::RemoveXattr, path).map(|_: Option<()>| false);
+    }
+    Ok(true)
+}
+
+fn missing_or_error<T>(op: Op, path: &Path) -> Result<Option<T>> {
+    let e = io::Error::last_os_error();
+    match e.raw_os_error() {
+        Some(ENOATTR) => Ok(None),
+        _ => Err(op.error(path, e)),
+    }
+}